@@ -7,14 +7,27 @@
 //!   sshwarma-admin remove-key <pubkey>
 //!   sshwarma-admin list
 //!   sshwarma-admin keys <handle>
+//!   sshwarma-admin backup [dest]
+//!   sshwarma-admin restore <file>
+//!   sshwarma-admin delete-room <room>
+//!   sshwarma-admin bundle export <thing> <file> [--equipment]
+//!   sshwarma-admin bundle import <file> [--owner <handle>] [--room <room>] [--replace]
+//!   sshwarma-admin replay <file.cast> [--speed N] [--idle SECS]
 
 use anyhow::{Context, Result};
 use std::env;
 use std::fs;
+use std::io::Write;
 use std::path::Path;
+use std::time::Duration;
 
+use sshwarma::config::Config;
+use sshwarma::db::backup;
+use sshwarma::db::bundles::{Bundle, BundleFormat, ImportOptions};
+use sshwarma::db::things::ids;
 use sshwarma::db::Database;
 use sshwarma::paths;
+use sshwarma::ui::{Cast, CastEvent};
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -24,6 +37,11 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Replaying doesn't touch the database
+    if args[1] == "replay" {
+        return cmd_replay(&args[2..]);
+    }
+
     let db_path = paths::db_path();
     let db = Database::open(&db_path).context("failed to open database")?;
 
@@ -33,6 +51,10 @@ fn main() -> Result<()> {
         "remove-key" => cmd_remove_key(&db, &args[2..])?,
         "list" => cmd_list(&db)?,
         "keys" => cmd_keys(&db, &args[2..])?,
+        "backup" => cmd_backup(&db, &args[2..])?,
+        "restore" => cmd_restore(&db, &args[2..])?,
+        "delete-room" => cmd_delete_room(&db, &args[2..])?,
+        "bundle" => cmd_bundle(&db, &args[2..])?,
         "help" | "--help" | "-h" => print_usage(),
         cmd => {
             eprintln!("Unknown command: {}", cmd);
@@ -55,24 +77,42 @@ Usage:
   sshwarma-admin remove-key "ssh-ed25519 AAAA..."
   sshwarma-admin list
   sshwarma-admin keys <handle>
+  sshwarma-admin backup [dest]
+  sshwarma-admin restore <file>
+  sshwarma-admin delete-room <room>
+  sshwarma-admin bundle export <thing> <file> [--equipment]
+  sshwarma-admin bundle import <file> [--owner <handle>] [--room <room>] [--replace]
+  sshwarma-admin replay <file.cast> [--speed N] [--idle SECS]
 
 Environment:
-  SSHWARMA_DB    Override database path
+  SSHWARMA_DB           Override database path
+  SSHWARMA_BACKUP_DIR   Override backup directory
+  SSHWARMA_BACKUP_KEEP  Backups kept when rotating (default 7)
+  SSHWARMA_RECORDINGS_DIR  Override /record output directory
 
 Paths:
   Data:   {data}
   Config: {config}
   DB:     {db}
+  Backup: {backup}
+  Casts:  {recordings}
 
 Examples:
   sshwarma-admin add amy ~/.ssh/id_ed25519.pub
   sshwarma-admin add bob --key "ssh-ed25519 AAAAC3... bob@laptop"
   sshwarma-admin list
   sshwarma-admin keys amy
+  sshwarma-admin backup
+  sshwarma-admin restore {backup}/sshwarma-20260101T000000.000Z.db
+  sshwarma-admin bundle export amy:fish fish.toml --equipment
+  sshwarma-admin bundle import fish.toml --owner bob
+  sshwarma-admin replay {recordings}/amy-20260101T120000Z.cast --speed 2
 "#,
         data = paths::data_dir().display(),
         config = paths::config_dir().display(),
         db = paths::db_path().display(),
+        backup = paths::backup_dir().display(),
+        recordings = paths::recordings_dir().display(),
     );
 }
 
@@ -215,3 +255,229 @@ fn cmd_keys(db: &Database, args: &[String]) -> Result<()> {
 
     Ok(())
 }
+
+fn cmd_backup(db: &Database, args: &[String]) -> Result<()> {
+    let path = if let Some(dest) = args.first() {
+        let dest = Path::new(dest);
+        db.backup_to(dest)?;
+        dest.to_path_buf()
+    } else {
+        let config = Config::from_env();
+        db.backup_rotated(Path::new(&config.backup_dir), config.backup_keep)?
+    };
+
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    println!("Backed up to {} ({} bytes)", path.display(), size);
+
+    Ok(())
+}
+
+fn cmd_restore(db: &Database, args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        anyhow::bail!("Usage: sshwarma-admin restore <file>");
+    }
+
+    let src = Path::new(&args[0]);
+    let version = backup::verify_backup(src)?;
+
+    // Keep a copy of what we're about to overwrite
+    let config = Config::from_env();
+    let safety = Path::new(&config.backup_dir).join(backup::backup_file_name(Some("pre-restore")));
+    db.backup_to(&safety)
+        .context("failed to save current database before restoring")?;
+    println!("Saved current database to {}", safety.display());
+
+    db.restore_from(src)?;
+
+    println!("Restored {} (schema version {})", src.display(), version);
+    println!("Restart sshwarma to pick up the restored data");
+
+    Ok(())
+}
+
+fn cmd_delete_room(db: &Database, args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        anyhow::bail!("Usage: sshwarma-admin delete-room <room>");
+    }
+
+    let name = &args[0];
+    let Some(room) = db.get_room_by_name(name)? else {
+        println!("Room {} not found", name);
+        return Ok(());
+    };
+
+    let deletion = db.delete_room(&room.id)?;
+    println!(
+        "Deleted room {} ({} buffers, {} rows, {} equipped tools)",
+        name, deletion.buffers, deletion.rows, deletion.equipment
+    );
+    for (from, direction) in &deletion.exits_in {
+        println!("  removed exit {} from {}", direction, from);
+    }
+    println!("Restart sshwarma if it's running so the room leaves its world map");
+
+    Ok(())
+}
+
+fn cmd_bundle(db: &Database, args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: sshwarma-admin bundle export <thing> <file> [--equipment]\n       \
+                         sshwarma-admin bundle import <file> [--owner <handle>] [--room <room>] [--replace]";
+
+    let Some(action) = args.first() else {
+        anyhow::bail!(USAGE);
+    };
+    let mut words = Vec::new();
+    let mut equipment = false;
+    let mut replace = false;
+    let mut owner = None;
+    let mut room = None;
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--equipment" => equipment = true,
+            "--replace" => replace = true,
+            "--owner" => owner = Some(iter.next().context("--owner requires a handle")?),
+            "--room" => room = Some(iter.next().context("--room requires a room name")?),
+            _ => words.push(arg),
+        }
+    }
+
+    match (action.as_str(), words.as_slice()) {
+        ("export", [thing, file]) => {
+            // Things by qualified name, or by ID (agent_<handle> is an inventory)
+            let root = match db.get_thing_by_qualified_name(thing)? {
+                Some(found) => found.id,
+                None => thing.to_string(),
+            };
+            let bundle = db.export_bundle(&root, equipment)?;
+            let path = Path::new(file);
+            fs::write(path, bundle.render(BundleFormat::from_path(path))?)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!(
+                "Exported {} things and {} equipment recipes to {}",
+                bundle.count(),
+                bundle.equipment.len(),
+                path.display()
+            );
+        }
+        ("import", [file]) => {
+            let text = fs::read_to_string(file.as_str())
+                .with_context(|| format!("failed to read {}", file))?;
+            let bundle = Bundle::parse(&text)?;
+
+            let mut options = ImportOptions {
+                parent_id: ids::SHARED.to_string(),
+                replace,
+                ..Default::default()
+            };
+            if let Some(owner) = owner {
+                options.parent_id = db.ensure_agent_thing(owner)?;
+                options.created_by = Some(owner.clone());
+                options.equip_agent = db.get_agent_by_name(owner)?.map(|a| a.id);
+            }
+            if let Some(room) = room {
+                let room_obj = db
+                    .get_room_by_name(room)?
+                    .with_context(|| format!("room {} not found", room))?;
+                options.equip_room = Some(room_obj.id);
+            }
+
+            let report = db.import_bundle(&bundle, &options)?;
+            println!(
+                "Imported {} things ({}), equipped {}",
+                report.things,
+                report.roots.join(", "),
+                report.equipped
+            );
+            for renamed in &report.renamed {
+                println!("  renamed {}", renamed);
+            }
+            for replaced in &report.replaced {
+                println!("  trashed the old {}", replaced);
+            }
+            for skipped in &report.skipped {
+                println!("  skipped {}", skipped);
+            }
+        }
+        _ => anyhow::bail!(USAGE),
+    }
+
+    Ok(())
+}
+
+fn cmd_replay(args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: sshwarma-admin replay <file.cast> [--speed N] [--idle SECS]";
+
+    let mut file = None;
+    let mut speed = 1.0_f64;
+    // Long pauses (someone reading, or away) are cut down to this
+    let mut idle = 2.0_f64;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--speed" => {
+                speed = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|s: &f64| *s > 0.0)
+                    .context("--speed requires a positive number")?;
+            }
+            "--idle" => {
+                idle = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|s: &f64| *s >= 0.0)
+                    .context("--idle requires a number of seconds")?;
+            }
+            _ if file.is_none() => file = Some(arg),
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+    let Some(file) = file else {
+        anyhow::bail!(USAGE);
+    };
+
+    let cast = Cast::read(Path::new(file))?;
+    let (width, height) = (cast.header.width, cast.header.height);
+    if let Ok((cols, rows)) = crossterm::terminal::size() {
+        if cols < width || rows < height {
+            eprintln!(
+                "Recorded at {}x{} but this terminal is {}x{}; output may wrap",
+                width, height, cols, rows
+            );
+            std::thread::sleep(Duration::from_secs(2));
+        }
+    }
+
+    // Play on the alternate screen, like the session it came from
+    let mut out = std::io::stdout().lock();
+    out.write_all(b"\x1b[?1049h\x1b[2J\x1b[H")?;
+    let mut last = 0.0;
+    for (time, event) in &cast.events {
+        let wait = (time - last).clamp(0.0, idle) / speed;
+        last = *time;
+        if wait > 0.0 {
+            out.flush()?;
+            std::thread::sleep(Duration::from_secs_f64(wait));
+        }
+        // Resizes can't be replayed; the next frame repaints what it needs
+        if let CastEvent::Output(data) = event {
+            out.write_all(data.as_bytes())?;
+        }
+    }
+    out.flush()?;
+    std::thread::sleep(Duration::from_secs(1));
+    out.write_all(b"\x1b[?25h\x1b[?1049l")?;
+    out.flush()?;
+
+    println!(
+        "Replayed {} ({}x{}, {:.1}s, {} events)",
+        file,
+        width,
+        height,
+        cast.duration(),
+        cast.events.len()
+    );
+
+    Ok(())
+}
@@ -23,6 +23,24 @@ pub struct Config {
     pub mcp_server_port: u16,
     /// Path to models config file
     pub models_config_path: String,
+    /// Directory for database backups
+    pub backup_dir: String,
+    /// Minutes between scheduled backups (0 = disabled)
+    pub backup_interval_mins: u64,
+    /// Number of backups to keep when rotating (0 = keep all)
+    pub backup_keep: usize,
+    /// Days deleted things stay in the trash before they're purged (0 = forever)
+    pub trash_retention_days: u64,
+    /// Directory for /record session recordings
+    pub recordings_dir: String,
+    /// Directory /bundle reads and writes thing bundles in
+    pub bundles_dir: String,
+    /// Handles allowed to run admin commands (empty = no admins)
+    pub admins: Vec<String>,
+    /// Seconds active streams get to finish when shutting down
+    pub shutdown_grace_secs: u64,
+    /// Seconds a disconnected session is kept for reattaching (0 = not kept)
+    pub session_grace_secs: u64,
 }
 
 impl Default for Config {
@@ -35,6 +53,15 @@ impl Default for Config {
             allow_open_registration: true,
             mcp_server_port: 2223,
             models_config_path: "models.toml".to_string(),
+            backup_dir: "backups".to_string(),
+            backup_interval_mins: 360,
+            backup_keep: 7,
+            trash_retention_days: 30,
+            recordings_dir: "recordings".to_string(),
+            bundles_dir: "bundles".to_string(),
+            admins: vec![],
+            shutdown_grace_secs: 15,
+            session_grace_secs: 300,
         }
     }
 }
@@ -53,6 +80,15 @@ impl Config {
     /// | `SSHWARMA_MCP_PORT` | MCP server port | `2223` |
     /// | `SSHWARMA_MCP_ENDPOINTS` | MCP endpoints (comma-separated) | `http://localhost:8080/mcp` |
     /// | `SSHWARMA_OPEN_REGISTRATION` | Allow registration | `true` |
+    /// | `SSHWARMA_BACKUP_DIR` | Backup directory | `~/.local/share/sshwarma/backups` |
+    /// | `SSHWARMA_BACKUP_INTERVAL_MINS` | Minutes between backups (0 = off) | `360` |
+    /// | `SSHWARMA_BACKUP_KEEP` | Backups to keep | `7` |
+    /// | `SSHWARMA_TRASH_RETENTION_DAYS` | Days before deleted things are purged (0 = never) | `30` |
+    /// | `SSHWARMA_RECORDINGS_DIR` | /record output directory | `~/.local/share/sshwarma/recordings` |
+    /// | `SSHWARMA_BUNDLES_DIR` | /bundle file directory | `~/.local/share/sshwarma/bundles` |
+    /// | `SSHWARMA_ADMINS` | Admin handles (comma-separated) | none |
+    /// | `SSHWARMA_SHUTDOWN_GRACE_SECS` | Stream grace period on shutdown | `15` |
+    /// | `SSHWARMA_SESSION_GRACE_SECS` | How long detached sessions are kept | `300` |
     pub fn from_env() -> Self {
         use crate::paths;
 
@@ -74,6 +110,40 @@ impl Config {
             .and_then(|s| s.parse().ok())
             .unwrap_or(2223);
 
+        let backup_interval_mins = std::env::var("SSHWARMA_BACKUP_INTERVAL_MINS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(360);
+
+        let backup_keep = std::env::var("SSHWARMA_BACKUP_KEEP")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(7);
+
+        let trash_retention_days = std::env::var("SSHWARMA_TRASH_RETENTION_DAYS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30);
+
+        let admins = std::env::var("SSHWARMA_ADMINS")
+            .map(|s| {
+                s.split(',')
+                    .map(|h| h.trim().to_string())
+                    .filter(|h| !h.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let shutdown_grace_secs = std::env::var("SSHWARMA_SHUTDOWN_GRACE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(15);
+
+        let session_grace_secs = std::env::var("SSHWARMA_SESSION_GRACE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(300);
+
         Self {
             listen_addr,
             host_key_path: paths::host_key_path().to_string_lossy().into_owned(),
@@ -82,8 +152,25 @@ impl Config {
             allow_open_registration,
             mcp_server_port,
             models_config_path: paths::models_config_path().to_string_lossy().into_owned(),
+            backup_dir: paths::backup_dir().to_string_lossy().into_owned(),
+            recordings_dir: paths::recordings_dir().to_string_lossy().into_owned(),
+            bundles_dir: paths::bundles_dir().to_string_lossy().into_owned(),
+            backup_interval_mins,
+            backup_keep,
+            trash_retention_days,
+            admins,
+            shutdown_grace_secs,
+            session_grace_secs,
         }
     }
+
+    /// Whether a handle may run admin commands
+    ///
+    /// With no `SSHWARMA_ADMINS` configured nobody is; ownership checks
+    /// then only let owners through, and `sshwarma-admin` covers the rest.
+    pub fn is_admin(&self, handle: &str) -> bool {
+        self.admins.iter().any(|a| a == handle)
+    }
 }
 
 /// Models configuration file structure
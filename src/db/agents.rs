@@ -273,7 +273,7 @@ impl Database {
 
     /// Get agent by ID
     pub fn get_agent(&self, id: &str) -> Result<Option<Agent>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -295,7 +295,7 @@ impl Database {
 
     /// Get agent by name
     pub fn get_agent_by_name(&self, name: &str) -> Result<Option<Agent>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -317,7 +317,7 @@ impl Database {
 
     /// List all agents, optionally filtered by kind
     pub fn list_agents(&self, kind: Option<AgentKind>) -> Result<Vec<Agent>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let sql = match kind {
             Some(_) => {
                 r#"
@@ -474,7 +474,7 @@ impl Database {
 
     /// Get session by ID
     pub fn get_session(&self, id: &str) -> Result<Option<AgentSession>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -494,7 +494,7 @@ impl Database {
 
     /// List active sessions for an agent
     pub fn list_active_sessions(&self, agent_id: &str) -> Result<Vec<AgentSession>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -0,0 +1,287 @@
+//! Online backup and restore
+//!
+//! Backups use SQLite's backup API, so they can be taken while the server is
+//! running. Scheduled and on-demand backups land in `paths::backup_dir()` as
+//! `sshwarma-<timestamp>.db` and are rotated to keep the newest N files.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::path::{Path, PathBuf};
+
+use super::{Database, SCHEMA_VERSION};
+
+/// Filename prefix for backups written by `backup_rotated`
+const BACKUP_PREFIX: &str = "sshwarma-";
+
+/// Filename extension for backups
+const BACKUP_EXT: &str = ".db";
+
+/// Build a backup filename for the current time
+///
+/// Timestamps are UTC with millisecond precision so names sort chronologically.
+/// Labelled backups (`pre-restore-<ts>.db`) don't carry the rotation prefix,
+/// so rotation never prunes them.
+pub fn backup_file_name(label: Option<&str>) -> String {
+    let ts = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+    match label {
+        Some(label) => format!("{}-{}{}", label, ts, BACKUP_EXT),
+        None => format!("{}{}{}", BACKUP_PREFIX, ts, BACKUP_EXT),
+    }
+}
+
+/// List backups in a directory, oldest first
+pub fn list_backups(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read backup directory {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXT) && path.is_file() {
+            backups.push(path);
+        }
+    }
+
+    // Names embed a sortable timestamp
+    backups.sort();
+    Ok(backups)
+}
+
+/// Delete the oldest backups so at most `keep` remain
+///
+/// Returns the number of files removed. `keep == 0` disables rotation.
+pub fn rotate_backups(dir: &Path, keep: usize) -> Result<usize> {
+    if keep == 0 {
+        return Ok(0);
+    }
+
+    let backups = list_backups(dir)?;
+    let excess = backups.len().saturating_sub(keep);
+    for path in &backups[..excess] {
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove old backup {}", path.display()))?;
+        tracing::debug!("rotated out backup {}", path.display());
+    }
+
+    Ok(excess)
+}
+
+/// Read the schema version of a database file without modifying it
+pub fn backup_schema_version(path: &Path) -> Result<i32> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    let version: i32 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .with_context(|| format!("failed to read schema version from {}", path.display()))?;
+    Ok(version)
+}
+
+/// Check that a file is a restorable sshwarma database
+///
+/// Rejects files that fail SQLite's integrity check, have no schema version
+/// (not an sshwarma database), or were written by a newer sshwarma.
+pub fn verify_backup(path: &Path) -> Result<i32> {
+    if !path.is_file() {
+        anyhow::bail!("backup not found: {}", path.display());
+    }
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .with_context(|| format!("failed to check {}", path.display()))?;
+    if check != "ok" {
+        anyhow::bail!("{} failed integrity check: {}", path.display(), check);
+    }
+    drop(conn);
+
+    let version = backup_schema_version(path)?;
+    if version == 0 {
+        anyhow::bail!("{} is not an sshwarma database", path.display());
+    }
+    if version > SCHEMA_VERSION {
+        anyhow::bail!(
+            "{} has schema version {}, newer than this build ({}); upgrade sshwarma first",
+            path.display(),
+            version,
+            SCHEMA_VERSION
+        );
+    }
+
+    Ok(version)
+}
+
+impl Database {
+    /// Write a consistent copy of the database to `dest`
+    ///
+    /// Safe to call while the server is running. The copy is written to a
+    /// temporary file and renamed into place so a partial backup is never
+    /// mistaken for a complete one.
+    pub fn backup_to(&self, dest: &Path) -> Result<()> {
+        if let Some(parent) = dest.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).with_context(|| {
+                    format!("failed to create backup directory {}", parent.display())
+                })?;
+            }
+        }
+
+        let tmp = dest.with_extension("db.partial");
+        {
+            let conn = self.conn()?;
+            conn.backup(DatabaseName::Main, &tmp, None)
+                .with_context(|| format!("failed to back up database to {}", tmp.display()))?;
+        }
+
+        std::fs::rename(&tmp, dest)
+            .with_context(|| format!("failed to move backup into {}", dest.display()))?;
+        Ok(())
+    }
+
+    /// Back up into `dir` with a timestamped name, then rotate old backups
+    ///
+    /// Returns the path of the new backup.
+    pub fn backup_rotated(&self, dir: &Path, keep: usize) -> Result<PathBuf> {
+        let dest = dir.join(backup_file_name(None));
+        self.backup_to(&dest)?;
+        let removed = rotate_backups(dir, keep)?;
+        tracing::info!(
+            path = %dest.display(),
+            removed,
+            "database backup written"
+        );
+        Ok(dest)
+    }
+
+    /// Replace the database contents with a backup file
+    ///
+    /// The backup is verified first, then copied over the live database and
+    /// migrated to the current schema. Returns the backup's schema version.
+    /// Callers should stop the server before restoring.
+    pub fn restore_from(&self, src: &Path) -> Result<i32> {
+        let version = verify_backup(src)?;
+
+        {
+            let mut conn = self.conn()?;
+            conn.restore(
+                DatabaseName::Main,
+                src,
+                None::<fn(rusqlite::backup::Progress)>,
+            )
+            .with_context(|| format!("failed to restore from {}", src.display()))?;
+        }
+
+        self.init()?;
+        tracing::info!(
+            path = %src.display(),
+            version,
+            "database restored from backup"
+        );
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::new_id;
+    use crate::db::rooms::Room;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sshwarma-backup-test-{}", new_id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_backup_and_restore() -> Result<()> {
+        let dir = temp_dir();
+        let db = Database::open(dir.join("live.db"))?;
+        db.insert_room(&Room::new("before"))?;
+
+        let backup = db.backup_rotated(&dir.join("backups"), 3)?;
+        assert!(backup.exists());
+        assert_eq!(verify_backup(&backup)?, SCHEMA_VERSION);
+
+        db.insert_room(&Room::new("after"))?;
+        assert!(db.get_room_by_name("after")?.is_some());
+
+        db.restore_from(&backup)?;
+        assert!(db.get_room_by_name("before")?.is_some());
+        assert!(db.get_room_by_name("after")?.is_none());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_in_memory() -> Result<()> {
+        let dir = temp_dir();
+        let db = Database::in_memory()?;
+        db.insert_room(&Room::new("mem"))?;
+
+        let dest = dir.join("mem.db");
+        db.backup_to(&dest)?;
+
+        let copy = Database::open(&dest)?;
+        assert!(copy.get_room_by_name("mem")?.is_some());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate_backups() -> Result<()> {
+        let dir = temp_dir();
+        for i in 0..5 {
+            let name = format!("{}2026010{}T000000.000Z{}", BACKUP_PREFIX, i, BACKUP_EXT);
+            std::fs::write(dir.join(name), b"")?;
+        }
+        std::fs::write(dir.join("unrelated.txt"), b"")?;
+        let safety = dir.join(backup_file_name(Some("pre-restore")));
+        std::fs::write(&safety, b"")?;
+
+        assert_eq!(rotate_backups(&dir, 2)?, 3);
+        let remaining = list_backups(&dir)?;
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining[0].to_string_lossy().contains("20260103"));
+        assert!(remaining[1].to_string_lossy().contains("20260104"));
+        assert!(dir.join("unrelated.txt").exists());
+        assert!(safety.exists());
+
+        // keep == 0 disables rotation
+        assert_eq!(rotate_backups(&dir, 0)?, 0);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_rejects_newer_schema() -> Result<()> {
+        let dir = temp_dir();
+        let future = dir.join("future.db");
+        {
+            let conn = Connection::open(&future)?;
+            conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1))?;
+        }
+        let err = verify_backup(&future).unwrap_err();
+        assert!(err.to_string().contains("newer"));
+
+        let blank = dir.join("blank.db");
+        Connection::open(&blank)?.execute_batch("CREATE TABLE t (x)")?;
+        assert!(verify_backup(&blank).is_err());
+
+        let db = Database::in_memory()?;
+        assert!(db.restore_from(&future).is_err());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@
 
 use super::{new_id, now_ms, Database};
 use anyhow::{Context, Result};
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
 /// Buffer type discriminator
@@ -83,6 +83,8 @@ pub struct Buffer {
 
     // Forking
     pub parent_buffer_id: Option<String>,
+    /// Last row of the parent this buffer shares (see `Database::buffer_ancestry`)
+    pub fork_row_id: Option<String>,
 
     // Wrap behavior
     pub include_in_wrap: bool,
@@ -103,6 +105,7 @@ impl Buffer {
             tombstone_summary: None,
             tombstoned_at: None,
             parent_buffer_id: None,
+            fork_row_id: None,
             include_in_wrap: true,
             wrap_priority: 100,
         }
@@ -141,6 +144,59 @@ impl Buffer {
     }
 }
 
+/// Tombstone a buffer on `conn` unless it already is one, returning
+/// whether this call did it
+pub(super) fn tombstone_buffer_with(
+    conn: &Connection,
+    buffer_id: &str,
+    status: TombstoneStatus,
+    summary: Option<&str>,
+) -> Result<bool> {
+    let updated = conn
+        .execute(
+            r#"
+            UPDATE buffers SET
+                tombstoned = 1, tombstone_status = ?2, tombstone_summary = ?3, tombstoned_at = ?4
+            WHERE id = ?1 AND tombstoned = 0
+            "#,
+            params![buffer_id, status.as_str(), summary, now_ms()],
+        )
+        .context("failed to tombstone buffer")?;
+    Ok(updated > 0)
+}
+
+/// Update a buffer on `conn`, e.g. inside a transaction
+pub(super) fn update_buffer_with(conn: &Connection, buffer: &Buffer) -> Result<()> {
+    let tombstone_status = buffer.tombstone_status.as_ref().map(|s| s.as_str());
+
+    conn.execute(
+        r#"
+        UPDATE buffers SET
+            room_id = ?2, owner_agent_id = ?3, buffer_type = ?4,
+            tombstoned = ?5, tombstone_status = ?6, tombstone_summary = ?7, tombstoned_at = ?8,
+            parent_buffer_id = ?9, include_in_wrap = ?10, wrap_priority = ?11,
+            fork_row_id = ?12
+        WHERE id = ?1
+        "#,
+        params![
+            buffer.id,
+            buffer.room_id,
+            buffer.owner_agent_id,
+            buffer.buffer_type.as_str(),
+            buffer.tombstoned as i32,
+            tombstone_status,
+            buffer.tombstone_summary,
+            buffer.tombstoned_at,
+            buffer.parent_buffer_id,
+            buffer.include_in_wrap as i32,
+            buffer.wrap_priority,
+            buffer.fork_row_id,
+        ],
+    )
+    .context("failed to update buffer")?;
+    Ok(())
+}
+
 // Database operations
 impl Database {
     /// Insert a new buffer
@@ -153,8 +209,8 @@ impl Database {
             INSERT INTO buffers (
                 id, room_id, owner_agent_id, buffer_type, created_at,
                 tombstoned, tombstone_status, tombstone_summary, tombstoned_at,
-                parent_buffer_id, include_in_wrap, wrap_priority
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                parent_buffer_id, include_in_wrap, wrap_priority, fork_row_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
             "#,
             params![
                 buffer.id,
@@ -169,6 +225,7 @@ impl Database {
                 buffer.parent_buffer_id,
                 buffer.include_in_wrap as i32,
                 buffer.wrap_priority,
+                buffer.fork_row_id,
             ],
         )
         .context("failed to insert buffer")?;
@@ -177,13 +234,13 @@ impl Database {
 
     /// Get buffer by ID
     pub fn get_buffer(&self, id: &str) -> Result<Option<Buffer>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
             SELECT id, room_id, owner_agent_id, buffer_type, created_at,
                    tombstoned, tombstone_status, tombstone_summary, tombstoned_at,
-                   parent_buffer_id, include_in_wrap, wrap_priority
+                   parent_buffer_id, include_in_wrap, wrap_priority, fork_row_id
             FROM buffers WHERE id = ?1
             "#,
             )
@@ -199,13 +256,13 @@ impl Database {
 
     /// List buffers for a room
     pub fn list_room_buffers(&self, room_id: &str) -> Result<Vec<Buffer>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
             SELECT id, room_id, owner_agent_id, buffer_type, created_at,
                    tombstoned, tombstone_status, tombstone_summary, tombstoned_at,
-                   parent_buffer_id, include_in_wrap, wrap_priority
+                   parent_buffer_id, include_in_wrap, wrap_priority, fork_row_id
             FROM buffers WHERE room_id = ?1
             ORDER BY created_at
             "#,
@@ -227,13 +284,13 @@ impl Database {
         room_id: &str,
         buffer_type: BufferType,
     ) -> Result<Vec<Buffer>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
             SELECT id, room_id, owner_agent_id, buffer_type, created_at,
                    tombstoned, tombstone_status, tombstone_summary, tombstoned_at,
-                   parent_buffer_id, include_in_wrap, wrap_priority
+                   parent_buffer_id, include_in_wrap, wrap_priority, fork_row_id
             FROM buffers WHERE room_id = ?1 AND buffer_type = ?2
             ORDER BY created_at
             "#,
@@ -265,32 +322,7 @@ impl Database {
     /// Update buffer (for tombstoning, etc.)
     pub fn update_buffer(&self, buffer: &Buffer) -> Result<()> {
         let conn = self.conn()?;
-        let tombstone_status = buffer.tombstone_status.as_ref().map(|s| s.as_str());
-
-        conn.execute(
-            r#"
-            UPDATE buffers SET
-                room_id = ?2, owner_agent_id = ?3, buffer_type = ?4,
-                tombstoned = ?5, tombstone_status = ?6, tombstone_summary = ?7, tombstoned_at = ?8,
-                parent_buffer_id = ?9, include_in_wrap = ?10, wrap_priority = ?11
-            WHERE id = ?1
-            "#,
-            params![
-                buffer.id,
-                buffer.room_id,
-                buffer.owner_agent_id,
-                buffer.buffer_type.as_str(),
-                buffer.tombstoned as i32,
-                tombstone_status,
-                buffer.tombstone_summary,
-                buffer.tombstoned_at,
-                buffer.parent_buffer_id,
-                buffer.include_in_wrap as i32,
-                buffer.wrap_priority,
-            ],
-        )
-        .context("failed to update buffer")?;
-        Ok(())
+        update_buffer_with(&conn, buffer)
     }
 
     /// Delete a buffer (cascades to rows)
@@ -301,6 +333,35 @@ impl Database {
         Ok(())
     }
 
+    /// Ancestors a forked buffer shares history with, nearest first
+    ///
+    /// Each entry is an ancestor buffer id and the position of the last of
+    /// its rows that the fork shares. Empty for buffers that aren't forks.
+    pub fn buffer_ancestry(&self, buffer_id: &str) -> Result<Vec<(String, f64)>> {
+        const MAX_DEPTH: usize = 64;
+
+        let mut ancestry = Vec::new();
+        let mut current = self.get_buffer(buffer_id)?;
+        while let Some(buffer) = current {
+            let (Some(parent_id), Some(fork_row_id)) =
+                (buffer.parent_buffer_id, buffer.fork_row_id)
+            else {
+                break;
+            };
+            // A deleted fork point leaves the fork with only its own rows
+            let Some(fork_row) = self.get_row(&fork_row_id)? else {
+                break;
+            };
+            if ancestry.len() >= MAX_DEPTH || ancestry.iter().any(|(id, _)| *id == parent_id) {
+                tracing::warn!(buffer_id, "buffer ancestry too deep or cyclic, truncating");
+                break;
+            }
+            ancestry.push((parent_id.clone(), fork_row.position));
+            current = self.get_buffer(&parent_id)?;
+        }
+        Ok(ancestry)
+    }
+
     fn buffer_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Buffer> {
         let type_str: String = row.get(3)?;
         let tombstoned_int: i32 = row.get(5)?;
@@ -320,6 +381,7 @@ impl Database {
             parent_buffer_id: row.get(9)?,
             include_in_wrap: include_in_wrap_int != 0,
             wrap_priority: row.get(11)?,
+            fork_row_id: row.get(12)?,
         })
     }
 }
@@ -0,0 +1,538 @@
+//! Thing bundles
+//!
+//! A bundle is a thing subtree written out as TOML or JSON so tools, hooks,
+//! commands and data built on one server can be installed on another. It
+//! keeps each thing's code, params, default slot and metadata, nests
+//! children under their parents, and can carry equipment recipes: the
+//! slots, configs and priorities the things were equipped with in rooms
+//! and by agents.
+//!
+//! Importing gives every thing a new ID, records the ID it had in the
+//! bundle as `copied_from`, can move qualified names into the importer's
+//! namespace, and renames things whose qualified name is already taken (or
+//! trashes the current holder, with `replace`).
+
+use super::equipped::{agent_equip_with, room_equip_with};
+use super::things::{
+    get_thing_by_qualified_name_with, insert_thing_with, soft_delete_thing_with, Thing, ThingKind,
+};
+use super::trash::{free_qualified_name_with, trash_thing_with};
+use super::Database;
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Bundle format version this server writes and reads
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Kinds that can travel in a bundle
+///
+/// Rooms, agents, zones and MCP servers are tied to rows outside the thing
+/// tree, so exporting an inventory or a room bundles what's in it instead.
+fn is_bundleable(kind: ThingKind) -> bool {
+    matches!(
+        kind,
+        ThingKind::Container
+            | ThingKind::Tool
+            | ThingKind::Data
+            | ThingKind::Reference
+            | ThingKind::Template
+    )
+}
+
+/// File format of a bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleFormat {
+    Toml,
+    Json,
+}
+
+impl BundleFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Format by file extension, TOML unless it ends in `.json`
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// A portable thing subtree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: u32,
+    /// Top-level things, each with its children nested inside
+    #[serde(default)]
+    pub things: Vec<BundleThing>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equipment: Vec<BundleEquip>,
+}
+
+/// A thing and everything under it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleThing {
+    /// ID on the exporting server; imports record it as `copied_from`
+    pub id: String,
+    pub kind: ThingKind,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qualified_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_slot: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<BundleThing>,
+}
+
+/// Where an equipment recipe applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EquipContext {
+    Room,
+    Agent,
+}
+
+/// How a bundled thing was equipped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleEquip {
+    /// Bundle ID of the thing
+    pub thing: String,
+    pub context: EquipContext,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+    #[serde(default)]
+    pub priority: f64,
+}
+
+impl Bundle {
+    /// Write the bundle out
+    pub fn render(&self, format: BundleFormat) -> Result<String> {
+        match format {
+            BundleFormat::Toml => toml::to_string_pretty(self).context("failed to write bundle"),
+            BundleFormat::Json => {
+                serde_json::to_string_pretty(self).context("failed to write bundle")
+            }
+        }
+    }
+
+    /// Read a bundle, JSON if it looks like an object and TOML otherwise
+    pub fn parse(text: &str) -> Result<Self> {
+        let bundle: Self = if text.trim_start().starts_with('{') {
+            serde_json::from_str(text).context("invalid JSON bundle")?
+        } else {
+            toml::from_str(text).context("invalid TOML bundle")?
+        };
+        if bundle.version > BUNDLE_VERSION {
+            bail!(
+                "bundle version {} is newer than this server reads ({})",
+                bundle.version,
+                BUNDLE_VERSION
+            );
+        }
+        Ok(bundle)
+    }
+
+    /// How many things the bundle holds, children included
+    pub fn count(&self) -> usize {
+        fn count(things: &[BundleThing]) -> usize {
+            things.iter().map(|t| 1 + count(&t.children)).sum()
+        }
+        count(&self.things)
+    }
+}
+
+/// Where and how to import a bundle
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Parent of the bundle's top-level things
+    pub parent_id: String,
+    pub created_by: Option<String>,
+    /// Namespace for imported qualified names (`fish:net` becomes
+    /// `<namespace>:net`); `None` keeps the bundle's
+    pub namespace: Option<String>,
+    /// Trash things holding a bundled qualified name instead of renaming
+    pub replace: bool,
+    /// Room to apply room recipes in
+    pub equip_room: Option<String>,
+    /// Agent to apply agent recipes for
+    pub equip_agent: Option<String>,
+}
+
+/// What importing a bundle did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub things: usize,
+    /// Imported top-level things, by qualified name or name
+    pub roots: Vec<String>,
+    /// Qualified names that were taken, as "old -> new"
+    pub renamed: Vec<String>,
+    /// Qualified names whose previous holder went to the trash
+    pub replaced: Vec<String>,
+    pub equipped: usize,
+    /// Parts that couldn't be imported, and why
+    pub skipped: Vec<String>,
+}
+
+impl Database {
+    /// Bundle a thing and everything under it
+    ///
+    /// A thing that can't be bundled (an agent's inventory, say), or an ID
+    /// that isn't a thing (a room's), bundles its children instead.
+    /// `with_equipment` adds recipes for how the things are equipped.
+    pub fn export_bundle(&self, root: &str, with_equipment: bool) -> Result<Bundle> {
+        let roots = match self.get_thing(root)? {
+            Some(thing) if thing.is_deleted() => bail!("{} is deleted", root),
+            Some(thing) if is_bundleable(thing.kind) => vec![thing],
+            _ => self
+                .get_thing_children(root)?
+                .into_iter()
+                .filter(|t| is_bundleable(t.kind))
+                .collect(),
+        };
+        if roots.is_empty() {
+            bail!("nothing to bundle in {}", root);
+        }
+
+        let mut equipment = Vec::new();
+        let mut things = Vec::new();
+        for thing in roots {
+            things.push(self.bundle_thing(thing, with_equipment, &mut equipment)?);
+        }
+        Ok(Bundle {
+            version: BUNDLE_VERSION,
+            things,
+            equipment,
+        })
+    }
+
+    fn bundle_thing(
+        &self,
+        thing: Thing,
+        with_equipment: bool,
+        equipment: &mut Vec<BundleEquip>,
+    ) -> Result<BundleThing> {
+        if with_equipment {
+            for eq in self.equip_recipes(&thing.id)? {
+                if !equipment.contains(&eq) {
+                    equipment.push(eq);
+                }
+            }
+        }
+
+        let mut children = Vec::new();
+        for child in self.get_thing_children(&thing.id)? {
+            if is_bundleable(child.kind) {
+                children.push(self.bundle_thing(child, with_equipment, equipment)?);
+            }
+        }
+
+        Ok(BundleThing {
+            id: thing.id,
+            kind: thing.kind,
+            name: thing.name,
+            qualified_name: thing.qualified_name,
+            description: thing.description,
+            content: thing.content,
+            uri: thing.uri,
+            metadata: thing.metadata,
+            code: thing.code,
+            default_slot: thing.default_slot,
+            params: thing.params,
+            children,
+        })
+    }
+
+    /// Distinct ways a thing is equipped in rooms and by agents
+    fn equip_recipes(&self, thing_id: &str) -> Result<Vec<BundleEquip>> {
+        let conn = self.read_conn()?;
+        let mut recipes = Vec::new();
+        for (table, context) in [
+            ("room_equip", EquipContext::Room),
+            ("agent_equip", EquipContext::Agent),
+        ] {
+            let mut stmt = conn.prepare(&format!(
+                r#"SELECT slot, config, priority FROM {}
+                   WHERE thing_id = ?1 AND deleted_at IS NULL
+                   ORDER BY priority, slot"#,
+                table
+            ))?;
+            let rows = stmt
+                .query_map(params![thing_id], |row| {
+                    Ok(BundleEquip {
+                        thing: thing_id.to_string(),
+                        context,
+                        slot: row.get(0)?,
+                        config: row.get(1)?,
+                        priority: row.get::<_, Option<f64>>(2)?.unwrap_or(0.0),
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("failed to read equipment")?;
+            for eq in rows {
+                if !recipes.contains(&eq) {
+                    recipes.push(eq);
+                }
+            }
+        }
+        Ok(recipes)
+    }
+
+    /// Install a bundle's things under `options.parent_id`
+    ///
+    /// Things of kinds that can't be bundled are skipped along with their
+    /// children, and recipes are only applied where the options give a
+    /// room or agent. It all happens in one transaction, so a failed import
+    /// trashes and adds nothing.
+    pub fn import_bundle(&self, bundle: &Bundle, options: &ImportOptions) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut ids = HashMap::new();
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        for thing in &bundle.things {
+            if let Some(imported) = import_thing(
+                &tx,
+                thing,
+                &options.parent_id,
+                options,
+                &mut ids,
+                &mut report,
+            )? {
+                report
+                    .roots
+                    .push(imported.qualified_name.unwrap_or(imported.name));
+            }
+        }
+
+        for eq in &bundle.equipment {
+            let Some(thing_id) = ids.get(&eq.thing) else {
+                report
+                    .skipped
+                    .push(format!("equipment for {}: not in the bundle", eq.thing));
+                continue;
+            };
+            match eq.context {
+                EquipContext::Room => {
+                    let Some(ref room_id) = options.equip_room else {
+                        continue;
+                    };
+                    room_equip_with(
+                        &tx,
+                        room_id,
+                        thing_id,
+                        eq.slot.as_deref(),
+                        eq.config.as_deref(),
+                        eq.priority,
+                    )?;
+                }
+                EquipContext::Agent => {
+                    let Some(ref agent_id) = options.equip_agent else {
+                        continue;
+                    };
+                    agent_equip_with(
+                        &tx,
+                        agent_id,
+                        thing_id,
+                        eq.slot.as_deref(),
+                        eq.config.as_deref(),
+                        eq.priority,
+                    )?;
+                }
+            }
+            report.equipped += 1;
+        }
+        tx.commit()?;
+
+        Ok(report)
+    }
+}
+
+/// Insert one bundled thing and its children on `conn`, returning the new thing
+fn import_thing(
+    conn: &Connection,
+    bundled: &BundleThing,
+    parent_id: &str,
+    options: &ImportOptions,
+    ids: &mut HashMap<String, String>,
+    report: &mut ImportReport,
+) -> Result<Option<Thing>> {
+    if !is_bundleable(bundled.kind) {
+        report.skipped.push(format!(
+            "{}: can't import a {}",
+            bundled.name,
+            bundled.kind.as_str()
+        ));
+        return Ok(None);
+    }
+
+    let mut qualified_name = bundled.qualified_name.clone();
+    if let Some(ref bundled_name) = bundled.qualified_name {
+        let mut qualified = match options.namespace {
+            Some(ref namespace) => {
+                let local = bundled_name
+                    .split_once(':')
+                    .map_or(bundled_name.as_str(), |(_, local)| local);
+                format!("{}:{}", namespace, local)
+            }
+            None => bundled_name.clone(),
+        };
+        if let Some(existing) = get_thing_by_qualified_name_with(conn, &qualified)? {
+            if options.replace {
+                match options.created_by {
+                    Some(ref by) => trash_thing_with(conn, &existing.id, by)?,
+                    None => soft_delete_thing_with(conn, &existing.id)?,
+                }
+                report.replaced.push(qualified.clone());
+            } else {
+                qualified = free_qualified_name_with(conn, &qualified)?;
+            }
+        }
+        if qualified != *bundled_name {
+            report
+                .renamed
+                .push(format!("{} -> {}", bundled_name, qualified));
+        }
+        qualified_name = Some(qualified);
+    }
+
+    let mut thing = Thing::new(bundled.name.clone(), bundled.kind).with_parent(parent_id);
+    thing.qualified_name = qualified_name;
+    thing.description = bundled.description.clone();
+    thing.content = bundled.content.clone();
+    thing.uri = bundled.uri.clone();
+    thing.metadata = bundled.metadata.clone();
+    thing.code = bundled.code.clone();
+    thing.default_slot = bundled.default_slot.clone();
+    thing.params = bundled.params.clone();
+    thing.created_by = options.created_by.clone();
+    thing.copied_from = Some(bundled.id.clone());
+    insert_thing_with(conn, &thing)
+        .with_context(|| format!("failed to import {}", bundled.name))?;
+    ids.insert(bundled.id.clone(), thing.id.clone());
+    report.things += 1;
+
+    for child in &bundled.children {
+        import_thing(conn, child, &thing.id, options, ids, report)?;
+    }
+    Ok(Some(thing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::things::ids;
+
+    #[test]
+    fn test_bundle_round_trip() -> Result<()> {
+        let db = Database::in_memory()?;
+        db.bootstrap_world()?;
+        let amy = db.ensure_agent_thing("amy")?;
+        db.create_room("workshop", None)?;
+        let workshop = db.get_room_by_name("workshop")?.unwrap();
+
+        let kit = Thing::container("kit").with_parent(&amy);
+        db.insert_thing(&kit)?;
+        let mut fish = Thing::tool("fish", "amy:fish").with_parent(&kit.id);
+        fish.code = Some("return function(args)\n  return \"><>\"\nend\n".to_string());
+        fish.params = Some(r#"{"type":"object"}"#.to_string());
+        fish.default_slot = Some("command:fish".to_string());
+        db.insert_thing(&fish)?;
+        let note = Thing::data("note", "bring bait").with_parent(&kit.id);
+        db.insert_thing(&note)?;
+        db.room_equip(&workshop.id, &fish.id, Some("command:fish"), None, 1.0)?;
+
+        let bundle = db.export_bundle(&amy, true)?;
+        assert_eq!(bundle.count(), 3);
+        assert_eq!(bundle.things[0].children.len(), 2);
+        assert_eq!(bundle.equipment.len(), 1);
+        for format in [BundleFormat::Toml, BundleFormat::Json] {
+            assert_eq!(Bundle::parse(&bundle.render(format)?)?, bundle);
+        }
+
+        // amy:fish is still here, so the import gets a new name
+        db.create_room("annex", None)?;
+        let annex = db.get_room_by_name("annex")?.unwrap();
+        let report = db.import_bundle(
+            &bundle,
+            &ImportOptions {
+                parent_id: ids::SHARED.to_string(),
+                created_by: Some("bob".to_string()),
+                equip_room: Some(annex.id.clone()),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(report.things, 3);
+        assert_eq!(report.roots, vec!["kit"]);
+        assert_eq!(report.renamed, vec!["amy:fish -> amy:fish-2"]);
+        assert_eq!(report.equipped, 1);
+
+        let copy = db.get_thing_by_qualified_name("amy:fish-2")?.unwrap();
+        assert_ne!(copy.id, fish.id);
+        assert_eq!(copy.copied_from.as_deref(), Some(fish.id.as_str()));
+        assert_eq!(copy.code, fish.code);
+        assert_eq!(copy.created_by.as_deref(), Some("bob"));
+        let kit_copy = db.get_thing(copy.parent_id.as_deref().unwrap())?.unwrap();
+        assert_eq!(kit_copy.parent_id.as_deref(), Some(ids::SHARED));
+        assert_eq!(db.get_thing_children(&kit_copy.id)?.len(), 2);
+        let equipment = db.get_room_equipment(&annex.id, Some("command:fish"))?;
+        assert_eq!(equipment[0].thing.id, copy.id);
+
+        // Replacing trashes the current holder instead
+        let report = db.import_bundle(
+            &bundle,
+            &ImportOptions {
+                parent_id: ids::SHARED.to_string(),
+                created_by: Some("bob".to_string()),
+                replace: true,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(report.replaced, vec!["amy:fish"]);
+        assert_eq!(report.equipped, 0);
+        assert!(db.find_trashed(&fish.id)?.is_some());
+
+        // A namespace moves every qualified name into it
+        let report = db.import_bundle(
+            &bundle,
+            &ImportOptions {
+                parent_id: ids::SHARED.to_string(),
+                created_by: Some("bob".to_string()),
+                namespace: Some("bob".to_string()),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(report.renamed, vec!["amy:fish -> bob:fish"]);
+        assert!(db.get_thing_by_qualified_name("bob:fish")?.is_some());
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@
 use super::things::{Thing, ThingKind};
 use super::{new_id, now_ms, Database};
 use anyhow::{Context, Result};
-use rusqlite::params;
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 
 /// Room equipment - a thing equipped in a room with optional slot
@@ -133,20 +133,97 @@ pub struct AgentEquippedThing {
 // - None     -> return ALL equipment (any slot value)
 // - Some("") -> return only where slot IS NULL (general availability)
 // - Some("hook:wrap") -> return only that specific slot
+// - Some("hotkey:*") -> glob match, for slot families
 
 /// Convert slot filter to SQL condition
 fn slot_filter_sql(filter: Option<&str>) -> (&'static str, Option<&str>) {
     match filter {
-        None => ("1=1", None),                    // No filter, match all
-        Some("") => ("e.slot IS NULL", None),     // Match NULL slots only
+        None => ("1=1", None),                // No filter, match all
+        Some("") => ("e.slot IS NULL", None), // Match NULL slots only
+        Some(slot) if slot.contains('*') => ("e.slot GLOB ?", Some(slot)), // Slot family
         Some(slot) => ("e.slot = ?", Some(slot)), // Match specific slot
     }
 }
 
+/// The same filter for equipment that isn't read from a table (zone equipment)
+fn slot_filter_matches(filter: Option<&str>, slot: Option<&str>) -> bool {
+    match (filter, slot) {
+        (None, _) => true,
+        (Some(""), slot) => slot.is_none(),
+        (Some(_), None) => false,
+        (Some(filter), Some(slot)) if filter.contains('*') => glob_matches(filter, slot),
+        (Some(filter), Some(slot)) => filter == slot,
+    }
+}
+
+/// `*` wildcards, as slot families use them
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, rest)) => text.strip_prefix(head).is_some_and(|tail| {
+            tail.char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(tail.len()))
+                .any(|i| glob_matches(rest, &tail[i..]))
+        }),
+    }
+}
+
 // =============================================================================
 // Room equipment operations
 // =============================================================================
 
+/// Equip a thing in a room on `conn`, e.g. inside a transaction
+pub(super) fn room_equip_with(
+    conn: &Connection,
+    room_id: &str,
+    thing_id: &str,
+    slot: Option<&str>,
+    config: Option<&str>,
+    priority: f64,
+) -> Result<String> {
+    let id = new_id();
+    let now = now_ms();
+
+    conn.execute(
+        r#"INSERT INTO room_equip (id, room_id, thing_id, slot, config, priority, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+           ON CONFLICT(room_id, thing_id, slot) WHERE deleted_at IS NULL
+           DO UPDATE SET
+               config = excluded.config,
+               priority = excluded.priority,
+               deleted_at = NULL"#,
+        params![id, room_id, thing_id, slot, config, priority, now],
+    )
+    .context("failed to room_equip")?;
+    Ok(id)
+}
+
+/// Unequip a thing from a room on `conn` (soft delete)
+pub(super) fn room_unequip_with(
+    conn: &Connection,
+    room_id: &str,
+    thing_id: &str,
+    slot: Option<&str>,
+) -> Result<()> {
+    let now = now_ms();
+
+    if let Some(slot_val) = slot {
+        conn.execute(
+            "UPDATE room_equip SET deleted_at = ?4 WHERE room_id = ?1 AND thing_id = ?2 AND slot = ?3",
+            params![room_id, thing_id, slot_val, now],
+        )
+        .context("failed to room_unequip")?;
+    } else {
+        conn.execute(
+            "UPDATE room_equip SET deleted_at = ?3 WHERE room_id = ?1 AND thing_id = ?2 AND slot IS NULL",
+            params![room_id, thing_id, now],
+        )
+        .context("failed to room_unequip")?;
+    }
+    Ok(())
+}
+
 impl Database {
     /// Insert room equipment
     pub fn insert_room_equip(&self, equip: &RoomEquip) -> Result<()> {
@@ -179,54 +256,43 @@ impl Database {
         priority: f64,
     ) -> Result<String> {
         let conn = self.conn()?;
-        let id = new_id();
-        let now = now_ms();
-
-        conn.execute(
-            r#"INSERT INTO room_equip (id, room_id, thing_id, slot, config, priority, created_at)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-               ON CONFLICT(room_id, thing_id, slot) WHERE deleted_at IS NULL
-               DO UPDATE SET
-                   config = excluded.config,
-                   priority = excluded.priority,
-                   deleted_at = NULL"#,
-            params![id, room_id, thing_id, slot, config, priority, now],
-        )
-        .context("failed to room_equip")?;
-        Ok(id)
+        room_equip_with(&conn, room_id, thing_id, slot, config, priority)
     }
 
     /// Unequip a thing from a room (soft delete)
     pub fn room_unequip(&self, room_id: &str, thing_id: &str, slot: Option<&str>) -> Result<()> {
         let conn = self.conn()?;
-        let now = now_ms();
-
-        if let Some(slot_val) = slot {
-            conn.execute(
-                "UPDATE room_equip SET deleted_at = ?4 WHERE room_id = ?1 AND thing_id = ?2 AND slot = ?3",
-                params![room_id, thing_id, slot_val, now],
-            )
-            .context("failed to room_unequip")?;
-        } else {
-            conn.execute(
-                "UPDATE room_equip SET deleted_at = ?3 WHERE room_id = ?1 AND thing_id = ?2 AND slot IS NULL",
-                params![room_id, thing_id, now],
-            )
-            .context("failed to room_unequip")?;
-        }
-        Ok(())
+        room_unequip_with(&conn, room_id, thing_id, slot)
     }
 
     /// Get room equipment with optional slot filter
     /// - None: all equipment
     /// - Some(""): only general availability (slot IS NULL)
     /// - Some("hook:wrap"): only specific slot
+    ///
+    /// Includes what the room inherits from its zone, unless the room
+    /// equips the same thing in the same slot itself.
     pub fn get_room_equipment(
         &self,
         room_id: &str,
         slot_filter: Option<&str>,
     ) -> Result<Vec<RoomEquippedThing>> {
-        let conn = self.conn()?;
+        let mut equipment = self.get_room_own_equipment(room_id, slot_filter)?;
+        self.inherit_zone_equipment(room_id, &mut equipment, |eq| {
+            slot_filter_matches(slot_filter, eq.slot.as_deref())
+        })?;
+        Ok(equipment)
+    }
+
+    /// Get the room's own equipment, without what its zone adds
+    ///
+    /// Templates, forks and merges work on this.
+    pub fn get_room_own_equipment(
+        &self,
+        room_id: &str,
+        slot_filter: Option<&str>,
+    ) -> Result<Vec<RoomEquippedThing>> {
+        let conn = self.read_conn()?;
 
         // Build query based on filter
         let (slot_condition, slot_param) = slot_filter_sql(slot_filter);
@@ -260,8 +326,9 @@ impl Database {
 
     /// Get room equipment for available tools only (general availability)
     pub fn get_room_equipment_tools(&self, room_id: &str) -> Result<Vec<RoomEquippedThing>> {
-        let conn = self.conn()?;
-        let mut stmt = conn.prepare(
+        let mut equipment = {
+            let conn = self.read_conn()?;
+            let mut stmt = conn.prepare(
             r#"SELECT e.id, e.room_id, e.slot, e.config, e.priority,
                       t.id, t.parent_id, t.kind, t.name, t.qualified_name, t.description,
                       t.content, t.uri, t.metadata, t.code, t.default_slot, t.params,
@@ -275,17 +342,52 @@ impl Database {
                  AND t.kind = 'tool'
                  AND t.available = 1
                ORDER BY e.priority, t.name"#,
-        )?;
+            )?;
 
-        let rows = stmt.query_map(params![room_id], Self::room_equipped_from_row)?;
-        rows.collect::<Result<Vec<_>, _>>()
-            .context("failed to get room equipment tools")
+            let rows = stmt.query_map(params![room_id], Self::room_equipped_from_row)?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .context("failed to get room equipment tools")?
+        };
+        self.inherit_zone_equipment(room_id, &mut equipment, |eq| {
+            eq.slot.is_none() && eq.thing.kind == ThingKind::Tool && eq.thing.available
+        })?;
+        Ok(equipment)
+    }
+
+    /// Add the zone equipment that passes `keep` and isn't already in
+    /// `equipment` under the same slot
+    fn inherit_zone_equipment(
+        &self,
+        room_id: &str,
+        equipment: &mut Vec<RoomEquippedThing>,
+        keep: impl Fn(&RoomEquippedThing) -> bool,
+    ) -> Result<()> {
+        let inherited: Vec<_> = self
+            .zone_equipment(room_id)?
+            .into_iter()
+            .filter(|z| {
+                keep(z)
+                    && !equipment
+                        .iter()
+                        .any(|e| e.thing.id == z.thing.id && e.slot == z.slot)
+            })
+            .collect();
+        if inherited.is_empty() {
+            return Ok(());
+        }
+        equipment.extend(inherited);
+        equipment.sort_by(|a, b| {
+            a.priority
+                .total_cmp(&b.priority)
+                .then_with(|| a.thing.name.cmp(&b.thing.name))
+        });
+        Ok(())
     }
 
     /// Copy room equipment from one room to another
     pub fn copy_room_equipment(&self, from_room_id: &str, to_room_id: &str) -> Result<()> {
         // Get existing equipment and insert copies for new room
-        let existing = self.get_room_equipment(from_room_id, None)?;
+        let existing = self.get_room_own_equipment(from_room_id, None)?;
         for eq in existing {
             self.room_equip(
                 to_room_id,
@@ -342,6 +444,32 @@ impl Database {
 // Agent equipment operations
 // =============================================================================
 
+/// Equip a thing on an agent on `conn`, e.g. inside a transaction
+pub(super) fn agent_equip_with(
+    conn: &Connection,
+    agent_id: &str,
+    thing_id: &str,
+    slot: Option<&str>,
+    config: Option<&str>,
+    priority: f64,
+) -> Result<String> {
+    let id = new_id();
+    let now = now_ms();
+
+    conn.execute(
+        r#"INSERT INTO agent_equip (id, agent_id, thing_id, slot, config, priority, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+           ON CONFLICT(agent_id, thing_id, slot) WHERE deleted_at IS NULL
+           DO UPDATE SET
+               config = excluded.config,
+               priority = excluded.priority,
+               deleted_at = NULL"#,
+        params![id, agent_id, thing_id, slot, config, priority, now],
+    )
+    .context("failed to agent_equip")?;
+    Ok(id)
+}
+
 impl Database {
     /// Insert agent equipment
     pub fn insert_agent_equip(&self, equip: &AgentEquip) -> Result<()> {
@@ -374,21 +502,7 @@ impl Database {
         priority: f64,
     ) -> Result<String> {
         let conn = self.conn()?;
-        let id = new_id();
-        let now = now_ms();
-
-        conn.execute(
-            r#"INSERT INTO agent_equip (id, agent_id, thing_id, slot, config, priority, created_at)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-               ON CONFLICT(agent_id, thing_id, slot) WHERE deleted_at IS NULL
-               DO UPDATE SET
-                   config = excluded.config,
-                   priority = excluded.priority,
-                   deleted_at = NULL"#,
-            params![id, agent_id, thing_id, slot, config, priority, now],
-        )
-        .context("failed to agent_equip")?;
-        Ok(id)
+        agent_equip_with(&conn, agent_id, thing_id, slot, config, priority)
     }
 
     /// Unequip a thing from an agent (soft delete)
@@ -418,7 +532,7 @@ impl Database {
         agent_id: &str,
         slot_filter: Option<&str>,
     ) -> Result<Vec<AgentEquippedThing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
 
         let (slot_condition, slot_param) = slot_filter_sql(slot_filter);
 
@@ -722,6 +836,13 @@ mod tests {
         assert_eq!(bg_hooks[0].thing.name, "ticker");
         assert!(bg_hooks[0].config.as_ref().unwrap().contains("interval_ms"));
 
+        // Glob filter: every hook slot, but not commands or NULL slots
+        let hooks = db.get_room_equipment(&room.id, Some("hook:*"))?;
+        assert_eq!(hooks.len(), 2);
+        assert!(hooks
+            .iter()
+            .all(|e| e.slot.as_deref().unwrap().starts_with("hook:")));
+
         Ok(())
     }
 }
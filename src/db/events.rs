@@ -0,0 +1,311 @@
+//! Row and room change notifications
+//!
+//! Every row write publishes a `RowEvent` on a broadcast channel. Sessions
+//! subscribe and redraw only when a row in the buffer they're watching has
+//! changed, instead of re-querying on a timer.
+//!
+//! Room writes (the rooms table and room_kv) publish a `RoomEvent` the same
+//! way, which is how `World` knows to drop cached rooms.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+use super::Database;
+
+/// How many events a slow subscriber can fall behind before it lags
+pub const ROW_EVENT_CAPACITY: usize = 1024;
+
+/// Room events are rare; a lagging subscriber just reloads everything
+pub const ROOM_EVENT_CAPACITY: usize = 256;
+
+/// What happened to a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowEventKind {
+    /// A new row was inserted
+    Appended,
+    /// Row fields were rewritten
+    Updated,
+    /// Streamed text was added to a mutable row
+    Streamed,
+    /// A streaming row was marked complete
+    Finalized,
+    /// The row was deleted
+    Deleted,
+}
+
+impl RowEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RowEventKind::Appended => "appended",
+            RowEventKind::Updated => "updated",
+            RowEventKind::Streamed => "streamed",
+            RowEventKind::Finalized => "finalized",
+            RowEventKind::Deleted => "deleted",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "appended" => Some(RowEventKind::Appended),
+            "updated" => Some(RowEventKind::Updated),
+            "streamed" => Some(RowEventKind::Streamed),
+            "finalized" => Some(RowEventKind::Finalized),
+            "deleted" => Some(RowEventKind::Deleted),
+            _ => None,
+        }
+    }
+}
+
+/// A change to a row
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowEvent {
+    pub buffer_id: String,
+    pub row_id: String,
+    pub kind: RowEventKind,
+}
+
+/// What happened to a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomEventKind {
+    Created,
+    /// Metadata in room_kv changed (vibe, exits, locks, ...)
+    Changed,
+    /// The room was deleted, along with exits into it from other rooms
+    Deleted,
+}
+
+/// A change to a room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomEvent {
+    pub room_id: String,
+    pub kind: RoomEventKind,
+}
+
+/// Broadcast channel plus a buffer lookup for rows that are still streaming
+pub(crate) struct RowEvents {
+    tx: broadcast::Sender<RowEvent>,
+    /// Buffer IDs of `thinking.stream` rows still being written, so
+    /// per-chunk events skip a query
+    streaming: Mutex<HashMap<String, String>>,
+}
+
+impl Default for RowEvents {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(ROW_EVENT_CAPACITY);
+        Self {
+            tx,
+            streaming: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Database {
+    /// Subscribe to room changes
+    ///
+    /// A receiver that lags should treat every room as changed.
+    pub fn subscribe_rooms(&self) -> broadcast::Receiver<RoomEvent> {
+        self.room_events.subscribe()
+    }
+
+    /// Publish a room event (no-op when nobody is subscribed)
+    pub(crate) fn emit_room_event(&self, room_id: &str, kind: RoomEventKind) {
+        if self.room_events.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.room_events.send(RoomEvent {
+            room_id: room_id.to_string(),
+            kind,
+        });
+    }
+
+    /// Subscribe to row changes across all buffers
+    ///
+    /// Receivers filter by `buffer_id` themselves. A receiver that falls more
+    /// than `ROW_EVENT_CAPACITY` events behind gets `RecvError::Lagged` and
+    /// should treat everything as changed.
+    pub fn subscribe_rows(&self) -> broadcast::Receiver<RowEvent> {
+        self.row_events.tx.subscribe()
+    }
+
+    /// Publish a row event (no-op when nobody is subscribed)
+    pub(crate) fn emit_row_event(&self, buffer_id: &str, row_id: &str, kind: RowEventKind) {
+        if self.row_events.tx.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.row_events.tx.send(RowEvent {
+            buffer_id: buffer_id.to_string(),
+            row_id: row_id.to_string(),
+            kind,
+        });
+    }
+
+    /// Publish a row event when only the row ID is known
+    ///
+    /// Must not be called while holding the writer connection: in-memory
+    /// databases read through the writer.
+    pub(crate) fn emit_row_event_by_id(&self, row_id: &str, kind: RowEventKind) {
+        if self.row_events.tx.receiver_count() == 0 {
+            return;
+        }
+        let Some(buffer_id) = self.row_buffer_id(row_id) else {
+            return;
+        };
+        self.emit_row_event(&buffer_id, row_id, kind);
+    }
+
+    /// Remember which buffer a streaming row lives in
+    pub(crate) fn track_streaming_row(&self, row_id: &str, buffer_id: &str) {
+        if let Ok(mut streaming) = self.row_events.streaming.lock() {
+            streaming.insert(row_id.to_string(), buffer_id.to_string());
+        }
+    }
+
+    /// Streaming rows as (row ID, buffer ID)
+    pub(crate) fn streaming_rows(&self) -> Vec<(String, String)> {
+        self.row_events
+            .streaming
+            .lock()
+            .map(|s| s.iter().map(|(r, b)| (r.clone(), b.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    /// Forget a row once it can no longer stream
+    pub(crate) fn untrack_streaming_row(&self, row_id: &str) -> Option<String> {
+        self.row_events.streaming.lock().ok()?.remove(row_id)
+    }
+
+    fn row_buffer_id(&self, row_id: &str) -> Option<String> {
+        let cached = self
+            .row_events
+            .streaming
+            .lock()
+            .ok()
+            .and_then(|s| s.get(row_id).cloned());
+        if cached.is_some() {
+            return cached;
+        }
+
+        let conn = self.read_conn().ok()?;
+        conn.query_row(
+            "SELECT buffer_id FROM rows WHERE id = ?1",
+            [row_id],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::agents::{Agent, AgentKind};
+    use crate::db::buffers::Buffer;
+    use crate::db::rooms::Room;
+    use crate::db::rows::Row;
+    use anyhow::Result;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    fn setup() -> Result<(Database, Buffer, Agent)> {
+        let db = Database::in_memory()?;
+        let room = Room::new("events");
+        db.insert_room(&room)?;
+        let buffer = Buffer::room_chat(&room.id);
+        db.insert_buffer(&buffer)?;
+        let agent = Agent::new("qwen", AgentKind::Model);
+        db.insert_agent(&agent)?;
+        Ok((db, buffer, agent))
+    }
+
+    fn drain(rx: &mut broadcast::Receiver<RowEvent>) -> Vec<RowEventKind> {
+        let mut kinds = Vec::new();
+        loop {
+            match rx.try_recv() {
+                Ok(event) => kinds.push(event.kind),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+                Err(TryRecvError::Lagged(_)) => continue,
+            }
+        }
+        kinds
+    }
+
+    #[test]
+    fn test_row_lifecycle_events() -> Result<()> {
+        let (db, buffer, agent) = setup()?;
+        let mut rx = db.subscribe_rows();
+
+        let mut row = Row::thinking(&buffer.id, &agent.id);
+        db.append_row(&mut row)?;
+        db.append_to_row(&row.id, "hello")?;
+        db.buffer_append(&row.id, " world")?;
+        db.finalize_row(&row.id)?;
+        db.set_row_ephemeral(&row.id, true)?;
+        db.delete_row(&row.id)?;
+
+        assert_eq!(
+            drain(&mut rx),
+            vec![
+                RowEventKind::Appended,
+                RowEventKind::Streamed,
+                RowEventKind::Streamed,
+                RowEventKind::Finalized,
+                RowEventKind::Updated,
+                RowEventKind::Deleted,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_carry_buffer_id() -> Result<()> {
+        let (db, buffer, agent) = setup()?;
+        let mut rx = db.subscribe_rows();
+
+        let mut row = Row::message(&buffer.id, &agent.id, "hi", false);
+        db.append_row(&mut row)?;
+        let event = rx.try_recv()?;
+        assert_eq!(event.buffer_id, buffer.id);
+        assert_eq!(event.row_id, row.id);
+
+        row.content = Some("edited".to_string());
+        db.update_row(&row)?;
+        let event = rx.try_recv()?;
+        assert_eq!(event.kind, RowEventKind::Updated);
+        assert_eq!(event.buffer_id, buffer.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_streams_are_tracked() -> Result<()> {
+        let (db, buffer, agent) = setup()?;
+        let tracked = |db: &Database| db.row_events.streaming.lock().unwrap().len();
+
+        let mut call = Row::tool_call(&buffer.id, &agent.id, "sshwarma:look", None::<String>);
+        db.append_row(&mut call)?;
+        assert_eq!(tracked(&db), 0);
+
+        let mut row = Row::thinking(&buffer.id, &agent.id);
+        db.append_row(&mut row)?;
+        assert_eq!(tracked(&db), 1);
+        db.finalize_row(&row.id)?;
+        assert_eq!(tracked(&db), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_kind_roundtrip() {
+        for kind in [
+            RowEventKind::Appended,
+            RowEventKind::Updated,
+            RowEventKind::Streamed,
+            RowEventKind::Finalized,
+            RowEventKind::Deleted,
+        ] {
+            assert_eq!(RowEventKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(RowEventKind::parse("bogus"), None);
+    }
+}
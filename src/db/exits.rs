@@ -94,7 +94,7 @@ impl Database {
 
     /// Get all exits from a room (with target room data)
     pub fn get_exits_from(&self, from_thing_id: &str) -> Result<Vec<ExitWithTarget>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT e.direction,
                       t.id, t.parent_id, t.kind, t.name, t.qualified_name, t.description,
@@ -141,7 +141,7 @@ impl Database {
 
     /// Get exit by direction
     pub fn get_exit(&self, from_thing_id: &str, direction: &str) -> Result<Option<Exit>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT from_thing_id, direction, to_thing_id, created_at, deleted_at
                FROM exits
@@ -0,0 +1,147 @@
+//! Per-agent input line history
+//!
+//! Every line an agent submits is kept once: entering it again bumps its
+//! `used_at` instead of adding a duplicate. Each agent keeps at most
+//! `INPUT_HISTORY_LIMIT` lines, oldest dropped first. Which lines are worth
+//! keeping is the UI's call; nothing here filters content.
+
+use anyhow::{Context, Result};
+use rusqlite::params;
+
+use super::{now_ms, Database};
+
+/// Most lines kept per agent
+pub const INPUT_HISTORY_LIMIT: usize = 500;
+
+impl Database {
+    /// Record a submitted line, moving it to the most recent position
+    pub fn record_input(&self, agent_id: &str, line: &str) -> Result<()> {
+        let conn = self.conn()?;
+        // used_at stays strictly increasing per agent so lines entered in
+        // the same millisecond still have an order
+        conn.execute(
+            r#"
+            INSERT INTO input_history (agent_id, line, use_count, used_at)
+            VALUES (?1, ?2, 1, MAX(?3, COALESCE(
+                (SELECT MAX(used_at) FROM input_history WHERE agent_id = ?1), 0
+            ) + 1))
+            ON CONFLICT(agent_id, line) DO UPDATE SET
+                use_count = use_count + 1,
+                used_at = excluded.used_at
+            "#,
+            params![agent_id, line, now_ms()],
+        )
+        .context("failed to record input")?;
+
+        conn.execute(
+            r#"
+            DELETE FROM input_history
+            WHERE agent_id = ?1 AND rowid NOT IN (
+                SELECT rowid FROM input_history
+                WHERE agent_id = ?1
+                ORDER BY used_at DESC
+                LIMIT ?2
+            )
+            "#,
+            params![agent_id, INPUT_HISTORY_LIMIT as i64],
+        )
+        .context("failed to trim input history")?;
+        Ok(())
+    }
+
+    /// Most recent lines for an agent, oldest first
+    pub fn list_input_history(&self, agent_id: &str, limit: usize) -> Result<Vec<String>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT line FROM (
+                SELECT line, used_at FROM input_history
+                WHERE agent_id = ?1
+                ORDER BY used_at DESC
+                LIMIT ?2
+            ) ORDER BY used_at ASC
+            "#,
+        )?;
+        let lines = stmt
+            .query_map(params![agent_id, limit as i64], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .context("failed to list input history")?;
+        Ok(lines)
+    }
+
+    /// Forget an agent's input history, returning how many lines were removed
+    pub fn clear_input_history(&self, agent_id: &str) -> Result<usize> {
+        let conn = self.conn()?;
+        let count = conn
+            .execute(
+                "DELETE FROM input_history WHERE agent_id = ?1",
+                params![agent_id],
+            )
+            .context("failed to clear input history")?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::agents::{Agent, AgentKind};
+
+    fn setup() -> Result<(Database, Agent)> {
+        let db = Database::in_memory()?;
+        let agent = Agent::new("alice", AgentKind::Human);
+        db.insert_agent(&agent)?;
+        Ok((db, agent))
+    }
+
+    #[test]
+    fn test_repeats_move_to_end() -> Result<()> {
+        let (db, agent) = setup()?;
+
+        db.record_input(&agent.id, "/look")?;
+        db.record_input(&agent.id, "hello")?;
+        db.record_input(&agent.id, "/look")?;
+
+        let lines = db.list_input_history(&agent.id, 10)?;
+        assert_eq!(lines, vec!["hello", "/look"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_is_capped() -> Result<()> {
+        let (db, agent) = setup()?;
+
+        for i in 0..INPUT_HISTORY_LIMIT + 5 {
+            db.record_input(&agent.id, &format!("line {}", i))?;
+        }
+
+        let lines = db.list_input_history(&agent.id, INPUT_HISTORY_LIMIT * 2)?;
+        assert_eq!(lines.len(), INPUT_HISTORY_LIMIT);
+        assert_eq!(lines[0], "line 5");
+        assert_eq!(
+            lines.last().map(String::as_str),
+            Some(format!("line {}", INPUT_HISTORY_LIMIT + 4).as_str())
+        );
+
+        // The limit argument keeps the newest lines
+        let recent = db.list_input_history(&agent.id, 2)?;
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1], lines[lines.len() - 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_is_per_agent() -> Result<()> {
+        let (db, alice) = setup()?;
+        let bob = Agent::new("bob", AgentKind::Human);
+        db.insert_agent(&bob)?;
+
+        db.record_input(&alice.id, "mine")?;
+        db.record_input(&bob.id, "theirs")?;
+
+        assert_eq!(db.clear_input_history(&alice.id)?, 1);
+        assert!(db.list_input_history(&alice.id, 10)?.is_empty());
+        assert_eq!(db.list_input_history(&bob.id, 10)?, vec!["theirs"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+//! Exit locks
+//!
+//! An exit can carry a rule, stored in the source room's room_kv as
+//! `lock.<direction>`, that decides who may take it:
+//!
+//! - `closed` - nobody
+//! - `members` - the target room's owner and the names in its `members` key
+//! - `capability:<name>` - agents with that capability
+//! - `check:<thing>` - a Lua predicate thing (see `lua::predicate`)
+//!
+//! Admins can take any exit. `ops::exit_denial` applies the rules.
+
+use super::Database;
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// room_kv key prefix for exit locks
+pub const LOCK_PREFIX: &str = "lock.";
+
+/// A rule guarding an exit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitLock {
+    Closed,
+    Members,
+    Capability(String),
+    /// Qualified name of a thing whose code is the predicate
+    Predicate(String),
+}
+
+impl ExitLock {
+    /// Parse a rule as stored (or typed after `/lock <direction>`)
+    pub fn parse(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        let lock = match rule.split_once(':') {
+            None if rule == "closed" => Self::Closed,
+            None if rule == "members" => Self::Members,
+            Some(("capability", cap)) if !cap.trim().is_empty() => {
+                Self::Capability(cap.trim().to_string())
+            }
+            Some(("check", thing)) if !thing.trim().is_empty() => {
+                Self::Predicate(thing.trim().to_string())
+            }
+            _ => bail!(
+                "unknown lock '{}' (closed, members, capability:<name> or check:<thing>)",
+                rule
+            ),
+        };
+        Ok(lock)
+    }
+
+    /// Short explanation of who gets through, for /exits
+    pub fn hint(&self, target: &str) -> String {
+        match self {
+            Self::Closed => "closed".to_string(),
+            Self::Members => format!("members of {} only", target),
+            Self::Capability(cap) => format!("needs the '{}' capability", cap),
+            Self::Predicate(thing) => format!("checked by {}", thing),
+        }
+    }
+}
+
+impl fmt::Display for ExitLock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "closed"),
+            Self::Members => write!(f, "members"),
+            Self::Capability(cap) => write!(f, "capability:{}", cap),
+            Self::Predicate(thing) => write!(f, "check:{}", thing),
+        }
+    }
+}
+
+impl Database {
+    /// Locked exits of a room, by direction
+    ///
+    /// Rules that no longer parse are logged and left out, so a bad value
+    /// can't strand anyone.
+    pub fn get_exit_locks(&self, room: &str) -> Result<BTreeMap<String, ExitLock>> {
+        let Some(room_obj) = self.get_room_by_name(room)? else {
+            return Ok(BTreeMap::new());
+        };
+        let mut locks = BTreeMap::new();
+        for (key, value) in self.get_all_room_kv(&room_obj.id)? {
+            let Some(direction) = key.strip_prefix(LOCK_PREFIX) else {
+                continue;
+            };
+            match ExitLock::parse(&value) {
+                Ok(lock) => {
+                    locks.insert(direction.to_string(), lock);
+                }
+                Err(e) => tracing::warn!(room, direction, "ignoring exit lock: {:#}", e),
+            }
+        }
+        Ok(locks)
+    }
+
+    /// Lock an exit, or unlock it with None
+    pub fn set_exit_lock(
+        &self,
+        room: &str,
+        direction: &str,
+        lock: Option<&ExitLock>,
+    ) -> Result<()> {
+        let Some(room_obj) = self.get_room_by_name(room)? else {
+            bail!("room '{}' not found", room);
+        };
+        let key = format!("{}{}", LOCK_PREFIX, direction);
+        match lock {
+            Some(lock) => self.set_room_kv(&room_obj.id, &key, Some(&lock.to_string())),
+            None => self.delete_room_kv(&room_obj.id, &key),
+        }
+    }
+
+    /// Names listed as members of a room (not including its owner)
+    pub fn get_room_members(&self, room: &str) -> Result<Vec<String>> {
+        let Some(room_obj) = self.get_room_by_name(room)? else {
+            return Ok(Vec::new());
+        };
+        Ok(self
+            .get_room_kv(&room_obj.id, "members")?
+            .map(|members| {
+                members
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Replace a room's member list
+    pub fn set_room_members(&self, room: &str, members: &[String]) -> Result<()> {
+        let Some(room_obj) = self.get_room_by_name(room)? else {
+            bail!("room '{}' not found", room);
+        };
+        if members.is_empty() {
+            self.delete_room_kv(&room_obj.id, "members")
+        } else {
+            self.set_room_kv(&room_obj.id, "members", Some(&members.join(",")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_lock_round_trip() -> Result<()> {
+        let db = Database::in_memory()?;
+        db.create_room("gate", None)?;
+        db.add_exit("gate", "north", "vault")?;
+
+        for rule in [
+            "closed",
+            "members",
+            "capability:deploy",
+            "check:gate:keycard",
+        ] {
+            let lock = ExitLock::parse(rule)?;
+            assert_eq!(lock.to_string(), rule);
+            db.set_exit_lock("gate", "north", Some(&lock))?;
+            assert_eq!(db.get_exit_locks("gate")?.get("north"), Some(&lock));
+        }
+        assert!(ExitLock::parse("capability:").is_err());
+        assert!(ExitLock::parse("sometimes").is_err());
+
+        db.set_exit_lock("gate", "north", None)?;
+        assert!(db.get_exit_locks("gate")?.is_empty());
+
+        assert!(db
+            .set_room_members("vault", &["alice".to_string()])
+            .is_err());
+        db.create_room("vault", None)?;
+        assert!(db.get_room_members("vault")?.is_empty());
+        db.set_room_members("vault", &["alice".to_string(), "bob".to_string()])?;
+        assert_eq!(db.get_room_members("vault")?, vec!["alice", "bob"]);
+        Ok(())
+    }
+}
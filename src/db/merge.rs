@@ -0,0 +1,547 @@
+//! Merging a forked room back into the room it was forked from
+//!
+//! A merge brings the fork's own chat (not the history it shares) back to
+//! the parent, either as a nested block under a `meta.merge` row or as a
+//! summary row, applies the fork's room_kv and equipment changes, and
+//! tombstones the fork's buffer.
+//!
+//! Reconciliation is three-way using timestamps: the moment of the fork is
+//! the common base. Only keys the fork changed after that carry over; one
+//! the parent changed too is kept as a conflict rather than overwritten.
+
+use super::buffers::{tombstone_buffer_with, TombstoneStatus};
+use super::equipped::{room_equip_with, room_unequip_with, RoomEquippedThing};
+use super::events::{RoomEventKind, RowEventKind};
+use super::rooms::{delete_room_kv_with, set_room_kv_with};
+use super::rows::{
+    insert_row_link_with, insert_row_with, last_buffer_position_with, LinkType, Row,
+};
+use super::Database;
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::params;
+use std::collections::{BTreeMap, HashMap};
+
+/// room_kv keys that describe the room itself rather than its context
+fn is_local_key(key: &str) -> bool {
+    matches!(key, "parent" | "owner" | "archived") || key.starts_with("exit.")
+}
+
+/// What a merge brings back to the parent's chat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMode {
+    /// Every row the fork added, nested under a merge row
+    All,
+    /// Only the fork's pinned rows, nested under a merge row
+    Pinned,
+    /// A single summary row, linked to the fork's last row
+    Summary,
+}
+
+impl MergeMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeMode::All => "all",
+            MergeMode::Pinned => "pinned",
+            MergeMode::Summary => "summary",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "all" => Some(MergeMode::All),
+            "pinned" => Some(MergeMode::Pinned),
+            "summary" => Some(MergeMode::Summary),
+            _ => None,
+        }
+    }
+}
+
+/// A room_kv difference between fork and parent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvChange {
+    pub key: String,
+    /// Parent's current value
+    pub parent: Option<String>,
+    /// Fork's value, None when the fork removed the key
+    pub fork: Option<String>,
+    /// The parent also changed this key since the fork; it is left alone
+    pub conflict: bool,
+}
+
+/// An equipment difference between fork and parent
+#[derive(Debug, Clone)]
+pub struct EquipChange {
+    pub item: RoomEquippedThing,
+    /// true: equipped in the fork, will be equipped in the parent.
+    /// false: unequipped in the fork, will be unequipped in the parent.
+    pub equip: bool,
+}
+
+/// Everything a merge would change, for previewing before applying
+#[derive(Debug, Clone)]
+pub struct MergePlan {
+    pub fork_room: String,
+    pub parent_room: String,
+    pub fork_buffer_id: String,
+    pub parent_buffer_id: String,
+    /// The fork's own top-level rows, oldest first
+    pub rows: Vec<Row>,
+    pub kv: Vec<KvChange>,
+    pub equipment: Vec<EquipChange>,
+}
+
+impl MergePlan {
+    /// Rows a mode would copy into the parent
+    pub fn rows_for(&self, mode: MergeMode) -> Vec<&Row> {
+        self.rows
+            .iter()
+            .filter(|r| !r.ephemeral)
+            .filter(|r| match mode {
+                MergeMode::All => true,
+                MergeMode::Pinned => r.pinned,
+                MergeMode::Summary => r.content_method.starts_with("message."),
+            })
+            .collect()
+    }
+}
+
+/// Result of applying a merge
+#[derive(Debug, Clone)]
+pub struct MergeOutcome {
+    /// The merge row (or summary row) added to the parent's chat
+    pub row_id: String,
+    pub rows_merged: usize,
+    pub kv_applied: usize,
+    pub equipment_applied: usize,
+}
+
+impl Database {
+    /// Work out what merging a fork back into its parent would change
+    pub fn plan_merge(&self, fork: &str) -> Result<MergePlan> {
+        let fork_room = self
+            .get_room_by_name(fork)?
+            .ok_or_else(|| anyhow!("room '{}' not found", fork))?;
+        let parent = self
+            .get_room_kv(&fork_room.id, "parent")?
+            .ok_or_else(|| anyhow!("'{}' isn't a fork", fork))?;
+        let parent_room = self
+            .get_room_by_name(&parent)?
+            .ok_or_else(|| anyhow!("'{}' was forked from '{}', which is gone", fork, parent))?;
+
+        let fork_buffer = self.get_or_create_room_chat_buffer(&fork_room.id)?;
+        if fork_buffer.tombstoned {
+            bail!(
+                "'{}' was already merged ({})",
+                fork,
+                fork_buffer.tombstone_summary.as_deref().unwrap_or("closed")
+            );
+        }
+        let parent_buffer = self.get_or_create_room_chat_buffer(&parent_room.id)?;
+
+        let rows = self
+            .list_buffer_rows(&fork_buffer.id)?
+            .into_iter()
+            .filter(|r| r.buffer_id == fork_buffer.id)
+            .collect();
+
+        // room_kv: fork wins unless the parent changed the key too
+        let fork_kv = self.room_kv_entries(&fork_room.id)?;
+        let parent_kv = self.room_kv_entries(&parent_room.id)?;
+        // Copied keys are stamped while forking; "parent" is written last
+        let forked_at = fork_kv
+            .get("parent")
+            .map(|(_, at)| *at)
+            .unwrap_or(fork_room.created_at);
+        let mut kv = Vec::new();
+        let keys: std::collections::BTreeSet<&String> =
+            fork_kv.keys().chain(parent_kv.keys()).collect();
+        for key in keys {
+            if is_local_key(key) {
+                continue;
+            }
+            let (fork_value, fork_updated) = match fork_kv.get(key) {
+                Some((v, at)) => (v.clone(), *at),
+                None => (None, 0),
+            };
+            let (parent_value, parent_updated) = match parent_kv.get(key) {
+                Some((v, at)) => (v.clone(), *at),
+                None => (None, 0),
+            };
+            if fork_value == parent_value {
+                continue;
+            }
+            // Keys the fork left alone keep whatever the parent has now
+            if fork_updated <= forked_at {
+                continue;
+            }
+            let parent_changed = parent_updated > fork_room.created_at;
+            kv.push(KvChange {
+                key: key.clone(),
+                parent: parent_value,
+                fork: fork_value,
+                conflict: parent_changed,
+            });
+        }
+
+        // Equipment: fork additions carry over; equipment the parent had at
+        // fork time but the fork no longer has was unequipped in the fork
+        let fork_equip = self.get_room_own_equipment(&fork_room.id, None)?;
+        let parent_equip = self.get_room_own_equipment(&parent_room.id, None)?;
+        let parent_equipped_at = self.room_equip_times(&parent_room.id)?;
+        let mut equipment = Vec::new();
+        for item in &fork_equip {
+            if !parent_equip
+                .iter()
+                .any(|p| p.thing.id == item.thing.id && p.slot == item.slot)
+            {
+                equipment.push(EquipChange {
+                    item: item.clone(),
+                    equip: true,
+                });
+            }
+        }
+        for item in &parent_equip {
+            let in_fork = fork_equip
+                .iter()
+                .any(|f| f.thing.id == item.thing.id && f.slot == item.slot);
+            let equipped_at = parent_equipped_at
+                .get(&item.equip_id)
+                .copied()
+                .unwrap_or(i64::MAX);
+            if !in_fork && equipped_at <= fork_room.created_at {
+                equipment.push(EquipChange {
+                    item: item.clone(),
+                    equip: false,
+                });
+            }
+        }
+
+        Ok(MergePlan {
+            fork_room: fork_room.name,
+            parent_room: parent_room.name,
+            fork_buffer_id: fork_buffer.id,
+            parent_buffer_id: parent_buffer.id,
+            rows,
+            kv,
+            equipment,
+        })
+    }
+
+    /// Apply a merge plan
+    ///
+    /// `agent_id` is who merged. Summary mode needs `summary` as the
+    /// (author agent id, text) of the summary row. Everything is written in
+    /// one transaction, so a failed merge leaves both rooms as they were.
+    pub fn apply_merge(
+        &self,
+        plan: &MergePlan,
+        mode: MergeMode,
+        agent_id: &str,
+        summary: Option<(&str, &str)>,
+    ) -> Result<MergeOutcome> {
+        let merged = plan.rows_for(mode);
+        let meta = serde_json::json!({
+            "merged_from": plan.fork_room,
+            "mode": mode.as_str(),
+        })
+        .to_string();
+
+        let parent_room = self
+            .get_room_by_name(&plan.parent_room)?
+            .ok_or_else(|| anyhow!("room '{}' not found", plan.parent_room))?;
+
+        // The merge (or summary) row, then any copies nested under it
+        let mut head = if mode == MergeMode::Summary {
+            let (author, text) = summary.context("summary merge needs a summary")?;
+            Row::message(&plan.parent_buffer_id, author, text, true)
+        } else {
+            let mut header = Row::new(&plan.parent_buffer_id, "meta.merge");
+            header.source_agent_id = Some(agent_id.to_string());
+            header.content = Some(format!(
+                "⎇ merged {} {} from {}",
+                merged.len(),
+                if merged.len() == 1 { "row" } else { "rows" },
+                plan.fork_room
+            ));
+            header
+        };
+        head.content_meta = Some(meta);
+        head.finalize();
+
+        // Copies (and their nested rows) sit between the merge row and
+        // whatever is appended next
+        let mut copies = Vec::new();
+        if mode != MergeMode::Summary {
+            for row in &merged {
+                self.collect_merge_copies(row, &head.id, &mut copies)?;
+            }
+            for copy in copies.iter_mut() {
+                copy.buffer_id = plan.parent_buffer_id.clone();
+            }
+        }
+
+        let applied: Vec<&KvChange> = plan.kv.iter().filter(|c| !c.conflict).collect();
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+
+        // The plan may be stale (summaries take a while): a fork merged in the
+        // meantime must not land twice
+        let summary_text = format!("merged into {} ({})", plan.parent_room, mode.as_str());
+        if !tombstone_buffer_with(
+            &tx,
+            &plan.fork_buffer_id,
+            TombstoneStatus::Success,
+            Some(&summary_text),
+        )? {
+            bail!("{} has already been merged or closed", plan.fork_room);
+        }
+
+        head.position = match last_buffer_position_with(&tx, &plan.parent_buffer_id)? {
+            Some(last) => super::rows::fractional::after(last),
+            None => 0.0,
+        };
+        let step = 1.0 / (copies.len() as f64 + 1.0);
+        for (i, copy) in copies.iter_mut().enumerate() {
+            copy.position = head.position + step * (i as f64 + 1.0);
+        }
+
+        insert_row_with(&tx, &head)?;
+        for copy in &copies {
+            insert_row_with(&tx, copy)?;
+        }
+
+        // Link back to where the fork left off
+        if let Some(tip) = plan.rows.last() {
+            insert_row_link_with(&tx, &head.id, &tip.id, LinkType::Relates)?;
+        }
+
+        for change in &applied {
+            match &change.fork {
+                Some(value) => set_room_kv_with(&tx, &parent_room.id, &change.key, Some(value))?,
+                None => delete_room_kv_with(&tx, &parent_room.id, &change.key)?,
+            }
+        }
+
+        for change in &plan.equipment {
+            let item = &change.item;
+            if change.equip {
+                room_equip_with(
+                    &tx,
+                    &parent_room.id,
+                    &item.thing.id,
+                    item.slot.as_deref(),
+                    item.config.as_deref(),
+                    item.priority,
+                )?;
+            } else {
+                room_unequip_with(&tx, &parent_room.id, &item.thing.id, item.slot.as_deref())?;
+            }
+        }
+
+        tx.commit().context("failed to commit merge")?;
+        drop(conn);
+
+        self.emit_row_event(&head.buffer_id, &head.id, RowEventKind::Appended);
+        for copy in &copies {
+            self.emit_row_event(&copy.buffer_id, &copy.id, RowEventKind::Appended);
+        }
+        if !applied.is_empty() {
+            self.emit_room_event(&parent_room.id, RoomEventKind::Changed);
+        }
+
+        tracing::info!(
+            fork = %plan.fork_room,
+            parent = %plan.parent_room,
+            mode = mode.as_str(),
+            rows = merged.len(),
+            "merged fork"
+        );
+
+        Ok(MergeOutcome {
+            row_id: head.id,
+            rows_merged: merged.len(),
+            kv_applied: applied.len(),
+            equipment_applied: plan.equipment.len(),
+        })
+    }
+
+    /// Copy a row and its nested rows with fresh ids, depth first
+    fn collect_merge_copies(&self, row: &Row, parent_id: &str, out: &mut Vec<Row>) -> Result<()> {
+        let mut copy = row.clone();
+        copy.id = super::new_id();
+        copy.parent_row_id = Some(parent_id.to_string());
+        copy.ephemeral = false;
+        copy.mutable = false;
+        let copy_id = copy.id.clone();
+        out.push(copy);
+        for child in self.list_child_rows(&row.id)? {
+            self.collect_merge_copies(&child, &copy_id, out)?;
+        }
+        Ok(())
+    }
+
+    /// room_kv rows with their last update time, including removed (NULL) values
+    fn room_kv_entries(&self, room_id: &str) -> Result<BTreeMap<String, (Option<String>, i64)>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn
+            .prepare("SELECT key, value, updated_at FROM room_kv WHERE room_id = ?1")
+            .context("failed to prepare room kv query")?;
+        let entries = stmt
+            .query(params![room_id])?
+            .mapped(|row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .context("failed to list room kv")?;
+        Ok(entries)
+    }
+
+    /// When each of a room's current equipment entries was equipped
+    fn room_equip_times(&self, room_id: &str) -> Result<HashMap<String, i64>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, created_at FROM room_equip WHERE room_id = ?1 AND deleted_at IS NULL",
+            )
+            .context("failed to prepare room equip query")?;
+        let times = stmt
+            .query(params![room_id])?
+            .mapped(|row| Ok((row.get(0)?, row.get(1)?)))
+            .collect::<Result<HashMap<_, _>, _>>()
+            .context("failed to list room equipment")?;
+        Ok(times)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::things::Thing;
+
+    fn setup() -> Result<(Database, String)> {
+        let db = Database::in_memory()?;
+        db.create_room("main", None)?;
+        let buffer = db.get_or_create_room_buffer("main")?;
+        let agent = db.get_or_create_human_agent("alice")?;
+        let mut row = Row::message(&buffer.id, &agent.id, "before the fork", false);
+        db.append_row(&mut row)?;
+        Ok((db, agent.id))
+    }
+
+    #[test]
+    fn test_merge_all_nests_fork_rows() -> Result<()> {
+        let (db, agent_id) = setup()?;
+        db.fork_room("main", "idea")?;
+
+        let fork_buffer = db.get_or_create_room_buffer("idea")?;
+        for text in ["try this", "it works"] {
+            let mut row = Row::message(&fork_buffer.id, &agent_id, text, false);
+            db.append_row(&mut row)?;
+        }
+
+        let plan = db.plan_merge("idea")?;
+        assert_eq!(plan.parent_room, "main");
+        assert_eq!(plan.rows.len(), 2, "shared history isn't merged");
+
+        let outcome = db.apply_merge(&plan, MergeMode::All, &agent_id, None)?;
+        assert_eq!(outcome.rows_merged, 2);
+
+        let main = db.get_or_create_room_buffer("main")?;
+        let top: Vec<_> = db
+            .list_buffer_rows(&main.id)?
+            .into_iter()
+            .map(|r| r.content_method)
+            .collect();
+        assert_eq!(top, vec!["message.user", "meta.merge"]);
+
+        let nested: Vec<_> = db
+            .list_child_rows(&outcome.row_id)?
+            .into_iter()
+            .filter_map(|r| r.content)
+            .collect();
+        assert_eq!(nested, vec!["try this", "it works"]);
+
+        // Nested rows show up in the chat, in order
+        let recent: Vec<_> = db
+            .list_recent_buffer_rows(&main.id, 10)?
+            .into_iter()
+            .filter_map(|r| r.content)
+            .collect();
+        assert_eq!(recent.len(), 4);
+        assert_eq!(recent[3], "it works");
+
+        let links = db.get_row_links_from(&outcome.row_id)?;
+        assert_eq!(links[0].link_type, LinkType::Relates);
+
+        let fork = db.get_buffer(&fork_buffer.id)?.unwrap();
+        assert!(fork.tombstoned);
+        assert_eq!(fork.tombstone_status, Some(TombstoneStatus::Success));
+        assert!(db.plan_merge("idea").is_err(), "can't merge twice");
+
+        // A plan made before the merge can't apply it again
+        let err = db
+            .apply_merge(&plan, MergeMode::Pinned, &agent_id, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("already been merged"));
+        assert_eq!(db.list_buffer_rows(&main.id)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_reconciles_kv_and_equipment() -> Result<()> {
+        let (db, agent_id) = setup()?;
+        let main = db.get_room_by_name("main")?.unwrap();
+        db.set_room_kv(&main.id, "vibe", Some("calm"))?;
+        db.set_room_kv(&main.id, "description", Some("the main room"))?;
+        db.set_room_kv(&main.id, "topic", Some("planning"))?;
+        let old_tool = Thing::tool("old", "test:old");
+        db.insert_thing(&old_tool)?;
+        db.room_equip(&main.id, &old_tool.id, None, None, 0.0)?;
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        db.fork_room("main", "idea")?;
+        let idea = db.get_room_by_name("idea")?.unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        db.set_room_kv(&idea.id, "vibe", Some("wild"))?;
+        db.set_room_kv(&idea.id, "description", Some("fork's take"))?;
+        db.set_room_kv(&main.id, "description", Some("edited in main"))?;
+        db.set_room_kv(&main.id, "topic", Some("shipping"))?;
+        db.room_unequip(&idea.id, &old_tool.id, None)?;
+        let new_tool = Thing::tool("new", "test:new");
+        db.insert_thing(&new_tool)?;
+        db.room_equip(&idea.id, &new_tool.id, None, None, 0.0)?;
+
+        let plan = db.plan_merge("idea")?;
+        let vibe = plan.kv.iter().find(|c| c.key == "vibe").unwrap();
+        assert!(!vibe.conflict);
+        let description = plan.kv.iter().find(|c| c.key == "description").unwrap();
+        assert!(description.conflict);
+        assert!(plan.kv.iter().all(|c| c.key != "parent"));
+        // Only main changed the topic: not a fork change, not a conflict
+        assert!(plan.kv.iter().all(|c| c.key != "topic"));
+        assert_eq!(plan.equipment.len(), 2);
+
+        let outcome = db.apply_merge(&plan, MergeMode::Pinned, &agent_id, None)?;
+        assert_eq!(outcome.rows_merged, 0);
+        assert_eq!(outcome.kv_applied, 1);
+
+        assert_eq!(db.get_room_kv(&main.id, "vibe")?.as_deref(), Some("wild"));
+        assert_eq!(
+            db.get_room_kv(&main.id, "description")?.as_deref(),
+            Some("edited in main")
+        );
+        assert_eq!(
+            db.get_room_kv(&main.id, "topic")?.as_deref(),
+            Some("shipping")
+        );
+        let equipped: Vec<_> = db
+            .get_room_equipment(&main.id, None)?
+            .into_iter()
+            .map(|e| e.thing.name)
+            .collect();
+        assert_eq!(equipped, vec!["new"]);
+
+        Ok(())
+    }
+}
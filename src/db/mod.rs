@@ -2,24 +2,41 @@
 //!
 //! Provides persistence for agents, rooms, buffers, rows, and UI state.
 //! Uses SQLite with UUIDv7 for primary keys and fractional indexing for ordering.
+//!
+//! File-backed databases run in WAL mode with one writer connection and a
+//! small pool of read-only connections, so render-path queries don't queue
+//! behind streaming writes. In-memory databases use the writer for everything.
 
 mod schema;
 
 pub mod agents;
+pub mod backup;
 pub mod buffers;
+pub mod bundles;
 pub mod equipped;
+pub mod events;
 pub mod exits;
+pub mod input_history;
+pub mod locks;
+pub mod merge;
+pub mod recovery;
 pub mod rooms;
 pub mod rows;
 pub mod scripts;
+pub mod templates;
 pub mod things;
+pub mod trash;
 pub mod view;
+pub mod write_behind;
+pub mod zones;
 
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
 use std::path::Path;
-use std::sync::{Mutex, MutexGuard};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 pub use schema::{PRESENCE_QUERY, ROW_DEPTH_CTE, SCHEMA, SCHEMA_VERSION};
@@ -47,9 +64,23 @@ fn format_timestamp(ms: i64) -> String {
         .unwrap_or_else(|| format!("{}", ms))
 }
 
+/// Number of read-only connections opened for file-backed databases
+const READ_POOL_SIZE: usize = 4;
+
+/// How long a connection waits on a locked database before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Database handle (thread-safe via Mutex)
+///
+/// Writes go through a single connection. Reads that don't need to see
+/// uncommitted state can use `read_conn()` to take a pooled reader instead.
 pub struct Database {
     conn: Mutex<Connection>,
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
+    pending_appends: write_behind::PendingAppends,
+    row_events: events::RowEvents,
+    room_events: broadcast::Sender<events::RoomEvent>,
 }
 
 impl Database {
@@ -60,14 +91,62 @@ impl Database {
             .map_err(|e| anyhow::anyhow!("database lock poisoned: {}", e))
     }
 
+    /// Acquire a connection for read-only queries
+    ///
+    /// Prefers an idle pooled reader, falls back to waiting on one in
+    /// round-robin order. Without a pool (in-memory), returns the writer.
+    pub(crate) fn read_conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        if self.readers.is_empty() {
+            return self.conn();
+        }
+
+        for reader in &self.readers {
+            if let Ok(guard) = reader.try_lock() {
+                return Ok(guard);
+            }
+        }
+
+        let idx = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[idx]
+            .lock()
+            .map_err(|e| anyhow::anyhow!("database reader lock poisoned: {}", e))
+    }
+
     /// Open or create database at path
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
-        let conn = Connection::open(path.as_ref())
-            .with_context(|| format!("failed to open database at {:?}", path.as_ref()))?;
-        let db = Self {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open database at {:?}", path))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .context("failed to set busy timeout")?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+            .context("failed to enable WAL mode")?;
+
+        let mut db = Self {
             conn: Mutex::new(conn),
+            readers: Vec::new(),
+            next_reader: AtomicUsize::new(0),
+            pending_appends: Default::default(),
+            row_events: Default::default(),
+            room_events: broadcast::channel(events::ROOM_EVENT_CAPACITY).0,
         };
         db.init()?;
+
+        // Readers are opened after init so they see the finished schema
+        for _ in 0..READ_POOL_SIZE {
+            let reader = Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY
+                    | OpenFlags::SQLITE_OPEN_NO_MUTEX
+                    | OpenFlags::SQLITE_OPEN_URI,
+            )
+            .with_context(|| format!("failed to open read connection to {:?}", path))?;
+            reader
+                .busy_timeout(BUSY_TIMEOUT)
+                .context("failed to set busy timeout")?;
+            db.readers.push(Mutex::new(reader));
+        }
+
         Ok(db)
     }
 
@@ -76,11 +155,31 @@ impl Database {
         let conn = Connection::open_in_memory().context("failed to open in-memory database")?;
         let db = Self {
             conn: Mutex::new(conn),
+            readers: Vec::new(),
+            next_reader: AtomicUsize::new(0),
+            pending_appends: Default::default(),
+            row_events: Default::default(),
+            room_events: broadcast::channel(events::ROOM_EVENT_CAPACITY).0,
         };
         db.init()?;
         Ok(db)
     }
 
+    /// Run blocking database work on tokio's blocking pool
+    ///
+    /// Async callers should use this rather than calling `Database` methods
+    /// directly, so SQLite I/O and lock waits don't stall executor threads.
+    pub async fn blocking<T, F>(self: &Arc<Self>, f: F) -> Result<T>
+    where
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = Arc::clone(self);
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .context("database task panicked")?
+    }
+
     /// Initialize schema and run migrations
     fn init(&self) -> Result<()> {
         let version = self.get_schema_version()?;
@@ -90,6 +189,7 @@ impl Database {
             self.conn()?
                 .execute_batch(SCHEMA)
                 .context("failed to create schema")?;
+            self.add_missing_columns()?;
             self.set_schema_version(SCHEMA_VERSION)?;
             tracing::info!("initialized database schema version {}", SCHEMA_VERSION);
         }
@@ -97,6 +197,34 @@ impl Database {
         Ok(())
     }
 
+    /// Add columns that `CREATE TABLE IF NOT EXISTS` won't add to existing tables
+    fn add_missing_columns(&self) -> Result<()> {
+        const COLUMNS: &[(&str, &str, &str)] = &[
+            ("buffers", "fork_row_id", "TEXT"),
+            ("things", "deleted_by", "TEXT"),
+        ];
+
+        let conn = self.conn()?;
+        for (table, column, decl) in COLUMNS {
+            let exists: bool = conn
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+                    rusqlite::params![table, column],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("failed to inspect table {}", table))?;
+            if !exists {
+                conn.execute(
+                    &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
+                    [],
+                )
+                .with_context(|| format!("failed to add {}.{}", table, column))?;
+                tracing::info!("added column {}.{}", table, column);
+            }
+        }
+        Ok(())
+    }
+
     /// Get current schema version from user_version pragma
     fn get_schema_version(&self) -> Result<i32> {
         let conn = self.conn()?;
@@ -506,15 +634,34 @@ impl Database {
     }
 
     /// Fork a room - create new room with copied KV and equipment
+    ///
+    /// The fork's chat shares all of the source's history so far.
     pub fn fork_room(&self, source: &str, new_name: &str) -> Result<()> {
+        self.fork_room_at(source, new_name, None)
+    }
+
+    /// Fork a room at a row - the fork's chat shares the source's history
+    /// up to and including that row (see `Database::resolve_row_ref`)
+    /// without copying it, then continues on its own
+    pub fn fork_room_at(&self, source: &str, new_name: &str, at_row: Option<&str>) -> Result<()> {
         if let Some(source_room) = self.get_room_by_name(source)? {
+            // Find the fork point before creating anything
+            let source_buffer = self.get_or_create_room_chat_buffer(&source_room.id)?;
+            let fork_row = match at_row {
+                Some(reference) => Some(self.resolve_row_ref(&source_buffer.id, reference)?),
+                None => self.last_shown_row(&source_buffer.id)?,
+            };
+
             // Create new room
             let new_room = rooms::Room::new(new_name);
             self.insert_room(&new_room)?;
 
-            // Copy all KV pairs from source
+            // Copy all KV pairs from source, except what belongs to the source itself
             let source_kv = self.get_all_room_kv(&source_room.id)?;
             for (key, value) in source_kv {
+                if key == "archived" || key == "owner" {
+                    continue;
+                }
                 self.set_room_kv(&new_room.id, &key, Some(&value))?;
             }
 
@@ -523,6 +670,15 @@ impl Database {
 
             // Copy room equipment (tools, hooks, commands)
             self.copy_room_equipment(&source_room.id, &new_room.id)?;
+
+            // Chat picks up where the fork point left off. The row may live
+            // in one of the source's own ancestors.
+            let mut buffer = buffers::Buffer::room_chat(&new_room.id);
+            if let Some(row) = fork_row {
+                buffer.parent_buffer_id = Some(row.buffer_id);
+                buffer.fork_row_id = Some(row.id);
+            }
+            self.insert_buffer(&buffer)?;
         }
         Ok(())
     }
@@ -710,6 +866,77 @@ impl Database {
         Ok(())
     }
 
+    /// Who created a room; None for rooms that predate ownership
+    pub fn get_room_owner(&self, room: &str) -> Result<Option<String>> {
+        match self.get_room_by_name(room)? {
+            Some(room_obj) => self.get_room_kv(&room_obj.id, "owner"),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_room_owner(&self, room: &str, owner: &str) -> Result<()> {
+        if let Some(room_obj) = self.get_room_by_name(room)? {
+            self.set_room_kv(&room_obj.id, "owner", Some(owner))?;
+        }
+        Ok(())
+    }
+
+    /// Whether a room is archived (read-only and hidden from room lists)
+    pub fn is_room_archived(&self, room: &str) -> Result<bool> {
+        match self.get_room_by_name(room)? {
+            Some(room_obj) => Ok(self.get_room_kv(&room_obj.id, "archived")?.is_some()),
+            None => Ok(false),
+        }
+    }
+
+    pub fn set_room_archived(&self, room: &str, archived: bool) -> Result<()> {
+        let room_obj = self
+            .get_room_by_name(room)?
+            .with_context(|| format!("room '{}' not found", room))?;
+        if archived {
+            self.set_room_kv(&room_obj.id, "archived", Some(&now_ms().to_string()))
+        } else {
+            self.delete_room_kv(&room_obj.id, "archived")
+        }
+    }
+
+    /// Names of archived rooms
+    pub fn list_archived_rooms(&self) -> Result<Vec<String>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            r#"SELECT r.name FROM rooms r
+               JOIN room_kv kv ON kv.room_id = r.id
+               WHERE kv.key = 'archived' AND kv.value IS NOT NULL
+               ORDER BY r.name"#,
+        )?;
+        let names = stmt
+            .query([])?
+            .mapped(|row| row.get(0))
+            .collect::<Result<Vec<String>, _>>()
+            .context("failed to list archived rooms")?;
+        Ok(names)
+    }
+
+    /// `ensure_room_writable` for a room known by ID
+    pub fn ensure_room_id_writable(&self, room_id: &str) -> Result<()> {
+        match self.get_room(room_id)? {
+            Some(room) => self.ensure_room_writable(&room.name),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail if a room can't take new messages or changes
+    pub fn ensure_room_writable(&self, room: &str) -> Result<()> {
+        if self.is_room_archived(room)? {
+            anyhow::bail!(
+                "{} is archived and read-only. Use /unarchive {} to reopen it.",
+                room,
+                room
+            );
+        }
+        Ok(())
+    }
+
     // =============================================================================
     // NEW HELPER METHODS FOR MIGRATION
     // =============================================================================
@@ -769,4 +996,134 @@ mod tests {
         assert_eq!(version, SCHEMA_VERSION);
         Ok(())
     }
+
+    fn temp_db_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("sshwarma-{}-{}", name, new_id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("test.db")
+    }
+
+    #[test]
+    fn test_file_database_uses_wal_and_readers() -> Result<()> {
+        let path = temp_db_path("wal");
+        let db = Database::open(&path)?;
+
+        let mode: String = db
+            .conn()?
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+        assert_eq!(mode.to_lowercase(), "wal");
+        assert_eq!(db.readers.len(), READ_POOL_SIZE);
+
+        // Committed writes are visible to pooled readers
+        db.insert_room(&rooms::Room::new("visible"))?;
+        assert!(db.get_room_by_name("visible")?.is_some());
+
+        // Readers reject writes
+        let reader = db.read_conn()?;
+        assert!(reader
+            .execute("DELETE FROM rooms WHERE name = 'visible'", [])
+            .is_err());
+        drop(reader);
+
+        std::fs::remove_dir_all(path.parent().unwrap())?;
+        Ok(())
+    }
+
+    // Performance benchmark (run with `cargo perf`)
+    //
+    // Simulates N SSH sessions re-rendering chat history while M models
+    // stream tokens into their thinking rows.
+    #[test]
+    fn perf_concurrent_render_while_streaming() -> Result<()> {
+        use std::sync::atomic::AtomicBool;
+        use std::time::Instant;
+
+        const SESSIONS: usize = 8;
+        const STREAMS: usize = 4;
+        const CHUNKS: usize = 500;
+
+        let path = temp_db_path("perf");
+        let db = Database::open(&path)?;
+
+        let room = rooms::Room::new("perf");
+        db.insert_room(&room)?;
+        let buffer = buffers::Buffer::room_chat(&room.id);
+        db.insert_buffer(&buffer)?;
+
+        for i in 0..200 {
+            let agent = db.get_or_create_human_agent(&format!("user{}", i % 8))?;
+            let mut row = rows::Row::message(&buffer.id, &agent.id, format!("hello {}", i), false);
+            db.append_row(&mut row)?;
+        }
+
+        let mut stream_rows = Vec::new();
+        for i in 0..STREAMS {
+            let model = db.get_or_create_model_agent(&format!("model{}", i))?;
+            let mut row = rows::Row::thinking(&buffer.id, &model.id);
+            db.append_row(&mut row)?;
+            stream_rows.push(row.id);
+        }
+
+        let done = AtomicBool::new(false);
+        let renders = AtomicUsize::new(0);
+        let start = Instant::now();
+
+        let db = &db;
+        std::thread::scope(|scope| {
+            for _ in 0..SESSIONS {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) {
+                        let rows = db.list_recent_buffer_rows(&buffer.id, 50).unwrap();
+                        for row in &rows {
+                            if let Some(ref agent_id) = row.source_agent_id {
+                                let _ = db.get_agent(agent_id).unwrap();
+                            }
+                        }
+                        renders.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+
+            let writers: Vec<_> = stream_rows
+                .iter()
+                .map(|row_id| {
+                    scope.spawn(move || {
+                        for _ in 0..CHUNKS {
+                            db.append_to_row(row_id, "token ").unwrap();
+                        }
+                    })
+                })
+                .collect();
+            for writer in writers {
+                writer.join().unwrap();
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+
+        let elapsed = start.elapsed();
+        let chunks = STREAMS * CHUNKS;
+        let renders = renders.load(Ordering::Relaxed);
+        eprintln!("\n=== Concurrent Render While Streaming ===");
+        eprintln!(
+            "  {} streams x {} chunks in {:?} ({:.0} chunks/sec)",
+            STREAMS,
+            CHUNKS,
+            elapsed,
+            chunks as f64 / elapsed.as_secs_f64()
+        );
+        eprintln!(
+            "  {} sessions rendered {} times ({:.0} renders/sec)",
+            SESSIONS,
+            renders,
+            renders as f64 / elapsed.as_secs_f64()
+        );
+
+        for row_id in &stream_rows {
+            let row = db.get_row(row_id)?.unwrap();
+            assert_eq!(row.content.unwrap().len(), "token ".len() * CHUNKS);
+        }
+
+        std::fs::remove_dir_all(path.parent().unwrap())?;
+        Ok(())
+    }
 }
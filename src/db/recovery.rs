@@ -0,0 +1,308 @@
+//! Startup recovery after an unclean shutdown
+//!
+//! A crash or kill leaves state that only a clean disconnect would tidy up:
+//! streaming rows that never got finalized, presence joins with no matching
+//! leave, and sessions with no disconnect time. `recover_after_crash` runs
+//! once at boot, before any connections are accepted, and repairs all three.
+//! Graceful shutdown closes presence and sessions the same way, but only
+//! finalizes the streams it still has open.
+
+use anyhow::{Context, Result};
+use rusqlite::params;
+
+use super::events::RowEventKind;
+use super::rows::Row;
+use super::{now_ms, Database};
+
+/// Appended to streaming rows that were cut off by a restart
+pub const INTERRUPTED_MARKER: &str = "\n\n[interrupted]";
+
+/// What a recovery pass repaired
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Streaming rows finalized with the interrupted marker
+    pub interrupted_rows: usize,
+    /// Synthetic presence.leave rows written
+    pub presence_left: usize,
+    /// agent_sessions rows given a disconnect time
+    pub sessions_closed: usize,
+}
+
+impl RecoveryReport {
+    /// True when there was nothing to repair
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A presence.join with no later presence row for the same agent and buffer
+struct DanglingJoin {
+    buffer_id: String,
+    source_agent_id: Option<String>,
+    content: Option<String>,
+}
+
+impl Database {
+    /// Repair state left behind by an unclean shutdown
+    ///
+    /// Must run before sessions start: any row still streaming or any
+    /// presence still joined is assumed to belong to a dead process.
+    pub fn recover_after_crash(&self) -> Result<RecoveryReport> {
+        let interrupted_rows = self.finalize_interrupted_rows()?;
+        let presence_left = self.close_open_presence()?;
+        let sessions_closed = self.close_open_sessions()?;
+
+        Ok(RecoveryReport {
+            interrupted_rows,
+            presence_left,
+            sessions_closed,
+        })
+    }
+
+    /// Finalize every unfinished model stream, appending `INTERRUPTED_MARKER`
+    ///
+    /// Only `thinking.stream` rows count: tool calls stay mutable after they
+    /// finish. Only safe when no model response is still writing.
+    pub fn finalize_interrupted_rows(&self) -> Result<usize> {
+        let conn = self.conn()?;
+        let now = now_ms();
+        let count = conn
+            .execute(
+                r#"
+                UPDATE rows SET
+                    content = COALESCE(content, '') || ?1,
+                    mutable = 0,
+                    finalized_at = ?2,
+                    updated_at = ?2
+                WHERE content_method = 'thinking.stream'
+                  AND mutable = 1 AND finalized_at IS NULL
+                "#,
+                params![INTERRUPTED_MARKER, now],
+            )
+            .context("failed to finalize orphaned rows")?;
+        Ok(count)
+    }
+
+    /// Finalize the streams this process still has open, appending
+    /// `INTERRUPTED_MARKER`
+    ///
+    /// For graceful shutdown: unlike `finalize_interrupted_rows`, rows
+    /// written by anyone else are left alone.
+    pub fn finalize_streaming_rows(&self) -> Result<usize> {
+        let mut count = 0;
+        for (row_id, buffer_id) in self.streaming_rows() {
+            let updated = {
+                let conn = self.conn()?;
+                let now = now_ms();
+                conn.execute(
+                    r#"
+                    UPDATE rows SET
+                        content = COALESCE(content, '') || ?2,
+                        mutable = 0,
+                        finalized_at = ?3,
+                        updated_at = ?3
+                    WHERE id = ?1 AND mutable = 1 AND finalized_at IS NULL
+                    "#,
+                    params![row_id, INTERRUPTED_MARKER, now],
+                )
+                .context("failed to finalize streaming row")?
+            };
+            self.untrack_streaming_row(&row_id);
+            if updated > 0 {
+                self.emit_row_event(&buffer_id, &row_id, RowEventKind::Finalized);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Write a presence.leave for every join that was never matched
+    ///
+    /// Presence rows identify the agent by `source_agent_id` when set and by
+    /// the username in `content` otherwise, so both are copied to the leave.
+    pub fn close_open_presence(&self) -> Result<usize> {
+        let dangling = {
+            let conn = self.conn()?;
+            let mut stmt = conn
+                .prepare(
+                    r#"
+                    WITH latest AS (
+                        SELECT
+                            buffer_id, source_agent_id, content, content_method,
+                            ROW_NUMBER() OVER (
+                                PARTITION BY buffer_id, COALESCE(source_agent_id, content)
+                                ORDER BY position DESC, created_at DESC
+                            ) AS rn
+                        FROM rows
+                        WHERE content_method LIKE 'presence.%'
+                    )
+                    SELECT buffer_id, source_agent_id, content
+                    FROM latest
+                    WHERE rn = 1 AND content_method = 'presence.join'
+                    "#,
+                )
+                .context("failed to prepare presence query")?;
+            let joins = stmt
+                .query_map([], |row| {
+                    Ok(DanglingJoin {
+                        buffer_id: row.get(0)?,
+                        source_agent_id: row.get(1)?,
+                        content: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("failed to query dangling presence")?;
+            joins
+        };
+
+        for join in &dangling {
+            let mut row = Row::new(&join.buffer_id, "presence.leave");
+            row.source_agent_id = join.source_agent_id.clone();
+            row.content = join.content.clone();
+            row.content_meta = Some(r#"{"reason":"recovered"}"#.to_string());
+            self.append_row(&mut row)?;
+        }
+
+        Ok(dangling.len())
+    }
+
+    /// Mark sessions without a disconnect time as disconnected now
+    pub fn close_open_sessions(&self) -> Result<usize> {
+        let conn = self.conn()?;
+        let count = conn
+            .execute(
+                "UPDATE agent_sessions SET disconnected_at = ?1 WHERE disconnected_at IS NULL",
+                params![now_ms()],
+            )
+            .context("failed to close dangling sessions")?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::agents::{Agent, AgentKind, AgentSession, SessionKind};
+    use crate::db::buffers::Buffer;
+    use crate::db::rooms::Room;
+
+    fn setup() -> Result<(Database, Buffer, Agent)> {
+        let db = Database::in_memory()?;
+        let room = Room::new("crashy");
+        db.insert_room(&room)?;
+        let buffer = Buffer::room_chat(&room.id);
+        db.insert_buffer(&buffer)?;
+        let agent = Agent::new("qwen", AgentKind::Model);
+        db.insert_agent(&agent)?;
+        Ok((db, buffer, agent))
+    }
+
+    fn presence(db: &Database, buffer_id: &str, method: &str, who: &str) -> Result<()> {
+        let mut row = Row::new(buffer_id, method);
+        row.content = Some(who.to_string());
+        db.append_row(&mut row)
+    }
+
+    #[test]
+    fn test_finalizes_orphaned_streams() -> Result<()> {
+        let (db, buffer, agent) = setup()?;
+
+        let mut orphan = Row::thinking(&buffer.id, &agent.id);
+        db.append_row(&mut orphan)?;
+        db.append_to_row(&orphan.id, "half an answ")?;
+
+        let mut done = Row::thinking(&buffer.id, &agent.id);
+        db.append_row(&mut done)?;
+        db.append_to_row(&done.id, "complete")?;
+        db.finalize_row(&done.id)?;
+
+        let report = db.recover_after_crash()?;
+        assert_eq!(report.interrupted_rows, 1);
+
+        let orphan = db.get_row(&orphan.id)?.unwrap();
+        assert!(!orphan.mutable);
+        assert!(orphan.finalized_at.is_some());
+        assert_eq!(
+            orphan.content.as_deref(),
+            Some(format!("half an answ{}", INTERRUPTED_MARKER).as_str())
+        );
+
+        let done = db.get_row(&done.id)?.unwrap();
+        assert_eq!(done.content.as_deref(), Some("complete"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_leaves_tool_calls_alone() -> Result<()> {
+        let (db, buffer, agent) = setup()?;
+
+        let mut call = Row::tool_call(&buffer.id, &agent.id, "sshwarma:look", None::<String>);
+        db.append_row(&mut call)?;
+        let mut result = Row::tool_result(&buffer.id, "sshwarma:look", "a room", true);
+        db.append_row(&mut result)?;
+
+        assert_eq!(db.recover_after_crash()?.interrupted_rows, 0);
+        let call = db.get_row(&call.id)?.unwrap();
+        assert_eq!(call.content.as_deref(), Some("sshwarma:look"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown_finalizes_only_open_streams() -> Result<()> {
+        let (db, buffer, agent) = setup()?;
+
+        let mut open = Row::thinking(&buffer.id, &agent.id);
+        db.append_row(&mut open)?;
+        db.append_to_row(&open.id, "still going")?;
+        let mut call = Row::tool_call(&buffer.id, &agent.id, "sshwarma:look", None::<String>);
+        db.append_row(&mut call)?;
+
+        assert_eq!(db.finalize_streaming_rows()?, 1);
+        let open = db.get_row(&open.id)?.unwrap();
+        assert!(!open.mutable);
+        assert_eq!(
+            open.content.as_deref(),
+            Some(format!("still going{}", INTERRUPTED_MARKER).as_str())
+        );
+        let call = db.get_row(&call.id)?.unwrap();
+        assert_eq!(call.content.as_deref(), Some("sshwarma:look"));
+        assert_eq!(db.finalize_streaming_rows()?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_closes_dangling_presence() -> Result<()> {
+        let (db, buffer, _agent) = setup()?;
+
+        presence(&db, &buffer.id, "presence.join", "alice")?;
+        presence(&db, &buffer.id, "presence.join", "bob")?;
+        presence(&db, &buffer.id, "presence.leave", "bob")?;
+
+        let report = db.recover_after_crash()?;
+        assert_eq!(report.presence_left, 1);
+
+        let last = db.get_last_buffer_row(&buffer.id)?.unwrap();
+        assert_eq!(last.content_method, "presence.leave");
+        assert_eq!(last.content.as_deref(), Some("alice"));
+
+        // Second pass finds nothing left to do
+        assert!(db.recover_after_crash()?.is_clean());
+        Ok(())
+    }
+
+    #[test]
+    fn test_closes_dangling_sessions() -> Result<()> {
+        let (db, _buffer, agent) = setup()?;
+
+        let open = AgentSession::new(&agent.id, SessionKind::Ssh);
+        db.insert_session(&open)?;
+        let closed = AgentSession::new(&agent.id, SessionKind::Mcp);
+        db.insert_session(&closed)?;
+        db.disconnect_session(&closed.id)?;
+
+        let report = db.recover_after_crash()?;
+        assert_eq!(report.sessions_closed, 1);
+        assert!(db.list_active_sessions(&agent.id)?.is_empty());
+        Ok(())
+    }
+}
@@ -2,11 +2,12 @@
 //!
 //! Rooms are shared spaces where agents collaborate. Metadata lives in room_kv.
 
+use super::events::RoomEventKind;
 use super::{new_id, now_ms, Database};
 use anyhow::{Context, Result};
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 /// A room in the system
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +28,47 @@ impl Room {
     }
 }
 
+/// What deleting a room removes
+#[derive(Debug, Clone, Default)]
+pub struct RoomDeletion {
+    pub buffers: usize,
+    pub rows: usize,
+    pub equipment: usize,
+    /// Exits in other rooms that lead here, as (room, direction)
+    pub exits_in: Vec<(String, String)>,
+    /// Rooms forked from this one whose chat still shares its history
+    pub forks: Vec<String>,
+}
+
+/// Set a room_kv value on `conn`, e.g. inside a transaction; emits no events
+pub(super) fn set_room_kv_with(
+    conn: &Connection,
+    room_id: &str,
+    key: &str,
+    value: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO room_kv (room_id, key, value, updated_at)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (room_id, key) DO UPDATE SET value = ?3, updated_at = ?4
+        "#,
+        params![room_id, key, value, now_ms()],
+    )
+    .context("failed to set room kv")?;
+    Ok(())
+}
+
+/// Delete a room_kv key on `conn`; emits no events
+pub(super) fn delete_room_kv_with(conn: &Connection, room_id: &str, key: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM room_kv WHERE room_id = ?1 AND key = ?2",
+        params![room_id, key],
+    )
+    .context("failed to delete room kv")?;
+    Ok(())
+}
+
 // Database operations
 impl Database {
     /// Insert a new room
@@ -37,12 +79,14 @@ impl Database {
             params![room.id, room.name, room.created_at],
         )
         .context("failed to insert room")?;
+        drop(conn);
+        self.emit_room_event(&room.id, RoomEventKind::Created);
         Ok(())
     }
 
     /// Get room by ID
     pub fn get_room(&self, id: &str) -> Result<Option<Room>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare("SELECT id, name, created_at FROM rooms WHERE id = ?1")
             .context("failed to prepare room query")?;
@@ -63,7 +107,7 @@ impl Database {
 
     /// Get room by name
     pub fn get_room_by_name(&self, name: &str) -> Result<Option<Room>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare("SELECT id, name, created_at FROM rooms WHERE name = ?1")
             .context("failed to prepare room query")?;
@@ -84,7 +128,7 @@ impl Database {
 
     /// List all rooms
     pub fn list_rooms(&self) -> Result<Vec<Room>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare("SELECT id, name, created_at FROM rooms ORDER BY name")
             .context("failed to prepare rooms query")?;
@@ -104,13 +148,201 @@ impl Database {
         Ok(rooms)
     }
 
-    /// Delete a room (cascades to room_kv)
-    pub fn delete_room(&self, id: &str) -> Result<()> {
-        let conn = self.conn()?;
-        conn.execute("DELETE FROM room_kv WHERE room_id = ?1", params![id])?;
-        conn.execute("DELETE FROM rooms WHERE id = ?1", params![id])
+    /// Every room's name with the room it was forked from, if any
+    pub fn list_room_parents(&self) -> Result<Vec<(String, Option<String>)>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn
+            .prepare(
+                r#"
+            SELECT r.name, kv.value
+            FROM rooms r
+            LEFT JOIN room_kv kv ON kv.room_id = r.id AND kv.key = 'parent'
+            ORDER BY r.created_at
+            "#,
+            )
+            .context("failed to prepare room parents query")?;
+
+        let parents = stmt
+            .query([])?
+            .mapped(|row| Ok((row.get(0)?, row.get(1)?)))
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to list room parents")?;
+
+        Ok(parents)
+    }
+
+    /// Work out what deleting a room would remove, without deleting anything
+    pub fn plan_room_deletion(&self, id: &str) -> Result<RoomDeletion> {
+        let room = self
+            .get_room(id)?
+            .with_context(|| format!("room {} not found", id))?;
+        let conn = self.read_conn()?;
+
+        let count = |sql: &str| -> Result<usize> {
+            let n: i64 = conn
+                .query_row(sql, params![id], |row| row.get(0))
+                .context("failed to count room contents")?;
+            Ok(n as usize)
+        };
+        let buffers = count("SELECT COUNT(*) FROM buffers WHERE room_id = ?1")?;
+        let rows = count(
+            "SELECT COUNT(*) FROM rows WHERE buffer_id IN (SELECT id FROM buffers WHERE room_id = ?1)",
+        )?;
+        let equipment =
+            count("SELECT COUNT(*) FROM room_equip WHERE room_id = ?1 AND deleted_at IS NULL")?;
+
+        // Exits live both in room_kv (exit.<dir> = room name) and between
+        // room things in the exits table
+        let mut stmt = conn
+            .prepare(
+                r#"
+            SELECT r.name, substr(kv.key, 6)
+            FROM room_kv kv JOIN rooms r ON r.id = kv.room_id
+            WHERE kv.key LIKE 'exit.%' AND kv.value = ?2 AND kv.room_id != ?1
+            UNION
+            SELECT f.name, e.direction
+            FROM exits e
+            JOIN things f ON f.id = e.from_thing_id
+            JOIN things t ON t.id = e.to_thing_id
+            WHERE t.kind = 'room' AND t.name = ?2 AND f.name != ?2 AND e.deleted_at IS NULL
+            ORDER BY 1, 2
+            "#,
+            )
+            .context("failed to prepare exits query")?;
+        let exits_in = stmt
+            .query(params![id, room.name])?
+            .mapped(|row| Ok((row.get(0)?, row.get(1)?)))
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to list exits into room")?;
+
+        let mut stmt = conn
+            .prepare(
+                r#"
+            SELECT DISTINCT r.name
+            FROM buffers b JOIN rooms r ON r.id = b.room_id
+            WHERE b.room_id != ?1
+              AND b.parent_buffer_id IN (SELECT id FROM buffers WHERE room_id = ?1)
+            ORDER BY r.name
+            "#,
+            )
+            .context("failed to prepare forks query")?;
+        let forks = stmt
+            .query(params![id])?
+            .mapped(|row| row.get(0))
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to list forks")?;
+
+        Ok(RoomDeletion {
+            buffers,
+            rows,
+            equipment,
+            exits_in,
+            forks,
+        })
+    }
+
+    /// Delete a room and everything that belongs to it
+    ///
+    /// Removes the room's kv, equipment, buffers and their rows, its room
+    /// thing (and anything inside it) with the exits and equipment attached
+    /// to it, room-scoped scripts, and exits in other rooms that lead here.
+    /// Refuses while forks still share the room's chat history.
+    pub fn delete_room(&self, id: &str) -> Result<RoomDeletion> {
+        let deletion = self.plan_room_deletion(id)?;
+        if !deletion.forks.is_empty() {
+            anyhow::bail!(
+                "forks still share this room's history: {}",
+                deletion.forks.join(", ")
+            );
+        }
+        let room = self
+            .get_room(id)?
+            .with_context(|| format!("room {} not found", id))?;
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute_batch(
+            r#"
+            CREATE TEMP TABLE IF NOT EXISTS doomed_rows (id TEXT PRIMARY KEY);
+            CREATE TEMP TABLE IF NOT EXISTS doomed_things (id TEXT PRIMARY KEY);
+            DELETE FROM doomed_rows;
+            DELETE FROM doomed_things;
+            "#,
+        )?;
+        tx.execute(
+            r#"INSERT INTO doomed_rows
+               SELECT id FROM rows WHERE buffer_id IN (SELECT id FROM buffers WHERE room_id = ?1)"#,
+            params![id],
+        )?;
+        tx.execute(
+            r#"INSERT INTO doomed_things
+               WITH RECURSIVE tree(id) AS (
+                   SELECT id FROM things WHERE kind = 'room' AND name = ?1
+                   UNION
+                   SELECT t.id FROM things t JOIN tree ON t.parent_id = tree.id
+               )
+               SELECT id FROM tree"#,
+            params![room.name],
+        )?;
+        tx.execute_batch(
+            r#"
+            DELETE FROM row_tags WHERE row_id IN (SELECT id FROM doomed_rows);
+            DELETE FROM row_reactions WHERE row_id IN (SELECT id FROM doomed_rows);
+            DELETE FROM row_links
+                WHERE from_row_id IN (SELECT id FROM doomed_rows)
+                   OR to_row_id IN (SELECT id FROM doomed_rows);
+            DELETE FROM rows WHERE id IN (SELECT id FROM doomed_rows);
+            DELETE FROM exits
+                WHERE from_thing_id IN (SELECT id FROM doomed_things)
+                   OR to_thing_id IN (SELECT id FROM doomed_things);
+            DELETE FROM equipped
+                WHERE context_id IN (SELECT id FROM doomed_things)
+                   OR thing_id IN (SELECT id FROM doomed_things);
+            DELETE FROM room_equip WHERE thing_id IN (SELECT id FROM doomed_things);
+            DELETE FROM agent_equip WHERE thing_id IN (SELECT id FROM doomed_things);
+            DELETE FROM things WHERE id IN (SELECT id FROM doomed_things);
+            DELETE FROM doomed_rows;
+            DELETE FROM doomed_things;
+            "#,
+        )?;
+        tx.execute(
+            "DELETE FROM buffer_scroll WHERE buffer_id IN (SELECT id FROM buffers WHERE room_id = ?1)",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM buffers WHERE room_id = ?1", params![id])?;
+        tx.execute("DELETE FROM room_equip WHERE room_id = ?1", params![id])?;
+        tx.execute("DELETE FROM room_kv WHERE room_id = ?1", params![id])?;
+        tx.execute(
+            r#"DELETE FROM room_kv
+               WHERE key LIKE 'lock.%'
+                 AND EXISTS (SELECT 1 FROM room_kv e
+                             WHERE e.room_id = room_kv.room_id
+                               AND e.key = 'exit.' || substr(room_kv.key, 6)
+                               AND e.value = ?1)"#,
+            params![room.name],
+        )?;
+        tx.execute(
+            "DELETE FROM room_kv WHERE key LIKE 'exit.%' AND value = ?1",
+            params![room.name],
+        )?;
+        tx.execute(
+            "DELETE FROM lua_scripts WHERE scope = 'room' AND scope_id = ?1",
+            params![room.name],
+        )?;
+        tx.execute("DELETE FROM rooms WHERE id = ?1", params![id])
             .context("failed to delete room")?;
-        Ok(())
+        tx.commit().context("failed to commit room deletion")?;
+        drop(conn);
+        self.emit_room_event(id, RoomEventKind::Deleted);
+
+        tracing::info!(
+            room = %room.name,
+            buffers = deletion.buffers,
+            rows = deletion.rows,
+            exits = deletion.exits_in.len(),
+            "deleted room"
+        );
+        Ok(deletion)
     }
 
     // --- Room KV operations ---
@@ -118,21 +350,15 @@ impl Database {
     /// Set a room key-value pair
     pub fn set_room_kv(&self, room_id: &str, key: &str, value: Option<&str>) -> Result<()> {
         let conn = self.conn()?;
-        conn.execute(
-            r#"
-            INSERT INTO room_kv (room_id, key, value, updated_at)
-            VALUES (?1, ?2, ?3, ?4)
-            ON CONFLICT (room_id, key) DO UPDATE SET value = ?3, updated_at = ?4
-            "#,
-            params![room_id, key, value, now_ms()],
-        )
-        .context("failed to set room kv")?;
+        set_room_kv_with(&conn, room_id, key, value)?;
+        drop(conn);
+        self.emit_room_event(room_id, RoomEventKind::Changed);
         Ok(())
     }
 
     /// Get a room key-value pair
     pub fn get_room_kv(&self, room_id: &str, key: &str) -> Result<Option<String>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare("SELECT value FROM room_kv WHERE room_id = ?1 AND key = ?2")
             .context("failed to prepare room kv query")?;
@@ -147,7 +373,7 @@ impl Database {
 
     /// Get all room key-value pairs
     pub fn get_all_room_kv(&self, room_id: &str) -> Result<HashMap<String, String>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare("SELECT key, value FROM room_kv WHERE room_id = ?1 AND value IS NOT NULL")
             .context("failed to prepare room kv query")?;
@@ -166,17 +392,15 @@ impl Database {
     /// Delete a room key-value pair
     pub fn delete_room_kv(&self, room_id: &str, key: &str) -> Result<()> {
         let conn = self.conn()?;
-        conn.execute(
-            "DELETE FROM room_kv WHERE room_id = ?1 AND key = ?2",
-            params![room_id, key],
-        )
-        .context("failed to delete room kv")?;
+        delete_room_kv_with(&conn, room_id, key)?;
+        drop(conn);
+        self.emit_room_event(room_id, RoomEventKind::Changed);
         Ok(())
     }
 
     /// Get room exits (keys starting with "exit.")
     pub fn get_room_exits(&self, room_id: &str) -> Result<HashMap<String, String>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 "SELECT key, value FROM room_kv WHERE room_id = ?1 AND key LIKE 'exit.%' AND value IS NOT NULL",
@@ -202,6 +426,74 @@ impl Database {
         let key = format!("exit.{}", direction);
         self.set_room_kv(room_id, &key, Some(target_room))
     }
+
+    /// Every room's exits, as room name -> direction -> target room name
+    pub fn exit_graph(&self) -> Result<BTreeMap<String, BTreeMap<String, String>>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn
+            .prepare(
+                r#"
+            SELECT r.name, substr(kv.key, 6), kv.value
+            FROM room_kv kv JOIN rooms r ON r.id = kv.room_id
+            WHERE kv.key LIKE 'exit.%' AND kv.value IS NOT NULL
+            "#,
+            )
+            .context("failed to prepare exit graph query")?;
+
+        let mut graph: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let (room, direction, target): (String, String, String) =
+                (row.get(0)?, row.get(1)?, row.get(2)?);
+            graph.entry(room).or_default().insert(direction, target);
+        }
+        Ok(graph)
+    }
+
+    /// Shortest path through exits from one room to another
+    ///
+    /// Returns the hops as (direction, room entered), empty when `from` is
+    /// `to`, or None when no path exists. Ties go to the alphabetically
+    /// first direction so the same trip always takes the same route.
+    pub fn find_exit_path(&self, from: &str, to: &str) -> Result<Option<Vec<(String, String)>>> {
+        self.find_exit_path_where(from, to, |_, _, _| true)
+    }
+
+    /// Shortest path using only exits `passable(room, direction, target)`
+    /// allows
+    pub fn find_exit_path_where(
+        &self,
+        from: &str,
+        to: &str,
+        mut passable: impl FnMut(&str, &str, &str) -> bool,
+    ) -> Result<Option<Vec<(String, String)>>> {
+        let graph = self.exit_graph()?;
+        let mut came_from: HashMap<&str, (&str, &str)> = HashMap::new();
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(room) = queue.pop_front() {
+            if room == to {
+                let mut path = Vec::new();
+                let mut at = to;
+                while let Some(&(prev, direction)) = came_from.get(at) {
+                    path.push((direction.to_string(), at.to_string()));
+                    at = prev;
+                }
+                path.reverse();
+                return Ok(Some(path));
+            }
+            for (direction, target) in graph.get(room).into_iter().flatten() {
+                if seen.contains(target.as_str()) || !passable(room, direction, target) {
+                    continue;
+                }
+                seen.insert(target.as_str());
+                came_from.insert(target.as_str(), (room, direction.as_str()));
+                queue.push_back(target.as_str());
+            }
+        }
+        Ok(None)
+    }
 }
 
 #[cfg(test)]
@@ -299,4 +591,87 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_delete_room_cleans_up_and_reports_exits() -> Result<()> {
+        use crate::db::rows::Row;
+
+        let db = Database::in_memory()?;
+
+        let lobby = Room::new("lobby");
+        db.insert_room(&lobby)?;
+        db.set_room_exit(&lobby.id, "north", "studio")?;
+        db.set_room_exit(&lobby.id, "east", "garden")?;
+
+        let buffer = db.get_or_create_room_buffer("studio")?;
+        let studio = db.get_room_by_name("studio")?.expect("room created");
+        db.set_room_exit(&studio.id, "south", "lobby")?;
+        let mut row = Row::message(&buffer.id, "agent", "hello", false);
+        db.append_row(&mut row)?;
+
+        // Forks keep the source alive until they're dealt with
+        db.fork_room("studio", "studio-v2")?;
+        let err = db.delete_room(&studio.id).unwrap_err();
+        assert!(err.to_string().contains("studio-v2"), "{err}");
+        let fork = db.get_room_by_name("studio-v2")?.expect("fork created");
+        db.delete_room(&fork.id)?;
+
+        let deletion = db.delete_room(&studio.id)?;
+        assert_eq!(deletion.rows, 1);
+        assert_eq!(
+            deletion.exits_in,
+            vec![("lobby".to_string(), "north".to_string())]
+        );
+
+        assert!(db.get_room_by_name("studio")?.is_none());
+        assert!(db.get_row(&row.id)?.is_none());
+        let exits = db.get_room_exits(&lobby.id)?;
+        assert_eq!(exits.len(), 1);
+        assert_eq!(exits.get("east"), Some(&"garden".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_exit_path() -> Result<()> {
+        let db = Database::in_memory()?;
+
+        for name in ["lobby", "hall", "studio", "garden", "attic"] {
+            db.insert_room(&Room::new(name))?;
+        }
+        let id = |name: &str| -> Result<String> {
+            Ok(db.get_room_by_name(name)?.expect("room exists").id)
+        };
+        db.set_room_exit(&id("lobby")?, "north", "hall")?;
+        db.set_room_exit(&id("hall")?, "east", "studio")?;
+        db.set_room_exit(&id("studio")?, "south", "garden")?;
+        // A longer way round to the same place
+        db.set_room_exit(&id("lobby")?, "west", "attic")?;
+        db.set_room_exit(&id("attic")?, "down", "hall")?;
+
+        let path = db.find_exit_path("lobby", "garden")?.expect("connected");
+        assert_eq!(
+            path,
+            vec![
+                ("north".to_string(), "hall".to_string()),
+                ("east".to_string(), "studio".to_string()),
+                ("south".to_string(), "garden".to_string()),
+            ]
+        );
+
+        assert_eq!(db.find_exit_path("lobby", "lobby")?, Some(vec![]));
+        // Exits are one-way unless dug both ways
+        assert_eq!(db.find_exit_path("garden", "lobby")?, None);
+
+        // With lobby north barred, the trip goes round through the attic
+        let path = db
+            .find_exit_path_where("lobby", "garden", |room, dir, _| {
+                !(room == "lobby" && dir == "north")
+            })?
+            .expect("still connected");
+        assert_eq!(path[0], ("west".to_string(), "attic".to_string()));
+        assert_eq!(path.len(), 4);
+
+        Ok(())
+    }
 }
@@ -3,9 +3,10 @@
 //! Rows are atomic units of content. Can nest via parent_row_id.
 //! Uses fractional indexing for ordering within a buffer.
 
+use super::events::RowEventKind;
 use super::{new_id, now_ms, Database};
 use anyhow::{Context, Result};
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
 /// A row in a buffer
@@ -276,53 +277,106 @@ pub mod fractional {
     }
 }
 
+/// "?, ?, ?" for an IN list of n values
+fn placeholders(n: usize) -> String {
+    vec!["?"; n].join(", ")
+}
+
+/// Position of a buffer's last top-level row on `conn`, if it has any
+pub(super) fn last_buffer_position_with(conn: &Connection, buffer_id: &str) -> Result<Option<f64>> {
+    conn.query_row(
+        r#"
+        SELECT position FROM rows
+        WHERE buffer_id = ?1 AND parent_row_id IS NULL
+        ORDER BY position DESC
+        LIMIT 1
+        "#,
+        params![buffer_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("failed to get last row position")
+}
+
+/// Insert a row on `conn`, e.g. inside a transaction; emits no events
+pub(super) fn insert_row_with(conn: &Connection, row: &Row) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO rows (
+            id, buffer_id, parent_row_id, position,
+            source_agent_id, source_session_id,
+            content_method, content_format, content_meta, content,
+            collapsed, ephemeral, mutable, pinned, hidden,
+            token_count, cost_usd, latency_ms,
+            created_at, updated_at, finalized_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)
+        "#,
+        params![
+            row.id,
+            row.buffer_id,
+            row.parent_row_id,
+            row.position,
+            row.source_agent_id,
+            row.source_session_id,
+            row.content_method,
+            row.content_format,
+            row.content_meta,
+            row.content,
+            row.collapsed as i32,
+            row.ephemeral as i32,
+            row.mutable as i32,
+            row.pinned as i32,
+            row.hidden as i32,
+            row.token_count,
+            row.cost_usd,
+            row.latency_ms,
+            row.created_at,
+            row.updated_at,
+            row.finalized_at,
+        ],
+    )
+    .context("failed to insert row")?;
+    Ok(())
+}
+
+/// Link two rows on `conn`, returning the link id
+pub(super) fn insert_row_link_with(
+    conn: &Connection,
+    from_row_id: &str,
+    to_row_id: &str,
+    link_type: LinkType,
+) -> Result<String> {
+    let id = new_id();
+    conn.execute(
+        r#"
+        INSERT INTO row_links (id, from_row_id, to_row_id, link_type, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        "#,
+        params![id, from_row_id, to_row_id, link_type.as_str(), now_ms()],
+    )
+    .context("failed to create row link")?;
+    Ok(id)
+}
+
 // Database operations
 impl Database {
     /// Insert a new row
     pub fn insert_row(&self, row: &Row) -> Result<()> {
         let conn = self.conn()?;
-        conn.execute(
-            r#"
-            INSERT INTO rows (
-                id, buffer_id, parent_row_id, position,
-                source_agent_id, source_session_id,
-                content_method, content_format, content_meta, content,
-                collapsed, ephemeral, mutable, pinned, hidden,
-                token_count, cost_usd, latency_ms,
-                created_at, updated_at, finalized_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)
-            "#,
-            params![
-                row.id,
-                row.buffer_id,
-                row.parent_row_id,
-                row.position,
-                row.source_agent_id,
-                row.source_session_id,
-                row.content_method,
-                row.content_format,
-                row.content_meta,
-                row.content,
-                row.collapsed as i32,
-                row.ephemeral as i32,
-                row.mutable as i32,
-                row.pinned as i32,
-                row.hidden as i32,
-                row.token_count,
-                row.cost_usd,
-                row.latency_ms,
-                row.created_at,
-                row.updated_at,
-                row.finalized_at,
-            ],
-        )
-        .context("failed to insert row")?;
+        insert_row_with(&conn, row)?;
+        drop(conn);
+
+        if row.mutable && row.content_method == "thinking.stream" {
+            self.track_streaming_row(&row.id, &row.buffer_id);
+        }
+        self.emit_row_event(&row.buffer_id, &row.id, RowEventKind::Appended);
         Ok(())
     }
 
     /// Get row by ID
     pub fn get_row(&self, id: &str) -> Result<Option<Row>> {
-        let conn = self.conn()?;
+        let _pending = self.hold_pending_appends();
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -337,17 +391,34 @@ impl Database {
             )
             .context("failed to prepare row query")?;
 
-        let row = stmt
+        let mut row = stmt
             .query_row(params![id], Self::row_from_sqlite)
             .optional()
             .context("failed to query row")?;
 
+        if let Some(ref mut r) = row {
+            self.merge_pending_appends(std::slice::from_mut(r));
+        }
         Ok(row)
     }
 
     /// List top-level rows in a buffer, ordered by position
+    ///
+    /// A forked buffer starts with the rows it shares with its ancestors.
     pub fn list_buffer_rows(&self, buffer_id: &str) -> Result<Vec<Row>> {
-        let conn = self.conn()?;
+        let _pending = self.hold_pending_appends();
+        let mut rows = Vec::new();
+        for (id, cutoff) in self.buffer_ancestry(buffer_id)?.into_iter().rev() {
+            rows.extend(self.query_buffer_rows(&id, Some(cutoff))?);
+        }
+        rows.extend(self.query_buffer_rows(buffer_id, None)?);
+
+        self.merge_pending_appends(&mut rows);
+        Ok(rows)
+    }
+
+    fn query_buffer_rows(&self, buffer_id: &str, cutoff: Option<f64>) -> Result<Vec<Row>> {
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -359,27 +430,56 @@ impl Database {
                    created_at, updated_at, finalized_at
             FROM rows
             WHERE buffer_id = ?1 AND parent_row_id IS NULL
+              AND (?2 IS NULL OR position <= ?2)
             ORDER BY position
             "#,
             )
             .context("failed to prepare rows query")?;
 
         let rows = stmt
-            .query(params![buffer_id])?
+            .query(params![buffer_id, cutoff])?
             .mapped(Self::row_from_sqlite)
             .collect::<Result<Vec<_>, _>>()
             .context("failed to list rows")?;
-
         Ok(rows)
     }
 
     /// List recent rows in a buffer for display, ordered by position (most recent last)
     ///
-    /// Includes top-level rows (messages) and tool rows (which may have parent_row_id set).
+    /// Includes top-level rows (messages), tool rows (which may have parent_row_id set)
+    /// and the rows of merged fork blocks.
+    /// A forked buffer with fewer than `limit` rows of its own is filled in
+    /// from the history it shares with its ancestors.
     pub fn list_recent_buffer_rows(&self, buffer_id: &str, limit: usize) -> Result<Vec<Row>> {
-        let conn = self.conn()?;
+        let _pending = self.hold_pending_appends();
+        let mut rows = self.query_recent_buffer_rows(buffer_id, None, limit)?;
+        if rows.len() < limit {
+            for (id, cutoff) in self.buffer_ancestry(buffer_id)? {
+                let mut older =
+                    self.query_recent_buffer_rows(&id, Some(cutoff), limit - rows.len())?;
+                older.append(&mut rows);
+                rows = older;
+                if rows.len() >= limit {
+                    break;
+                }
+            }
+        }
+
+        self.merge_pending_appends(&mut rows);
+        Ok(rows)
+    }
+
+    fn query_recent_buffer_rows(
+        &self,
+        buffer_id: &str,
+        cutoff: Option<f64>,
+        limit: usize,
+    ) -> Result<Vec<Row>> {
+        let conn = self.read_conn()?;
         // Get the last N rows by position (subquery to reverse order)
-        // Include top-level rows AND tool rows (which have parent_row_id for context linking)
+        // Include top-level rows AND tool rows (which have parent_row_id for context linking),
+        // plus rows nested in a merged fork's block. Past a fork point, nested rows go by
+        // their parent's position.
         let mut stmt = conn
             .prepare(
                 r#"
@@ -390,9 +490,17 @@ impl Database {
                    token_count, cost_usd, latency_ms,
                    created_at, updated_at, finalized_at
             FROM (
-                SELECT * FROM rows
-                WHERE buffer_id = ?1 AND (parent_row_id IS NULL OR content_method LIKE 'tool.%')
-                ORDER BY position DESC
+                SELECT * FROM rows r
+                WHERE r.buffer_id = ?1
+                  AND (r.parent_row_id IS NULL OR r.content_method LIKE 'tool.%' OR EXISTS (
+                      SELECT 1 FROM rows m
+                      WHERE m.id = r.parent_row_id AND m.content_method = 'meta.merge'
+                  ))
+                  AND (?3 IS NULL OR COALESCE(
+                      (SELECT p.position FROM rows p WHERE p.id = r.parent_row_id),
+                      r.position
+                  ) <= ?3)
+                ORDER BY r.position DESC
                 LIMIT ?2
             )
             ORDER BY position
@@ -401,17 +509,17 @@ impl Database {
             .context("failed to prepare recent rows query")?;
 
         let rows = stmt
-            .query(params![buffer_id, limit as i64])?
+            .query(params![buffer_id, limit as i64, cutoff])?
             .mapped(Self::row_from_sqlite)
             .collect::<Result<Vec<_>, _>>()
             .context("failed to list recent rows")?;
-
         Ok(rows)
     }
 
     /// List child rows of a parent, ordered by position
     pub fn list_child_rows(&self, parent_row_id: &str) -> Result<Vec<Row>> {
-        let conn = self.conn()?;
+        let _pending = self.hold_pending_appends();
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -428,12 +536,13 @@ impl Database {
             )
             .context("failed to prepare child rows query")?;
 
-        let rows = stmt
+        let mut rows = stmt
             .query(params![parent_row_id])?
             .mapped(Self::row_from_sqlite)
             .collect::<Result<Vec<_>, _>>()
             .context("failed to list child rows")?;
 
+        self.merge_pending_appends(&mut rows);
         Ok(rows)
     }
 
@@ -465,6 +574,75 @@ impl Database {
         Ok(row)
     }
 
+    /// The last top-level row a buffer shows, shared history included
+    pub fn last_shown_row(&self, buffer_id: &str) -> Result<Option<Row>> {
+        if let Some(row) = self.get_last_buffer_row(buffer_id)? {
+            return Ok(Some(row));
+        }
+        match self.get_buffer(buffer_id)?.and_then(|b| b.fork_row_id) {
+            Some(fork_row_id) => self.get_row(&fork_row_id),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether a buffer shows a top-level row, as its own or shared history
+    pub fn buffer_shows_row(&self, buffer_id: &str, row: &Row) -> Result<bool> {
+        if row.buffer_id == buffer_id {
+            return Ok(true);
+        }
+        Ok(self
+            .buffer_ancestry(buffer_id)?
+            .iter()
+            .any(|(id, cutoff)| *id == row.buffer_id && row.position <= *cutoff))
+    }
+
+    /// Find a top-level row a buffer shows from a user-supplied reference
+    ///
+    /// Accepts a row id, the last few characters of one, or `-N` for the
+    /// Nth most recent message (`-1` is the latest). Nested rows resolve to
+    /// the top-level row they belong to.
+    pub fn resolve_row_ref(&self, buffer_id: &str, reference: &str) -> Result<Row> {
+        let reference = reference.trim();
+
+        if let Some(n) = reference.strip_prefix('-') {
+            let n: usize = n.parse().ok().filter(|n| *n > 0).with_context(|| {
+                format!("bad row offset '{}', use -1 for the latest", reference)
+            })?;
+            return self
+                .list_buffer_rows(buffer_id)?
+                .into_iter()
+                .rev()
+                .filter(|r| r.content_method.starts_with("message."))
+                .nth(n - 1)
+                .with_context(|| format!("there aren't {} messages here", n));
+        }
+
+        if let Some(mut row) = self.get_row(reference)? {
+            while let Some(parent_id) = row.parent_row_id.clone() {
+                row = self
+                    .get_row(&parent_id)?
+                    .with_context(|| format!("row {} has a missing parent", row.id))?;
+            }
+            if !self.buffer_shows_row(buffer_id, &row)? {
+                anyhow::bail!("row {} isn't in this room's history", reference);
+            }
+            return Ok(row);
+        }
+
+        if reference.len() < 4 {
+            anyhow::bail!("row reference '{}' is too short", reference);
+        }
+        let mut matches = self
+            .list_buffer_rows(buffer_id)?
+            .into_iter()
+            .filter(|r| r.id.ends_with(reference));
+        match (matches.next(), matches.next()) {
+            (Some(row), None) => Ok(row),
+            (Some(_), Some(_)) => anyhow::bail!("row reference '{}' is ambiguous", reference),
+            _ => anyhow::bail!("no row matching '{}'", reference),
+        }
+    }
+
     /// Append a row to the end of a buffer
     pub fn append_row(&self, row: &mut Row) -> Result<()> {
         if let Some(last) = self.get_last_buffer_row(&row.buffer_id)? {
@@ -512,14 +690,27 @@ impl Database {
             ],
         )
         .context("failed to update row")?;
+        drop(conn);
+
+        if !row.mutable {
+            self.untrack_streaming_row(&row.id);
+        }
+        self.emit_row_event(&row.buffer_id, &row.id, RowEventKind::Updated);
         Ok(())
     }
 
     /// Delete a row (cascades to children, tags, reactions, links)
     pub fn delete_row(&self, id: &str) -> Result<()> {
+        let buffer_id = self.get_row(id)?.map(|r| r.buffer_id);
+        self.untrack_streaming_row(id);
         let conn = self.conn()?;
         conn.execute("DELETE FROM rows WHERE id = ?1", params![id])
             .context("failed to delete row")?;
+        drop(conn);
+
+        if let Some(buffer_id) = buffer_id {
+            self.emit_row_event(&buffer_id, id, RowEventKind::Deleted);
+        }
         Ok(())
     }
 
@@ -575,7 +766,7 @@ impl Database {
 
     /// Get all tags for a row
     pub fn get_row_tags(&self, row_id: &str) -> Result<Vec<String>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare("SELECT tag FROM row_tags WHERE row_id = ?1 ORDER BY tag")
             .context("failed to prepare tags query")?;
@@ -591,7 +782,8 @@ impl Database {
 
     /// Find rows with a specific tag in a buffer
     pub fn find_rows_by_tag(&self, buffer_id: &str, tag: &str) -> Result<Vec<Row>> {
-        let conn = self.conn()?;
+        let _pending = self.hold_pending_appends();
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -609,12 +801,13 @@ impl Database {
             )
             .context("failed to prepare tagged rows query")?;
 
-        let rows = stmt
+        let mut rows = stmt
             .query(params![buffer_id, tag])?
             .mapped(Self::row_from_sqlite)
             .collect::<Result<Vec<_>, _>>()
             .context("failed to find rows by tag")?;
 
+        self.merge_pending_appends(&mut rows);
         Ok(rows)
     }
 
@@ -647,9 +840,69 @@ impl Database {
         Ok(())
     }
 
+    /// Add a reaction, or take it back if the agent already reacted that way.
+    /// Returns true when the reaction was added.
+    pub fn toggle_row_reaction(
+        &self,
+        row_id: &str,
+        agent_id: &str,
+        reaction: &str,
+    ) -> Result<bool> {
+        let conn = self.conn()?;
+        let removed = conn
+            .execute(
+                "DELETE FROM row_reactions WHERE row_id = ?1 AND agent_id = ?2 AND reaction = ?3",
+                params![row_id, agent_id, reaction],
+            )
+            .context("failed to remove reaction")?;
+        if removed == 0 {
+            conn.execute(
+                r#"
+                INSERT INTO row_reactions (id, row_id, agent_id, reaction, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+                params![new_id(), row_id, agent_id, reaction, now_ms()],
+            )
+            .context("failed to add reaction")?;
+        }
+        drop(conn);
+
+        self.emit_row_event_by_id(row_id, RowEventKind::Updated);
+        Ok(removed == 0)
+    }
+
+    /// Reaction counts for the given rows: (row_id, reaction, count),
+    /// each row's reactions in the order they were first used
+    pub fn list_reaction_counts(&self, row_ids: &[String]) -> Result<Vec<(String, String, i64)>> {
+        if row_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.read_conn()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                r#"
+                SELECT row_id, reaction, COUNT(*)
+                FROM row_reactions
+                WHERE row_id IN ({})
+                GROUP BY row_id, reaction
+                ORDER BY MIN(created_at), MIN(rowid)
+                "#,
+                placeholders(row_ids.len())
+            ))
+            .context("failed to prepare reaction counts query")?;
+
+        let counts = stmt
+            .query(params_from_iter(row_ids))?
+            .mapped(|r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to list reaction counts")?;
+
+        Ok(counts)
+    }
+
     /// Get all reactions for a row
     pub fn get_row_reactions(&self, row_id: &str) -> Result<Vec<RowReaction>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 "SELECT id, row_id, agent_id, reaction, created_at FROM row_reactions WHERE row_id = ?1 ORDER BY created_at",
@@ -683,21 +936,38 @@ impl Database {
         link_type: LinkType,
     ) -> Result<String> {
         let conn = self.conn()?;
-        let id = new_id();
-        conn.execute(
-            r#"
-            INSERT INTO row_links (id, from_row_id, to_row_id, link_type, created_at)
-            VALUES (?1, ?2, ?3, ?4, ?5)
-            "#,
-            params![id, from_row_id, to_row_id, link_type.as_str(), now_ms()],
-        )
-        .context("failed to create row link")?;
-        Ok(id)
+        insert_row_link_with(&conn, from_row_id, to_row_id, link_type)
+    }
+
+    /// Reply links from the given rows: (reply_row_id, replied_to_row_id)
+    pub fn list_replies_from(&self, row_ids: &[String]) -> Result<Vec<(String, String)>> {
+        if row_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.read_conn()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                r#"
+                SELECT from_row_id, to_row_id
+                FROM row_links
+                WHERE from_row_id IN ({}) AND link_type = 'reply'
+                "#,
+                placeholders(row_ids.len())
+            ))
+            .context("failed to prepare replies query")?;
+
+        let replies = stmt
+            .query(params_from_iter(row_ids))?
+            .mapped(|r| Ok((r.get(0)?, r.get(1)?)))
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to list replies")?;
+
+        Ok(replies)
     }
 
     /// Get outgoing links from a row
     pub fn get_row_links_from(&self, row_id: &str) -> Result<Vec<RowLink>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 "SELECT id, from_row_id, to_row_id, link_type, created_at FROM row_links WHERE from_row_id = ?1",
@@ -724,7 +994,7 @@ impl Database {
 
     /// Get incoming links to a row
     pub fn get_row_links_to(&self, row_id: &str) -> Result<Vec<RowLink>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 "SELECT id, from_row_id, to_row_id, link_type, created_at FROM row_links WHERE to_row_id = ?1",
@@ -763,7 +1033,17 @@ impl Database {
     ///
     /// Used for streaming responses where content is incrementally added.
     /// Only works on mutable rows.
+    ///
+    /// Any text buffered by `buffer_append` is written first to keep ordering.
     pub fn append_to_row(&self, row_id: &str, text: &str) -> Result<()> {
+        self.push_pending_append(row_id, text);
+        self.flush_row_appends(row_id)?;
+        self.emit_row_event_by_id(row_id, RowEventKind::Streamed);
+        Ok(())
+    }
+
+    /// Issue the UPDATE for an append (no write-behind handling)
+    pub(crate) fn write_row_append(&self, row_id: &str, text: &str) -> Result<()> {
         let conn = self.conn()?;
         let now = now_ms();
         conn.execute(
@@ -782,7 +1062,10 @@ impl Database {
     /// Finalize a row (mark as complete, set mutable=false)
     ///
     /// Used when streaming is complete or when a row should no longer be modified.
+    ///
+    /// Flushes any buffered appends for the row before marking it final.
     pub fn finalize_row(&self, row_id: &str) -> Result<()> {
+        self.flush_row_appends(row_id)?;
         let conn = self.conn()?;
         let now = now_ms();
         conn.execute(
@@ -796,6 +1079,10 @@ impl Database {
             params![row_id, now],
         )
         .context("failed to finalize row")?;
+        drop(conn);
+
+        self.emit_row_event_by_id(row_id, RowEventKind::Finalized);
+        self.untrack_streaming_row(row_id);
         Ok(())
     }
 
@@ -816,6 +1103,23 @@ impl Database {
             params![row_id, ephemeral, now],
         )
         .context("failed to set row ephemeral")?;
+        drop(conn);
+
+        self.emit_row_event_by_id(row_id, RowEventKind::Updated);
+        Ok(())
+    }
+
+    /// Pin or unpin a row
+    pub fn set_row_pinned(&self, row_id: &str, pinned: bool) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE rows SET pinned = ?2, updated_at = ?3 WHERE id = ?1",
+            params![row_id, pinned, now_ms()],
+        )
+        .context("failed to set row pinned")?;
+        drop(conn);
+
+        self.emit_row_event_by_id(row_id, RowEventKind::Updated);
         Ok(())
     }
 
@@ -824,7 +1128,8 @@ impl Database {
     /// Returns rows with position > the position of the given row.
     /// If since_id is None, returns all rows.
     pub fn rows_since(&self, buffer_id: &str, since_id: Option<&str>) -> Result<Vec<Row>> {
-        let conn = self.conn()?;
+        let _pending = self.hold_pending_appends();
+        let conn = self.read_conn()?;
 
         // Get the position of the since row
         let since_position: f64 = if let Some(id) = since_id {
@@ -855,19 +1160,20 @@ impl Database {
             )
             .context("failed to prepare rows since query")?;
 
-        let rows = stmt
+        let mut rows = stmt
             .query(params![buffer_id, since_position])?
             .mapped(Self::row_from_sqlite)
             .collect::<Result<Vec<_>, _>>()
             .context("failed to list rows since")?;
 
+        self.merge_pending_appends(&mut rows);
         Ok(rows)
     }
 
     /// List tool call rows for a buffer (tool.call and tool.result)
     /// Returns rows ordered by position (oldest first)
     pub fn list_tool_calls(&self, buffer_id: &str, limit: usize) -> Result<Vec<Row>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -903,7 +1209,7 @@ impl Database {
         &self,
         buffer_id: &str,
     ) -> Result<std::collections::HashMap<String, usize>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"
             SELECT content, COUNT(*) as cnt
@@ -933,7 +1239,8 @@ impl Database {
 
     /// List rows by content method pattern (LIKE query)
     pub fn list_rows_by_method(&self, buffer_id: &str, method_pattern: &str) -> Result<Vec<Row>> {
-        let conn = self.conn()?;
+        let _pending = self.hold_pending_appends();
+        let conn = self.read_conn()?;
         let mut stmt = conn
             .prepare(
                 r#"
@@ -950,12 +1257,13 @@ impl Database {
             )
             .context("failed to prepare rows by method query")?;
 
-        let rows = stmt
+        let mut rows = stmt
             .query(params![buffer_id, method_pattern])?
             .mapped(Self::row_from_sqlite)
             .collect::<Result<Vec<_>, _>>()
             .context("failed to list rows by method")?;
 
+        self.merge_pending_appends(&mut rows);
         Ok(rows)
     }
 }
@@ -1107,6 +1415,19 @@ mod tests {
         let reactions = db.get_row_reactions(&row.id)?;
         assert_eq!(reactions.len(), 2);
 
+        // Toggling adds, then takes back
+        assert!(db.toggle_row_reaction(&row.id, &agent_id, "clap")?);
+        let counts = db.list_reaction_counts(&[row.id.clone()])?;
+        assert_eq!(
+            counts,
+            vec![
+                (row.id.clone(), "laugh".to_string(), 1),
+                (row.id.clone(), "clap".to_string(), 2),
+            ]
+        );
+        assert!(!db.toggle_row_reaction(&row.id, &agent_id, "clap")?);
+        assert_eq!(db.get_row_reactions(&row.id)?.len(), 2);
+
         Ok(())
     }
 
@@ -1132,6 +1453,9 @@ mod tests {
         let to_links = db.get_row_links_to(&row1.id)?;
         assert_eq!(to_links.len(), 1);
 
+        let replies = db.list_replies_from(&[row1.id.clone(), row2.id.clone()])?;
+        assert_eq!(replies, vec![(row2.id.clone(), row1.id.clone())]);
+
         db.delete_row_link(&link_id)?;
         let from_links = db.get_row_links_from(&row2.id)?;
         assert!(from_links.is_empty());
@@ -1260,4 +1584,86 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_fork_shares_history_up_to_row() -> Result<()> {
+        let (db, buffer_id, agent_id) = setup()?;
+
+        let mut first = Row::message(&buffer_id, &agent_id, "first", false);
+        let mut second = Row::message(&buffer_id, &agent_id, "second", false);
+        let mut third = Row::message(&buffer_id, &agent_id, "third", false);
+        db.append_row(&mut first)?;
+        db.append_row(&mut second)?;
+        db.append_row(&mut third)?;
+
+        db.fork_room_at("test", "fork", Some(&second.id))?;
+        let fork_buffer = db.get_or_create_room_buffer("fork")?;
+        assert_eq!(fork_buffer.fork_row_id.as_deref(), Some(second.id.as_str()));
+
+        let mut own = Row::message(&fork_buffer.id, &agent_id, "fork only", false);
+        db.append_row(&mut own)?;
+
+        let contents = |rows: Vec<Row>| -> Vec<String> {
+            rows.into_iter().filter_map(|r| r.content).collect()
+        };
+        assert_eq!(
+            contents(db.list_buffer_rows(&fork_buffer.id)?),
+            vec!["first", "second", "fork only"]
+        );
+        assert_eq!(
+            contents(db.list_recent_buffer_rows(&fork_buffer.id, 2)?),
+            vec!["second", "fork only"]
+        );
+
+        // The source is unaffected
+        assert_eq!(
+            contents(db.list_buffer_rows(&buffer_id)?),
+            vec!["first", "second", "third"]
+        );
+
+        // A fork of the fork sees through both levels
+        db.fork_room_at("fork", "grandchild", Some("-3"))?;
+        let grandchild = db.get_or_create_room_buffer("grandchild")?;
+        assert_eq!(
+            grandchild.parent_buffer_id.as_deref(),
+            Some(buffer_id.as_str())
+        );
+        assert_eq!(
+            contents(db.list_buffer_rows(&grandchild.id)?),
+            vec!["first"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_row_ref() -> Result<()> {
+        let (db, buffer_id, agent_id) = setup()?;
+
+        let mut first = Row::message(&buffer_id, &agent_id, "first", false);
+        let mut second = Row::message(&buffer_id, &agent_id, "second", false);
+        db.append_row(&mut first)?;
+        db.append_row(&mut second)?;
+
+        assert_eq!(db.resolve_row_ref(&buffer_id, "-1")?.id, second.id);
+        assert_eq!(db.resolve_row_ref(&buffer_id, "-2")?.id, first.id);
+        assert!(db.resolve_row_ref(&buffer_id, "-3").is_err());
+        assert!(db.resolve_row_ref(&buffer_id, "-0").is_err());
+
+        assert_eq!(db.resolve_row_ref(&buffer_id, &first.id)?.id, first.id);
+        let suffix = &second.id[second.id.len() - 8..];
+        assert_eq!(db.resolve_row_ref(&buffer_id, suffix)?.id, second.id);
+        assert!(db.resolve_row_ref(&buffer_id, "abc").is_err());
+
+        // Rows from another room don't resolve
+        let elsewhere = Room::new("elsewhere");
+        db.insert_room(&elsewhere)?;
+        let other = Buffer::room_chat(&elsewhere.id);
+        db.insert_buffer(&other)?;
+        let mut stray = Row::message(&other.id, &agent_id, "stray", false);
+        db.append_row(&mut stray)?;
+        assert!(db.resolve_row_ref(&buffer_id, &stray.id).is_err());
+
+        Ok(())
+    }
 }
@@ -4,7 +4,7 @@
 //! Uses UUIDv7 for primary keys (time-sortable) and fractional REAL for ordering.
 
 /// Schema version for migrations
-pub const SCHEMA_VERSION: i32 = 104; // 104: Add copied_from for CoW tracking
+pub const SCHEMA_VERSION: i32 = 107; // 107: Add things.deleted_by
 
 /// Complete schema SQL
 pub const SCHEMA: &str = r#"
@@ -99,8 +99,9 @@ CREATE TABLE IF NOT EXISTS buffers (
     tombstone_summary TEXT,
     tombstoned_at INTEGER,
 
-    -- Forking
+    -- Forking: a fork shares its parent's rows up to and including fork_row_id
     parent_buffer_id TEXT,
+    fork_row_id TEXT,
 
     -- Wrap behavior
     include_in_wrap INTEGER DEFAULT 1,
@@ -264,6 +265,22 @@ CREATE TABLE IF NOT EXISTS user_ui_config (
     updated_at INTEGER NOT NULL
 );
 
+--------------------------------------------------------------------------------
+-- INPUT HISTORY
+-- Lines each agent has submitted, one row per distinct line
+--------------------------------------------------------------------------------
+
+CREATE TABLE IF NOT EXISTS input_history (
+    agent_id TEXT NOT NULL REFERENCES agents(id),
+    line TEXT NOT NULL,
+    use_count INTEGER NOT NULL DEFAULT 1,
+    used_at INTEGER NOT NULL,               -- last submitted
+    PRIMARY KEY (agent_id, line)
+);
+
+CREATE INDEX IF NOT EXISTS idx_input_history_recent
+    ON input_history(agent_id, used_at DESC);
+
 --------------------------------------------------------------------------------
 -- THINGS
 -- Tree of everything: rooms, agents, MCPs, tools, data, references
@@ -272,7 +289,7 @@ CREATE TABLE IF NOT EXISTS user_ui_config (
 CREATE TABLE IF NOT EXISTS things (
     id TEXT PRIMARY KEY,                    -- UUIDv7
     parent_id TEXT REFERENCES things(id),   -- NULL = root (world)
-    kind TEXT NOT NULL,                     -- 'container', 'room', 'agent', 'mcp', 'tool', 'data', 'reference'
+    kind TEXT NOT NULL,                     -- 'container', 'room', 'agent', 'mcp', 'tool', 'data', 'reference', 'template', 'zone'
     name TEXT NOT NULL,                     -- display name
     qualified_name TEXT,                    -- unique: 'holler:sample', 'sshwarma:look'
     description TEXT,
@@ -295,7 +312,8 @@ CREATE TABLE IF NOT EXISTS things (
     updated_at INTEGER NOT NULL,            -- Unix timestamp ms
     deleted_at INTEGER,                     -- NULL = not deleted (soft delete)
     created_by TEXT,                        -- Agent who created this thing
-    copied_from TEXT REFERENCES things(id)  -- Source thing ID for CoW copies
+    copied_from TEXT REFERENCES things(id), -- Source thing ID for CoW copies
+    deleted_by TEXT                         -- Agent who deleted this thing (see db::trash)
 );
 
 CREATE INDEX IF NOT EXISTS idx_things_parent ON things(parent_id) WHERE deleted_at IS NULL;
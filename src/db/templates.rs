@@ -0,0 +1,394 @@
+//! Room templates
+//!
+//! A template is a `template`-kind thing under `shared` whose content is a
+//! JSON room blueprint: description, vibe, room_kv, equipment, exits, seed
+//! rows and room scripts. `/template save` captures a room as a blueprint
+//! and `/create <name> --from <template>` sets a new room up from one.
+
+use super::rows::Row;
+use super::scripts::ScriptScope;
+use super::things::{Thing, ThingKind};
+use super::Database;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Qualified names of templates are `template:<name>`
+pub const TEMPLATE_PREFIX: &str = "template:";
+
+/// room_kv keys that belong to one particular room and aren't captured
+fn is_room_specific_key(key: &str) -> bool {
+    matches!(
+        key,
+        "parent" | "owner" | "members" | "archived" | "vibe" | "description"
+    ) || key.starts_with("exit.")
+}
+
+/// Everything needed to set up a room
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoomBlueprint {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vibe: Option<String>,
+    /// Other room settings (navigation, tags, ...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub kv: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equipment: Vec<BlueprintEquip>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exits: Vec<BlueprintExit>,
+    /// Rows posted to the new room's chat, e.g. a welcome note
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<BlueprintRow>,
+    /// Room scripts, including prompts (`prompt.<name>`), by module path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>,
+}
+
+/// A thing to equip in the room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlueprintEquip {
+    /// Qualified name, or the thing's id if it has none
+    pub thing: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+    #[serde(default)]
+    pub priority: f64,
+}
+
+/// An exit to dig from the new room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlueprintExit {
+    pub direction: String,
+    pub target: String,
+    /// Direction of the exit back from the target, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub back: Option<String>,
+}
+
+/// A row to seed the new room's chat with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlueprintRow {
+    #[serde(default = "default_row_method")]
+    pub method: String,
+    pub content: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+fn default_row_method() -> String {
+    "message.user".to_string()
+}
+
+/// What applying a blueprint did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlueprintReport {
+    pub kv: usize,
+    pub equipped: usize,
+    /// Exits dug, as "direction -> target"
+    pub exits: Vec<String>,
+    pub rows: usize,
+    pub scripts: usize,
+    /// Parts that couldn't be applied, and why
+    pub skipped: Vec<String>,
+}
+
+impl Database {
+    /// Capture a room as a blueprint
+    ///
+    /// Seed rows are the room's pinned rows.
+    pub fn room_blueprint(&self, room: &str) -> Result<RoomBlueprint> {
+        let room_obj = self
+            .get_room_by_name(room)?
+            .with_context(|| format!("room '{}' not found", room))?;
+        let kv = self.get_all_room_kv(&room_obj.id)?;
+
+        let mut exits = Vec::new();
+        for (direction, target) in self.get_room_exits(&room_obj.id)? {
+            let back = self
+                .get_exits(&target)?
+                .into_iter()
+                .find(|(_, dest)| dest == room)
+                .map(|(dir, _)| dir);
+            exits.push(BlueprintExit {
+                direction,
+                target,
+                back,
+            });
+        }
+        exits.sort_by(|a, b| a.direction.cmp(&b.direction));
+
+        let equipment = self
+            .get_room_own_equipment(&room_obj.id, None)?
+            .into_iter()
+            .map(|eq| BlueprintEquip {
+                thing: eq.thing.qualified_name.unwrap_or(eq.thing.id),
+                slot: eq.slot,
+                config: eq.config,
+                priority: eq.priority,
+            })
+            .collect();
+
+        let buffer = self.get_or_create_room_chat_buffer(&room_obj.id)?;
+        let rows = self
+            .list_buffer_rows(&buffer.id)?
+            .into_iter()
+            .filter(|r| r.pinned)
+            .filter_map(|r| {
+                Some(BlueprintRow {
+                    method: r.content_method,
+                    content: r.content?,
+                    pinned: true,
+                })
+            })
+            .collect();
+
+        let scripts = self
+            .list_scripts(ScriptScope::Room, Some(room))?
+            .into_iter()
+            .map(|s| (s.module_path, s.code))
+            .collect();
+
+        Ok(RoomBlueprint {
+            description: kv.get("description").cloned(),
+            vibe: kv.get("vibe").cloned(),
+            kv: kv
+                .into_iter()
+                .filter(|(k, _)| !is_room_specific_key(k))
+                .collect(),
+            equipment,
+            exits,
+            rows,
+            scripts,
+        })
+    }
+
+    /// Save a blueprint as `template:<name>`, replacing any template of that name
+    pub fn save_template(
+        &self,
+        name: &str,
+        blueprint: &RoomBlueprint,
+        created_by: Option<&str>,
+    ) -> Result<Thing> {
+        let qualified = format!("{}{}", TEMPLATE_PREFIX, name);
+        let content =
+            serde_json::to_string_pretty(blueprint).context("failed to serialize blueprint")?;
+        let description = blueprint
+            .description
+            .clone()
+            .unwrap_or_else(|| "Room template".to_string());
+
+        if let Some(mut existing) = self.get_thing_by_qualified_name(&qualified)? {
+            if existing.kind != ThingKind::Template {
+                anyhow::bail!("{} exists and isn't a template", qualified);
+            }
+            existing.content = Some(content);
+            existing.description = Some(description);
+            self.update_thing(&existing)?;
+            return Ok(existing);
+        }
+
+        let mut thing = Thing::template(name, content)
+            .with_parent("shared")
+            .with_description(description);
+        thing.qualified_name = Some(qualified);
+        thing.created_by = created_by.map(|s| s.to_string());
+        self.insert_thing(&thing)?;
+        Ok(thing)
+    }
+
+    /// Load a template's blueprint by name or qualified name
+    pub fn get_template(&self, name: &str) -> Result<Option<(Thing, RoomBlueprint)>> {
+        let qualified = if name.starts_with(TEMPLATE_PREFIX) {
+            name.to_string()
+        } else {
+            format!("{}{}", TEMPLATE_PREFIX, name)
+        };
+        let Some(thing) = self.get_thing_by_qualified_name(&qualified)? else {
+            return Ok(None);
+        };
+        if thing.kind != ThingKind::Template {
+            return Ok(None);
+        }
+        let blueprint = serde_json::from_str(thing.content.as_deref().unwrap_or("{}"))
+            .with_context(|| format!("{} has an invalid blueprint", qualified))?;
+        Ok(Some((thing, blueprint)))
+    }
+
+    /// All templates, by name
+    pub fn list_templates(&self) -> Result<Vec<Thing>> {
+        self.list_things_by_kind(ThingKind::Template)
+    }
+
+    /// Set up a room from a blueprint
+    ///
+    /// Meant for a freshly created room. Equipment that no longer exists,
+    /// exit targets that are gone, and exits back that would replace an
+    /// existing one are skipped and reported rather than failing the lot.
+    pub fn apply_blueprint(
+        &self,
+        room: &str,
+        blueprint: &RoomBlueprint,
+        agent_id: Option<&str>,
+        created_by: &str,
+    ) -> Result<BlueprintReport> {
+        let room_obj = self
+            .get_room_by_name(room)?
+            .with_context(|| format!("room '{}' not found", room))?;
+        let mut report = BlueprintReport::default();
+
+        if let Some(ref description) = blueprint.description {
+            self.set_room_kv(&room_obj.id, "description", Some(description))?;
+        }
+        if let Some(ref vibe) = blueprint.vibe {
+            self.set_room_kv(&room_obj.id, "vibe", Some(vibe))?;
+        }
+        for (key, value) in &blueprint.kv {
+            if is_room_specific_key(key) {
+                continue;
+            }
+            self.set_room_kv(&room_obj.id, key, Some(value))?;
+            report.kv += 1;
+        }
+
+        for eq in &blueprint.equipment {
+            let thing = match self.get_thing_by_qualified_name(&eq.thing)? {
+                Some(thing) => Some(thing),
+                None => self.get_thing(&eq.thing)?.filter(|t| !t.is_deleted()),
+            };
+            let Some(thing) = thing else {
+                report
+                    .skipped
+                    .push(format!("equipment {}: not found", eq.thing));
+                continue;
+            };
+            self.room_equip(
+                &room_obj.id,
+                &thing.id,
+                eq.slot.as_deref(),
+                eq.config.as_deref(),
+                eq.priority,
+            )?;
+            report.equipped += 1;
+        }
+
+        for exit in &blueprint.exits {
+            if self.get_room_by_name(&exit.target)?.is_none() {
+                report.skipped.push(format!(
+                    "exit {} -> {}: no such room",
+                    exit.direction, exit.target
+                ));
+                continue;
+            }
+            self.add_exit(room, &exit.direction, &exit.target)?;
+            report
+                .exits
+                .push(format!("{} -> {}", exit.direction, exit.target));
+
+            if let Some(ref back) = exit.back {
+                match self.get_exits(&exit.target)?.get(back) {
+                    Some(existing) => report.skipped.push(format!(
+                        "exit {} from {}: already leads to {}",
+                        back, exit.target, existing
+                    )),
+                    None => {
+                        self.add_exit(&exit.target, back, room)?;
+                        report
+                            .exits
+                            .push(format!("{} -> {} (from {})", back, room, exit.target));
+                    }
+                }
+            }
+        }
+
+        if !blueprint.rows.is_empty() {
+            let buffer = self.get_or_create_room_chat_buffer(&room_obj.id)?;
+            for seed in &blueprint.rows {
+                let mut row = Row::new(&buffer.id, &seed.method);
+                row.source_agent_id = agent_id.map(|s| s.to_string());
+                row.content = Some(seed.content.clone());
+                row.pinned = seed.pinned;
+                self.append_row(&mut row)?;
+                report.rows += 1;
+            }
+        }
+
+        for (module_path, code) in &blueprint.scripts {
+            self.create_script(ScriptScope::Room, Some(room), module_path, code, created_by)?;
+            report.scripts += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_round_trip() -> Result<()> {
+        let db = Database::in_memory()?;
+        db.bootstrap_world()?;
+
+        db.create_room("project", Some("A project room"))?;
+        db.set_vibe("project", Some("heads down"))?;
+        let project = db.get_room_by_name("project")?.expect("room created");
+        db.set_room_kv(&project.id, "navigation", Some("false"))?;
+        db.set_room_owner("project", "amy")?;
+        db.add_exit("project", "south", "lobby")?;
+        db.add_exit("lobby", "north", "project")?;
+        let look = db.get_thing_by_qualified_name("sshwarma:look")?.unwrap();
+        db.room_equip(&project.id, &look.id, Some("command:l"), None, 1.0)?;
+        db.set_prompt("project", "style", "Be terse.", "amy")?;
+
+        let buffer = db.get_or_create_room_buffer("project")?;
+        let mut pinned = Row::message(&buffer.id, "agent", "Read the README first", false);
+        pinned.pinned = true;
+        db.append_row(&mut pinned)?;
+        let mut chatter = Row::message(&buffer.id, "agent", "hi", false);
+        db.append_row(&mut chatter)?;
+
+        let blueprint = db.room_blueprint("project")?;
+        assert_eq!(blueprint.vibe.as_deref(), Some("heads down"));
+        assert_eq!(
+            blueprint.kv.get("navigation").map(String::as_str),
+            Some("false")
+        );
+        assert!(!blueprint.kv.contains_key("owner"));
+        assert_eq!(blueprint.rows.len(), 1);
+
+        db.save_template("project", &blueprint, Some("amy"))?;
+        let (thing, loaded) = db.get_template("project")?.expect("template saved");
+        assert_eq!(thing.kind, ThingKind::Template);
+        assert_eq!(loaded, blueprint);
+        assert_eq!(db.list_templates()?.len(), 1);
+
+        db.create_room("project-2", None)?;
+        let report = db.apply_blueprint("project-2", &loaded, None, "amy")?;
+        assert_eq!(report.equipped, 1);
+        assert_eq!(report.rows, 1);
+        assert_eq!(report.scripts, 1);
+        // The lobby's north exit already leads to the original
+        assert_eq!(report.exits, vec!["south -> lobby"]);
+        assert_eq!(report.skipped.len(), 1);
+
+        let copy = db.get_room_by_name("project-2")?.unwrap();
+        assert_eq!(db.get_vibe("project-2")?.as_deref(), Some("heads down"));
+        assert_eq!(
+            db.get_room_kv(&copy.id, "description")?.as_deref(),
+            Some("A project room")
+        );
+        assert_eq!(db.get_room_equipment(&copy.id, None)?.len(), 1);
+        assert!(db.get_prompt("project-2", "style")?.is_some());
+        assert_eq!(
+            db.get_exits("project-2")?.get("south").map(String::as_str),
+            Some("lobby")
+        );
+
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@
 
 use super::{new_id, now_ms, Database};
 use anyhow::{Context, Result};
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
 /// Thing kinds in the world tree
@@ -26,6 +26,10 @@ pub enum ThingKind {
     Data,
     /// URI to external resource
     Reference,
+    /// Room blueprint (see `db::templates`)
+    Template,
+    /// Group of rooms under `rooms` (see `db::zones`)
+    Zone,
 }
 
 impl ThingKind {
@@ -38,6 +42,8 @@ impl ThingKind {
             Self::Tool => "tool",
             Self::Data => "data",
             Self::Reference => "reference",
+            Self::Template => "template",
+            Self::Zone => "zone",
         }
     }
 
@@ -50,6 +56,8 @@ impl ThingKind {
             "tool" => Some(Self::Tool),
             "data" => Some(Self::Data),
             "reference" => Some(Self::Reference),
+            "template" => Some(Self::Template),
+            "zone" => Some(Self::Zone),
             _ => None,
         }
     }
@@ -148,6 +156,13 @@ impl Thing {
         thing
     }
 
+    /// Create a template thing holding a room blueprint as JSON
+    pub fn template(name: impl Into<String>, blueprint: impl Into<String>) -> Self {
+        let mut thing = Self::new(name, ThingKind::Template);
+        thing.content = Some(blueprint.into());
+        thing
+    }
+
     /// Set parent (builder pattern)
     pub fn with_parent(mut self, parent_id: impl Into<String>) -> Self {
         self.parent_id = Some(parent_id.into());
@@ -172,6 +187,66 @@ impl Thing {
     }
 }
 
+/// Insert a thing on `conn`, e.g. inside a transaction
+pub(super) fn insert_thing_with(conn: &Connection, thing: &Thing) -> Result<()> {
+    conn.execute(
+        r#"INSERT INTO things
+           (id, parent_id, kind, name, qualified_name, description,
+            content, uri, metadata, code, default_slot, params,
+            available, created_at, updated_at, deleted_at, created_by, copied_from)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)"#,
+        params![
+            thing.id,
+            thing.parent_id,
+            thing.kind.as_str(),
+            thing.name,
+            thing.qualified_name,
+            thing.description,
+            thing.content,
+            thing.uri,
+            thing.metadata,
+            thing.code,
+            thing.default_slot,
+            thing.params,
+            thing.available,
+            thing.created_at,
+            thing.updated_at,
+            thing.deleted_at,
+            thing.created_by,
+            thing.copied_from,
+        ],
+    )
+    .context("failed to insert thing")?;
+    Ok(())
+}
+
+/// Get a thing by qualified name on `conn` (not deleted)
+pub(super) fn get_thing_by_qualified_name_with(
+    conn: &Connection,
+    qualified_name: &str,
+) -> Result<Option<Thing>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT id, parent_id, kind, name, qualified_name, description,
+                  content, uri, metadata, code, default_slot, params,
+                  available, created_at, updated_at, deleted_at, created_by, copied_from
+           FROM things
+           WHERE qualified_name = ?1 AND deleted_at IS NULL"#,
+    )?;
+    stmt.query_row(params![qualified_name], Database::thing_from_row)
+        .optional()
+        .context("failed to get thing by qualified name")
+}
+
+/// Soft-delete a thing on `conn`
+pub(super) fn soft_delete_thing_with(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE things SET deleted_at = ?2, updated_at = ?2 WHERE id = ?1",
+        params![id, now_ms()],
+    )
+    .context("failed to soft-delete thing")?;
+    Ok(())
+}
+
 // =============================================================================
 // Database operations
 // =============================================================================
@@ -180,40 +255,12 @@ impl Database {
     /// Insert a new thing
     pub fn insert_thing(&self, thing: &Thing) -> Result<()> {
         let conn = self.conn()?;
-        conn.execute(
-            r#"INSERT INTO things
-               (id, parent_id, kind, name, qualified_name, description,
-                content, uri, metadata, code, default_slot, params,
-                available, created_at, updated_at, deleted_at, created_by, copied_from)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)"#,
-            params![
-                thing.id,
-                thing.parent_id,
-                thing.kind.as_str(),
-                thing.name,
-                thing.qualified_name,
-                thing.description,
-                thing.content,
-                thing.uri,
-                thing.metadata,
-                thing.code,
-                thing.default_slot,
-                thing.params,
-                thing.available,
-                thing.created_at,
-                thing.updated_at,
-                thing.deleted_at,
-                thing.created_by,
-                thing.copied_from,
-            ],
-        )
-        .context("failed to insert thing")?;
-        Ok(())
+        insert_thing_with(&conn, thing)
     }
 
     /// Get a thing by ID
     pub fn get_thing(&self, id: &str) -> Result<Option<Thing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, parent_id, kind, name, qualified_name, description,
                       content, uri, metadata, code, default_slot, params,
@@ -227,22 +274,31 @@ impl Database {
 
     /// Get a thing by qualified name (unique, not deleted)
     pub fn get_thing_by_qualified_name(&self, qualified_name: &str) -> Result<Option<Thing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
+        get_thing_by_qualified_name_with(&conn, qualified_name)
+    }
+
+    /// Get a room's thing by room name (not deleted)
+    ///
+    /// Only rooms placed in the world tree (lobby, home, rooms in a zone)
+    /// have one.
+    pub fn get_room_thing(&self, room: &str) -> Result<Option<Thing>> {
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, parent_id, kind, name, qualified_name, description,
                       content, uri, metadata, code, default_slot, params,
                       available, created_at, updated_at, deleted_at, created_by, copied_from
                FROM things
-               WHERE qualified_name = ?1 AND deleted_at IS NULL"#,
+               WHERE kind = 'room' AND name = ?1 AND deleted_at IS NULL"#,
         )?;
-        stmt.query_row(params![qualified_name], Self::thing_from_row)
+        stmt.query_row(params![room], Self::thing_from_row)
             .optional()
-            .context("failed to get thing by qualified name")
+            .context("failed to get room thing")
     }
 
     /// Get children of a thing (not deleted)
     pub fn get_thing_children(&self, parent_id: &str) -> Result<Vec<Thing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, parent_id, kind, name, qualified_name, description,
                       content, uri, metadata, code, default_slot, params,
@@ -262,7 +318,7 @@ impl Database {
         parent_id: &str,
         kind: ThingKind,
     ) -> Result<Vec<Thing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, parent_id, kind, name, qualified_name, description,
                       content, uri, metadata, code, default_slot, params,
@@ -278,7 +334,7 @@ impl Database {
 
     /// List all things of a kind (not deleted)
     pub fn list_things_by_kind(&self, kind: ThingKind) -> Result<Vec<Thing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, parent_id, kind, name, qualified_name, description,
                       content, uri, metadata, code, default_slot, params,
@@ -294,7 +350,7 @@ impl Database {
 
     /// Find things by name pattern (glob matching, not deleted)
     pub fn find_things_by_name(&self, pattern: &str) -> Result<Vec<Thing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, parent_id, kind, name, qualified_name, description,
                       content, uri, metadata, code, default_slot, params,
@@ -310,7 +366,7 @@ impl Database {
 
     /// Find things by qualified name pattern (glob matching, not deleted)
     pub fn find_things_by_qualified_name(&self, pattern: &str) -> Result<Vec<Thing>> {
-        let conn = self.conn()?;
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, parent_id, kind, name, qualified_name, description,
                       content, uri, metadata, code, default_slot, params,
@@ -357,19 +413,14 @@ impl Database {
     /// Soft-delete a thing (sets deleted_at)
     pub fn soft_delete_thing(&self, id: &str) -> Result<()> {
         let conn = self.conn()?;
-        conn.execute(
-            "UPDATE things SET deleted_at = ?2, updated_at = ?2 WHERE id = ?1",
-            params![id, now_ms()],
-        )
-        .context("failed to soft-delete thing")?;
-        Ok(())
+        soft_delete_thing_with(&conn, id)
     }
 
     /// Restore a soft-deleted thing
     pub fn restore_thing(&self, id: &str) -> Result<()> {
         let conn = self.conn()?;
         conn.execute(
-            "UPDATE things SET deleted_at = NULL, updated_at = ?2 WHERE id = ?1",
+            "UPDATE things SET deleted_at = NULL, deleted_by = NULL, updated_at = ?2 WHERE id = ?1",
             params![id, now_ms()],
         )
         .context("failed to restore thing")?;
@@ -388,10 +439,24 @@ impl Database {
     }
 
     /// Hard-delete a thing (permanent, for garbage collection)
+    ///
+    /// Equipment and exits that point at it go too.
     pub fn hard_delete_thing(&self, id: &str) -> Result<()> {
-        let conn = self.conn()?;
-        conn.execute("DELETE FROM things WHERE id = ?1", params![id])
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM equipped WHERE context_id = ?1 OR thing_id = ?1",
+            params![id],
+        )?;
+        tx.execute(
+            "DELETE FROM exits WHERE from_thing_id = ?1 OR to_thing_id = ?1",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM room_equip WHERE thing_id = ?1", params![id])?;
+        tx.execute("DELETE FROM agent_equip WHERE thing_id = ?1", params![id])?;
+        tx.execute("DELETE FROM things WHERE id = ?1", params![id])
             .context("failed to hard-delete thing")?;
+        tx.commit()?;
         Ok(())
     }
 
@@ -546,7 +611,7 @@ impl Database {
     // 0: id, 1: parent_id, 2: kind, 3: name, 4: qualified_name, 5: description,
     // 6: content, 7: uri, 8: metadata, 9: code, 10: default_slot, 11: params,
     // 12: available, 13: created_at, 14: updated_at, 15: deleted_at, 16: created_by, 17: copied_from
-    fn thing_from_row(row: &rusqlite::Row) -> rusqlite::Result<Thing> {
+    pub(super) fn thing_from_row(row: &rusqlite::Row) -> rusqlite::Result<Thing> {
         let kind_str: String = row.get(2)?;
         let kind = ThingKind::parse(&kind_str).unwrap_or(ThingKind::Data);
         Ok(Thing {
@@ -580,9 +645,9 @@ impl Database {
     /// - Internal tools (sshwarma:look, sshwarma:say, etc.)
     /// - Default equipped relationships
     pub fn bootstrap_world(&self) -> Result<()> {
-        // Check if world already exists
+        // Check if world already exists; older worlds may predate some tools
         if self.get_thing("world")?.is_some() {
-            return Ok(());
+            return self.ensure_internal_tools().map(|_| ());
         }
 
         // Create world (use fixed ID for stability)
@@ -632,6 +697,15 @@ impl Database {
         lobby_room.id = "lobby".to_string();
         self.insert_room(&lobby_room)?;
 
+        let tools = self.ensure_internal_tools()?;
+        tracing::info!("bootstrapped world structure with {} internal tools", tools);
+        Ok(())
+    }
+
+    /// Register any internal tools that aren't in the world yet
+    ///
+    /// New tools are equipped to the lobby. Returns how many were added.
+    fn ensure_internal_tools(&self) -> Result<usize> {
         // Register internal tools with Lua code
         // Format: (name, qualified_name, description, code, default_slot)
         let internal_tools: Vec<(&str, &str, &str, &str, Option<&str>)> = vec![
@@ -708,6 +782,13 @@ impl Database {
                 include_str!("../embedded/tools/go.lua"),
                 None,
             ),
+            (
+                "travel",
+                "sshwarma:travel",
+                "Walk the shortest exit path to a room",
+                include_str!("../embedded/tools/travel.lua"),
+                None,
+            ),
             (
                 "create",
                 "sshwarma:create",
@@ -724,28 +805,28 @@ impl Database {
             ),
         ];
 
-        let mut tool_ids = Vec::new();
-        for (name, qualified, desc, code, default_slot) in internal_tools {
+        let mut added = 0;
+        for (i, (name, qualified, desc, code, default_slot)) in
+            internal_tools.into_iter().enumerate()
+        {
+            let id = format!("tool_{}", name);
+            if self.get_thing(&id)?.is_some() {
+                continue;
+            }
             let mut tool = Thing::tool(name, qualified)
                 .with_parent("internal")
                 .with_description(desc);
-            tool.id = format!("tool_{}", name);
+            tool.id = id;
             tool.code = Some(code.to_string());
             tool.default_slot = default_slot.map(|s| s.to_string());
             self.insert_thing(&tool)?;
-            tool_ids.push(tool.id);
-        }
 
-        // Equip internal tools directly to lobby room
-        for (i, tool_id) in tool_ids.iter().enumerate() {
-            self.room_equip("lobby", tool_id, None, None, i as f64)?;
+            // Equip internal tools directly to lobby room
+            self.room_equip("lobby", &tool.id, None, None, i as f64)?;
+            added += 1;
         }
 
-        tracing::info!(
-            "bootstrapped world structure with {} internal tools",
-            tool_ids.len()
-        );
-        Ok(())
+        Ok(added)
     }
 
     /// Ensure the world structure exists (called on startup)
@@ -959,11 +1040,11 @@ mod tests {
 
         // Verify internal tools
         let tools = db.get_thing_children("internal")?;
-        assert_eq!(tools.len(), 12); // 12 internal tools with Lua code
+        assert_eq!(tools.len(), 13); // 13 internal tools with Lua code
 
         // Verify lobby has equipped tools
         let equipped = db.get_room_equipment("lobby", None)?;
-        assert_eq!(equipped.len(), 12);
+        assert_eq!(equipped.len(), 13);
 
         // Verify tools have Lua code
         let look = db.get_thing_by_qualified_name("sshwarma:look")?.unwrap();
@@ -976,6 +1057,12 @@ mod tests {
 
         // Bootstrap should be idempotent
         db.bootstrap_world()?;
+        assert_eq!(db.get_thing_children("internal")?.len(), 13);
+
+        // Tools missing from an older world are filled in
+        db.hard_delete_thing("tool_travel")?;
+        db.bootstrap_world()?;
+        assert!(db.get_thing_by_qualified_name("sshwarma:travel")?.is_some());
 
         Ok(())
     }
@@ -0,0 +1,271 @@
+//! Trash: soft-deleted things
+//!
+//! Deleting a thing sets `deleted_at` (and `deleted_by`, when someone did
+//! it) and hides it everywhere else; its equipment rows stay, so a restored
+//! thing comes back equipped where it was. `purge_trash` hard-deletes things
+//! once they've been in the trash long enough.
+
+use super::things::{get_thing_by_qualified_name_with, Thing};
+use super::{now_ms, Database};
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::time::Duration;
+
+const TRASH_COLUMNS: &str = "id, parent_id, kind, name, qualified_name, description, \
+     content, uri, metadata, code, default_slot, params, \
+     available, created_at, updated_at, deleted_at, created_by, copied_from, deleted_by";
+
+/// A deleted thing and who deleted it
+#[derive(Debug, Clone)]
+pub struct TrashItem {
+    pub thing: Thing,
+    pub deleted_by: Option<String>,
+}
+
+fn trash_item_from_row(row: &rusqlite::Row) -> rusqlite::Result<TrashItem> {
+    Ok(TrashItem {
+        thing: Database::thing_from_row(row)?,
+        deleted_by: row.get(18)?,
+    })
+}
+
+/// Soft-delete a thing on `conn`, recording who deleted it
+pub(super) fn trash_thing_with(conn: &Connection, id: &str, deleted_by: &str) -> Result<()> {
+    conn.execute(
+        r#"UPDATE things SET deleted_at = ?2, deleted_by = ?3, updated_at = ?2
+           WHERE id = ?1 AND deleted_at IS NULL"#,
+        params![id, now_ms(), deleted_by],
+    )
+    .context("failed to trash thing")?;
+    Ok(())
+}
+
+/// A qualified name nothing is using, looked up on `conn`
+pub(super) fn free_qualified_name_with(conn: &Connection, base: &str) -> Result<String> {
+    if get_thing_by_qualified_name_with(conn, base)?.is_none() {
+        return Ok(base.to_string());
+    }
+    let mut n = 2;
+    loop {
+        let candidate = format!("{}-{}", base, n);
+        if get_thing_by_qualified_name_with(conn, &candidate)?.is_none() {
+            return Ok(candidate);
+        }
+        n += 1;
+    }
+}
+
+impl Database {
+    /// Soft-delete a thing, recording who deleted it
+    pub fn trash_thing(&self, id: &str, deleted_by: &str) -> Result<()> {
+        let conn = self.conn()?;
+        trash_thing_with(&conn, id, deleted_by)
+    }
+
+    /// Deleted things directly under a parent, most recently deleted first
+    pub fn list_trash(&self, parent_id: &str) -> Result<Vec<TrashItem>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {} FROM things
+               WHERE parent_id = ?1 AND deleted_at IS NOT NULL
+               ORDER BY deleted_at DESC"#,
+            TRASH_COLUMNS
+        ))?;
+        let items = stmt
+            .query_map(params![parent_id], trash_item_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("failed to list trash")?;
+        Ok(items)
+    }
+
+    /// The most recently deleted thing with this qualified name or ID
+    pub fn find_trashed(&self, name: &str) -> Result<Option<TrashItem>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {} FROM things
+               WHERE (qualified_name = ?1 OR id = ?1) AND deleted_at IS NOT NULL
+               ORDER BY deleted_at DESC LIMIT 1"#,
+            TRASH_COLUMNS
+        ))?;
+        stmt.query_row(params![name], trash_item_from_row)
+            .optional()
+            .context("failed to find deleted thing")
+    }
+
+    /// A qualified name nothing is using: `base`, or `base-2`, `base-3`, ...
+    pub fn free_qualified_name(&self, base: &str) -> Result<String> {
+        let conn = self.read_conn()?;
+        free_qualified_name_with(&conn, base)
+    }
+
+    /// Bring a deleted thing back
+    ///
+    /// `qualified_name` renames it on the way, for when something else has
+    /// taken its name since; without it, a taken name is an error that
+    /// suggests a free one. A thing whose parent is gone comes back under
+    /// `fallback_parent`.
+    pub fn restore_from_trash(
+        &self,
+        id: &str,
+        qualified_name: Option<&str>,
+        fallback_parent: &str,
+    ) -> Result<Thing> {
+        let thing = self
+            .get_thing(id)?
+            .with_context(|| format!("thing {} not found", id))?;
+        if !thing.is_deleted() {
+            bail!("{} isn't deleted", qualified_name_or_id(&thing));
+        }
+
+        let qualified = qualified_name
+            .map(|s| s.to_string())
+            .or(thing.qualified_name.clone());
+        if let Some(ref qualified) = qualified {
+            if self.get_thing_by_qualified_name(qualified)?.is_some() {
+                bail!(
+                    "{} is taken by another thing; restore it as {} instead",
+                    qualified,
+                    self.free_qualified_name(qualified)?
+                );
+            }
+        }
+
+        // Things dropped in a room hang off the room's id rather than a thing
+        let parent_alive = match thing.parent_id.as_deref() {
+            Some(parent) => {
+                self.get_thing(parent)?.is_some_and(|p| !p.is_deleted())
+                    || self.get_room(parent)?.is_some()
+            }
+            None => false,
+        };
+        let parent = if parent_alive {
+            thing.parent_id.clone()
+        } else {
+            Some(fallback_parent.to_string())
+        };
+
+        let conn = self.conn()?;
+        conn.execute(
+            r#"UPDATE things
+               SET deleted_at = NULL, deleted_by = NULL, qualified_name = ?2, parent_id = ?3,
+                   updated_at = ?4
+               WHERE id = ?1"#,
+            params![id, qualified, parent, now_ms()],
+        )
+        .context("failed to restore thing")?;
+        drop(conn);
+
+        self.get_thing(id)?
+            .with_context(|| format!("thing {} vanished while restoring", id))
+    }
+
+    /// Hard-delete things deleted before `cutoff_ms`, returning how many went
+    ///
+    /// Things that still have children are kept until the children go, so
+    /// nothing is left pointing at a parent that no longer exists.
+    pub fn purge_trash(&self, cutoff_ms: i64) -> Result<usize> {
+        let mut purged = 0;
+        loop {
+            let ids = self.purgeable_trash(cutoff_ms)?;
+            if ids.is_empty() {
+                return Ok(purged);
+            }
+            for id in &ids {
+                self.hard_delete_thing(id)?;
+            }
+            purged += ids.len();
+        }
+    }
+
+    /// Hard-delete things that have been in the trash longer than `age`
+    pub fn purge_trash_older_than(&self, age: Duration) -> Result<usize> {
+        self.purge_trash(now_ms() - age.as_millis() as i64)
+    }
+
+    /// Things deleted before the cutoff that nothing hangs off any more
+    fn purgeable_trash(&self, cutoff_ms: i64) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            r#"SELECT id FROM things t
+               WHERE deleted_at IS NOT NULL AND deleted_at < ?1
+                 AND NOT EXISTS (SELECT 1 FROM things c WHERE c.parent_id = t.id)"#,
+        )?;
+        let ids = stmt
+            .query_map(params![cutoff_ms], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .context("failed to find old trash")?;
+        Ok(ids)
+    }
+}
+
+fn qualified_name_or_id(thing: &Thing) -> &str {
+    thing.qualified_name.as_deref().unwrap_or(&thing.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::things::ids;
+
+    #[test]
+    fn test_trash_and_restore() -> Result<()> {
+        let db = Database::in_memory()?;
+        db.bootstrap_world()?;
+        let amy = db.ensure_agent_thing("amy")?;
+
+        let mut note = Thing::data("note", "remember the milk").with_parent(&amy);
+        note.qualified_name = Some("amy:note".to_string());
+        db.insert_thing(&note)?;
+        db.trash_thing(&note.id, "amy")?;
+        assert!(db.get_thing_by_qualified_name("amy:note")?.is_none());
+
+        let trash = db.list_trash(&amy)?;
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].deleted_by.as_deref(), Some("amy"));
+        let found = db.find_trashed("amy:note")?.expect("note is in the trash");
+        assert_eq!(found.thing.id, note.id);
+
+        // Someone takes the name in the meantime
+        let mut other = Thing::data("note", "another note").with_parent(&amy);
+        other.qualified_name = Some("amy:note".to_string());
+        db.insert_thing(&other)?;
+        let err = db
+            .restore_from_trash(&note.id, None, ids::SHARED)
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("amy:note-2"));
+
+        let restored = db.restore_from_trash(&note.id, Some("amy:note-2"), ids::SHARED)?;
+        assert_eq!(restored.qualified_name.as_deref(), Some("amy:note-2"));
+        assert_eq!(restored.parent_id.as_deref(), Some(amy.as_str()));
+        assert!(db.list_trash(&amy)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_trash_keeps_recent_and_parents() -> Result<()> {
+        let db = Database::in_memory()?;
+        db.bootstrap_world()?;
+
+        let box_ = Thing::container("box").with_parent(ids::SHARED);
+        db.insert_thing(&box_)?;
+        let inside = Thing::data("inside", "x").with_parent(&box_.id);
+        db.insert_thing(&inside)?;
+        let recent = Thing::data("recent", "y").with_parent(ids::SHARED);
+        db.insert_thing(&recent)?;
+
+        db.trash_thing(&box_.id, "amy")?;
+        db.trash_thing(&inside.id, "amy")?;
+        db.trash_thing(&recent.id, "amy")?;
+        db.conn()?.execute(
+            "UPDATE things SET deleted_at = 1 WHERE id IN (?1, ?2)",
+            params![box_.id, inside.id],
+        )?;
+
+        // The box goes once what was inside it has
+        assert_eq!(db.purge_trash(1000)?, 2);
+        assert!(db.get_thing(&box_.id)?.is_none());
+        assert!(db.get_thing(&inside.id)?.is_none());
+        assert!(db.find_trashed(&recent.id)?.is_some());
+        Ok(())
+    }
+}
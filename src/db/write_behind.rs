@@ -0,0 +1,313 @@
+//! Write-behind buffer for streaming row appends
+//!
+//! Models stream one token at a time. Rather than issuing an UPDATE per token,
+//! chunks accumulate here per row and are flushed on a short interval, when a
+//! row's tail grows past `APPEND_FLUSH_BYTES`, or when the row is finalized.
+//! Row queries merge the pending tail into mutable rows, so readers see the
+//! live content whether or not it has reached SQLite yet.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock, RwLockReadGuard};
+use std::time::Duration;
+
+use super::events::RowEventKind;
+use super::rows::Row;
+use super::Database;
+
+/// Flush a row immediately once its pending tail reaches this many bytes
+pub const APPEND_FLUSH_BYTES: usize = 1024;
+
+/// How often streaming tasks flush buffered tails
+pub const APPEND_FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Pending appends keyed by row ID
+///
+/// A flush writes a snapshot of a row's tail and only then drains that many
+/// bytes, so text pushed while the write is in flight stays queued for the
+/// next flush rather than being dropped with the snapshot. Readers hold
+/// `flushing` from before they read rows until they've merged the tails, so
+/// they see a row and its tail from the same side of a flush.
+#[derive(Default)]
+pub(crate) struct PendingAppends {
+    tails: Mutex<HashMap<String, String>>,
+    /// Written across snapshot, write and drain so flushes don't interleave;
+    /// read by row readers
+    flushing: RwLock<()>,
+}
+
+impl PendingAppends {
+    /// Add text to a row's tail, returning the tail's new length
+    fn push(&self, row_id: &str, text: &str) -> usize {
+        let Ok(mut tails) = self.tails.lock() else {
+            return 0;
+        };
+        let tail = tails.entry(row_id.to_string()).or_default();
+        tail.push_str(text);
+        tail.len()
+    }
+
+    /// Drop the first `written` bytes of a row's tail once they're stored
+    fn drain(&self, row_id: &str, written: usize) {
+        let Ok(mut tails) = self.tails.lock() else {
+            return;
+        };
+        if let Some(tail) = tails.get_mut(row_id) {
+            tail.drain(..written.min(tail.len()));
+            if tail.is_empty() {
+                tails.remove(row_id);
+            }
+        }
+    }
+
+    /// Copy of a row's tail, if any
+    fn peek(&self, row_id: &str) -> Option<String> {
+        let tails = self.tails.lock().ok()?;
+        tails.get(row_id).cloned()
+    }
+
+    /// IDs of all rows with pending text
+    fn row_ids(&self) -> Vec<String> {
+        self.tails
+            .lock()
+            .map(|tails| tails.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tails.lock().map(|t| t.is_empty()).unwrap_or(true)
+    }
+}
+
+impl Database {
+    /// Buffer streamed text for a row instead of writing it immediately
+    ///
+    /// Flushes the row right away if its tail has grown past
+    /// `APPEND_FLUSH_BYTES`. Returns true when a flush happened.
+    ///
+    /// Only the chunk that starts a tail emits `Streamed`, so subscribers
+    /// hear about a streaming row once per flush rather than once per token.
+    pub fn buffer_append(&self, row_id: &str, text: &str) -> Result<bool> {
+        let pending = self.pending_appends.push(row_id, text);
+        // Readers already see the merged tail, so notify before any flush
+        if !text.is_empty() && pending == text.len() {
+            self.emit_row_event_by_id(row_id, RowEventKind::Streamed);
+        }
+
+        if pending >= APPEND_FLUSH_BYTES {
+            self.flush_row_appends(row_id)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Write a row's buffered tail to the database
+    ///
+    /// The tail stays buffered until the write succeeds, so a failed write
+    /// is retried by the next flush.
+    pub fn flush_row_appends(&self, row_id: &str) -> Result<()> {
+        let _flushing = self.pending_appends.flushing.write();
+        let Some(tail) = self.pending_appends.peek(row_id) else {
+            return Ok(());
+        };
+        if !tail.is_empty() {
+            self.write_row_append(row_id, &tail)?;
+        }
+        self.pending_appends.drain(row_id, tail.len());
+        Ok(())
+    }
+
+    /// Flush every buffered tail, returning how many rows were written
+    pub fn flush_all_appends(&self) -> Result<usize> {
+        let mut flushed = 0;
+        for row_id in self.pending_appends.row_ids() {
+            self.flush_row_appends(&row_id)?;
+            flushed += 1;
+        }
+        Ok(flushed)
+    }
+
+    /// Keep flushes out until dropped; take it before reading rows that
+    /// will go through `merge_pending_appends`
+    pub(crate) fn hold_pending_appends(&self) -> Option<RwLockReadGuard<'_, ()>> {
+        self.pending_appends.flushing.read().ok()
+    }
+
+    /// Merge pending tails into rows read from the database
+    ///
+    /// The caller must hold `hold_pending_appends()` from before the read,
+    /// or a flush landing in between would show a tail twice or not at all.
+    pub(crate) fn merge_pending_appends(&self, rows: &mut [Row]) {
+        if self.pending_appends.is_empty() {
+            return;
+        }
+        for row in rows.iter_mut().filter(|r| r.mutable) {
+            if let Some(tail) = self.pending_appends.peek(&row.id) {
+                row.content.get_or_insert_with(String::new).push_str(&tail);
+            }
+        }
+    }
+
+    /// Queue text behind a row's pending tail, for a direct write to flush
+    pub(crate) fn push_pending_append(&self, row_id: &str, text: &str) {
+        self.pending_appends.push(row_id, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::agents::{Agent, AgentKind};
+    use crate::db::buffers::Buffer;
+    use crate::db::rooms::Room;
+
+    fn setup() -> Result<(Database, String)> {
+        let db = Database::in_memory()?;
+        let room = Room::new("stream");
+        db.insert_room(&room)?;
+        let buffer = Buffer::room_chat(&room.id);
+        db.insert_buffer(&buffer)?;
+        let agent = Agent::new("qwen", AgentKind::Model);
+        db.insert_agent(&agent)?;
+        let mut row = Row::thinking(&buffer.id, &agent.id);
+        db.append_row(&mut row)?;
+        Ok((db, row.id))
+    }
+
+    fn stored_content(db: &Database, row_id: &str) -> Result<Option<String>> {
+        let conn = db.conn()?;
+        let content =
+            conn.query_row("SELECT content FROM rows WHERE id = ?1", [row_id], |row| {
+                row.get(0)
+            })?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_buffered_appends_visible_to_readers() -> Result<()> {
+        let (db, row_id) = setup()?;
+
+        assert!(!db.buffer_append(&row_id, "hello ")?);
+        assert!(!db.buffer_append(&row_id, "world")?);
+
+        // Not written yet, but readers see the merged tail
+        assert_eq!(stored_content(&db, &row_id)?, None);
+        let row = db.get_row(&row_id)?.unwrap();
+        assert_eq!(row.content.as_deref(), Some("hello world"));
+
+        let recent = db.list_recent_buffer_rows(&row.buffer_id, 10)?;
+        assert_eq!(recent[0].content.as_deref(), Some("hello world"));
+
+        db.flush_row_appends(&row_id)?;
+        assert_eq!(
+            stored_content(&db, &row_id)?.as_deref(),
+            Some("hello world")
+        );
+
+        // Nothing pending after a flush, so no double counting
+        let row = db.get_row(&row_id)?.unwrap();
+        assert_eq!(row.content.as_deref(), Some("hello world"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_events_once_per_tail() -> Result<()> {
+        let (db, row_id) = setup()?;
+        let mut rx = db.subscribe_rows();
+        let mut streamed = || {
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .filter(|e| e.kind == RowEventKind::Streamed)
+                .count()
+        };
+
+        for token in ["one", " two", " three"] {
+            db.buffer_append(&row_id, token)?;
+        }
+        assert_eq!(streamed(), 1);
+
+        db.flush_row_appends(&row_id)?;
+        db.buffer_append(&row_id, " four")?;
+        assert_eq!(streamed(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_buffer_flushes_at_threshold() -> Result<()> {
+        let (db, row_id) = setup()?;
+
+        let big = "x".repeat(APPEND_FLUSH_BYTES);
+        assert!(db.buffer_append(&row_id, &big)?);
+        assert_eq!(
+            stored_content(&db, &row_id)?.map(|c| c.len()),
+            Some(big.len())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_buffered_during_flush_is_kept() -> Result<()> {
+        let (db, row_id) = setup()?;
+
+        db.buffer_append(&row_id, "first")?;
+        // A chunk arriving between the snapshot and the drain
+        let snapshot = db.pending_appends.peek(&row_id).unwrap();
+        db.buffer_append(&row_id, " second")?;
+        db.write_row_append(&row_id, &snapshot)?;
+        db.pending_appends.drain(&row_id, snapshot.len());
+
+        assert_eq!(db.pending_appends.peek(&row_id).as_deref(), Some(" second"));
+        db.flush_row_appends(&row_id)?;
+        assert_eq!(
+            stored_content(&db, &row_id)?.as_deref(),
+            Some("first second")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_waits_for_readers() -> Result<()> {
+        let (db, row_id) = setup()?;
+        let db = std::sync::Arc::new(db);
+        db.buffer_append(&row_id, "tail")?;
+
+        let reading = db.hold_pending_appends();
+        let flusher = {
+            let db = db.clone();
+            let row_id = row_id.clone();
+            std::thread::spawn(move || db.flush_row_appends(&row_id))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        // Mid-read, the tail is still pending and not yet in the row
+        assert_eq!(db.pending_appends.peek(&row_id).as_deref(), Some("tail"));
+        drop(reading);
+
+        flusher.join().expect("flush thread panicked")?;
+        assert!(db.pending_appends.peek(&row_id).is_none());
+        assert_eq!(
+            db.get_row(&row_id)?.unwrap().content.as_deref(),
+            Some("tail")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_and_direct_append_flush_pending() -> Result<()> {
+        let (db, row_id) = setup()?;
+
+        db.buffer_append(&row_id, "partial")?;
+        db.append_to_row(&row_id, " + direct")?;
+        assert_eq!(
+            stored_content(&db, &row_id)?.as_deref(),
+            Some("partial + direct")
+        );
+
+        db.buffer_append(&row_id, " tail")?;
+        db.finalize_row(&row_id)?;
+        assert_eq!(
+            stored_content(&db, &row_id)?.as_deref(),
+            Some("partial + direct tail")
+        );
+        assert_eq!(db.flush_all_appends()?, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+//! Room zones
+//!
+//! A zone is a `zone`-kind thing under `rooms` that groups rooms beneath it
+//! in the thing tree. Its metadata holds settings shared by those rooms: a
+//! vibe for rooms without their own, equipment they inherit, and an access
+//! rule (an exit lock rule) for entering any of them.
+//!
+//! Like the vibe, zone equipment is resolved when a room's equipment is
+//! read, so changes reach every room in the zone at once and a room that
+//! leaves stops having it.
+//!
+//! Rooms outside the world tree have no room thing; putting one in a zone
+//! creates it, and taking it out moves it back under `rooms`.
+
+use super::equipped::RoomEquippedThing;
+use super::events::RoomEventKind;
+use super::locks::ExitLock;
+use super::templates::BlueprintEquip;
+use super::things::{ids, Thing, ThingKind};
+use super::Database;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Qualified names of zones are `zone:<name>`
+pub const ZONE_PREFIX: &str = "zone:";
+
+/// Settings shared by a zone's rooms
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZoneSettings {
+    /// Vibe for rooms that don't set one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vibe: Option<String>,
+    /// Equipment the zone's rooms inherit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equipment: Vec<BlueprintEquip>,
+    /// Who may enter the zone's rooms, as a lock rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<String>,
+    /// Who gets in under a `members` rule, besides the zone's owner
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+}
+
+/// A zone and its settings
+#[derive(Debug, Clone)]
+pub struct Zone {
+    /// Thing ID
+    pub id: String,
+    pub name: String,
+    /// Who created it
+    pub owner: Option<String>,
+    pub settings: ZoneSettings,
+}
+
+impl Zone {
+    fn from_thing(thing: Thing) -> Result<Self> {
+        let settings = match thing.metadata.as_deref() {
+            Some(json) => serde_json::from_str(json)
+                .with_context(|| format!("zone {} has invalid settings", thing.name))?,
+            None => ZoneSettings::default(),
+        };
+        Ok(Self {
+            id: thing.id,
+            name: thing.name,
+            owner: thing.created_by,
+            settings,
+        })
+    }
+
+    /// The access rule, if it's set and valid
+    pub fn access(&self) -> Option<ExitLock> {
+        let rule = self.settings.access.as_deref()?;
+        match ExitLock::parse(rule) {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                tracing::warn!(zone = %self.name, "ignoring zone access rule: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Whether someone is the owner or a member
+    pub fn is_member(&self, name: &str) -> bool {
+        self.owner.as_deref() == Some(name) || self.settings.members.iter().any(|m| m == name)
+    }
+}
+
+impl Database {
+    /// Create a zone under `rooms`
+    pub fn create_zone(&self, name: &str, created_by: &str) -> Result<Zone> {
+        let qualified = format!("{}{}", ZONE_PREFIX, name);
+        if self.get_thing_by_qualified_name(&qualified)?.is_some() {
+            bail!("zone '{}' already exists", name);
+        }
+        let mut thing = Thing::new(name, ThingKind::Zone)
+            .with_parent(ids::ROOMS)
+            .with_metadata("{}");
+        thing.qualified_name = Some(qualified);
+        thing.created_by = Some(created_by.to_string());
+        self.insert_thing(&thing)?;
+        Zone::from_thing(thing)
+    }
+
+    /// Look a zone up by name
+    pub fn get_zone(&self, name: &str) -> Result<Option<Zone>> {
+        let qualified = format!("{}{}", ZONE_PREFIX, name);
+        match self.get_thing_by_qualified_name(&qualified)? {
+            Some(thing) if thing.kind == ThingKind::Zone => Ok(Some(Zone::from_thing(thing)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// All zones, by name
+    pub fn list_zones(&self) -> Result<Vec<Zone>> {
+        self.list_things_by_kind(ThingKind::Zone)?
+            .into_iter()
+            .map(Zone::from_thing)
+            .collect()
+    }
+
+    /// Replace a zone's settings
+    pub fn set_zone_settings(&self, zone: &str, settings: &ZoneSettings) -> Result<()> {
+        let qualified = format!("{}{}", ZONE_PREFIX, zone);
+        let mut thing = self
+            .get_thing_by_qualified_name(&qualified)?
+            .filter(|t| t.kind == ThingKind::Zone)
+            .with_context(|| format!("zone '{}' not found", zone))?;
+        thing.metadata =
+            Some(serde_json::to_string(settings).context("failed to serialize zone settings")?);
+        self.update_thing(&thing)?;
+
+        // Rooms pick up the zone's vibe and equipment
+        for room in self.zone_rooms(zone)? {
+            if let Some(room) = self.get_room_by_name(&room)? {
+                self.emit_room_event(&room.id, RoomEventKind::Changed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the rooms in a zone
+    pub fn zone_rooms(&self, zone: &str) -> Result<Vec<String>> {
+        let zone = self
+            .get_zone(zone)?
+            .with_context(|| format!("zone '{}' not found", zone))?;
+        let mut rooms: Vec<_> = self
+            .get_thing_children_by_kind(&zone.id, ThingKind::Room)?
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        rooms.sort();
+        Ok(rooms)
+    }
+
+    /// The zone a room is in, if any
+    pub fn room_zone(&self, room: &str) -> Result<Option<Zone>> {
+        let Some(parent) = self.get_room_thing(room)?.and_then(|t| t.parent_id) else {
+            return Ok(None);
+        };
+        match self.get_thing(&parent)? {
+            Some(thing) if thing.kind == ThingKind::Zone && !thing.is_deleted() => {
+                Ok(Some(Zone::from_thing(thing)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Put a room in a zone, or take it out with `None`
+    pub fn set_room_zone(&self, room: &str, zone: Option<&str>) -> Result<()> {
+        let room_obj = self
+            .get_room_by_name(room)?
+            .with_context(|| format!("room '{}' not found", room))?;
+        let existing = self.get_room_thing(room)?;
+
+        match zone {
+            Some(zone) => {
+                let zone = self
+                    .get_zone(zone)?
+                    .with_context(|| format!("zone '{}' not found", zone))?;
+                match existing {
+                    Some(thing) => self.move_thing(&thing.id, &zone.id)?,
+                    None => self.insert_thing(&Thing::room(room).with_parent(&zone.id))?,
+                }
+            }
+            None => {
+                if let Some(thing) = existing {
+                    if self.room_zone(room)?.is_some() {
+                        self.move_thing(&thing.id, ids::ROOMS)?;
+                    }
+                }
+            }
+        }
+
+        self.emit_room_event(&room_obj.id, RoomEventKind::Changed);
+        Ok(())
+    }
+
+    /// The equipment a room inherits from its zone, if it's in one
+    pub(crate) fn zone_equipment(&self, room_id: &str) -> Result<Vec<RoomEquippedThing>> {
+        let Some(room) = self.get_room(room_id)? else {
+            return Ok(Vec::new());
+        };
+        let Some(zone) = self.room_zone(&room.name)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut equipment = Vec::new();
+        for eq in &zone.settings.equipment {
+            let thing = match self.get_thing_by_qualified_name(&eq.thing)? {
+                Some(thing) => Some(thing),
+                None => self.get_thing(&eq.thing)?,
+            };
+            let Some(thing) = thing.filter(|t| !t.is_deleted()) else {
+                tracing::warn!(zone = %zone.name, thing = %eq.thing, "zone equipment not found");
+                continue;
+            };
+            equipment.push(RoomEquippedThing {
+                equip_id: format!("{}:{}", zone.id, thing.id),
+                room_id: room_id.to_string(),
+                slot: eq.slot.clone(),
+                config: eq.config.clone(),
+                priority: eq.priority,
+                thing,
+            });
+        }
+        Ok(equipment)
+    }
+
+    /// Delete an empty zone
+    pub fn delete_zone(&self, zone: &str) -> Result<()> {
+        let rooms = self.zone_rooms(zone)?;
+        if !rooms.is_empty() {
+            bail!(
+                "zone '{}' still has rooms: {}. Move them out first.",
+                zone,
+                rooms.join(", ")
+            );
+        }
+        let zone = self
+            .get_zone(zone)?
+            .with_context(|| format!("zone '{}' not found", zone))?;
+        self.soft_delete_thing(&zone.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rooms_join_and_leave_zones() -> Result<()> {
+        let db = Database::in_memory()?;
+        db.bootstrap_world()?;
+        db.create_room("mockups", None)?;
+        db.create_room("specs", None)?;
+
+        db.create_zone("design", "amy")?;
+        assert!(db.create_zone("design", "bob").is_err());
+
+        let look = db.get_thing_by_qualified_name("sshwarma:look")?.unwrap();
+        let settings = ZoneSettings {
+            vibe: Some("sketchy".to_string()),
+            equipment: vec![BlueprintEquip {
+                thing: "sshwarma:look".to_string(),
+                slot: None,
+                config: None,
+                priority: 0.0,
+            }],
+            access: Some("members".to_string()),
+            members: vec!["bob".to_string()],
+        };
+        db.set_zone_settings("design", &settings)?;
+
+        db.set_room_zone("mockups", Some("design"))?;
+        db.set_room_zone("specs", Some("design"))?;
+        db.set_room_zone("mockups", Some("design"))?;
+        assert_eq!(db.zone_rooms("design")?, vec!["mockups", "specs"]);
+
+        let zone = db.room_zone("mockups")?.expect("mockups is zoned");
+        assert_eq!(zone.owner.as_deref(), Some("amy"));
+        assert_eq!(zone.settings, settings);
+        assert_eq!(zone.access(), Some(ExitLock::Members));
+        assert!(zone.is_member("bob") && !zone.is_member("carol"));
+        let mockups = db.get_room_by_name("mockups")?.unwrap();
+        let equipment = db.get_room_equipment(&mockups.id, None)?;
+        assert_eq!(equipment.len(), 1);
+        assert_eq!(equipment[0].thing.id, look.id);
+        assert!(db.get_room_own_equipment(&mockups.id, None)?.is_empty());
+
+        // Equipment is inherited, not copied: dropping it from the zone
+        // takes it out of the zone's rooms
+        db.set_zone_settings(
+            "design",
+            &ZoneSettings {
+                equipment: Vec::new(),
+                ..settings.clone()
+            },
+        )?;
+        assert!(db.get_room_equipment(&mockups.id, None)?.is_empty());
+        db.set_zone_settings("design", &settings)?;
+
+        assert!(db.delete_zone("design").is_err());
+        db.set_room_zone("mockups", None)?;
+        db.set_room_zone("specs", None)?;
+        assert!(db.room_zone("mockups")?.is_none());
+        assert!(db.get_room_equipment(&mockups.id, None)?.is_empty());
+        let thing = db.get_room_thing("mockups")?.expect("room thing stays");
+        assert_eq!(thing.parent_id.as_deref(), Some(ids::ROOMS));
+
+        db.delete_zone("design")?;
+        assert!(db.get_zone("design")?.is_none());
+        assert!(db.list_zones()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+--- commands/admin.lua - Server administration commands
+---
+--- Commands that operate on the server as a whole rather than a room.
+--- Permission checks happen on the Rust side; these handlers only format.
+
+local M = {}
+
+local function format_bytes(n)
+    if n >= 1024 * 1024 then
+        return string.format("%.1f MiB", n / (1024 * 1024))
+    elseif n >= 1024 then
+        return string.format("%.1f KiB", n / 1024)
+    end
+    return string.format("%d B", n)
+end
+
+--------------------------------------------------------------------------------
+-- /backup - Take an online database backup
+--------------------------------------------------------------------------------
+
+function M.backup(args)
+    local result = tools.backup()
+
+    if not result.success then
+        return {
+            text = string.format("Backup failed: %s", result.error or "unknown error"),
+            mode = "notification"
+        }
+    end
+
+    local text = string.format("Backup written: %s (%s)", result.path, format_bytes(result.bytes or 0))
+    if result.kept and result.kept > 0 then
+        text = text .. string.format(", keeping last %d", result.kept)
+    end
+
+    return { text = text, mode = "notification" }
+end
+
+return M
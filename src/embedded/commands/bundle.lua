@@ -0,0 +1,151 @@
+-- Bundle command handlers for sshwarma
+--
+-- /bundle writes a thing and everything under it to a TOML or JSON file
+-- in the server's bundles directory, and imports such files into your
+-- inventory. Copy the files between servers (or use
+-- `sshwarma-admin bundle export/import`) to move tools, hooks and data.
+
+local page = require('page')
+local fun = require('fun')
+
+local M = {}
+
+local USAGE = [[Usage:
+  /bundle                        List bundle files
+  /bundle show <thing> [--json] [--equipment]         Preview a bundle
+  /bundle export <thing> <file> [--json] [--equipment] [--force]  Save a bundle
+  /bundle import <file> [--equip] [--replace]         Import into your inventory
+
+<thing> is a qualified name or id, me (your inventory) or room (what's here).
+--equipment records how the things are equipped; --equip applies that on import,
+in this room and for you. --force replaces an existing file. Imported names
+move into your namespace unless you're an admin. --replace (admins) trashes
+things holding a bundled name.]]
+
+--- Split off --flags, returning the plain words and a set of flags
+local function parse_args(rest)
+    local words, flags = {}, {}
+    for word in rest:gmatch("%S+") do
+        local flag = word:match("^%-%-(%S+)$")
+        if flag then
+            flags[flag] = true
+        else
+            table.insert(words, word)
+        end
+    end
+    return words, flags
+end
+
+local function err_text(err)
+    return { text = "bundle: " .. tostring(err), mode = "notification" }
+end
+
+local function list()
+    local files, err = tools.bundles()
+    if not files then
+        return err_text(err)
+    end
+    if #files == 0 then
+        return {
+            text = "No bundles yet. /bundle export <thing> <file> saves one.",
+            mode = "notification"
+        }
+    end
+    local lines = fun.iter(files):map(function(_, file) return "  " .. file end):totable()
+    page.show("Bundles", "Bundles:\n\n" .. table.concat(lines, "\n") .. "\n")
+    return {}
+end
+
+-- /bundle <subcommand> ... - Export, preview, import and list bundles
+function M.bundle(args)
+    local action, rest = (args or ""):match("^%s*(%S*)%s*(.-)%s*$")
+    local words, flags = parse_args(rest)
+
+    if action == "" or action == "list" then
+        return list()
+    end
+
+    if action == "show" or action == "export" then
+        local target, file = words[1], words[2]
+        if not target or (action == "export" and not file) then
+            return { text = USAGE, mode = "notification" }
+        end
+        local export, err = tools.bundle_export(target, {
+            format = flags.json and "json" or "toml",
+            equipment = flags.equipment or false,
+            file = action == "export" and file or nil,
+            force = flags.force or false,
+        })
+        if not export then
+            return err_text(err)
+        end
+        if action == "show" then
+            page.show("Bundle " .. target, export.text)
+            return {}
+        end
+        return {
+            text = string.format("Saved %d thing(s) and %d recipe(s) to %s",
+                export.things, export.equipment, export.path),
+            mode = "notification"
+        }
+    end
+
+    if action == "import" then
+        local file = words[1]
+        if not file then
+            return { text = USAGE, mode = "notification" }
+        end
+        local report, err = tools.bundle_import({
+            file = file,
+            equip = flags.equip or false,
+            replace = flags.replace or false,
+        })
+        if not report then
+            return err_text(err)
+        end
+        local text = string.format("Imported %d thing(s) into your inventory: %s.",
+            report.things, table.concat(report.roots, ", "))
+        if report.equipped > 0 then
+            text = text .. string.format(" Equipped %d.", report.equipped)
+        end
+        if #report.renamed > 0 then
+            text = text .. " Renamed " .. table.concat(report.renamed, ", ") .. "."
+        end
+        if #report.replaced > 0 then
+            text = text .. " Trashed the old " .. table.concat(report.replaced, ", ") .. "."
+        end
+        if #report.skipped > 0 then
+            text = text .. " Skipped " .. table.concat(report.skipped, "; ") .. "."
+        end
+        return { text = text, mode = "notification" }
+    end
+
+    return { text = USAGE, mode = "notification" }
+end
+
+-- Tab completion (see ui/complete.lua)
+M.completers = {
+    bundle = function(index, words)
+        if index == 1 then
+            return { "list", "show", "export", "import" }
+        end
+        local action = words[1]
+        if index == 2 then
+            if action == "show" or action == "export" then return "thing" end
+            if action == "import" then return "bundle" end
+            return nil
+        end
+        if action == "import" then
+            return { "--equip", "--replace" }
+        end
+        if action == "export" and index > 3 then
+            return { "--json", "--equipment", "--force" }
+        end
+        if action == "show" then
+            return { "--json", "--equipment" }
+        end
+        return nil
+    end,
+}
+
+return M
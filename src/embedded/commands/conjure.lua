@@ -234,9 +234,18 @@ Example:
     end
 
     return {
-        text = string.format("Deleted: %s", qualified_name),
+        text = string.format("Deleted: %s (/restore %s brings it back)", qualified_name, qualified_name),
         mode = "notification"
     }
 end
 
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+M.completers = {
+    conjure = { "target" },
+    unconjure = { "thing" },
+}
+
 return M
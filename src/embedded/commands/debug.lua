@@ -92,4 +92,9 @@ function M.wrap(args)
     return {}
 end
 
+-- Tab completion (see ui/complete.lua)
+M.completers = {
+    wrap = { "model" },
+}
+
 return M
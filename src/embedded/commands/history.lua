@@ -150,4 +150,12 @@ function M.history_stats(args)
     return {}
 end
 
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+M.completers = {
+    history = { { "--tools", "--stats" } },
+}
+
 return M
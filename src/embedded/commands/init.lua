@@ -2,13 +2,25 @@
 --
 -- Provides a Lua-based command handler that maps slash commands to
 -- appropriate handlers. Commands are grouped into submodules:
---   - commands.nav:       Navigation (rooms, join, leave, go, exits, look, who)
---   - commands.room:      Room management (create, fork, vibe, nav)
---   - commands.inventory: Inventory system (inv, equip, unequip)
+--   - commands.nav:       Navigation (rooms, join, leave, go, exits, map, travel, look, who)
+--   - commands.room:      Room management (create, template, fork, branches, merge,
+--                         archive, delete-room, vibe, lock, members, nav)
+--   - commands.zone:      Zones of rooms (zone)
+--   - commands.inventory: Inventory system (inv, equip, unequip, trash, restore)
 --   - commands.mcp:       MCP tools (mcp, tools, run)
+--   - commands.admin:     Server administration (backup)
+--   - commands.keys:      Keymap (keys)
+--   - commands.term:      Terminal capabilities (term)
+--   - commands.record:    Session recording (record)
+--   - commands.bundle:    Thing bundles (bundle)
 --
 -- Commands that display content use page.show() directly. Commands returning
 -- quick feedback use: {text = "...", mode = "notification"}
+--
+-- Submodules may also export M.completers = {name = spec}, where spec lists
+-- a completer per argument position or is a function(index, words)
+-- returning the completer for that position. An argument's completer may
+-- itself be a function(word, words); see ui/complete.lua.
 
 local fun = require('fun')
 
@@ -18,13 +30,17 @@ local M = {}
 -- Load submodules
 -- ============================================================================
 
--- Navigation commands (rooms, join, leave, go, exits, look, who)
+-- Navigation commands (rooms, join, leave, go, exits, map, travel, look, who)
 local nav = require("commands.nav")
 
--- Room management commands (create, fork, vibe, dig, nav)
+-- Room management commands (create, template, fork, branches, merge, archive, delete-room, vibe,
+-- dig, lock, members, nav)
 local room = require("commands.room")
 
--- Inventory commands (inv, equip, unequip)
+-- Zone commands (zone)
+local zone = require("commands.zone")
+
+-- Inventory commands (inv, equip, unequip, trash, restore)
 local inventory = require("commands.inventory")
 
 -- MCP commands (mcp, tools, run)
@@ -42,6 +58,21 @@ local reload = require("commands.reload")
 -- Conjure commands (conjure, unconjure)
 local conjure = require("commands.conjure")
 
+-- Admin commands (backup)
+local admin = require("commands.admin")
+
+-- Keymap commands (keys)
+local keys = require("commands.keys")
+
+-- Terminal commands (term)
+local term = require("commands.term")
+
+-- Recording commands (record)
+local record = require("commands.record")
+
+-- Bundle commands (bundle)
+local bundle = require("commands.bundle")
+
 -- ============================================================================
 -- System commands (inline implementations)
 -- ============================================================================
@@ -69,17 +100,25 @@ local function cmd_help(args)
     -- General command help
     local help_text = [[
 Navigation:
-  /rooms              List rooms
+  /rooms [archived]   List rooms by zone (or archived ones)
   /join <room>        Enter a room
   /leave              Return to lobby
-  /create <name>      New room
+  /create <name> [--from <template>]  New room
+  /template [list|save <name>]  Room templates
   /go <direction>     Navigate via exit
   /exits              List exits from room
-  /fork <name>        Fork room (inherit context)
+  /map [radius]       Map of nearby rooms and who's there
+  /travel <room>      Walk the shortest path to a room
+  /fork <name> [--at <ref>]  Fork room (shares history up to <ref>)
+  /branches [all]     Show the fork tree
+  /merge <fork> [--all|--pinned|--summary]  Merge a fork back
+  /archive [room]     Make a room read-only and hide it
+  /unarchive <room>   Reopen an archived room
+  /delete-room <room> [--confirm]  Delete a room (owner/admin)
 
 Looking:
   /look               Room summary
-  /who                Who's online
+  /who [zone [name]]  Who's here, or in each room of a zone
   /history [n]        Recent messages
   /history --tools    Tool call history
   /history --stats    Tool usage statistics
@@ -88,22 +127,41 @@ Room Context:
   /vibe [text]        Set/view room vibe
   /nav [on|off]       Toggle model navigation
   /portal <dir> <room>  Create exit to another room
+  /lock <dir> <rule>  Lock an exit (closed, members, capability:<x>, check:<thing>)
+  /unlock <dir>       Open a locked exit
+  /members [add|remove <name>]  Who gets through members-only exits here
+
+Zones:
+  /zone               List zones
+  /zone create <name> New zone of rooms
+  /zone add <zone> [room]  Put a room in a zone
+  /zone remove [room] Take a room out of its zone
+  /zone vibe|equip|lock|members <zone> ...  Settings shared by its rooms
+  /zone who [name]    Who's in the zone's rooms
+  /zone help          All zone subcommands
 
 Inventory:
   /inv [target]       Show contents (me, room, shared, @agent)
   /take <thing>       Copy thing into your inventory
   /drop <thing>       Move thing to current room
-  /destroy owner:name Delete a thing
+  /destroy owner:name Delete a thing (moves it to the trash)
   /conjure <t> <name> Create thing (t=me,room,shared,@agent)
   /unconjure <qname>  Delete a thing
+  /trash [me|room]    Deleted things, who deleted them and when
+  /restore <qname> [--as <new>]  Bring a deleted thing back
+  /bundle [show|export|import] ...  Move things between servers as files
 
 Equipment:
   /equip <ctx> <thing>    Equip tool (me, room, @agent)
   /unequip <ctx> <thing>  Unequip tool
+  /keys [mode|all]        Show keymap, incl. hotkey: slots
 
 Communication:
   <text>              Say to room
   @model <msg>        Message a model
+  Alt+Enter           New line; :compose for a longer draft
+  (paste)             Multi-line pastes become one message;
+                      large ones are saved as a data thing
 
 Tools:
   /tools              List available tools
@@ -111,11 +169,16 @@ Tools:
 
 MCP:
   /mcp                List connected MCP servers
-  /mcp connect <name> <url>  Connect to MCP server
+  /mcp connect <name> <url>  Connect to MCP server (not saved to input history)
   /mcp disconnect <name>     Disconnect from server
   /mcp refresh <name>        Refresh tool list
 
+Admin:
+  /backup             Back up the database now
+
 UI:
+  /term [colors] [glyphs]  Show/override terminal colors and glyphs
+  /record [start|stop]     Record your screen as an asciicast file
   /reload             Reload UI from database
   /reload default     Reset to embedded default UI
   /reload <module>    Reload specific module
@@ -160,22 +223,38 @@ local handlers = {
     ["leave"]   = nav.leave,
     ["go"]      = nav.go,
     ["exits"]   = nav.exits,
+    ["map"]     = nav.map,
+    ["travel"]  = nav.travel,
     ["look"]    = nav.look,
     ["who"]     = nav.who,
 
     -- Room management (from commands.room)
     ["create"]  = room.create,
+    ["template"] = room.template,
     ["fork"]    = room.fork,
+    ["branches"] = room.branches,
+    ["merge"]   = room.merge,
+    ["archive"] = room.archive,
+    ["unarchive"] = room.unarchive,
+    ["delete-room"] = room.delete_room,
     ["vibe"]    = room.vibe,
     ["portal"]  = room.portal,
+    ["lock"]    = room.lock,
+    ["unlock"]  = room.unlock,
+    ["members"] = room.members,
     ["nav"]     = room.nav,
 
+    -- Zones (from commands.zone)
+    ["zone"]    = zone.zone,
+
     -- Inventory (from commands.inventory)
     ["inv"]       = inventory.inv,
     ["inventory"] = inventory.inv,  -- alias
     ["take"]      = inventory.take,
     ["drop"]      = inventory.drop,
     ["destroy"]   = inventory.destroy,
+    ["trash"]     = inventory.trash,
+    ["restore"]   = inventory.restore,
     ["equip"]     = inventory.equip,
     ["unequip"]   = inventory.unequip,
 
@@ -197,12 +276,74 @@ local handlers = {
     ["conjure"]   = conjure.conjure,
     ["unconjure"] = conjure.unconjure,
 
+    -- Admin (from commands.admin)
+    ["backup"] = admin.backup,
+
+    -- Keymap (from commands.keys)
+    ["keys"] = keys.keys,
+
+    -- Terminal (from commands.term)
+    ["term"] = term.term,
+
+    -- Recording (from commands.record)
+    ["record"] = record.record,
+
+    -- Bundles (from commands.bundle)
+    ["bundle"] = bundle.bundle,
+
     -- System (inline)
     ["help"]  = cmd_help,
     ["quit"]  = cmd_quit,
     ["clear"] = cmd_clear,
 }
 
+-- ============================================================================
+-- Argument completers
+-- ============================================================================
+
+local completers = {
+    ["help"] = { "help_topic" },
+}
+
+for _, module in ipairs({ nav, room, zone, inventory, mcp, history, debug, reload, conjure, admin, keys, term, record, bundle }) do
+    for name, spec in pairs(module.completers or {}) do
+        completers[name] = spec
+    end
+end
+completers["inventory"] = completers["inv"]
+
+--- Find the thing equipped in a command slot, agent first then room
+--- @param name string The command name
+--- @return table|nil Equipment entry
+local function find_equipped(name)
+    local slot_name = "command:" .. name
+    local session = tools.session()
+
+    local equipped = {}
+    if session and session.agent_id then
+        equipped = tools.get_agent_equipment(session.agent_id, slot_name) or {}
+    end
+
+    if #equipped == 0 and session and session.room_id then
+        equipped = tools.get_room_equipment(session.room_id, slot_name) or {}
+    end
+
+    return equipped[1]
+end
+
+--- Read completers from an equipped thing's params, e.g. {"complete": ["room"]}
+--- @param item table Equipment entry
+--- @return table|nil Completer per argument position
+local function equipped_completers(item)
+    local list = item.params and item.params:match('"complete"%s*:%s*%[(.-)%]')
+    if not list then return nil end
+    local spec = {}
+    for kind in list:gmatch('"([^"]*)"') do
+        table.insert(spec, kind ~= "" and kind or false)
+    end
+    return spec
+end
+
 -- ============================================================================
 -- Public API
 -- ============================================================================
@@ -231,22 +372,10 @@ function M.dispatch(name, args)
     end
 
     -- Check for equipped command slot (e.g., command:fish)
-    local slot_name = "command:" .. name
-    local session = tools.session()
-
-    -- Check agent equipment first, then room equipment
-    local equipped = {}
-    if session and session.agent_id then
-        equipped = tools.get_agent_equipment(session.agent_id, slot_name) or {}
-    end
+    local item = find_equipped(name)
 
-    if #equipped == 0 and session and session.room_id then
-        equipped = tools.get_room_equipment(session.room_id, slot_name) or {}
-    end
-
-    if #equipped > 0 then
+    if item then
         -- Found an equipped thing for this command slot
-        local item = equipped[1]
         if item.code then
             -- Execute the thing's Lua code
             local result = tools.execute_code(item.code, args or "")
@@ -289,6 +418,55 @@ function M.list()
     return names
 end
 
+--- Get built-in and equipped command names
+--- @return table Array of command names (sorted, unique)
+function M.list_all()
+    local names = M.list()
+    local seen = {}
+    for _, name in ipairs(names) do seen[name] = true end
+
+    local session = tools.session()
+    local equipped = {}
+    if session and session.agent_id then
+        equipped = tools.get_agent_equipment(session.agent_id, "command:*") or {}
+    end
+    if session and session.room_id then
+        for _, item in ipairs(tools.get_room_equipment(session.room_id, "command:*") or {}) do
+            table.insert(equipped, item)
+        end
+    end
+    for _, item in ipairs(equipped) do
+        local name = item.slot and item.slot:match("^command:(.+)$")
+        if name and not seen[name] then
+            seen[name] = true
+            table.insert(names, name)
+        end
+    end
+
+    table.sort(names)
+    return names
+end
+
+--- Get the completer for a command argument
+--- @param name string The command name
+--- @param index number Argument position (1-based)
+--- @param words table Arguments before the one being completed
+--- @return string|table|function|nil Completer (see ui/complete.lua)
+function M.completer(name, index, words)
+    local spec = completers[name]
+    if spec == nil and not handlers[name] then
+        local item = find_equipped(name)
+        spec = item and equipped_completers(item)
+    end
+
+    if type(spec) == "function" then
+        return spec(index, words)
+    elseif type(spec) == "table" then
+        return spec[index] or nil
+    end
+    return nil
+end
+
 --- Register a new command handler
 --- Used by submodules to extend the command set
 --- @param name string The command name
@@ -301,6 +479,14 @@ end
 --- @param name string The command name
 function M.unregister(name)
     handlers[name] = nil
+    completers[name] = nil
+end
+
+--- Register completers for a command's arguments
+--- @param name string The command name
+--- @param spec table|function Completer per argument position
+function M.register_completer(name, spec)
+    completers[name] = spec
 end
 
 return M
@@ -5,6 +5,8 @@
 ---   take     - Copy a thing into your inventory (CoW)
 ---   drop     - Move a thing from your inventory to current room
 ---   destroy  - Delete a thing (must specify owner)
+---   trash    - List deleted things (me, room)
+---   restore  - Bring a deleted thing back
 ---
 --- Equipment commands (what's ACTIVE):
 ---   equip    - Equip things to user or room with optional slot
@@ -15,6 +17,7 @@
 ---   command:*     - Slash command binding (e.g., command:fish)
 ---   hook:wrap     - Context composition hook
 ---   hook:background - Background execution hook
+---   hotkey:*      - Key binding (e.g., hotkey:ctrl-g, hotkey:normal:gp)
 
 local page = require('page')
 local str = require('str')
@@ -217,31 +220,90 @@ function M.destroy(args)
         return { text = "Not found: " .. owner .. ":" .. thing_name, mode = "notification" }
     end
 
-    if tools.thing_delete(thing.id) then
-        return { text = "Destroyed " .. thing_name, mode = "notification" }
+    local result = tools.thing_delete(thing.id)
+    if result and result.success then
+        return { text = "Destroyed " .. thing_name .. " (/trash to undo)", mode = "notification" }
     else
-        return { text = "Failed to destroy " .. thing_name, mode = "notification" }
+        return {
+            text = "Failed to destroy " .. thing_name .. ": " .. (result and result.error or "unknown error"),
+            mode = "notification"
+        }
     end
 end
 
 --------------------------------------------------------------------------------
--- /equip <context> [slot] <pattern>
+-- /trash [me|room] - List deleted things, most recent first
+--------------------------------------------------------------------------------
+
+function M.trash(args)
+    local target = args and args:match("^%s*(%S+)") or "me"
+    if target ~= "me" and target ~= "room" then
+        return { text = "Usage: /trash [me|room]", mode = "notification" }
+    end
+
+    local items, err = tools.trash(target)
+    if not items then
+        return { text = "Error: " .. tostring(err), mode = "notification" }
+    end
+    if #items == 0 then
+        return { text = "The trash is empty.", mode = "notification" }
+    end
+
+    local lines = {target == "me" and "Your Trash:" or "Room Trash:"}
+    for _, item in ipairs(items) do
+        table.insert(lines, string.format("  %s (%s) deleted %s by %s",
+            item.qualified_name or item.id, item.kind,
+            util.format_time(item.deleted_at), item.deleted_by or "unknown"))
+    end
+    table.insert(lines, "")
+    table.insert(lines, "Restore with: /restore <qname> [--as <new-qname>]")
+
+    page.show("Trash", table.concat(lines, "\n"))
+    return {}
+end
+
+--------------------------------------------------------------------------------
+-- /restore <qname> [--as <new-qname>] - Bring a deleted thing back
+--------------------------------------------------------------------------------
+
+function M.restore(args)
+    local name, rest = (args or ""):match("^%s*(%S*)%s*(.-)%s*$")
+    local rename = rest:match("^%-%-as%s+(%S+)$")
+    if name == "" or (rest ~= "" and not rename) then
+        return { text = "Usage: /restore <qname> [--as <new-qname>]", mode = "notification" }
+    end
+
+    local thing, err = tools.restore(name, rename)
+    if not thing then
+        return { text = "Error: " .. tostring(err), mode = "notification" }
+    end
+    return {
+        text = "Restored " .. (thing.qualified_name or thing.name),
+        mode = "notification"
+    }
+end
+
+--------------------------------------------------------------------------------
+-- /equip <context> [slot] <pattern> [--priority n]
 --
 -- Examples:
 --   /equip me sshwarma:*           - Equip all sshwarma tools to user
 --   /equip room holler:sample      - Equip tool to room
 --   /equip me command:fish atobey:fish  - Equip with specific slot
 --   /equip @qwenl holler:*         - Equip holler tools to agent qwenl
+--   /equip me hotkey:normal:gp me:jump  - Bind g then p over built-in g
+--   /equip me hotkey:normal:g me:jump --priority 0  - Yield to built-in g
 --------------------------------------------------------------------------------
 
 function M.equip(args)
     if not args or args:match("^%s*$") then
         page.show("Equip", [[
-Usage: /equip <context> [slot] <pattern>
+Usage: /equip <context> [slot] <pattern> [--priority n]
 
 Context: me | room | @agent_name
 Pattern: qualified name or glob (e.g., sshwarma:*, holler:sample)
-Slot (optional): command:*, hook:wrap, hook:background
+Slot (optional): command:*, hook:wrap, hook:background, hotkey:<keys>
+Priority (optional): lower wins; built-in keys are 0, hotkeys default below
 
 Examples:
   /equip me                         Show your current equipment
@@ -251,10 +313,20 @@ Examples:
   /equip @qwenl holler:*            Equip holler tools to agent qwenl
   /equip me command:fish atobey:fish   Bind /fish to atobey:fish
   /equip room hook:wrap myns:wrap   Add wrap hook to room
+  /equip me hotkey:ctrl-g me:greet  Bind Ctrl+G to me:greet
+  /equip me hotkey:normal:gp me:jump   Bind g p, replacing built-in g
 ]])
         return {}
     end
 
+    -- Optional explicit priority (otherwise things are ranked in match order)
+    local priority
+    local prio_str = args:match("%-%-priority%s+(%-?[%d%.]+)")
+    if prio_str then
+        priority = tonumber(prio_str)
+        args = args:gsub("%s*%-%-priority%s+%-?[%d%.]+", "")
+    end
+
     local parts = str.split(args, "%s+")
     local context = parts[1]  -- 'me', 'room', or '@agent_name'
 
@@ -336,7 +408,7 @@ Examples:
 
     -- Parse slot config (e.g., hook:background:1000 -> slot=hook:background, config)
     local config = nil
-    if slot then
+    if slot and slot:match("^hook:") then
         local base, extra = slot:match("^([^:]+:[^:]+):(.+)$")
         if base then
             slot = base
@@ -376,14 +448,20 @@ Examples:
     end
 
     -- Equip each matching thing
+    local mode = require('ui.mode')
     local equipped = {}
     fun.iter(things):enumerate():each(function(i, thing)
         local actual_slot = slot or thing.default_slot
+        local rank = priority or i
+        if not priority and actual_slot and actual_slot:match("^hotkey:") then
+            -- Hotkeys replace built-ins unless given a priority
+            rank = mode.BUILTIN_PRIORITY - #things - 1 + i
+        end
         local success
         if context_type == "agent" then
-            success = tools.agent_equip(target_id, thing.id, actual_slot, config, i)
+            success = tools.agent_equip(target_id, thing.id, actual_slot, config, rank)
         else
-            success = tools.room_equip(target_id, thing.id, actual_slot, i)
+            success = tools.room_equip(target_id, thing.id, actual_slot, rank)
         end
         if success then
             table.insert(equipped, thing.qualified_name or thing.name)
@@ -394,11 +472,26 @@ Examples:
         return { text = "Error: could not equip any items", mode = "notification" }
     end
 
+    -- Pick up hotkey: bindings on the next keypress
+    mode.invalidate_hotkeys()
+
     local lines = {string.format("Equipped %d item(s) to %s:", #equipped, display_name)}
     fun.iter(equipped):each(function(_, name)
         table.insert(lines, "  " .. name)
     end)
 
+    -- Say so when a new binding can't fire
+    local just_equipped = {}
+    for _, name in ipairs(equipped) do
+        just_equipped[name] = true
+    end
+    for _, row in ipairs(mode.keymap()) do
+        if row.shadowed_by and just_equipped[row.action] then
+            table.insert(lines, string.format("  warning: %s %s (%s) is shadowed by %s",
+                row.mode, row.keys, row.action, row.shadowed_by))
+        end
+    end
+
     page.show("Equip", table.concat(lines, "\n"))
     return {}
 end
@@ -419,7 +512,7 @@ Usage: /unequip <context> [slot] <pattern>
 
 Context: me | room | @agent_name
 Pattern: qualified name or glob
-Slot (optional): command:*, hook:wrap, hook:background
+Slot (optional): command:*, hook:wrap, hook:background, hotkey:<keys>
 
 Examples:
   /unequip me sshwarma:*            Unequip all sshwarma tools
@@ -510,6 +603,9 @@ Examples:
         return { text = "Error: could not unequip any items", mode = "notification" }
     end
 
+    -- Pick up hotkey: bindings on the next keypress
+    require('ui.mode').invalidate_hotkeys()
+
     local lines = {string.format("Unequipped %d item(s) from %s:", #unequipped, display_name)}
     fun.iter(unequipped):each(function(_, name)
         table.insert(lines, "  " .. name)
@@ -519,4 +615,24 @@ Examples:
     return {}
 end
 
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+--- /equip and /unequip: context, then a thing (slots are typed by hand)
+local function equipment_completer(index)
+    return index == 1 and "context" or "thing"
+end
+
+M.completers = {
+    inv = { "target" },
+    take = { "thing" },
+    drop = { "thing" },
+    destroy = { "thing" },
+    trash = { { "me", "room" } },
+    restore = { "trashed", { "--as" } },
+    equip = equipment_completer,
+    unequip = equipment_completer,
+}
+
 return M
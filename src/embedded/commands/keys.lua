@@ -0,0 +1,66 @@
+-- Keymap command handlers for sshwarma
+--
+-- /keys shows the effective keymap: built-in bindings merged with things
+-- equipped in hotkey:<keyspec> slots on you and the current room.
+-- Commands that display content use page.show() directly.
+
+local page = require('page')
+local M = {}
+
+local SOURCE_LABELS = {
+    ["built-in"] = "built-in",
+    agent = "me",
+    room = "room",
+}
+
+-- /keys [normal|insert|all] - Show the effective keymap
+function M.keys(args)
+    local filter = args and args:match("^%s*(%S+)")
+    if filter and filter ~= "normal" and filter ~= "insert" and filter ~= "all" then
+        return { text = "Usage: /keys [normal|insert|all]", mode = "notification" }
+    end
+    local show_shadowed = filter == "all"
+
+    local mode = require('ui.mode')
+    local rows = mode.keymap()
+
+    local lines = {}
+    local current
+    for _, row in ipairs(rows) do
+        local wanted = not filter or show_shadowed or row.mode == filter
+        if wanted and (show_shadowed or not row.shadowed_by) then
+            if row.mode ~= current then
+                if current then table.insert(lines, "") end
+                table.insert(lines, row.mode:sub(1, 1):upper() .. row.mode:sub(2) .. " mode:")
+                current = row.mode
+            end
+
+            local line = string.format("  %-12s %-28s %s",
+                row.keys, row.action, SOURCE_LABELS[row.source] or row.source)
+            if row.source ~= "built-in" then
+                line = line .. string.format(" (priority %g)", row.priority)
+            end
+            if row.shadowed_by then
+                line = line .. " - shadowed by " .. row.shadowed_by
+            end
+            table.insert(lines, line)
+        end
+    end
+
+    table.insert(lines, "")
+    table.insert(lines, "Bind keys with: /equip me hotkey:<keyspec> <thing>")
+    table.insert(lines, "  e.g. hotkey:ctrl-g, hotkey:normal:gp, hotkey:insert:f2")
+    if not show_shadowed then
+        table.insert(lines, "/keys all also lists shadowed bindings")
+    end
+
+    page.show("Keys", table.concat(lines, "\n"))
+    return {}
+end
+
+-- Tab completion (see ui/complete.lua)
+M.completers = {
+    keys = { { "normal", "insert", "all" } },
+}
+
+return M
@@ -179,4 +179,29 @@ Example: /run orpheus_generate
     return {}
 end
 
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+--- /mcp <subcommand> <server>
+local function mcp_completer(index, words)
+    if index == 1 then
+        return { "connect", "disconnect", "refresh" }
+    elseif index == 2 and (words[1] == "disconnect" or words[1] == "refresh") then
+        return function()
+            local servers = {}
+            for _, conn in ipairs(tools.mcp_connections() or {}) do
+                table.insert(servers, conn.name)
+            end
+            return servers
+        end
+    end
+    return nil
+end
+
+M.completers = {
+    mcp = mcp_completer,
+    run = { "mcp_tool" },
+}
+
 return M
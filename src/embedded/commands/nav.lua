@@ -10,33 +10,71 @@ local util = require('util')
 local M = {}
 
 --------------------------------------------------------------------------------
--- /rooms - List all rooms
+-- /rooms [archived] - List rooms grouped by zone, or the archived ones
 --------------------------------------------------------------------------------
 
+local function room_line(room)
+    local desc = room.description or ""
+    if #desc > 40 then
+        desc = desc:sub(1, 37) .. "..."
+    end
+    local user_plural = room.user_count == 1 and "user" or "users"
+    local desc_part = desc ~= "" and string.format(" (%s)", desc) or ""
+    return string.format("  %s ... %d %s%s\n",
+        room.name, room.user_count, user_plural, desc_part)
+end
+
 function M.rooms(args)
     local rooms = tools.rooms()
+    local want_archived = (args or ""):match("^%s*archived%s*$") ~= nil
 
-    if not rooms or #rooms == 0 then
-        return {
-            text = "No rooms yet. Use /create <name> to start one.",
-            mode = "notification"
-        }
+    local shown = fun.iter(rooms or {})
+        :filter(function(_, room) return (room.archived or false) == want_archived end)
+        :map(function(_, room) return room end)
+        :totable()
+    local archived_count = fun.iter(rooms or {})
+        :filter(function(_, room) return room.archived end)
+        :length()
+
+    if #shown == 0 then
+        local text = want_archived and "No archived rooms."
+            or "No rooms yet. Use /create <name> to start one."
+        return { text = text, mode = "notification" }
     end
 
-    local lines = {"Rooms:\n"}
+    local lines = {want_archived and "Archived rooms (read-only):\n" or "Rooms:\n"}
 
-    fun.iter(rooms):each(function(_, room)
-        local desc = room.description or ""
-        if #desc > 40 then
-            desc = desc:sub(1, 37) .. "..."
+    -- Rooms outside any zone first, then each zone's rooms under a heading
+    local by_zone = {}
+    fun.iter(shown):each(function(_, room)
+        if room.zone then
+            by_zone[room.zone] = by_zone[room.zone] or {}
+            table.insert(by_zone[room.zone], room)
+        else
+            table.insert(lines, room_line(room))
         end
-        local user_plural = room.user_count == 1 and "user" or "users"
-        local desc_part = desc ~= "" and string.format(" (%s)", desc) or ""
-        table.insert(lines, string.format("  %s ... %d %s%s\n",
-            room.name, room.user_count, user_plural, desc_part))
     end)
 
-    page.show("Rooms", table.concat(lines))
+    local zone_vibes = {}
+    fun.iter(tools.zones() or {}):each(function(_, zone)
+        zone_vibes[zone.name] = zone.vibe
+    end)
+    local zone_names = fun.iter(by_zone):map(function(name, _) return name end):totable()
+    table.sort(zone_names)
+    fun.iter(zone_names):each(function(_, name)
+        local vibe = zone_vibes[name]
+        table.insert(lines, string.format("\nZone %s%s:\n", name,
+            vibe and string.format(" (%s)", vibe) or ""))
+        fun.iter(by_zone[name]):each(function(_, room)
+            table.insert(lines, room_line(room))
+        end)
+    end)
+
+    if not want_archived and archived_count > 0 then
+        table.insert(lines, string.format("\n(+%d archived: /rooms archived)\n", archived_count))
+    end
+
+    page.show(want_archived and "Archived Rooms" or "Rooms", table.concat(lines))
     return {}
 end
 
@@ -136,17 +174,258 @@ function M.exits(args)
         }
     end
 
+    local locks = tools.exit_locks() or {}
     local lines = {"Exits:\n"}
+    local barred = false
 
     fun.iter(exits):each(function(dir, dest)
         local glyph = util.DIR_GLYPHS[dir] or "-"
-        table.insert(lines, string.format("  %s %s -> %s\n", glyph, dir, dest))
+        local lock = locks[dir]
+        if not lock then
+            table.insert(lines, string.format("  %s %s -> %s\n", glyph, dir, dest))
+        elseif lock.open then
+            table.insert(lines, string.format("  %s %s -> %s  (locked: %s; you can pass)\n",
+                glyph, dir, dest, lock.hint))
+        else
+            barred = true
+            table.insert(lines, string.format("  %s %s -> %s  [locked: %s]\n",
+                glyph, dir, dest, lock.hint))
+        end
     end)
 
+    if barred then
+        table.insert(lines, "\nExits marked [locked] won't let you through; /go says why.\n")
+    end
+
     page.show("Exits", table.concat(lines))
     return {}
 end
 
+--------------------------------------------------------------------------------
+-- /map [radius] - Rooms around this one, laid out by exit direction
+--------------------------------------------------------------------------------
+
+local MAP_OFFSETS = {
+    north = {0, -1}, south = {0, 1}, east = {1, 0}, west = {-1, 0},
+    northeast = {1, -1}, northwest = {-1, -1}, southeast = {1, 1}, southwest = {-1, 1},
+    ne = {1, -1}, nw = {-1, -1}, se = {1, 1}, sw = {-1, 1},
+}
+local MAP_CELL = 14
+
+local function map_key(x, y)
+    return x .. "," .. y
+end
+
+local function sorted_keys(t)
+    local keys = {}
+    for k in pairs(t) do
+        table.insert(keys, k)
+    end
+    table.sort(keys)
+    return keys
+end
+
+--- Place rooms on a grid, walking out from here along compass exits.
+--- Rooms reached only by up/down or custom exits, or whose cell is taken,
+--- stay off the grid.
+local function layout_map(by_name, here)
+    local pos = { [here] = {0, 0} }
+    local taken = { [map_key(0, 0)] = here }
+    local queue, head = {here}, 1
+
+    while queue[head] do
+        local name = queue[head]
+        head = head + 1
+        local exits = by_name[name].exits
+        for _, dir in ipairs(sorted_keys(exits)) do
+            local target = exits[dir]
+            local offset = MAP_OFFSETS[util.DIR_NORMALIZE[dir] or dir]
+            if offset and by_name[target] and not pos[target] then
+                local x, y = pos[name][1] + offset[1], pos[name][2] + offset[2]
+                if not taken[map_key(x, y)] then
+                    pos[target] = {x, y}
+                    taken[map_key(x, y)] = target
+                    table.insert(queue, target)
+                end
+            end
+        end
+    end
+
+    return pos, taken
+end
+
+local function map_linked(by_name, a, b)
+    if not a or not b then
+        return false
+    end
+    for _, target in pairs(by_name[a].exits) do
+        if target == b then return true end
+    end
+    for _, target in pairs(by_name[b].exits) do
+        if target == a then return true end
+    end
+    return false
+end
+
+local function map_others(room, you)
+    local others = {}
+    for _, user in ipairs(room.users or {}) do
+        if user ~= you then
+            table.insert(others, user)
+        end
+    end
+    return others
+end
+
+--- "[@studio*2]" centred in a cell: @ is you, *N is other people there
+local function map_cell(room, here, you)
+    local name = room.name
+    if #name > MAP_CELL - 5 then
+        name = name:sub(1, MAP_CELL - 6) .. "~"
+    end
+    local label = (room.name == here and "@" or "") .. name
+    local others = #map_others(room, you)
+    if others > 0 then
+        label = label .. "*" .. others
+    end
+    label = "[" .. label .. "]"
+    local pad = math.max(MAP_CELL - #label, 0)
+    local left = math.floor(pad / 2)
+    return string.rep(" ", left) .. label .. string.rep(" ", pad - left)
+end
+
+function M.map(args)
+    local radius = tonumber(args:match("^%s*(%d*)%s*$") or "") or 2
+    radius = math.max(1, math.min(radius, 5))
+
+    local map, err = tools.map(radius)
+    if not map then
+        if tostring(err):find("not in a room") then
+            return { text = "You're in the lobby. /join a room to see its map.", mode = "notification" }
+        end
+        return { text = "map: " .. tostring(err), mode = "notification" }
+    end
+
+    local by_name = {}
+    for _, room in ipairs(map.rooms) do
+        by_name[room.name] = room
+    end
+    local pos, taken = layout_map(by_name, map.here)
+
+    local minx, maxx, miny, maxy = 0, 0, 0, 0
+    for _, p in pairs(pos) do
+        minx, maxx = math.min(minx, p[1]), math.max(maxx, p[1])
+        miny, maxy = math.min(miny, p[2]), math.max(maxy, p[2])
+    end
+
+    local lines = { string.format("=== Around %s (%d %s) ===", map.here, radius,
+        radius == 1 and "exit" or "exits"), "" }
+    local blank = string.rep(" ", MAP_CELL)
+    local half = math.floor(MAP_CELL / 2)
+
+    for y = miny, maxy do
+        local row, below = {}, {}
+        for x = minx, maxx do
+            local name = taken[map_key(x, y)]
+            table.insert(row, name and map_cell(by_name[name], map.here, map.you) or blank)
+            local vertical = map_linked(by_name, name, taken[map_key(x, y + 1)])
+            table.insert(below, string.rep(" ", half) .. (vertical and "|" or " ")
+                .. string.rep(" ", MAP_CELL - half - 1))
+
+            if x < maxx then
+                local right = taken[map_key(x + 1, y)]
+                table.insert(row, map_linked(by_name, name, right) and "---" or "   ")
+                local down = map_linked(by_name, name, taken[map_key(x + 1, y + 1)])
+                local up = map_linked(by_name, right, taken[map_key(x, y + 1)])
+                local diagonal = (down and up) and "X" or down and "\\" or up and "/" or " "
+                table.insert(below, " " .. diagonal .. " ")
+            end
+        end
+        table.insert(lines, (table.concat(row):gsub("%s+$", "")))
+        if y < maxy then
+            table.insert(lines, (table.concat(below):gsub("%s+$", "")))
+        end
+    end
+
+    -- Rooms that couldn't go on the grid, with how to reach them
+    local off_grid = {}
+    for _, room in ipairs(map.rooms) do
+        if not pos[room.name] then
+            for _, from in ipairs(map.rooms) do
+                local dir
+                for d, target in pairs(from.exits) do
+                    if target == room.name then dir = d end
+                end
+                if dir then
+                    local label = map_cell(room, map.here, map.you):match("^%s*(.-)%s*$")
+                    table.insert(off_grid, string.format("  %s  (%s from %s)", label, dir, from.name))
+                    break
+                end
+            end
+        end
+    end
+    if #off_grid > 0 then
+        table.insert(lines, "")
+        table.insert(lines, "Also nearby:")
+        for _, line in ipairs(off_grid) do
+            table.insert(lines, line)
+        end
+    end
+
+    local people = {}
+    for _, room in ipairs(map.rooms) do
+        local others = map_others(room, map.you)
+        if #others > 0 then
+            table.insert(people, string.format("  %s: %s", room.name, table.concat(others, ", ")))
+        end
+    end
+    if #people > 0 then
+        table.insert(lines, "")
+        table.insert(lines, "People nearby:")
+        for _, line in ipairs(people) do
+            table.insert(lines, line)
+        end
+    end
+
+    table.insert(lines, "")
+    table.insert(lines, "@ you are here, *N people in a room. /travel <room> walks there.")
+    page.show("Map", table.concat(lines, "\n"))
+    return {}
+end
+
+--------------------------------------------------------------------------------
+-- /travel <room> - Walk the shortest exit path to a room
+--------------------------------------------------------------------------------
+
+function M.travel(args)
+    local target = args:match("^%s*(%S*)%s*$")
+    if not target or target == "" then
+        return { text = "Usage: /travel <room>", mode = "notification" }
+    end
+
+    local result = tools.travel(target)
+    if not result.success then
+        local err = result.error or "unknown error"
+        if err:find("not in a room") then
+            return {
+                text = string.format("You're in the lobby. Use /join %s instead.", target),
+                mode = "notification"
+            }
+        end
+        return { text = string.format("Error: %s", err), mode = "notification" }
+    end
+
+    local steps = {}
+    for _, hop in ipairs(result.path or {}) do
+        table.insert(steps, string.format("%s %s", hop.direction, hop.room))
+    end
+    local text = string.format("Arrived in %s via %s", result.room.name, table.concat(steps, ", "))
+    if result.room.vibe then
+        text = text .. string.format(" (%s)", result.room.vibe)
+    end
+    return { text = text, mode = "notification" }
+end
+
 --------------------------------------------------------------------------------
 -- /look [target] - Room summary
 --------------------------------------------------------------------------------
@@ -172,10 +451,41 @@ function M.look(args)
 end
 
 --------------------------------------------------------------------------------
--- /who - Who's in room
+-- /who [zone [name]] - Who's in the room, or in each room of a zone
 --------------------------------------------------------------------------------
 
+local function who_zone(name)
+    if name == "" then
+        local zone, err = tools.zone()
+        if not zone then
+            return { text = tostring(err) .. ". Usage: /who zone <name>", mode = "notification" }
+        end
+        name = zone.name
+    end
+
+    local rooms, err = tools.zone_who(name)
+    if not rooms then
+        return { text = "Error: " .. err, mode = "notification" }
+    end
+    if #rooms == 0 then
+        return { text = string.format("Nobody is in zone %s.", name), mode = "notification" }
+    end
+
+    local lines = {string.format("=== Zone %s ===\n\n", name)}
+    fun.iter(rooms):each(function(_, entry)
+        table.insert(lines, string.format("%s: %s\n", entry.room, table.concat(entry.users, ", ")))
+    end)
+
+    page.show("Who", table.concat(lines))
+    return {}
+end
+
 function M.who(args)
+    local zone_name = (args or ""):match("^%s*zone%s*(%S*)%s*$")
+    if zone_name then
+        return who_zone(zone_name)
+    end
+
     local participants = tools.who()
     local room = tools.look()
     local room_name = room.room or "lobby"
@@ -235,6 +545,10 @@ function format_look_output(room)
         table.insert(lines, string.format("\nVibe: %s\n", room.vibe))
     end
 
+    if room.zone then
+        table.insert(lines, string.format("Zone: %s\n", room.zone))
+    end
+
     if room.users and #room.users > 0 then
         table.insert(lines, string.format("\nUsers: %s\n", table.concat(room.users, ", ")))
     end
@@ -285,4 +599,16 @@ function format_room_entry(room)
     return {}
 end
 
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+M.completers = {
+    rooms = { { "archived" } },
+    join = { "room" },
+    who = { { "zone" }, "zone" },
+    go = { "exit" },
+    travel = { "room" },
+}
+
 return M
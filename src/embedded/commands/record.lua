@@ -0,0 +1,51 @@
+-- Recording command handlers for sshwarma
+--
+-- /record captures what your screen shows as an asciicast v2 file in the
+-- server's recordings directory, for demos and bug reports. Play it back
+-- with `sshwarma-admin replay <file>` or `asciinema play <file>`.
+
+local M = {}
+
+local USAGE = "Usage: /record [start|stop]"
+
+-- /record [start|stop] - Start, stop or check a session recording
+function M.record(args)
+    local action = (args or ""):match("^%s*(%S*)"):lower()
+
+    if action == "" then
+        local path = tools.recording()
+        if path then
+            return { text = "Recording to " .. path .. " (/record stop to finish)", mode = "notification" }
+        end
+        return { text = "Not recording. " .. USAGE, mode = "notification" }
+    end
+
+    if action == "start" then
+        local rec, err = tools.record_start()
+        if not rec then
+            return { text = "record: " .. tostring(err), mode = "notification" }
+        end
+        return { text = "Recording to " .. rec.path, mode = "notification" }
+    end
+
+    if action == "stop" then
+        local rec, err = tools.record_stop()
+        if not rec then
+            return { text = "record: " .. tostring(err), mode = "notification" }
+        end
+        local text = string.format("Saved %s (%.1fs, %d frames)", rec.path, rec.seconds, rec.events)
+        if rec.truncated then
+            text = text .. ", output cut off at the size limit"
+        end
+        return { text = text, mode = "notification" }
+    end
+
+    return { text = USAGE, mode = "notification" }
+end
+
+-- Tab completion (see ui/complete.lua)
+M.completers = {
+    record = { { "start", "stop" } },
+}
+
+return M
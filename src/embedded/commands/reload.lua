@@ -91,4 +91,12 @@ function M.help()
     }
 end
 
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+M.completers = {
+    reload = { { "default" } },
+}
+
 return M
@@ -9,17 +9,27 @@ local util = require('util')
 local M = {}
 
 --------------------------------------------------------------------------------
--- /create <name> - Create new room
+-- /create <name> [--from <template>] - Create new room
 --------------------------------------------------------------------------------
 
+local CREATE_USAGE = "Usage: /create <name> [--from <template>]"
+
 function M.create(args)
-    local room_name = args:match("^%s*(.-)%s*$")
+    local room_name, rest = args:match("^%s*(%S*)%s*(.-)%s*$")
+
+    if room_name == "" or room_name:sub(1, 2) == "--" then
+        return { text = CREATE_USAGE, mode = "notification" }
+    end
 
-    if room_name == "" then
-        return { text = "Usage: /create <name>", mode = "notification" }
+    local template = nil
+    if rest ~= "" then
+        template = rest:match("^%-%-from%s+(%S+)$")
+        if not template then
+            return { text = CREATE_USAGE, mode = "notification" }
+        end
     end
 
-    local result = tools.create(room_name)
+    local result = tools.create(room_name, nil, template)
 
     if result.success then
         local lines = {string.format("Created room '%s'.\n\n", room_name)}
@@ -31,6 +41,22 @@ function M.create(args)
             end
         end
 
+        local report = result.template
+        if report then
+            table.insert(lines, string.format(
+                "\nFrom template:%s: %d settings, %d equipped, %d rows, %d scripts\n",
+                template, report.kv, report.equipped, report.rows, report.scripts))
+            for _, exit in ipairs(report.exits) do
+                table.insert(lines, string.format("  exit %s\n", exit))
+            end
+            if #report.skipped > 0 then
+                table.insert(lines, "\nSkipped:\n")
+                for _, why in ipairs(report.skipped) do
+                    table.insert(lines, string.format("  %s\n", why))
+                end
+            end
+        end
+
         page.show(room_name, table.concat(lines))
         return {}
     else
@@ -41,23 +67,88 @@ function M.create(args)
     end
 end
 
+--------------------------------------------------------------------------------
+-- /template [list] | save <name> - Room templates
+--------------------------------------------------------------------------------
+
+local TEMPLATE_USAGE = "Usage: /template [list] | /template save <name>"
+
+function M.template(args)
+    local sub, name = args:match("^%s*(%S*)%s*(%S*)%s*$")
+    if not sub then
+        return { text = TEMPLATE_USAGE, mode = "notification" }
+    end
+
+    if sub == "" or sub == "list" then
+        local templates, err = tools.templates()
+        if not templates then
+            return { text = "template: " .. tostring(err), mode = "notification" }
+        end
+        if #templates == 0 then
+            return {
+                text = "No templates yet. /template save <name> captures this room as one.",
+                mode = "notification"
+            }
+        end
+        local lines = {"Templates:", ""}
+        for _, t in ipairs(templates) do
+            table.insert(lines, string.format("  %-16s %s%s", t.name, t.description or "",
+                t.created_by and string.format(" (by %s)", t.created_by) or ""))
+        end
+        table.insert(lines, "")
+        table.insert(lines, "Use one with /create <name> --from <template>.")
+        page.show("Templates", table.concat(lines, "\n"))
+        return {}
+    end
+
+    if sub == "save" and name ~= "" then
+        local saved, err = tools.save_template(name)
+        if not saved then
+            if tostring(err):find("not in a room") then
+                return { text = "Join the room you want to save first.", mode = "notification" }
+            end
+            return { text = "template: " .. tostring(err), mode = "notification" }
+        end
+        return {
+            text = string.format(
+                "Saved %s from %s: %d settings, %d equipped, %d exits, %d rows, %d scripts.",
+                saved.name, saved.room, saved.kv, saved.equipment, saved.exits, saved.rows,
+                saved.scripts),
+            mode = "notification"
+        }
+    end
+
+    return { text = TEMPLATE_USAGE, mode = "notification" }
+end
+
 --------------------------------------------------------------------------------
 -- /fork [name] - Fork current room (inherits vibe, assets)
 --------------------------------------------------------------------------------
 
 function M.fork(args)
-    local new_name = args:match("^%s*(.-)%s*$")
+    local new_name, at = args:match("^%s*(%S+)%s+%-%-at%s+(%S+)%s*$")
+    if not new_name then
+        new_name = args:match("^%s*(.-)%s*$")
+    end
 
-    if new_name == "" then
-        return { text = "Usage: /fork <new_room_name>", mode = "notification" }
+    if new_name == "" or new_name:find("%s") then
+        return { text = "Usage: /fork <name> [--at <row-ref>]", mode = "notification" }
+    end
+
+    -- "--at ." branches from the row selected with the mouse
+    if at == "." then
+        at = require('ui.mouse').selected()
+        if not at then
+            return { text = "No message selected. Click a message first.", mode = "notification" }
+        end
     end
 
-    local result = tools.fork(new_name)
+    local result = tools.fork(new_name, at)
 
     if result.success then
         local lines = {
             string.format("Forked room as '%s'.\n", new_name),
-            "Inherited: vibe, tags, assets.\n\n"
+            "Inherited: vibe, tags, assets, and history up to the fork point.\n\n"
         }
 
         if result.room then
@@ -78,6 +169,199 @@ function M.fork(args)
     end
 end
 
+--------------------------------------------------------------------------------
+-- /branches [all] - Show the fork tree
+--------------------------------------------------------------------------------
+
+local function render_branch(lines, node, children, here, prefix, last, root)
+    local line
+    if root then
+        line = node.name
+    else
+        line = prefix .. (last and "└─ " or "├─ ") .. node.name
+    end
+    if node.fork_at then
+        line = line .. "  ⎇ at " .. node.fork_at
+    end
+    if node.name == here then
+        line = line .. "  ◀ you are here"
+    end
+    table.insert(lines, line)
+
+    local kids = children[node.name] or {}
+    local child_prefix = root and "" or (prefix .. (last and "   " or "│  "))
+    for i, kid in ipairs(kids) do
+        render_branch(lines, kid, children, here, child_prefix, i == #kids, false)
+    end
+end
+
+function M.branches(args)
+    local show_all = args:match("^%s*(%S*)") == "all"
+    local here = tools.look().room
+
+    local nodes = {}
+    local children = {}
+    local order = {}
+    for _, b in ipairs(tools.branches()) do
+        nodes[b.name] = b
+        table.insert(order, b)
+        if b.parent then
+            children[b.parent] = children[b.parent] or {}
+            table.insert(children[b.parent], b)
+        end
+    end
+
+    local roots = {}
+    if show_all then
+        for _, b in ipairs(order) do
+            if (not b.parent or not nodes[b.parent]) and children[b.name] then
+                table.insert(roots, b)
+            end
+        end
+    else
+        if not here then
+            return { text = "You need to be in a room to see its branches. Try /branches all.", mode = "notification" }
+        end
+        local root = nodes[here]
+        local seen = {}
+        while root and root.parent and nodes[root.parent] and not seen[root.name] do
+            seen[root.name] = true
+            root = nodes[root.parent]
+        end
+        if root then
+            table.insert(roots, root)
+        end
+    end
+
+    if #roots == 0 or (not show_all and not children[roots[1].name]) then
+        local text = show_all and "No rooms have been forked." or
+            string.format("%s has no branches. Use /fork <name> to make one.", here)
+        return { text = text, mode = "notification" }
+    end
+
+    local lines = {}
+    for i, root in ipairs(roots) do
+        if i > 1 then
+            table.insert(lines, "")
+        end
+        render_branch(lines, root, children, here, "", true, true)
+    end
+
+    page.show("Branches", table.concat(lines, "\n"))
+    return {}
+end
+
+--------------------------------------------------------------------------------
+-- /merge <fork> [--all | --pinned | --summary [model]] - Merge a fork back
+--------------------------------------------------------------------------------
+
+local MERGE_USAGE = "Usage: /merge <fork> [--all | --pinned | --summary [model]]"
+
+local function quote(value)
+    if value == nil then
+        return "(unset)"
+    end
+    local line = value:match("^[^\n]*")
+    if #line > 40 or #line < #value then
+        line = line:sub(1, 40) .. "…"
+    end
+    return '"' .. line .. '"'
+end
+
+local function merge_preview_text(plan)
+    local lines = {
+        string.format("Merge %s → %s", plan.fork, plan.parent),
+        "",
+        "Chat:",
+        string.format("  --all       %d rows as a nested block", plan.rows.all),
+        string.format("  --pinned    %d pinned rows as a nested block", plan.rows.pinned),
+        string.format("  --summary   a model's summary of %d messages", plan.rows.summary),
+        "",
+        "Room settings:",
+    }
+
+    if #plan.kv == 0 then
+        table.insert(lines, "  (no differences)")
+    end
+    for _, change in ipairs(plan.kv) do
+        if change.conflict then
+            table.insert(lines, string.format("  ! %s: keeps %s, changed in both (fork has %s)",
+                change.key, quote(change.parent), quote(change.fork)))
+        elseif change.fork == nil then
+            table.insert(lines, string.format("  - %s (removed in fork)", change.key))
+        elseif change.parent == nil then
+            table.insert(lines, string.format("  + %s: %s", change.key, quote(change.fork)))
+        else
+            table.insert(lines, string.format("  ~ %s: %s → %s",
+                change.key, quote(change.parent), quote(change.fork)))
+        end
+    end
+
+    table.insert(lines, "")
+    table.insert(lines, "Equipment:")
+    if #plan.equipment == 0 then
+        table.insert(lines, "  (no differences)")
+    end
+    for _, change in ipairs(plan.equipment) do
+        local slot = change.slot and (" [" .. change.slot .. "]") or ""
+        table.insert(lines, string.format("  %s %s%s", change.equip and "+" or "-", change.name, slot))
+    end
+
+    table.insert(lines, "")
+    table.insert(lines, string.format(
+        "Apply with /merge %s --all (or --pinned, --summary). The fork's chat is closed afterwards.",
+        plan.fork))
+    return table.concat(lines, "\n")
+end
+
+function M.merge(args)
+    local fork, rest = args:match("^%s*(%S+)%s*(.-)%s*$")
+    if not fork or fork:sub(1, 2) == "--" then
+        return { text = MERGE_USAGE, mode = "notification" }
+    end
+
+    local flag, model = rest:match("^%-%-(%S+)%s*(%S*)$")
+    if rest ~= "" and not flag then
+        return { text = MERGE_USAGE, mode = "notification" }
+    end
+
+    -- No mode: show what would change
+    if not flag then
+        local plan, err = tools.merge_preview(fork)
+        if not plan then
+            return { text = "merge: " .. tostring(err), mode = "notification" }
+        end
+        page.show("Merge " .. fork, merge_preview_text(plan))
+        return {}
+    end
+
+    if flag ~= "all" and flag ~= "pinned" and flag ~= "summary" then
+        return { text = MERGE_USAGE, mode = "notification" }
+    end
+    if model ~= "" and flag ~= "summary" then
+        return { text = MERGE_USAGE, mode = "notification" }
+    end
+
+    local result, err = tools.merge(fork, flag, model ~= "" and model:gsub("^@", "") or nil)
+    if not result then
+        return { text = "merge: " .. tostring(err), mode = "notification" }
+    end
+
+    if result.pending then
+        return {
+            text = string.format("Summarizing %d messages from %s for %s; the merge lands when it's written.",
+                result.rows, fork, result.parent),
+            mode = "notification",
+        }
+    end
+
+    return {
+        text = string.format("Merged %s into %s: %d rows, %d settings, %d equipment changes.",
+            fork, result.parent, result.rows, result.kv, result.equipment),
+        mode = "notification",
+    }
+end
+
 --------------------------------------------------------------------------------
 -- /vibe [text] - Get or set room vibe
 --------------------------------------------------------------------------------
@@ -151,6 +435,203 @@ function M.portal(args)
     end
 end
 
+--------------------------------------------------------------------------------
+-- /lock <direction> <rule>, /unlock <direction> - Control who can take an exit
+--------------------------------------------------------------------------------
+
+local LOCK_USAGE = [[Usage: /lock <direction> <rule>
+
+Rules:
+  closed              Nobody gets through
+  members             Only the target room's owner and members
+  capability:<name>   Only agents with that capability
+  check:<thing>       A Lua predicate thing decides
+
+/unlock <direction> opens it again. Admins can take any exit.]]
+
+function M.lock(args)
+    local direction, rule = args:match("^%s*(%S+)%s+(%S+)%s*$")
+    if not direction then
+        return { text = LOCK_USAGE, mode = "notification" }
+    end
+    direction = util.DIR_NORMALIZE[direction:lower()] or direction:lower()
+
+    local ok, err = tools.lock_exit(direction, rule)
+    if not ok then
+        return { text = string.format("Error: %s", tostring(err)), mode = "notification" }
+    end
+    return {
+        text = string.format("Locked %s (%s). /unlock %s opens it.", direction, rule, direction),
+        mode = "notification"
+    }
+end
+
+function M.unlock(args)
+    local direction = args:match("^%s*(%S+)%s*$")
+    if not direction then
+        return { text = "Usage: /unlock <direction>", mode = "notification" }
+    end
+    direction = util.DIR_NORMALIZE[direction:lower()] or direction:lower()
+
+    local ok, err = tools.lock_exit(direction, nil)
+    if not ok then
+        return { text = string.format("Error: %s", tostring(err)), mode = "notification" }
+    end
+    return { text = string.format("Unlocked %s.", direction), mode = "notification" }
+end
+
+--------------------------------------------------------------------------------
+-- /members [add|remove <name>] - Who gets through "members" exits into this room
+--------------------------------------------------------------------------------
+
+function M.members(args)
+    local here = tools.look()
+    local room = here and here.room
+    if not room then
+        return { text = "You need to be in a room to manage members.", mode = "notification" }
+    end
+
+    local action, name = args:match("^%s*(%S*)%s*(%S*)%s*$")
+    if action == "" then
+        local members, err = tools.room_members(room)
+        if not members then
+            return { text = string.format("Error: %s", tostring(err)), mode = "notification" }
+        end
+        if #members == 0 then
+            return {
+                text = string.format("%s has no members besides its owner. /members add <name> adds one.", room),
+                mode = "notification"
+            }
+        end
+        return {
+            text = string.format("Members of %s: %s", room, table.concat(members, ", ")),
+            mode = "notification"
+        }
+    end
+
+    if (action ~= "add" and action ~= "remove") or name == "" then
+        return { text = "Usage: /members [add|remove <name>]", mode = "notification" }
+    end
+
+    local members, err = tools.room_member(room, name, action == "add")
+    if not members then
+        return { text = string.format("Error: %s", tostring(err)), mode = "notification" }
+    end
+    local done = action == "add" and "Added %s to %s." or "Removed %s from %s."
+    return {
+        text = string.format(done, name, room) .. " Members: "
+            .. (#members > 0 and table.concat(members, ", ") or "none"),
+        mode = "notification"
+    }
+end
+
+--------------------------------------------------------------------------------
+-- /archive [room], /unarchive <room> - Make a room read-only and hide it
+--------------------------------------------------------------------------------
+
+local function set_archived(args, archived)
+    local room = args:match("^%s*(%S*)%s*$")
+    if room == "" then
+        if not archived then
+            return { text = "Usage: /unarchive <room>", mode = "notification" }
+        end
+        local here = tools.look()
+        room = here and here.room
+        if not room or room == "lobby" then
+            return { text = "Usage: /archive [room]", mode = "notification" }
+        end
+    end
+
+    local ok, err = tools.archive_room(room, archived)
+    if not ok then
+        return { text = string.format("Error: %s", tostring(err)), mode = "notification" }
+    end
+    local text = archived
+        and string.format("Archived %s. It is read-only and hidden from /rooms; /unarchive %s reopens it.", room, room)
+        or string.format("Unarchived %s.", room)
+    return { text = text, mode = "notification" }
+end
+
+function M.archive(args)
+    return set_archived(args, true)
+end
+
+function M.unarchive(args)
+    return set_archived(args, false)
+end
+
+--------------------------------------------------------------------------------
+-- /delete-room <room> [--confirm] - Permanently delete a room
+--------------------------------------------------------------------------------
+
+local function plural(n, word)
+    return string.format("%d %s%s", n, word, n == 1 and "" or "s")
+end
+
+function M.delete_room(args)
+    local room, flag = args:match("^%s*(%S+)%s*(%S*)%s*$")
+    if not room or (flag ~= "" and flag ~= "--confirm") then
+        return { text = "Usage: /delete-room <room> [--confirm]", mode = "notification" }
+    end
+
+    local here = tools.look()
+    if here and here.room == room then
+        return { text = "You're in " .. room .. ". /leave first, then delete it.", mode = "notification" }
+    end
+
+    if flag == "" then
+        local plan, err = tools.delete_room_preview(room)
+        if not plan then
+            return { text = "delete-room: " .. tostring(err), mode = "notification" }
+        end
+
+        local lines = {
+            string.format("=== Delete %s ===", room),
+            "",
+            string.format("Owner: %s", plan.owner or "(none, admin only)"),
+            string.format("Removes %s, %s and %s.",
+                plural(plan.buffers, "buffer"), plural(plan.rows, "row"),
+                plural(plan.equipment, "equipped tool")),
+        }
+        if #plan.exits_in > 0 then
+            table.insert(lines, "")
+            table.insert(lines, "Exits leading here will be removed:")
+            for _, exit in ipairs(plan.exits_in) do
+                table.insert(lines, string.format("  %s → %s", exit.room, exit.direction))
+            end
+        end
+        if #plan.forks > 0 then
+            table.insert(lines, "")
+            table.insert(lines, "Blocked: these forks share its history. Merge or delete them first:")
+            for _, fork in ipairs(plan.forks) do
+                table.insert(lines, "  " .. fork)
+            end
+        else
+            table.insert(lines, "")
+            table.insert(lines, string.format(
+                "This cannot be undone. /delete-room %s --confirm to proceed, or /archive %s to keep it read-only.",
+                room, room))
+        end
+        page.show("Delete " .. room, table.concat(lines, "\n"))
+        return {}
+    end
+
+    local result, err = tools.delete_room(room)
+    if not result then
+        return { text = "delete-room: " .. tostring(err), mode = "notification" }
+    end
+
+    local text = string.format("Deleted %s (%s).", room, plural(result.rows, "row"))
+    if #result.exits_in > 0 then
+        local exits = {}
+        for _, exit in ipairs(result.exits_in) do
+            table.insert(exits, exit.room .. " " .. exit.direction)
+        end
+        text = text .. " Removed exits: " .. table.concat(exits, ", ") .. "."
+    end
+    return { text = text, mode = "notification" }
+end
+
 --------------------------------------------------------------------------------
 -- /nav [on|off] - Toggle model navigation for room
 -- TODO: implement actual nav toggle when ops::set_room_navigation is exposed
@@ -177,4 +658,24 @@ function M.nav(args)
     end
 end
 
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+M.completers = {
+    create = { {}, { "--from" }, "template" },
+    template = { { "list", "save" } },
+    fork = { "room" },
+    merge = { "room", { "--all", "--pinned", "--summary" }, "model" },
+    portal = { "direction", "room" },
+    lock = { "exit", { "closed", "members", "capability:", "check:" } },
+    unlock = { "exit" },
+    members = { { "add", "remove" }, "user" },
+    nav = { { "on", "off" } },
+    branches = { { "all" } },
+    archive = { "room" },
+    unarchive = { "room" },
+    ["delete-room"] = { "room", { "--confirm" } },
+}
+
 return M
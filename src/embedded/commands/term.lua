@@ -0,0 +1,79 @@
+-- Terminal command handlers for sshwarma
+--
+-- /term shows what we detected about your terminal (from the pty request
+-- and COLORTERM) and lets you override it for this session.
+-- Commands that display content use page.show() directly.
+
+local page = require('page')
+local M = {}
+
+local COLORS = { "truecolor", "256", "16", "mono" }
+local GLYPHS = { "powerline", "unicode", "ascii" }
+
+local function contains(list, value)
+    for _, v in ipairs(list) do
+        if v == value then return true end
+    end
+    return false
+end
+
+local function describe(caps)
+    return string.format("colors: %s, glyphs: %s", caps.colors, caps.glyphs)
+end
+
+local USAGE = "Usage: /term [truecolor|256|16|mono] [powerline|unicode|ascii] | /term auto"
+
+-- /term [colors] [glyphs] - Show or override terminal capabilities
+function M.term(args)
+    local words = {}
+    for word in (args or ""):gmatch("%S+") do
+        table.insert(words, word:lower())
+    end
+
+    if #words == 0 then
+        local caps = tools.term_caps()
+        local term = caps.term ~= "" and caps.term or "unknown"
+        local lines = {
+            string.format("term: %s%s", term,
+                caps.colorterm and (" (COLORTERM=" .. caps.colorterm .. ")") or ""),
+            describe(caps),
+            "",
+            USAGE,
+            "auto goes back to what was detected.",
+            "Clients only send COLORTERM with SendEnv COLORTERM in their ssh config.",
+        }
+        page.show("Terminal", table.concat(lines, "\n"))
+        return {}
+    end
+
+    local opts = {}
+    for _, word in ipairs(words) do
+        if word == "auto" then
+            opts.detect = true
+        elseif contains(COLORS, word) then
+            opts.colors = word
+        elseif contains(GLYPHS, word) then
+            opts.glyphs = word
+        else
+            return { text = USAGE, mode = "notification" }
+        end
+    end
+
+    local caps, err = tools.set_term_caps(opts)
+    if not caps then
+        return { text = "term: " .. tostring(err), mode = "notification" }
+    end
+    return { text = "Terminal set to " .. describe(caps), mode = "notification" }
+end
+
+-- Tab completion (see ui/complete.lua)
+local WORDS = { "auto" }
+for _, list in ipairs({ COLORS, GLYPHS }) do
+    for _, v in ipairs(list) do table.insert(WORDS, v) end
+end
+
+M.completers = {
+    term = { WORDS, WORDS },
+}
+
+return M
@@ -0,0 +1,301 @@
+--- commands/zone.lua - Zone command handlers
+---
+--- Zones group rooms under a shared vibe, default equipment and access rule.
+--- /zone with no subcommand lists them.
+
+local page = require('page')
+local fun = require('fun')
+
+local M = {}
+
+local ZONE_USAGE = [[Usage:
+  /zone                          List zones
+  /zone show [name]              Zone settings (default: this room's zone)
+  /zone create <name>            New zone, owned by you
+  /zone add <zone> [room]        Put a room in a zone (default: this room)
+  /zone remove [room]            Take a room out of its zone
+  /zone vibe <zone> [text]       Set or clear the zone's vibe
+  /zone equip <zone> <thing> [slot]  Equip a thing in every room of the zone
+  /zone unequip <zone> <thing>   Take it out of the zone's rooms
+  /zone lock <zone> <rule>       Guard the zone (closed, members, capability:<x>, check:<thing>)
+  /zone unlock <zone>            Open the zone to everyone
+  /zone members <zone> [add|remove <name>]  Who gets in under a members rule
+  /zone who [name]               Who is in each room of the zone
+  /zone delete <zone>            Delete an empty zone]]
+
+local function err_text(err)
+    return { text = string.format("Error: %s", tostring(err)), mode = "notification" }
+end
+
+local function current_room()
+    local here = tools.look()
+    return here and here.room
+end
+
+--------------------------------------------------------------------------------
+-- /zone - List zones
+--------------------------------------------------------------------------------
+
+local function list()
+    local zones, err = tools.zones()
+    if not zones then
+        return err_text(err)
+    end
+    if #zones == 0 then
+        return {
+            text = "No zones yet. /zone create <name> starts one.",
+            mode = "notification"
+        }
+    end
+
+    local lines = {"Zones:\n"}
+    fun.iter(zones):each(function(_, zone)
+        local room_plural = #zone.rooms == 1 and "room" or "rooms"
+        local extras = {}
+        if zone.vibe then table.insert(extras, zone.vibe) end
+        if zone.access then table.insert(extras, "access: " .. zone.access) end
+        local extra = #extras > 0 and string.format(" (%s)", table.concat(extras, "; ")) or ""
+        table.insert(lines, string.format("  %s ... %d %s, owner %s%s\n",
+            zone.name, #zone.rooms, room_plural, zone.owner or "nobody", extra))
+    end)
+
+    page.show("Zones", table.concat(lines))
+    return {}
+end
+
+--------------------------------------------------------------------------------
+-- /zone show [name] - One zone's settings
+--------------------------------------------------------------------------------
+
+local function show(name)
+    local zone, err = tools.zone(name ~= "" and name or nil)
+    if not zone then
+        return err_text(err)
+    end
+
+    local function listed(items)
+        return #items > 0 and table.concat(items, ", ") or "none"
+    end
+
+    local lines = {string.format("=== Zone %s ===\n\n", zone.name)}
+    table.insert(lines, string.format("Owner: %s\n", zone.owner or "nobody"))
+    table.insert(lines, string.format("Vibe: %s\n", zone.vibe or "none"))
+    table.insert(lines, string.format("Access: %s\n", zone.access or "open"))
+    table.insert(lines, string.format("Members: %s\n", listed(zone.members)))
+    table.insert(lines, string.format("Equipment: %s\n", listed(zone.equipment)))
+    table.insert(lines, string.format("\nRooms: %s\n", listed(zone.rooms)))
+
+    page.show("Zone " .. zone.name, table.concat(lines))
+    return {}
+end
+
+--------------------------------------------------------------------------------
+-- /zone members <zone> [add|remove <name>]
+--------------------------------------------------------------------------------
+
+local function members(rest)
+    local zone_name, action, name = rest:match("^(%S*)%s*(%S*)%s*(%S*)$")
+    if zone_name == "" then
+        return { text = "Usage: /zone members <zone> [add|remove <name>]", mode = "notification" }
+    end
+
+    if action == "" then
+        local zone, err = tools.zone(zone_name)
+        if not zone then
+            return err_text(err)
+        end
+        if #zone.members == 0 then
+            return {
+                text = string.format("%s has no members besides its owner.", zone.name),
+                mode = "notification"
+            }
+        end
+        return {
+            text = string.format("Members of %s: %s", zone.name, table.concat(zone.members, ", ")),
+            mode = "notification"
+        }
+    end
+
+    if (action ~= "add" and action ~= "remove") or name == "" then
+        return { text = "Usage: /zone members <zone> [add|remove <name>]", mode = "notification" }
+    end
+    local result, err = tools.zone_member(zone_name, name, action == "add")
+    if not result then
+        return err_text(err)
+    end
+    local done = action == "add" and "Added %s to %s." or "Removed %s from %s."
+    return {
+        text = string.format(done, name, zone_name) .. " Members: "
+            .. (#result > 0 and table.concat(result, ", ") or "none"),
+        mode = "notification"
+    }
+end
+
+--------------------------------------------------------------------------------
+-- /zone <subcommand> ... - Dispatch
+--------------------------------------------------------------------------------
+
+function M.zone(args)
+    local action, rest = (args or ""):match("^%s*(%S*)%s*(.-)%s*$")
+
+    if action == "" or action == "list" then
+        return list()
+    elseif action == "show" then
+        return show(rest)
+    elseif action == "who" then
+        return require("commands.nav").who("zone " .. rest)
+    elseif action == "members" then
+        return members(rest)
+    end
+
+    local zone_name, arg = rest:match("^(%S*)%s*(.-)$")
+
+    if action == "create" then
+        if zone_name == "" then
+            return { text = "Usage: /zone create <name>", mode = "notification" }
+        end
+        local zone, err = tools.zone_create(zone_name)
+        if not zone then
+            return err_text(err)
+        end
+        return {
+            text = string.format("Created zone %s. /zone add %s puts this room in it.", zone.name, zone.name),
+            mode = "notification"
+        }
+
+    elseif action == "add" then
+        local room = arg ~= "" and arg or current_room()
+        if zone_name == "" or not room then
+            return { text = "Usage: /zone add <zone> [room]", mode = "notification" }
+        end
+        local inherited, err = tools.zone_room(room, zone_name)
+        if not inherited then
+            return err_text(err)
+        end
+        local extra = inherited > 0 and string.format(" Inherits %d zone thing(s).", inherited) or ""
+        return {
+            text = string.format("%s is now in zone %s.%s", room, zone_name, extra),
+            mode = "notification"
+        }
+
+    elseif action == "remove" then
+        local room = zone_name ~= "" and zone_name or current_room()
+        if not room then
+            return { text = "Usage: /zone remove [room]", mode = "notification" }
+        end
+        local ok, err = tools.zone_room(room, nil)
+        if not ok then
+            return err_text(err)
+        end
+        return { text = string.format("%s is no longer in a zone.", room), mode = "notification" }
+
+    elseif action == "vibe" then
+        if zone_name == "" then
+            return { text = "Usage: /zone vibe <zone> [text]", mode = "notification" }
+        end
+        local ok, err = tools.zone_vibe(zone_name, arg ~= "" and arg or nil)
+        if not ok then
+            return err_text(err)
+        end
+        local text = arg ~= "" and string.format("Zone %s vibe: %s", zone_name, arg)
+            or string.format("Cleared the vibe of zone %s.", zone_name)
+        return { text = text, mode = "notification" }
+
+    elseif action == "equip" then
+        local thing, slot = arg:match("^(%S*)%s*(%S*)$")
+        if zone_name == "" or thing == "" then
+            return { text = "Usage: /zone equip <zone> <thing> [slot]", mode = "notification" }
+        end
+        local rooms, err = tools.zone_equip(zone_name, thing, slot ~= "" and slot or nil)
+        if not rooms then
+            return err_text(err)
+        end
+        return {
+            text = string.format("Zone %s equips %s in its %d room(s).", zone_name, thing, rooms),
+            mode = "notification"
+        }
+
+    elseif action == "unequip" then
+        if zone_name == "" or arg == "" then
+            return { text = "Usage: /zone unequip <zone> <thing>", mode = "notification" }
+        end
+        local ok, err = tools.zone_unequip(zone_name, arg)
+        if not ok then
+            return err_text(err)
+        end
+        return {
+            text = string.format("Zone %s no longer equips %s.", zone_name, arg),
+            mode = "notification"
+        }
+
+    elseif action == "lock" then
+        if zone_name == "" or arg == "" then
+            return { text = "Usage: /zone lock <zone> <rule>", mode = "notification" }
+        end
+        local ok, err = tools.zone_access(zone_name, arg)
+        if not ok then
+            return err_text(err)
+        end
+        return {
+            text = string.format("Locked zone %s (%s). Its owner and members always get in.", zone_name, arg),
+            mode = "notification"
+        }
+
+    elseif action == "unlock" then
+        if zone_name == "" then
+            return { text = "Usage: /zone unlock <zone>", mode = "notification" }
+        end
+        local ok, err = tools.zone_access(zone_name, nil)
+        if not ok then
+            return err_text(err)
+        end
+        return { text = string.format("Unlocked zone %s.", zone_name), mode = "notification" }
+
+    elseif action == "delete" then
+        if zone_name == "" then
+            return { text = "Usage: /zone delete <zone>", mode = "notification" }
+        end
+        local ok, err = tools.zone_delete(zone_name)
+        if not ok then
+            return err_text(err)
+        end
+        return { text = string.format("Deleted zone %s.", zone_name), mode = "notification" }
+    end
+
+    return { text = ZONE_USAGE, mode = "notification" }
+end
+
+--------------------------------------------------------------------------------
+-- Tab completion (see ui/complete.lua)
+--------------------------------------------------------------------------------
+
+local ACTIONS = {
+    "list", "show", "create", "add", "remove", "vibe", "equip", "unequip",
+    "lock", "unlock", "members", "who", "delete",
+}
+
+M.completers = {
+    zone = function(index, words)
+        if index == 1 then
+            return ACTIONS
+        end
+        local action = words[1]
+        if index == 2 then
+            if action == "remove" then return "room" end
+            if action == "create" then return nil end
+            return "zone"
+        end
+        if index == 3 then
+            if action == "add" then return "room" end
+            if action == "equip" or action == "unequip" then return "thing" end
+            if action == "lock" then return { "closed", "members", "capability:", "check:" } end
+            if action == "members" then return { "add", "remove" } end
+        end
+        if index == 4 and action == "members" then
+            return "user"
+        end
+        return nil
+    end,
+}
+
+return M
@@ -0,0 +1,105 @@
+-- mcp/bundles.lua - Thing bundles (export, import)
+-- Wave 8: Bundle MCP tools
+
+local M = {}
+
+--- Tool definitions for MCP registration
+M.tools = {
+    {
+        name = "bundle_export",
+        description = "Write a thing and everything under it as a portable TOML or JSON bundle, to import on another server",
+        schema = {
+            type = "object",
+            properties = {
+                thing = {
+                    type = "string",
+                    description = "Qualified name or id, 'me' for your inventory, or 'room' for the current room's things"
+                },
+                format = {
+                    type = "string",
+                    enum = { "toml", "json" },
+                    description = "Bundle format (default toml)"
+                },
+                equipment = {
+                    type = "boolean",
+                    description = "Include recipes for how the things are equipped"
+                }
+            },
+            required = { "thing" }
+        },
+        module_path = "mcp.bundles",
+        handler_name = "export"
+    },
+    {
+        name = "bundle_import",
+        description = "Import a bundle into your inventory. Things get new ids; taken qualified names get a suffix",
+        schema = {
+            type = "object",
+            properties = {
+                bundle = {
+                    type = "string",
+                    description = "Bundle text, as bundle_export returns it"
+                },
+                equip = {
+                    type = "boolean",
+                    description = "Apply the bundle's equipment recipes in the current room and for you"
+                }
+            },
+            required = { "bundle" }
+        },
+        module_path = "mcp.bundles",
+        handler_name = "import"
+    }
+}
+
+--- Export a bundle
+--- @param params table { thing: string, format?: string, equipment?: boolean }
+--- @return table Bundle text or error
+function M.export(params)
+    if not params.thing then
+        return { error = "thing parameter is required" }
+    end
+
+    local export, err = tools.bundle_export(params.thing, {
+        format = params.format,
+        equipment = params.equipment or false
+    })
+    if not export then
+        return { error = err }
+    end
+
+    return {
+        bundle = export.text,
+        things = export.things,
+        equipment = export.equipment
+    }
+end
+
+--- Import a bundle
+--- @param params table { bundle: string, equip?: boolean }
+--- @return table Import report or error
+function M.import(params)
+    if not params.bundle then
+        return { error = "bundle parameter is required" }
+    end
+
+    local report, err = tools.bundle_import({
+        text = params.bundle,
+        equip = params.equip or false
+    })
+    if not report then
+        return { error = err }
+    end
+
+    return {
+        status = "imported",
+        things = report.things,
+        roots = report.roots,
+        renamed = report.renamed,
+        equipped = report.equipped,
+        skipped = report.skipped,
+        message = string.format("Imported %d thing(s) into your inventory", report.things)
+    }
+end
+
+return M
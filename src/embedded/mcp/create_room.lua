@@ -17,6 +17,10 @@ M.tool = {
             description = {
                 type = "string",
                 description = "Optional description for the room"
+            },
+            template = {
+                type = "string",
+                description = "Optional template to set the room up from (see list_templates)"
             }
         },
         required = { "name" }
@@ -32,15 +36,42 @@ function M.handler(params)
         return { error = "name parameter is required" }
     end
 
+    -- Check the template before creating anything
+    if params.template then
+        local templates = tools.templates() or {}
+        local found = false
+        for _, t in ipairs(templates) do
+            if t.name == params.template or t.qualified_name == params.template then
+                found = true
+            end
+        end
+        if not found then
+            return { error = string.format("Template '%s' not found", params.template) }
+        end
+    end
+
     -- Use the db_create_room primitive which handles validation
     local result = tools.db_create_room(params.name, params.description)
 
     if result.success then
-        return {
+        local response = {
             status = "created",
             room = result.room,
             message = string.format("Created room '%s'", result.room)
         }
+
+        if params.template then
+            local report, err = tools.apply_template(result.room, params.template)
+            if not report then
+                response.template_error = err
+            else
+                response.template = report
+                response.message = string.format("Created room '%s' from template '%s'",
+                    result.room, params.template)
+            end
+        end
+
+        return response
     else
         return { error = result.error }
     end
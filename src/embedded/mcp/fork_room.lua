@@ -17,6 +17,10 @@ M.tool = {
             new_name = {
                 type = "string",
                 description = "Name for the new forked room (alphanumeric, dashes, underscores)"
+            },
+            at = {
+                type = "string",
+                description = "Message to branch from: row id, id suffix, or -N for the Nth latest message (default: latest)"
             }
         },
         required = { "source", "new_name" }
@@ -37,7 +41,7 @@ function M.handler(params)
     end
 
     -- Use the db_fork_room primitive which handles validation and context copying
-    local result = tools.db_fork_room(params.source, params.new_name)
+    local result = tools.db_fork_room(params.source, params.new_name, params.at)
 
     if result.success then
         return {
@@ -45,7 +49,7 @@ function M.handler(params)
             room = result.room,
             source = result.source,
             message = string.format(
-                "Forked '%s' from '%s'. Inherited: vibe, tags, assets, equipment.",
+                "Forked '%s' from '%s'. Inherited: vibe, tags, assets, equipment, and history up to the fork point.",
                 result.room, result.source
             )
         }
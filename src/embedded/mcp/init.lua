@@ -61,6 +61,15 @@ function M.init()
     register_tool(require('mcp.things'))
     register_tool(require('mcp.scripts'))
 
+    -- Wave 6: Room templates
+    register_tool(require('mcp.templates'))
+
+    -- Wave 7: Trash
+    register_tool(require('mcp.trash'))
+
+    -- Wave 8: Thing bundles
+    register_tool(require('mcp.bundles'))
+
     -- Echo test tool for debugging
     register_tool(require('mcp.echo_test'))
 end
@@ -19,16 +19,18 @@ M.tool = {
 --- @return table Array of room objects with name, description, and vibe
 function M.handler(params)
     -- Use the db_rooms primitive which returns:
-    -- { id, name, created_at, vibe?, description? }
+    -- { id, name, created_at, vibe?, description?, owner?, archived }
     local rooms = tools.db_rooms()
     local result = {}
 
-    for i, room in ipairs(rooms) do
-        result[i] = {
-            name = room.name,
-            description = room.description,
-            vibe = room.vibe
-        }
+    for _, room in ipairs(rooms) do
+        if not room.archived then
+            table.insert(result, {
+                name = room.name,
+                description = room.description,
+                vibe = room.vibe
+            })
+        end
     end
 
     return result
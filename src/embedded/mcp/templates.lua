@@ -0,0 +1,91 @@
+-- mcp/templates.lua - Room templates (list, save)
+-- Wave 6: Room template MCP tools
+
+local M = {}
+
+--- Tool definitions for MCP registration
+M.tools = {
+    {
+        name = "list_templates",
+        description = "List room templates that create_room can start from",
+        schema = {
+            type = "object",
+            properties = {},
+        },
+        module_path = "mcp.templates",
+        handler_name = "list"
+    },
+    {
+        name = "save_template",
+        description = "Capture a room's settings, equipment, exits, pinned rows and prompts as a template",
+        schema = {
+            type = "object",
+            properties = {
+                room = {
+                    type = "string",
+                    description = "Room to capture"
+                },
+                name = {
+                    type = "string",
+                    description = "Template name (alphanumeric, dashes, underscores)"
+                }
+            },
+            required = { "room", "name" }
+        },
+        module_path = "mcp.templates",
+        handler_name = "save"
+    }
+}
+
+--- List available templates
+--- @param params table Parameters (unused)
+--- @return table List of templates
+function M.list(params)
+    local templates, err = tools.templates()
+    if not templates then
+        return { error = err }
+    end
+
+    local list = {}
+    for i, t in ipairs(templates) do
+        list[i] = {
+            name = t.name,
+            qualified_name = t.qualified_name,
+            description = t.description,
+            created_by = t.created_by
+        }
+    end
+
+    return {
+        templates = list,
+        count = #list
+    }
+end
+
+--- Save a room as a template
+--- @param params table { room: string, name: string }
+--- @return table Result with counts or error
+function M.save(params)
+    if not params.room or not params.name then
+        return { error = "room and name parameters are required" }
+    end
+
+    local saved, err = tools.save_template(params.name, params.room)
+    if not saved then
+        return { error = err }
+    end
+
+    return {
+        status = "saved",
+        template = saved.name,
+        room = saved.room,
+        kv = saved.kv,
+        equipment = saved.equipment,
+        exits = saved.exits,
+        rows = saved.rows,
+        scripts = saved.scripts,
+        message = string.format("Saved '%s' as %s", saved.room, saved.name)
+    }
+end
+
+return M
@@ -95,7 +95,7 @@ M.tools = {
     },
     {
         name = "thing_destroy",
-        description = "Delete a thing (must specify owner:name)",
+        description = "Delete a thing (must specify owner:name); restore_thing brings it back",
         schema = {
             type = "object",
             properties = {
@@ -365,8 +365,8 @@ function M.destroy(params)
         return { error = string.format("'%s' not found under '%s'", thing_name, owner) }
     end
 
-    -- Use thing_delete primitive
-    local result = tools.thing_delete(thing.qualified_name or (owner .. ":" .. thing_name))
+    -- Use thing_delete primitive (moves it to the trash)
+    local result = tools.thing_delete(thing.id)
     if result and result.success then
         return {
             status = "destroyed",
@@ -0,0 +1,95 @@
+-- mcp/trash.lua - Trash (list deleted things, restore)
+-- Wave 7: Trash MCP tools
+
+local M = {}
+
+--- Tool definitions for MCP registration
+M.tools = {
+    {
+        name = "list_trash",
+        description = "List deleted things with who deleted them and when, most recent first",
+        schema = {
+            type = "object",
+            properties = {
+                target = {
+                    type = "string",
+                    enum = { "me", "room" },
+                    description = "Whose trash: your inventory (me, default) or the current room"
+                }
+            },
+        },
+        module_path = "mcp.trash",
+        handler_name = "list"
+    },
+    {
+        name = "restore_thing",
+        description = "Restore a deleted thing. If its qualified name was taken since, pass a new one as 'as'",
+        schema = {
+            type = "object",
+            properties = {
+                name = {
+                    type = "string",
+                    description = "Qualified name or id of the deleted thing"
+                },
+                as = {
+                    type = "string",
+                    description = "New qualified name to restore it under"
+                }
+            },
+            required = { "name" }
+        },
+        module_path = "mcp.trash",
+        handler_name = "restore"
+    }
+}
+
+--- List deleted things
+--- @param params table { target?: "me"|"room" }
+--- @return table Deleted things or error
+function M.list(params)
+    local items, err = tools.trash(params.target or "me")
+    if not items then
+        return { error = err }
+    end
+
+    local list = {}
+    for i, item in ipairs(items) do
+        list[i] = {
+            id = item.id,
+            name = item.name,
+            qualified_name = item.qualified_name,
+            kind = item.kind,
+            deleted_by = item.deleted_by,
+            deleted_at = item.deleted_at
+        }
+    end
+
+    return {
+        trash = list,
+        count = #list
+    }
+end
+
+--- Restore a deleted thing
+--- @param params table { name: string, as?: string }
+--- @return table Restored thing or error
+function M.restore(params)
+    if not params.name then
+        return { error = "name parameter is required" }
+    end
+
+    local thing, err = tools.restore(params.name, params.as)
+    if not thing then
+        return { error = err }
+    end
+
+    return {
+        status = "restored",
+        id = thing.id,
+        qualified_name = thing.qualified_name,
+        parent_id = thing.parent_id,
+        message = string.format("Restored %s", thing.qualified_name or thing.name)
+    }
+end
+
+return M
@@ -9,12 +9,29 @@ local pages = require 'ui.pages'
 local scroll = require 'ui.scroll'
 local mode = require 'ui.mode'
 local input = require 'ui.input'
+local complete = require 'ui.complete'
+local mouse = require 'ui.mouse'
 
 local M = {}
 
--- Powerline / Nerd Font glyphs via Unicode escapes
-local ARROW_RIGHT = "\u{E0B0}"  --
-local ARROW_LEFT  = "\u{E0B2}"  --
+-- Bar separators for each ctx.caps.glyphs level. Powerline / Nerd Font
+-- glyphs need a patched font; the others draw anywhere.
+M.separators = {
+    powerline = {right = "\u{E0B0}", left = "\u{E0B2}"},
+    unicode   = {right = "▌", left = "▐"},
+    ascii     = {right = ">", left = "<"},
+}
+
+-- Current separators (picked from ctx.caps each frame)
+local ARROW_RIGHT = M.separators.powerline.right
+local ARROW_LEFT  = M.separators.powerline.left
+
+--- Pick glyphs to suit the terminal
+---@param caps table ctx.caps
+function M.apply_caps(caps)
+    local seps = M.separators[caps and caps.glyphs] or M.separators.powerline
+    ARROW_RIGHT, ARROW_LEFT = seps.right, seps.left
+end
 
 -- Colors - vibrant palette with dark purple chrome
 M.colors = {
@@ -77,7 +94,7 @@ bars.define("topbar", {
     position = "top",
     priority = 100,
     height = 1,
-    items = {"room_badge", "vibe_snippet", "spacer", "exits_compass", "world_stats"},
+    items = {"room_badge", "vibe_snippet", "page_tabs", "spacer", "exits_compass", "world_stats"},
     style = {bg = M.colors.topbar},
 })
 
@@ -150,6 +167,31 @@ bars.item("vibe_snippet", function(state, _width)
     }
 end)
 
+-- Page tabs - only when pages are open; click one to switch to it
+bars.item("page_tabs", function(_state, _width)
+    if pages.count() < 2 then
+        return {}
+    end
+
+    local C = M.colors
+    local segs = {}
+    for i, page in ipairs(pages.list()) do
+        local name = type(page) == "table" and page.name or page
+        local current = (i == pages.index())
+        table.insert(segs, {
+            text = " " .. name .. " ",
+            style = current
+                and {fg = C.cyan, bg = C.topbar2, bold = true}
+                or {fg = C.dim, bg = C.topbar},
+            on_click = function(ev)
+                if ev.button ~= "left" or ev.action ~= "press" then return false end
+                pages.goto(name)
+            end,
+        })
+    end
+    return segs
+end)
+
 -- Exits compass - show available directions with glyphs
 bars.item("exits_compass", function(state, _width)
     local C = M.colors
@@ -369,21 +411,35 @@ bars.item("mode_indicator", function(_state, _width)
     end
 end)
 
-bars.item("prompt", function(state, _width)
-    local room = state.room or {}
-    local room_name = room.name or "lobby"
-
-    if mode.is_normal() then
-        return {{text = room_name .. "│", style = {fg = M.colors.dim}}}
-    else
-        return {{text = room_name .. "> ", style = {fg = M.colors.system}}}
+--- Prompt shown before the input text
+---@return string
+function M.prompt_text(state)
+    local room = (state.room or {}).name or "lobby"
+    local search = input.search_state()
+    if search then
+        local label = search.failed and "(failed search)" or "(search)"
+        return string.format("%s`%s': ", label, search.query)
+    end
+    if mode.reply_target() then
+        return "↪ " .. room .. "> "
     end
+    return mode.is_normal() and (room .. "│") or (room .. "> ")
+end
+
+bars.item("prompt", function(state, _width)
+    local style = {fg = mode.is_normal() and M.colors.dim or M.colors.system}
+    return {{text = M.prompt_text(state), style = style}}
 end)
 
+--- Input text as shown on the one-line input bar (line breaks as ↵)
+function M.input_display(text)
+    return (text:gsub("\n", "↵"))
+end
+
 bars.item("input_text", function(_state, _width)
     local inp = input.get_state()
-    local text = inp.text or ""
-    local cursor = inp.cursor or 0
+    local text = M.input_display(inp.text or "")
+    local cursor = #M.input_display((inp.text or ""):sub(1, inp.cursor or 0))
     local segs = {}
 
     if #text == 0 then
@@ -502,6 +558,12 @@ function M.build_display_lines(messages, width, my_name)
         content_width = width
     end
 
+    -- Authors by row, for reply markers
+    local authors = {}
+    for _, msg in ipairs(messages) do
+        if msg.row_id then authors[msg.row_id] = msg.author end
+    end
+
     for _, msg in ipairs(messages) do
         local author = msg.author or "???"
         local content = msg.content or ""
@@ -566,6 +628,10 @@ function M.build_display_lines(messages, width, my_name)
                 nick_color = C.system
             end
 
+            if msg.reply_to then
+                content = string.format("↪ %s: %s", authors[msg.reply_to] or "earlier message", content)
+            end
+
             local wrapped = M.wrap_text(content, content_width)
 
             for i, line_text in ipairs(wrapped) do
@@ -580,6 +646,19 @@ function M.build_display_lines(messages, width, my_name)
                     row_id = msg.row_id,
                 })
             end
+
+            -- Pin and reactions go on a line of their own
+            local marks = {}
+            if msg.pinned then table.insert(marks, "📌 pinned") end
+            if msg.reactions then table.insert(marks, msg.reactions) end
+            if #marks > 0 then
+                table.insert(display_lines, {
+                    text = table.concat(marks, "  "),
+                    is_meta = true,
+                    prefix_width = prefix_width,
+                    row_id = msg.row_id,
+                })
+            end
         end
     end
 
@@ -598,6 +677,8 @@ function M.render_chat(ctx, display_lines, page_name, height)
     if end_line > total_lines then end_line = total_lines end
     if start_line < 0 then start_line = 0 end
 
+    local selected = mouse.selected()
+
     for i = start_line, end_line - 1 do
         local line_idx = i + 1
         local line = display_lines[line_idx]
@@ -606,18 +687,36 @@ function M.render_chat(ctx, display_lines, page_name, height)
             local y = i - start_line
             local x = 0
 
+            -- Selected row gets a highlighted background
+            local bg = nil
+            if selected and line.row_id == selected then
+                bg = C.status2
+                ctx:fill(0, y, ctx.w, 1, " ", {bg = bg})
+            end
+
+            -- Clicking a line selects its row; clicking again clears it
+            if line.row_id then
+                local row_id = line.row_id
+                mouse.region(ctx, 0, y, ctx.w, 1, function(ev)
+                    if ev.button ~= "left" or ev.action ~= "press" then return false end
+                    mouse.select(mouse.selected() ~= row_id and row_id or nil)
+                end)
+            end
+
             -- Tool rows have no author prefix, render with their own styling
             if line.is_tool_call or line.is_tool_result then
-                local style = {fg = line.nick_color, italic = true}
+                local style = {fg = line.nick_color, bg = bg, italic = true}
                 ctx:print(x, y, line.text, style)
+            elseif line.is_meta then
+                ctx:print(line.prefix_width or 0, y, line.text, {fg = C.dim, bg = bg})
             else
                 -- Regular message with author prefix
                 if line.is_first_line and line.author then
-                    ctx:print(x, y, "<", {fg = C.dim})
+                    ctx:print(x, y, "<", {fg = C.dim, bg = bg})
                     x = x + 1
-                    ctx:print(x, y, line.author, {fg = line.nick_color})
+                    ctx:print(x, y, line.author, {fg = line.nick_color, bg = bg})
                     x = x + M.display_width(line.author)
-                    ctx:print(x, y, "> ", {fg = C.dim})
+                    ctx:print(x, y, "> ", {fg = C.dim, bg = bg})
                     x = x + 2
                 else
                     x = line.prefix_width or 0
@@ -627,7 +726,7 @@ function M.render_chat(ctx, display_lines, page_name, height)
                 if line.is_streaming and line.is_last_line then
                     text = text .. " ◌"
                 end
-                ctx:print(x, y, text)
+                ctx:print(x, y, text, bg and {bg = bg} or nil)
             end
         end
     end
@@ -656,9 +755,20 @@ Navigation (Normal Mode):
   ?             Open help
   i             Enter insert mode
   / @           Enter insert with prefix
+  :history      Input history (:history clear forgets it)
+  :compose      Multi-line message (Ctrl+D sends)
+  R / p / +     Reply to / pin / react to selected message
+
+Mouse:
+  Wheel         Scroll content
+  Click         Select a message (again to deselect)
+  Click a tab   Switch pages
+  Shift+drag    Terminal's own text selection
 
 Editing (Insert Mode):
   ↑/↓           History prev/next
+  Alt+Enter     New line (or Shift+Enter)
+  Ctrl+R        Search history (again for older)
   ←/→           Cursor movement
   Ctrl+A/E      Beginning/end of line
   Ctrl+W/U/K    Delete word/to-start/to-end
@@ -674,6 +784,7 @@ Commands:
   /look         Room summary
   /who          Who's in the room
   /history [n]  Recent messages
+  /keys         Full keymap, incl. equipped hotkeys
 
 Press q to close this help.
 ]]
@@ -767,6 +878,14 @@ end
 
 function on_tick(dirty_tags, tick, ctx)
     ctx:clear()
+    mouse.begin_frame()
+    M.apply_caps(ctx.caps)
+
+    -- Terminal changed size: layout follows ctx.w/ctx.h, but scrolled
+    -- pages need their offsets carried over to the rewrapped content
+    if dirty_tags and dirty_tags.resize then
+        scroll.reflow_all()
+    end
 
     local state = M.fetch_state()
     local bar_layout = bars.compute_layout(ctx.w, ctx.h, state)
@@ -815,19 +934,31 @@ function on_tick(dirty_tags, tick, ctx)
         end
     end
 
+    -- Multi-line drafts are shown in full above the input bar
+    local draft = input.get_state().text or ""
+    if content and bar_layout["input"] and (mode.is_compose() or draft:find("\n", 1, true)) then
+        M.render_compose(ctx, draft, content)
+    end
+
+    -- Completion candidates float over the bottom of the content area
+    local popup = complete.popup()
+    if popup and content and bar_layout["input"] then
+        local head = (input.get_state().text or ""):sub(1, popup.start - 1)
+        local x = M.display_width(M.prompt_text(state)) + M.display_width(M.input_display(head))
+        M.render_completion(ctx, popup, content, x)
+    end
+
     -- Report hardware cursor position for layered blink effect
     -- Layout is 1-indexed (matches ANSI), columns are 1-indexed
     if bar_layout["input"] and tools and tools.set_cursor_pos then
         local inp = input.get_state()
-        local room = (state.room or {}).name or "lobby"
 
         -- Calculate prompt width (must match bars.item("prompt") output)
-        local prompt_text = mode.is_normal() and (room .. "│") or (room .. "> ")
-        local prompt_width = M.display_width(prompt_text)
+        local prompt_width = M.display_width(M.prompt_text(state))
 
         -- Calculate text before cursor width
         local text_before = (inp.text or ""):sub(1, inp.cursor or 0)
-        local text_width = M.display_width(text_before)
+        local text_width = M.display_width(M.input_display(text_before))
 
         -- Layout row is 1-indexed, column needs +1 for ANSI 1-indexing
         local cursor_row = bar_layout["input"].row
@@ -837,6 +968,71 @@ function on_tick(dirty_tags, tick, ctx)
     end
 end
 
+--- Draw a multi-line draft with its last row just above the input bar
+function M.render_compose(ctx, text, content)
+    local C = M.colors
+    local lines = {}
+    for line in (text .. "\n"):gmatch("([^\n]*)\n") do
+        table.insert(lines, line)
+    end
+
+    local shown = math.min(#lines, math.min(content.height, 12) - 1)
+    if shown < 1 then return end
+
+    -- Keep the cursor's line in view, preferring the end of the draft
+    local before = text:sub(1, input.get_state().cursor or 0)
+    local _, cursor_line = before:gsub("\n", "")
+    cursor_line = cursor_line + 1
+    local first = math.max(1, math.min(#lines - shown + 1, cursor_line))
+
+    local header = mode.is_compose()
+        and "compose: Enter new line · Ctrl+D send · Esc stop"
+        or "Enter sends · Shift+Enter or Alt+Enter adds a line"
+    local header_style = {fg = C.dim, bg = C.status2}
+    local line_style = {fg = C.fg, bg = C.status2}
+
+    -- content.row is 1-indexed, ctx rows are 0-indexed
+    local top = content.row - 1 + content.height - shown - 1
+    ctx:fill(0, top, ctx.w, 1, " ", header_style)
+    ctx:print(1, top, header, header_style)
+    for i = first, first + shown - 1 do
+        local y = top + 1 + i - first
+        local style = line_style
+        if i == cursor_line then
+            style = {fg = C.fg, bg = C.status2, bold = true}
+        end
+        ctx:fill(0, y, ctx.w, 1, " ", style)
+        ctx:print(1, y, lines[i], style)
+    end
+end
+
+--- Draw the completion popup with its last row just above the input bar
+function M.render_completion(ctx, popup, content, x)
+    local C = M.colors
+    local count = #popup.items
+    if count > content.height then return end
+
+    local width = 0
+    for _, item in ipairs(popup.items) do
+        width = math.max(width, M.display_width(item))
+    end
+    width = math.min(width + 2, ctx.w)
+    if x + width > ctx.w then
+        x = math.max(0, ctx.w - width)
+    end
+
+    -- content.row is 1-indexed, ctx rows are 0-indexed
+    local top = content.row - 1 + content.height - count
+    for i, item in ipairs(popup.items) do
+        local style = {fg = C.fg, bg = C.status2}
+        if i == popup.selected then
+            style = {fg = C.status, bg = C.cursor}
+        end
+        ctx:fill(x, top + i - 1, width, 1, " ", style)
+        ctx:print(x + 1, top + i - 1, item, style)
+    end
+end
+
 -- Track last-run tick for each UI background hook
 local hook_last_run = {}
 
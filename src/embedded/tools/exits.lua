@@ -1,4 +1,12 @@
 -- sshwarma:exits - List room exits
+-- Locked exits show their rule, and whether you can take them
 return function(args)
-    return tools.exits()
+    local exits = tools.exits()
+    for dir, lock in pairs(tools.exit_locks() or {}) do
+        if exits[dir] then
+            exits[dir] = string.format("%s (locked: %s%s)", exits[dir], lock.hint,
+                lock.open and "; you can pass" or "")
+        end
+    end
+    return exits
 end
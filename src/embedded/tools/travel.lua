@@ -0,0 +1,8 @@
+-- sshwarma:travel - Walk the shortest exit path to a room
+return function(args)
+    local room = args and args.room or args
+    if not room then
+        return {success = false, error = "room name required"}
+    end
+    return tools.travel(room)
+end
@@ -18,8 +18,11 @@
 --   bars.item("room_name", function(state, width)
 --     return {{text = state.room.name, style = {fg = "#7dcfff"}}}
 --   end)
+--
+-- A segment with on_click = function(ev) is clickable (see ui/mouse.lua).
 
 local layout = require 'ui.layout'
+local mouse = require 'ui.mouse'
 local fun = require 'fun'
 
 local M = {}
@@ -259,6 +262,8 @@ function M.render_items(bar_def, width, state)
                     text = text,
                     style = seg.style or bar_def.style,
                     x = x,
+                    width = text_width,
+                    on_click = seg.on_click,
                 })
                 x = x + text_width
             end
@@ -299,6 +304,9 @@ function M.render(ctx, bar_def, state)
     -- Draw each segment
     for _, seg in ipairs(segments) do
         ctx:print(seg.x, 0, seg.text, seg.style)
+        if seg.on_click then
+            mouse.region(ctx, seg.x, 0, seg.width, ctx.h, seg.on_click)
+        end
     end
 end
 
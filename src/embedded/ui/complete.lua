@@ -0,0 +1,291 @@
+-- ui/complete.lua - Tab completion for the input line
+--
+-- Tab completes the word before the cursor:
+--   /cm          command names (built-in and equipped command: slots)
+--   /cmd ar      that command's completer for the argument position
+--   @na          model names, anywhere in the line
+--
+-- Command completers are declared by the commands modules (see
+-- commands.completer): a list with one completer per argument position, or
+-- a function(index, words) returning the completer for position index.
+-- Each argument's completer is a source name from M.sources, a list of
+-- literal words, or a function(word, words) returning either of those.
+-- In both functions words holds the arguments before the one completed.
+--
+-- A single candidate completes in place. Several cycle on repeated Tab,
+-- with a popup above the input bar; any other key ends the cycle.
+
+local input = require 'ui.input'
+
+local M = {}
+
+-- Most candidates listed in the popup at once
+M.POPUP_MAX = 8
+
+--------------------------------------------------------------------------------
+-- Candidate Sources
+--------------------------------------------------------------------------------
+
+local function names(list, field)
+    local out = {}
+    for _, item in ipairs(list or {}) do
+        local name = item[field]
+        if name then table.insert(out, name) end
+    end
+    return out
+end
+
+--- Agent names prefixed with @ (for me/room/@agent targets)
+local function at_agents()
+    local out = {}
+    for _, agent in ipairs(tools.db_agents() or {}) do
+        table.insert(out, "@" .. agent.name)
+    end
+    return out
+end
+
+--- Candidate sources by name: function(word) -> string[]
+M.sources = {
+    room = function()
+        return names(tools.rooms(), "name")
+    end,
+    exit = function()
+        local out = {}
+        for dir in pairs(tools.exits() or {}) do
+            table.insert(out, dir)
+        end
+        return out
+    end,
+    direction = function()
+        local out = {}
+        for dir in pairs(require('util').DIR_OPPOSITE) do
+            table.insert(out, dir)
+        end
+        return out
+    end,
+    user = function()
+        local out = {}
+        for _, agent in ipairs(tools.db_agents() or {}) do
+            if agent.kind == "human" then
+                table.insert(out, agent.name)
+            end
+        end
+        return out
+    end,
+    model = function()
+        return names(tools.list_models(), "short_name")
+    end,
+    mention = function()
+        local out = {}
+        for _, name in ipairs(names(tools.list_models(), "short_name")) do
+            table.insert(out, "@" .. name)
+        end
+        return out
+    end,
+    template = function()
+        return names(tools.templates(), "name")
+    end,
+    zone = function()
+        return names(tools.zones(), "name")
+    end,
+    trashed = function()
+        return names(tools.trash("me"), "qualified_name")
+    end,
+    bundle = function()
+        return tools.bundles() or {}
+    end,
+    thing = function(word)
+        return names(tools.things_match((word or "") .. "*"), "qualified_name")
+    end,
+    mcp_tool = function()
+        local result = tools.mcp_tools() or {}
+        return names(result.tools, "name")
+    end,
+    help_topic = function()
+        return names(require('help').list(), "name")
+    end,
+    -- Containers for /inv and /conjure
+    target = function()
+        local out = { "me", "room", "shared" }
+        for _, name in ipairs(at_agents()) do table.insert(out, name) end
+        return out
+    end,
+    -- Equipment contexts for /equip and /unequip
+    context = function()
+        local out = { "me", "room" }
+        for _, name in ipairs(at_agents()) do table.insert(out, name) end
+        return out
+    end,
+    command = function()
+        local out = {}
+        for _, name in ipairs(require('commands').list_all()) do
+            table.insert(out, "/" .. name)
+        end
+        return out
+    end,
+}
+
+--- Add or replace a candidate source
+---@param name string source name used in completer declarations
+---@param fn function(word) -> string[]
+function M.register(name, fn)
+    M.sources[name] = fn
+end
+
+--- Resolve a completer declaration to candidates matching word
+---@param spec string|table|function|nil
+---@param word string partial word
+---@param words string[] preceding arguments
+---@return string[]
+function M.candidates(spec, word, words)
+    if type(spec) == "function" then
+        spec = spec(word, words)
+    end
+
+    local list
+    if type(spec) == "string" then
+        local source = M.sources[spec]
+        if not source then return {} end
+        local ok, result = pcall(source, word)
+        list = ok and result or {}
+    elseif type(spec) == "table" then
+        list = spec
+    else
+        return {}
+    end
+
+    local seen, out = {}, {}
+    for _, candidate in ipairs(list) do
+        if not seen[candidate] and candidate:sub(1, #word) == word then
+            seen[candidate] = true
+            table.insert(out, candidate)
+        end
+    end
+    table.sort(out)
+    return out
+end
+
+--------------------------------------------------------------------------------
+-- Word Analysis
+--------------------------------------------------------------------------------
+
+--- Work out what the word before the cursor is and how to complete it
+---@param text string input text
+---@param cursor number byte offset of the cursor
+---@return table|nil {start, word, spec, words}
+function M.context(text, cursor)
+    local before = text:sub(1, cursor)
+    local start = (before:match("^.*()%s") or 0) + 1
+    local word = before:sub(start)
+
+    -- Command name
+    if start == 1 and word:sub(1, 1) == "/" then
+        return { start = start, word = word, spec = "command", words = {} }
+    end
+
+    -- Model mention, anywhere in the line
+    if word:sub(1, 1) == "@" and not before:match("^/") then
+        return { start = start, word = word, spec = "mention", words = {} }
+    end
+
+    -- Command argument
+    local name, rest = before:match("^/(%S+)%s+(.*)$")
+    if not name then return nil end
+    local words = {}
+    for w in rest:sub(1, #rest - #word):gmatch("%S+") do
+        table.insert(words, w)
+    end
+    local spec = require('commands').completer(name, #words + 1, words)
+    if not spec then return nil end
+    return { start = start, word = word, spec = spec, words = words }
+end
+
+--------------------------------------------------------------------------------
+-- Cycling
+--------------------------------------------------------------------------------
+
+-- Active cycle: {candidates, index, start, tail, text, cursor}
+local cycle = nil
+
+--- Put candidate i of the cycle into the input line
+local function apply(i, trailing)
+    local state = input.get_state()
+    local head = state.text:sub(1, cycle.start - 1)
+    local completed = cycle.candidates[i] .. (trailing or "")
+    local text = head .. completed .. cycle.tail
+    local cursor = #head + #completed
+    input.set_text(text, cursor)
+    cycle.index = i
+    cycle.text = text
+    cycle.cursor = cursor
+end
+
+--- Whether the input still shows what the cycle last put there
+local function cycle_current()
+    if not cycle then return false end
+    local state = input.get_state()
+    return state.text == cycle.text and state.cursor == cycle.cursor
+end
+
+--- Complete the word at the cursor, or advance an active cycle
+---@return number candidates found (0 when nothing matched)
+function M.tab()
+    if cycle_current() then
+        apply(cycle.index % #cycle.candidates + 1)
+        return #cycle.candidates
+    end
+    cycle = nil
+
+    local state = input.get_state()
+    local ctx = M.context(state.text, state.cursor)
+    if not ctx then return 0 end
+
+    local candidates = M.candidates(ctx.spec, ctx.word, ctx.words)
+    if #candidates == 0 then return 0 end
+
+    cycle = {
+        candidates = candidates,
+        index = 0,
+        start = ctx.start,
+        tail = state.text:sub(state.cursor + 1),
+    }
+    if #candidates == 1 then
+        apply(1, cycle.tail:match("^%s") and "" or " ")
+        cycle = nil
+        return 1
+    end
+    apply(1)
+    return #candidates
+end
+
+--- End any active cycle
+function M.reset()
+    cycle = nil
+end
+
+--- Popup contents while cycling through several candidates
+---@return table|nil {items, selected, start} window of at most POPUP_MAX items
+function M.popup()
+    if not cycle_current() then
+        cycle = nil
+        return nil
+    end
+
+    local total = #cycle.candidates
+    local first = 1
+    if total > M.POPUP_MAX then
+        first = math.max(1, math.min(cycle.index - M.POPUP_MAX // 2, total - M.POPUP_MAX + 1))
+    end
+    local items = {}
+    for i = first, math.min(total, first + M.POPUP_MAX - 1) do
+        table.insert(items, cycle.candidates[i])
+    end
+    return {
+        items = items,
+        selected = cycle.index - first + 1,
+        start = cycle.start,
+        total = total,
+    }
+end
+
+return M
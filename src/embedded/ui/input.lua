@@ -4,7 +4,7 @@
 --   - Escape sequence parsing (M.parse)
 --   - UTF-8 character handling
 --   - Input buffer with cursor movement
---   - History navigation
+--   - History navigation, persisted per agent, and reverse search
 --
 -- Note: mode.lua is the entry point for key handling.
 -- This module provides the primitives that mode.lua uses.
@@ -32,8 +32,14 @@ local CSI_SEQUENCES = {
     ["6~"] = { type = "pagedown" },
     ["7~"] = { type = "home" },  -- rxvt
     ["8~"] = { type = "end" },   -- rxvt
+    -- Shift+Enter, where the terminal reports it at all
+    ["13;2u"] = { type = "shift_enter" },     -- CSI u (kitty, foot, wezterm)
+    ["27;2;13~"] = { type = "shift_enter" },  -- xterm modifyOtherKeys
 }
 
+-- Low two bits of an SGR mouse report's button code
+local MOUSE_BUTTONS = { [0] = "left", [1] = "middle", [2] = "right", [3] = "none" }
+
 -- SS3 sequences: ESC O <char> (some terminals use these for arrows/F-keys)
 local SS3_SEQUENCES = {
     ["A"] = { type = "arrow", dir = "up" },
@@ -49,6 +55,8 @@ local SS3_SEQUENCES = {
 }
 
 -- Control characters (single-byte)
+-- Unannotated entries have no built-in binding; hotkeys can claim them.
+-- Ctrl+H, I, J and M share bytes with backspace, tab and enter.
 local CTRL = {
     ["\x01"] = { type = "ctrl", char = "a" },  -- Ctrl+A (home)
     ["\x02"] = { type = "ctrl", char = "b" },  -- Ctrl+B (back char)
@@ -56,10 +64,22 @@ local CTRL = {
     ["\x04"] = { type = "ctrl", char = "d" },  -- Ctrl+D (EOF)
     ["\x05"] = { type = "ctrl", char = "e" },  -- Ctrl+E (end)
     ["\x06"] = { type = "ctrl", char = "f" },  -- Ctrl+F (forward char)
+    ["\x07"] = { type = "ctrl", char = "g" },
     ["\x0b"] = { type = "ctrl", char = "k" },  -- Ctrl+K (kill to end)
     ["\x0c"] = { type = "ctrl", char = "l" },  -- Ctrl+L (clear)
+    ["\x0e"] = { type = "ctrl", char = "n" },
+    ["\x0f"] = { type = "ctrl", char = "o" },
+    ["\x10"] = { type = "ctrl", char = "p" },
+    ["\x11"] = { type = "ctrl", char = "q" },
+    ["\x12"] = { type = "ctrl", char = "r" },
+    ["\x13"] = { type = "ctrl", char = "s" },
+    ["\x14"] = { type = "ctrl", char = "t" },
     ["\x15"] = { type = "ctrl", char = "u" },  -- Ctrl+U (kill line)
+    ["\x16"] = { type = "ctrl", char = "v" },
     ["\x17"] = { type = "ctrl", char = "w" },  -- Ctrl+W (kill word)
+    ["\x18"] = { type = "ctrl", char = "x" },
+    ["\x19"] = { type = "ctrl", char = "y" },
+    ["\x1a"] = { type = "ctrl", char = "z" },
     ["\x7f"] = { type = "backspace" },
     ["\x08"] = { type = "backspace" },
     ["\r"] = { type = "enter" },
@@ -77,6 +97,7 @@ local CTRL = {
 ---@field prompt string Prompt string (for display)
 ---@field history string[] Command history
 ---@field history_pos number|nil Current position in history (nil = editing new input)
+---@field history_loaded boolean Whether persisted history has been merged in
 ---@field saved_input string Saved input when navigating history
 ---@field kill_ring string Last killed text for yanking
 
@@ -86,6 +107,7 @@ local state = {
     prompt = "> ",
     history = {},
     history_pos = nil,
+    history_loaded = false,
     saved_input = "",
     kill_ring = "",
 }
@@ -174,9 +196,45 @@ end
 --------------------------------------------------------------------------------
 
 ---@class ParsedKey
----@field type string "char"|"arrow"|"ctrl"|"enter"|"backspace"|"delete"|"home"|"end"|"tab"|"escape"|"pageup"|"pagedown"|"f1".."f12"|"unknown"
+---@field type string "char"|"arrow"|"ctrl"|"enter"|"shift_enter"|"mouse"|"backspace"|"delete"|"home"|"end"|"tab"|"escape"|"pageup"|"pagedown"|"f1".."f12"|"unknown"
 ---@field char? string For type="char" or type="ctrl"
 ---@field dir? string For type="arrow": "up"|"down"|"left"|"right"
+---@field button? string For type="mouse": "left"|"middle"|"right"|"none"|"wheel_up"|"wheel_down"
+---@field action? string For type="mouse": "press"|"release"|"drag"|"move"|"scroll"
+---@field x? number For type="mouse": column, 0-indexed
+---@field y? number For type="mouse": row, 0-indexed
+
+--- Decode an SGR mouse report: ESC [ < button ; x ; y (M press, m release)
+--- @param params string parameters after the "<"
+--- @param final string "M" or "m"
+--- @return ParsedKey|nil
+function M.parse_mouse(params, final)
+    local b, x, y = params:match("^(%d+);(%d+);(%d+)$")
+    if not b then return nil end
+    b = tonumber(b)
+
+    local key = {
+        type = "mouse",
+        x = tonumber(x) - 1,
+        y = tonumber(y) - 1,
+        shift = bit32.btest(b, 4),
+        alt = bit32.btest(b, 8),
+        ctrl = bit32.btest(b, 16),
+    }
+    local low = bit32.band(b, 3)
+    if bit32.btest(b, 64) then
+        key.button = low == 0 and "wheel_up" or low == 1 and "wheel_down" or "wheel"
+        key.action = "scroll"
+    else
+        key.button = MOUSE_BUTTONS[low]
+        if bit32.btest(b, 32) then
+            key.action = key.button == "none" and "move" or "drag"
+        else
+            key.action = final == "M" and "press" or "release"
+        end
+    end
+    return key
+end
 
 --- Parse raw bytes into key events
 --- @param bytes string Raw bytes from SSH
@@ -215,8 +273,13 @@ function M.parse(bytes)
                     if j <= #bytes then
                         local final = string.char(bytes:byte(j))
                         local seq_key = params .. final
+                        local mouse = params:sub(1, 1) == "<"
+                            and (final == "M" or final == "m")
+                            and M.parse_mouse(params:sub(2), final)
 
-                        if CSI_SEQUENCES[seq_key] then
+                        if mouse then
+                            table.insert(keys, mouse)
+                        elseif CSI_SEQUENCES[seq_key] then
                             table.insert(keys, CSI_SEQUENCES[seq_key])
                         elseif CSI_SEQUENCES[final] then
                             -- Simple sequence without params
@@ -247,6 +310,12 @@ function M.parse(bytes)
                         i = i + 1
                     end
 
+                elseif next_byte == 0x0d then  -- '\r'
+                    -- Alt+Enter: stands in for Shift+Enter on terminals
+                    -- that send a plain CR for it
+                    table.insert(keys, { type = "shift_enter" })
+                    i = i + 2
+
                 else
                     -- Unknown sequence after ESC, treat as bare escape
                     table.insert(keys, { type = "escape" })
@@ -393,6 +462,16 @@ function M.word_back()
     sync_state()
 end
 
+--- Replace the input text (completion, history search)
+--- @param text string
+--- @param cursor? number Byte offset (default: end of text)
+function M.set_text(text, cursor)
+    state.text = text
+    state.cursor = cursor or #text
+    state.history_pos = nil
+    sync_state()
+end
+
 --- Clear input line
 function M.clear()
     state.text = ""
@@ -407,15 +486,8 @@ end
 function M.submit()
     local text = state.text
 
-    -- Add to history (skip empty and duplicates at end)
     if #text > 0 then
-        if #state.history == 0 or state.history[#state.history] ~= text then
-            table.insert(state.history, text)
-            -- Limit history size
-            while #state.history > 500 do
-                table.remove(state.history, 1)
-            end
-        end
+        M.history_add(text)
     end
 
     M.clear()
@@ -466,8 +538,92 @@ function M.kill_word_back()
     sync_state()
 end
 
+--------------------------------------------------------------------------------
+-- History
+--------------------------------------------------------------------------------
+
+--- Most lines kept in memory (matches the server-side cap)
+local HISTORY_MAX = 500
+
+--- Lines matching any of these are kept for this session but never
+--- persisted, since they tend to carry credentials. Lines starting with a
+--- space are never persisted either.
+M.history_ignore = {
+    "^/mcp%s+connect%s",
+}
+
+--- Whether a submitted line may be written to the database
+--- @param text string
+--- @return boolean
+function M.should_persist(text)
+    if text:match("^%s") then return false end
+    for _, pattern in ipairs(M.history_ignore) do
+        if text:match(pattern) then return false end
+    end
+    return true
+end
+
+--- Append a line to a history list, dropping any earlier copy
+local function push_unique(list, text)
+    for i = #list, 1, -1 do
+        if list[i] == text then
+            table.remove(list, i)
+            break
+        end
+    end
+    table.insert(list, text)
+    while #list > HISTORY_MAX do
+        table.remove(list, 1)
+    end
+end
+
+--- Merge in the agent's persisted history the first time it's needed
+local function ensure_history()
+    if state.history_loaded then return end
+    if not (tools and tools.session and tools.input_history) then return end
+    local session = tools.session()
+    if not session or not session.agent_id then return end
+    state.history_loaded = true
+
+    -- Anything entered before the load stays newest
+    local merged = tools.input_history(HISTORY_MAX) or {}
+    for _, line in ipairs(state.history) do
+        push_unique(merged, line)
+    end
+    state.history = merged
+end
+
+--- Record a submitted line
+--- @param text string
+function M.history_add(text)
+    ensure_history()
+    push_unique(state.history, text)
+    if M.should_persist(text) and tools and tools.input_history_add then
+        tools.input_history_add(text)
+    end
+end
+
+--- All history lines, oldest first
+--- @return string[]
+function M.history_entries()
+    ensure_history()
+    return state.history
+end
+
+--- Forget history in memory and in the database
+--- @return number lines removed from the database
+function M.history_clear()
+    state.history = {}
+    state.history_pos = nil
+    if tools and tools.input_history_clear then
+        return tools.input_history_clear()
+    end
+    return 0
+end
+
 --- Navigate to previous history entry (Up arrow)
 function M.history_prev()
+    ensure_history()
     if #state.history == 0 then return end
 
     if state.history_pos == nil then
@@ -503,6 +659,99 @@ function M.history_next()
     sync_state()
 end
 
+--------------------------------------------------------------------------------
+-- Reverse Search (Ctrl+R)
+--------------------------------------------------------------------------------
+
+-- Active search: {query, index, failed, saved_text, saved_cursor}
+local search = nil
+
+--- Show the newest match for the query at or before history index
+local function search_update(index)
+    if search.query == "" then
+        search.failed = false
+        state.text = search.saved_text
+        state.cursor = search.saved_cursor
+        sync_state()
+        return
+    end
+
+    for i = math.min(index, #state.history), 1, -1 do
+        local pos = state.history[i]:find(search.query, 1, true)
+        if pos then
+            search.index = i
+            search.failed = false
+            state.text = state.history[i]
+            state.cursor = pos - 1
+            sync_state()
+            return
+        end
+    end
+    search.failed = true
+end
+
+--- Start an incremental reverse search
+function M.search_start()
+    ensure_history()
+    search = {
+        query = "",
+        index = #state.history + 1,
+        failed = false,
+        saved_text = state.text,
+        saved_cursor = state.cursor,
+    }
+end
+
+--- Whether a reverse search is in progress
+--- @return boolean
+function M.search_active()
+    return search ~= nil
+end
+
+--- Search prompt state for rendering
+--- @return table|nil {query, failed}
+function M.search_state()
+    if not search then return nil end
+    return { query = search.query, failed = search.failed }
+end
+
+--- Extend the query, staying on the current match if it still fits
+--- @param char string
+function M.search_type(char)
+    search.query = search.query .. char
+    search_update(search.index)
+end
+
+--- Shorten the query and search again from the newest line
+function M.search_backspace()
+    local q = search.query
+    if #q > 0 then
+        search.query = q:sub(1, M.prev_utf8_start(q, #q + 1) - 1)
+    end
+    search_update(#state.history)
+end
+
+--- Jump to the next older match (Ctrl+R again)
+function M.search_next()
+    search_update(search.index - 1)
+end
+
+--- Keep the matched line in the buffer and end the search
+function M.search_accept()
+    search = nil
+    state.history_pos = nil
+    sync_state()
+end
+
+--- Restore the buffer from before the search
+function M.search_cancel()
+    if not search then return end
+    state.text = search.saved_text
+    state.cursor = search.saved_cursor
+    search = nil
+    sync_state()
+end
+
 -- Export to global for cross-module access
 _G.input = M
 
@@ -10,10 +10,15 @@
 --
 -- In normal mode, raw characters are ignored (except mode-entry chars).
 -- In insert mode, everything goes to the input buffer.
+--
+-- Things equipped in hotkey:<keyspec> slots on the agent or room add
+-- bindings on top of the built-in maps (see Hotkeys below).
 
 local pages = require 'ui.pages'
 local scroll = require 'ui.scroll'
 local input = require 'ui.input'
+local complete = require 'ui.complete'
+local mouse = require 'ui.mouse'
 
 local M = {}
 
@@ -25,6 +30,69 @@ local function current_page()
     return pages.current_name()
 end
 
+-- ==========================================================================
+-- Ex Commands (":" in normal mode)
+-- ==========================================================================
+
+-- Set while the input line was started with ":" from normal mode
+local ex_line = false
+
+-- Compose mode: Enter adds a line, Ctrl+D sends the whole draft
+local compose = false
+
+-- Row the input line answers, set by R on a selected row
+local reply_to = nil
+
+-- Reaction toggled by + on a selected row
+M.REACTION = "👍"
+
+local ex_commands = {}
+
+--- :history [filter] - page of past input; :history clear forgets it
+function ex_commands.history(args)
+    if args == "clear" then
+        local removed = input.history_clear()
+        tools.notify(string.format("Cleared input history (%d saved lines)", removed))
+        return
+    end
+
+    local lines = {}
+    for i, line in ipairs(input.history_entries()) do
+        if args == "" or line:find(args, 1, true) then
+            table.insert(lines, string.format("%4d  %s", i, line))
+        end
+    end
+    if #lines == 0 then
+        table.insert(lines, args == "" and "No input history yet." or "No matching lines.")
+    end
+    table.insert(lines, "")
+    table.insert(lines, "Up/Down recall lines, Ctrl+R searches them.")
+    table.insert(lines, "Lines starting with a space and /mcp connect are not saved.")
+
+    require('page').show("Input History", table.concat(lines, "\n"))
+    if tools and tools.mark_dirty then
+        tools.mark_dirty("chat")
+    end
+end
+
+--- :compose - write a multi-line message
+function ex_commands.compose()
+    compose = true
+    M.current = "insert"
+end
+
+--- Run an ex command line (without the leading ":")
+local function run_ex(line)
+    local name, args = line:match("^%s*(%S+)%s*(.-)%s*$")
+    if not name then return end
+    local handler = ex_commands[name]
+    if not handler then
+        tools.notify("Unknown command :" .. name, "warning")
+        return
+    end
+    handler(args)
+end
+
 -- ==========================================================================
 -- Normal Mode Key Map
 -- ==========================================================================
@@ -73,6 +141,45 @@ local normal_keys = {
         input.insert("@")
         return { type = "redraw" }
     end,
+    [":"] = function()
+        M.current = "insert"
+        input.insert(":")
+        ex_line = true
+        return { type = "redraw" }
+    end,
+
+    -- Reverse history search
+    ctrl_r = function()
+        M.current = "insert"
+        input.search_start()
+        return { type = "redraw" }
+    end,
+
+    -- Selected row (click a message to select it)
+    ["R"] = function()
+        local row_id = mouse.selected()
+        if not row_id then return nil end
+        reply_to = row_id
+        M.current = "insert"
+        return { type = "redraw" }
+    end,
+    ["p"] = function()
+        local row_id = mouse.selected()
+        if not row_id then return nil end
+        local pinned, err = tools.row_pin(row_id)
+        if pinned == nil then
+            tools.notify(err or "Can't pin that", "warning")
+        else
+            tools.notify(pinned and "Pinned" or "Unpinned")
+        end
+        return { type = "redraw" }
+    end,
+    ["+"] = function()
+        local row_id = mouse.selected()
+        if not row_id then return nil end
+        tools.row_react(row_id, M.REACTION)
+        return { type = "redraw" }
+    end,
 
     -- Jump commands
     ["g"] = function()
@@ -100,9 +207,11 @@ local normal_keys = {
         return { type = "redraw" }
     end,
 
-    -- Escape closes non-chat pages
+    -- Escape clears the selection, then closes non-chat pages
     escape = function()
-        if not pages.is_chat() then
+        if mouse.selected() then
+            mouse.select(nil)
+        elseif not pages.is_chat() then
             pages.close()
             -- Mark chat dirty so content area refreshes
             if tools and tools.mark_dirty then
@@ -188,23 +297,56 @@ local insert_keys = {
     -- Exit insert mode
     escape = function()
         M.current = "normal"
+        ex_line = false
+        compose = false
+        reply_to = nil
         return { type = "redraw" }
     end,
 
-    -- Submit and exit
+    -- Submit and exit (compose mode starts a new line instead)
     enter = function()
+        if compose then
+            input.insert("\n")
+            return { type = "redraw" }
+        end
         local text = input.submit()
         M.current = "normal"
+        if ex_line and text:sub(1, 1) == ":" then
+            ex_line = false
+            run_ex(text:sub(2))
+            return { type = "redraw" }
+        end
+        ex_line = false
+        local target = reply_to
+        reply_to = nil
         if text and #text > 0 then
+            if target then
+                return { type = "reply", text = text, row_id = target }
+            end
             return { type = "execute", text = text }
         end
         return { type = "redraw" }
     end,
 
+    -- New line in a multi-line message
+    shift_enter = function()
+        input.insert("\n")
+        return { type = "redraw" }
+    end,
+
     -- Clear and exit
     ctrl_c = function()
         input.clear()
         M.current = "normal"
+        ex_line = false
+        compose = false
+        reply_to = nil
+        return { type = "redraw" }
+    end,
+
+    -- Reverse history search
+    ctrl_r = function()
+        input.search_start()
         return { type = "redraw" }
     end,
 
@@ -252,6 +394,21 @@ local insert_keys = {
     end,
     ctrl_d = function()
         local state = input.get_state()
+        if compose then
+            -- Send the draft
+            compose = false
+            M.current = "normal"
+            local text = input.submit()
+            local target = reply_to
+            reply_to = nil
+            if text:match("%S") then
+                if target then
+                    return { type = "reply", text = text, row_id = target }
+                end
+                return { type = "execute", text = text }
+            end
+            return { type = "redraw" }
+        end
         if #state.text == 0 then
             return { type = "quit" }
         end
@@ -264,6 +421,362 @@ local insert_keys = {
     end,
 }
 
+-- ==========================================================================
+-- Built-in Key Descriptions (for /keys)
+-- ==========================================================================
+
+local builtin_help = {
+    normal = {
+        up = "scroll up", down = "scroll down",
+        k = "scroll up", j = "scroll down",
+        pageup = "scroll up a page", pagedown = "scroll down a page",
+        left = "previous page", right = "next page",
+        h = "previous page", l = "next page",
+        i = "insert mode", ["/"] = "insert mode with /", ["@"] = "insert mode with @",
+        [":"] = "ex command (:history, :compose)", ctrl_r = "search history",
+        g = "jump to bottom", G = "jump to top",
+        q = "close page", ["?"] = "help", escape = "clear selection / close page", r = "redraw",
+        R = "reply to selected row", p = "pin selected row", ["+"] = "react to selected row",
+        ["^"] = "cursor to start", ["$"] = "cursor to end",
+        w = "cursor word forward", b = "cursor word back",
+        ctrl_d = "quit", ctrl_l = "clear screen",
+    },
+    insert = {
+        up = "history previous", down = "history next",
+        left = "cursor left", right = "cursor right",
+        home = "cursor to start", ["end"] = "cursor to end",
+        escape = "normal mode", enter = "submit", ctrl_c = "clear input",
+        backspace = "delete back", delete = "delete forward",
+        ctrl_a = "cursor to start", ctrl_e = "cursor to end",
+        ctrl_u = "kill to start", ctrl_k = "kill to end", ctrl_w = "kill word",
+        ctrl_b = "cursor left", ctrl_f = "cursor right",
+        ctrl_l = "clear screen", ctrl_d = "quit (empty input) / send compose", tab = "complete",
+        ctrl_r = "search history", shift_enter = "new line",
+    },
+}
+
+local builtin_maps = { normal = normal_keys, insert = insert_keys }
+
+-- ==========================================================================
+-- Hotkeys
+-- ==========================================================================
+--
+-- A thing equipped in a hotkey:<keyspec> slot binds a key sequence to its
+-- code. Keyspecs:
+--   hotkey:ctrl-g          Ctrl+G in both modes
+--   hotkey:normal:gp       g then p, normal mode only
+--   hotkey:insert:f2       F2 in insert mode
+--   hotkey:normal:g,ctrl-p g then Ctrl+P (comma separates named keys)
+--
+-- Without a mode, sequences starting with a printable character bind in
+-- normal mode only (so typing isn't hijacked); others bind in both.
+--
+-- The code is called with {key, mode, text} and may return an action table
+-- (e.g. {type = "execute", text = "/look"}), a {text = ...} notification,
+-- a string, or nothing.
+--
+-- Conflicts resolve by equip priority, lower first. Built-ins sit at
+-- BUILTIN_PRIORITY and /equip ranks hotkeys below it unless given
+-- --priority, so a hotkey replaces a built-in sharing its first key; on a
+-- tie the built-in wins, and between equal hotkeys the agent's beats the
+-- room's. A sequence that is an exact binding also shadows longer ones.
+
+local BUILTIN_PRIORITY = 0
+M.BUILTIN_PRIORITY = BUILTIN_PRIORITY
+
+-- Seconds before equipped hotkeys are re-read from the database
+local HOTKEY_TTL_SECS = 2
+
+local NAMED_KEYS = {
+    up = true, down = true, left = true, right = true,
+    home = true, ["end"] = true, delete = true, backspace = true,
+    pageup = true, pagedown = true, tab = true, enter = true, escape = true,
+    f1 = true, f2 = true, f3 = true, f4 = true,
+}
+
+--- Convert one keyspec token to a key name, or nil if it isn't a named key
+---@param token string e.g. "ctrl-g", "f2", "pageup"
+---@return string|nil
+local function named_key(token)
+    local ctrl = token:match("^ctrl%-(%a)$")
+    if ctrl then
+        return "ctrl_" .. ctrl:lower()
+    end
+    if NAMED_KEYS[token] then
+        return token
+    end
+    return nil
+end
+
+--- Parse a hotkey slot into its modes and key names
+---@param slot string e.g. "hotkey:normal:gp"
+---@return table|nil {modes = string[], keys = string[]}
+function M.parse_keyspec(slot)
+    local spec = slot and slot:match("^hotkey:(.+)$")
+    if not spec then return nil end
+
+    local mode = spec:match("^(normal):") or spec:match("^(insert):")
+    if mode then
+        spec = spec:sub(#mode + 2)
+    end
+    if spec == "" then return nil end
+
+    local keys = {}
+    local printable_first = false
+    local single = named_key(spec)
+    if single then
+        keys = { single }
+    elseif #spec > 1 and spec:find(",", 1, true) then
+        for token in spec:gmatch("[^,]+") do
+            local name = named_key(token)
+            if not name then
+                if input.char_count(token) ~= 1 then return nil end
+                name = token
+                if #keys == 0 then printable_first = true end
+            end
+            table.insert(keys, name)
+        end
+    else
+        local i = 1
+        while i <= #spec do
+            local char, len = input.next_utf8_char(spec, i)
+            if len == 0 then break end
+            table.insert(keys, char)
+            i = i + len
+        end
+        printable_first = true
+    end
+    if #keys == 0 then return nil end
+
+    local modes
+    if mode then
+        modes = { mode }
+    elseif printable_first then
+        modes = { "normal" }
+    else
+        modes = { "normal", "insert" }
+    end
+    return { modes = modes, keys = keys }
+end
+
+--- Render key names back into keyspec form
+---@param keys string[]
+---@return string
+function M.format_keys(keys)
+    local parts, all_chars = {}, true
+    for _, name in ipairs(keys) do
+        local shown = name:gsub("^ctrl_", "ctrl-")
+        table.insert(parts, shown)
+        if input.char_count(name) ~= 1 then all_chars = false end
+    end
+    return table.concat(parts, all_chars and "" or ",")
+end
+
+--- Whether a built-in binding fires on this key in this mode
+local function builtin_for(mode, name)
+    if builtin_maps[mode][name] then return true end
+    -- Insert mode inserts any printable character
+    return mode == "insert" and input.char_count(name) == 1
+end
+
+--- True when binding a should win a conflict against binding b
+local function outranks(a, b)
+    if a.priority ~= b.priority then
+        return a.priority < b.priority
+    end
+    return a.source == "agent" and b.source ~= "agent"
+end
+
+local hotkey_cache = { key = nil, loaded_at = 0, keymap = nil }
+
+-- Keys held while they could still become a longer hotkey
+local pending = {}
+
+--- Resolve equipped hotkey things into per-mode keymaps
+---@param items table[] equipment entries with a source field
+---@return table {normal = {bindings, prefixes}, insert = {...}, entries = {...}}
+local function build_keymap(items)
+    local keymap = { entries = {} }
+    for mode in pairs(builtin_maps) do
+        keymap[mode] = { bindings = {}, prefixes = {} }
+    end
+
+    -- Best binding per mode and sequence
+    for _, item in ipairs(items) do
+        local spec = M.parse_keyspec(item.slot)
+        if spec then
+            for _, mode in ipairs(spec.modes) do
+                local entry = {
+                    mode = mode,
+                    keys = spec.keys,
+                    seq = table.concat(spec.keys, " "),
+                    slot = item.slot,
+                    name = item.qualified_name or item.name,
+                    code = item.code,
+                    priority = item.priority or 0,
+                    source = item.source,
+                }
+                table.insert(keymap.entries, entry)
+
+                local best = keymap[mode].bindings[entry.seq]
+                if not best then
+                    keymap[mode].bindings[entry.seq] = entry
+                elseif outranks(entry, best) then
+                    best.shadowed_by = entry.name
+                    keymap[mode].bindings[entry.seq] = entry
+                else
+                    entry.shadowed_by = best.name
+                end
+            end
+        end
+    end
+
+    for mode in pairs(builtin_maps) do
+        local bindings = keymap[mode].bindings
+        for seq, entry in pairs(bindings) do
+            -- The first key reaches a built-in before the rest is typed
+            if builtin_for(mode, entry.keys[1])
+                and entry.priority >= BUILTIN_PRIORITY then
+                entry.shadowed_by = "built-in " .. M.format_keys({ entry.keys[1] })
+                bindings[seq] = nil
+            end
+        end
+        for seq, entry in pairs(bindings) do
+            -- A shorter exact binding fires first
+            for n = 1, #entry.keys - 1 do
+                local prefix = table.concat(entry.keys, " ", 1, n)
+                local shorter = bindings[prefix]
+                if shorter then
+                    entry.shadowed_by = shorter.name
+                    break
+                end
+            end
+        end
+        for seq, entry in pairs(bindings) do
+            if entry.shadowed_by then
+                bindings[seq] = nil
+            else
+                for n = 1, #entry.keys - 1 do
+                    keymap[mode].prefixes[table.concat(entry.keys, " ", 1, n)] = true
+                end
+            end
+        end
+    end
+
+    return keymap
+end
+
+--- Current hotkey keymap, re-read when stale or the room changes
+---@return table
+local function hotkeys()
+    local session = tools and tools.session and tools.session() or {}
+    local cache_key = (session.agent_id or "") .. "|" .. (session.room_id or "")
+    local now = os.time()
+    if hotkey_cache.keymap and hotkey_cache.key == cache_key
+        and now - hotkey_cache.loaded_at < HOTKEY_TTL_SECS then
+        return hotkey_cache.keymap
+    end
+
+    local items = {}
+    if session.agent_id and tools.get_agent_equipment then
+        for _, item in ipairs(tools.get_agent_equipment(session.agent_id, "hotkey:*") or {}) do
+            item.source = "agent"
+            table.insert(items, item)
+        end
+    end
+    if session.room_id and tools.get_room_equipment then
+        for _, item in ipairs(tools.get_room_equipment(session.room_id, "hotkey:*") or {}) do
+            item.source = "room"
+            table.insert(items, item)
+        end
+    end
+
+    hotkey_cache.key = cache_key
+    hotkey_cache.loaded_at = now
+    hotkey_cache.keymap = build_keymap(items)
+    return hotkey_cache.keymap
+end
+
+--- Drop cached hotkeys so the next key re-reads equipment
+function M.invalidate_hotkeys()
+    hotkey_cache.keymap = nil
+    pending = {}
+end
+
+--- Run a hotkey thing's code and turn its result into an action
+---@param entry table keymap entry
+---@return table action
+local function run_hotkey(entry)
+    if not entry.code then
+        tools.notify(string.format("Hotkey %s has no code", entry.name), "warning")
+        return { type = "redraw" }
+    end
+
+    local result = tools.execute_code(entry.code, {
+        key = M.format_keys(entry.keys),
+        mode = M.current,
+        text = input.get_state().text,
+    })
+
+    if type(result) == "table" then
+        if result.success == false and result.error then
+            tools.notify(string.format("Hotkey %s: %s", entry.name, result.error), "error")
+        elseif result.type then
+            return result
+        elseif result.text and #result.text > 0 then
+            tools.notify(result.text)
+        end
+    elseif type(result) == "string" and #result > 0 then
+        tools.notify(result)
+    end
+    return { type = "redraw" }
+end
+
+--- Summarize the effective keymap for display
+---@return table[] {mode, keys, action, source, priority?, shadowed_by?}
+function M.keymap()
+    local rows = {}
+    for _, mode in ipairs({ "normal", "insert" }) do
+        for name, desc in pairs(builtin_help[mode]) do
+            table.insert(rows, {
+                mode = mode,
+                keys = M.format_keys({ name }),
+                action = desc,
+                source = "built-in",
+                priority = BUILTIN_PRIORITY,
+            })
+        end
+    end
+    for _, entry in ipairs(hotkeys().entries) do
+        table.insert(rows, {
+            mode = entry.mode,
+            keys = M.format_keys(entry.keys),
+            action = entry.name,
+            source = entry.source,
+            priority = entry.priority,
+            shadowed_by = entry.shadowed_by,
+        })
+    end
+
+    -- A built-in replaced by a hotkey is no longer effective
+    for _, row in ipairs(rows) do
+        if row.source == "built-in" then
+            for _, entry in ipairs(hotkeys().entries) do
+                if entry.mode == row.mode and not entry.shadowed_by
+                    and M.format_keys({ entry.keys[1] }) == row.keys then
+                    row.shadowed_by = entry.name
+                end
+            end
+        end
+    end
+
+    table.sort(rows, function(a, b)
+        if a.mode ~= b.mode then return a.mode < b.mode end
+        return a.keys < b.keys
+    end)
+    return rows
+end
+
 -- ==========================================================================
 -- Key Handling
 -- ==========================================================================
@@ -283,13 +796,11 @@ local function key_name(key)
     end
 end
 
---- Handle a single key event
----@param key table ParsedKey from input.parse()
+--- Handle a key with the built-in maps only
+---@param key table ParsedKey
+---@param name string key name
 ---@return table|nil action
-function M.handle_key(key)
-    local name = key_name(key)
-    if not name then return nil end
-
+local function builtin_key(key, name)
     if M.current == "normal" then
         local handler = normal_keys[name]
         if handler then
@@ -310,6 +821,108 @@ function M.handle_key(key)
     end
 end
 
+--- Handle a key while reverse search is active
+---@param key table ParsedKey
+---@param name string key name
+---@return table|nil action, or nil when the key ended the search
+local function search_key(key, name)
+    if name == "ctrl_r" then
+        input.search_next()
+    elseif name == "backspace" then
+        input.search_backspace()
+    elseif name == "escape" or name == "ctrl_c" or name == "ctrl_g" then
+        input.search_cancel()
+    elseif key.type == "char" and key.char then
+        input.search_type(key.char)
+    else
+        -- Anything else keeps the match and is handled as usual
+        input.search_accept()
+        return nil
+    end
+    return { type = "redraw" }
+end
+
+-- Actions from replayed keys waiting their turn, drained by on_input("")
+local queued = {}
+
+--- Handle a single key event, returning every action it produced in order
+---@param key table ParsedKey from input.parse()
+---@return table[] actions
+local function key_actions(key)
+    local name = key_name(key)
+    if not name then return {} end
+
+    -- Any key but Tab ends a completion cycle
+    if name ~= "tab" then
+        complete.reset()
+    end
+
+    if key.type == "mouse" then
+        return { mouse.handle(key) }
+    end
+
+    if input.search_active() then
+        local action = search_key(key, name)
+        if action then return { action } end
+    end
+
+    local map = hotkeys()[M.current]
+    if not map then
+        return { builtin_key(key, name) }
+    end
+
+    table.insert(pending, { key = key, name = name })
+    local names = {}
+    for _, held in ipairs(pending) do
+        table.insert(names, held.name)
+    end
+    local seq = table.concat(names, " ")
+
+    local entry = map.bindings[seq]
+    if entry then
+        pending = {}
+        return { run_hotkey(entry) }
+    end
+    if map.prefixes[seq] then
+        -- Wait for the rest of the sequence
+        return {}
+    end
+
+    -- Not a hotkey: the first held key goes to the built-ins and the rest
+    -- are handled again, since they may start a sequence of their own
+    local held = pending
+    pending = {}
+    local actions = { builtin_key(held[1].key, held[1].name) }
+    for i = 2, #held do
+        for _, action in ipairs(key_actions(held[i].key)) do
+            table.insert(actions, action)
+        end
+    end
+    return actions
+end
+
+--- Handle a single key event
+---
+--- When replaying held keys yields several actions, the first that does
+--- something is returned and the rest are queued, in key order, for
+--- on_input("") to hand out.
+---@param key table ParsedKey from input.parse()
+---@return table|nil action
+function M.handle_key(key)
+    local first, last
+    for _, action in ipairs(key_actions(key)) do
+        last = action
+        if action.type ~= "redraw" then
+            if first then
+                table.insert(queued, action)
+            else
+                first = action
+            end
+        end
+    end
+    return first or last
+end
+
 -- ==========================================================================
 -- Mode API
 -- ==========================================================================
@@ -326,6 +939,18 @@ function M.is_normal()
     return M.current == "normal"
 end
 
+--- Check if composing a multi-line message
+---@return boolean
+function M.is_compose()
+    return compose
+end
+
+--- Row the input line will reply to, if any
+---@return string|nil
+function M.reply_target()
+    return reply_to
+end
+
 --- Get mode indicator for status bar
 ---@return string
 function M.indicator()
@@ -336,11 +961,21 @@ end
 ---@param mode string "normal" or "insert"
 function M.set(mode)
     M.current = mode
+    pending = {}
+    ex_line = false
+    compose = false
+    reply_to = nil
 end
 
 --- Reset to normal mode
 function M.reset()
     M.current = "normal"
+    pending = {}
+    queued = {}
+    ex_line = false
+    compose = false
+    reply_to = nil
+    input.search_cancel()
 end
 
 -- ==========================================================================
@@ -352,6 +987,11 @@ end
 ---@param bytes string Raw bytes from SSH channel
 ---@return table|nil Action to take
 function _G.on_input(bytes)
+    -- Rust calls with no bytes after an action to drain replayed actions
+    if #bytes == 0 and #queued > 0 then
+        return table.remove(queued, 1)
+    end
+
     local keys = input.parse(bytes)
     local last_action = nil
 
@@ -369,4 +1009,86 @@ function _G.on_input(bytes)
     return last_action
 end
 
+-- ==========================================================================
+-- Paste (called from Rust)
+-- ==========================================================================
+
+--- Pastes larger than this (in bytes) are stored as a data thing and
+--- referenced from the input instead of being inserted
+M.PASTE_INLINE_MAX = 4096
+
+--- Store a large paste in the agent's inventory
+---@param text string
+---@return string|nil reference to insert, string|nil error
+local function store_paste(text)
+    local parent_id = tools.get_agent_thing_id and tools.get_agent_thing_id()
+    local user = tools.current_user and tools.current_user()
+    if not parent_id or not user then
+        return nil, "not logged in"
+    end
+
+    -- Several pastes can land in the same second; never overwrite one
+    local base = "paste-" .. os.date("!%Y%m%d-%H%M%S")
+    local name, n = base, 1
+    while tools.things_get(user.name .. ":" .. name) do
+        n = n + 1
+        name = base .. "-" .. n
+    end
+    local qualified_name = user.name .. ":" .. name
+    local _, newlines = text:gsub("\n", "")
+    local result = tools.thing_create({
+        qualified_name = qualified_name,
+        name = name,
+        kind = "data",
+        parent_id = parent_id,
+        description = string.format("Pasted text (%d lines)", newlines + 1),
+        content = text,
+        created_by = user.name,
+    })
+    if not result or not result.success then
+        return nil, result and result.error or "unknown error"
+    end
+    return string.format("[%s, %d lines](thing:%s)", name, newlines + 1, qualified_name)
+end
+
+--- Entry point for a completed bracketed paste.
+--- Multi-line text goes into the input as-is; Enter sends it as one message.
+---@param text string Pasted text
+---@param truncated boolean Whether the paste hit the size cap
+---@return table|nil Action to take
+function _G.on_paste(text, truncated)
+    complete.reset()
+    if input.search_active() then
+        input.search_accept()
+    end
+    if truncated then
+        tools.notify("Paste was too large and has been truncated", "warning")
+    end
+
+    -- Terminals send line breaks as CR; drop other control bytes so a paste
+    -- can't smuggle escape sequences into the screen
+    text = text:gsub("\r\n?", "\n"):gsub("[\1-\8\11-\31\127]", "")
+    if text == "" then return nil end
+
+    M.current = "insert"
+    pending = {}
+
+    if #text > M.PASTE_INLINE_MAX then
+        local ref, err = store_paste(text)
+        if not ref then
+            tools.notify("Could not store paste: " .. err, "error")
+            return { type = "redraw" }
+        end
+        tools.notify("Large paste saved to your inventory")
+        input.insert(ref)
+        return { type = "redraw" }
+    end
+
+    -- A trailing newline from copying whole lines shouldn't end up in the
+    -- draft, and tabs would throw off the input line's cursor
+    text = text:gsub("\n+$", ""):gsub("\t", "    ")
+    input.insert(text)
+    return { type = "redraw" }
+end
+
 return M
@@ -0,0 +1,119 @@
+-- ui/mouse.lua - Mouse events and clickable regions
+--
+-- The terminal reports the mouse in SGR format (ESC [ < b ; x ; y M/m).
+-- input.parse turns each report into a key like:
+--   {type = "mouse", button = "left", action = "press", x = 10, y = 3}
+-- with 0-indexed cells, the same coordinates draw contexts use.
+-- mode.lua hands these keys to M.handle.
+--
+-- Anything drawn during a frame can make itself clickable:
+--   mouse.region(ctx, x, y, w, h, function(ev, lx, ly) ... end)
+-- Regions are cleared when a frame starts, and the last one registered
+-- at a point wins, so overlays beat what they cover. A handler returns
+-- an action table, nothing (redraw), or false to let the event through.
+--
+-- Unclaimed wheel events scroll the current page.
+
+local pages = require 'ui.pages'
+local scroll = require 'ui.scroll'
+
+local M = {}
+
+-- Lines scrolled per wheel notch
+M.WHEEL_LINES = 3
+
+-- Clickable regions for the current frame: {x, y, w, h, handler}
+local regions = {}
+
+-- Row selected by clicking in the chat (target for reply, react and pin)
+local selected = nil
+
+--------------------------------------------------------------------------------
+-- Regions
+--------------------------------------------------------------------------------
+
+--- Forget the previous frame's regions (called before each render)
+function M.begin_frame()
+    regions = {}
+end
+
+--- Make an area of a draw context clickable
+---@param ctx table draw context the coordinates are relative to
+---@param x number
+---@param y number
+---@param w number
+---@param h number
+---@param handler function(ev, lx, ly) -> action|nil|false
+function M.region(ctx, x, y, w, h, handler)
+    table.insert(regions, {
+        x = ctx.x + x,
+        y = ctx.y + y,
+        w = w,
+        h = h,
+        handler = handler,
+    })
+end
+
+--- Topmost regions containing a cell, last registered first
+local function regions_at(x, y)
+    local hits = {}
+    for i = #regions, 1, -1 do
+        local r = regions[i]
+        if x >= r.x and x < r.x + r.w and y >= r.y and y < r.y + r.h then
+            table.insert(hits, r)
+        end
+    end
+    return hits
+end
+
+--------------------------------------------------------------------------------
+-- Selection
+--------------------------------------------------------------------------------
+
+--- Select a row (nil clears)
+---@param row_id string|nil
+function M.select(row_id)
+    selected = row_id
+end
+
+--- Currently selected row id
+---@return string|nil
+function M.selected()
+    return selected
+end
+
+--------------------------------------------------------------------------------
+-- Dispatch
+--------------------------------------------------------------------------------
+
+--- Handle a mouse key from input.parse
+---@param ev table mouse key
+---@return table|nil action
+function M.handle(ev)
+    for _, r in ipairs(regions_at(ev.x, ev.y)) do
+        local ok, result = pcall(r.handler, ev, ev.x - r.x, ev.y - r.y)
+        if not ok then
+            if tools and tools.log_warn then
+                tools.log_warn("mouse handler failed: " .. tostring(result))
+            end
+            return { type = "redraw" }
+        end
+        if result ~= false then
+            return result or { type = "redraw" }
+        end
+    end
+
+    if ev.action == "scroll" then
+        local page = pages.current_name()
+        if ev.button == "wheel_up" then
+            scroll.up(page, M.WHEEL_LINES)
+        elseif ev.button == "wheel_down" then
+            scroll.down(page, M.WHEEL_LINES)
+        end
+        return { type = "redraw" }
+    end
+
+    return nil
+end
+
+return M
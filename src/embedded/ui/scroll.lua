@@ -4,6 +4,8 @@
 -- - Content streams in at bottom when following
 -- - Scroll up detaches viewport
 -- - Jump to bottom reattaches
+-- - After a resize (M.reflow_all), a detached viewport keeps its relative
+--   position as wrapped content grows or shrinks
 
 local M = {}
 
@@ -30,10 +32,15 @@ end
 ---@param height number total content lines
 function M.set_content_height(page, height)
     local s = M.get(page)
-    s.content_height = height
-    if s.following then
-        s.offset = math.max(0, height - s.viewport_height)
+    if s.reflow then
+        -- Rewrapped at a new width: stay at the same point in the content
+        if not s.following and s.content_height > 0 then
+            s.offset = math.floor(s.offset * height / s.content_height + 0.5)
+        end
+        s.reflow = nil
     end
+    s.content_height = height
+    M.clamp(page)
 end
 
 --- Set viewport height
@@ -42,6 +49,27 @@ end
 function M.set_viewport_height(page, height)
     local s = M.get(page)
     s.viewport_height = height
+    M.clamp(page)
+end
+
+--- Keep the offset in range (and at the bottom when following)
+---@param page string
+function M.clamp(page)
+    local s = M.get(page)
+    local max_offset = math.max(0, s.content_height - s.viewport_height)
+    if s.following then
+        s.offset = max_offset
+    else
+        s.offset = math.max(0, math.min(s.offset, max_offset))
+    end
+end
+
+--- Note that the terminal changed size: every page's content will be
+--- rewrapped, so scale offsets on the next set_content_height
+function M.reflow_all()
+    for _, s in pairs(scroll_states) do
+        s.reflow = true
+    end
 end
 
 --- Scroll up by lines
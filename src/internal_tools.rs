@@ -25,7 +25,7 @@ fn anyhow_to_tool_error(e: anyhow::Error) -> ToolError {
 /// Configuration for internal tools
 #[derive(Debug, Clone)]
 pub struct InternalToolConfig {
-    /// Enable navigation tools (join, leave, go, create, fork)
+    /// Enable navigation tools (join, leave, go, travel, create, fork)
     pub enable_navigation: bool,
 }
 
@@ -130,6 +130,10 @@ pub async fn register_tools(
                 handle.add_tool(SshwarmaGo { ctx: ctx.clone() }).await?;
                 count += 1;
             }
+            if should_register("travel") {
+                handle.add_tool(SshwarmaTravel { ctx: ctx.clone() }).await?;
+                count += 1;
+            }
             if should_register("create") {
                 handle.add_tool(SshwarmaCreate { ctx: ctx.clone() }).await?;
                 count += 1;
@@ -333,7 +337,7 @@ impl ToolDyn for SshwarmaExits {
         Box::pin(async move {
             ToolDefinition {
                 name: "sshwarma_exits".to_string(),
-                description: "List exits from the current room (directions and destinations)"
+                description: "List exits from the current room (directions, targets, locks)"
                     .to_string(),
                 parameters: json!({
                     "type": "object",
@@ -349,8 +353,12 @@ impl ToolDyn for SshwarmaExits {
             let exits = ops::exits(&self.ctx.state, &self.ctx.room)
                 .await
                 .map_err(anyhow_to_tool_error)?;
+            let locked = ops::locked_exits(&self.ctx.state, &self.ctx.username, &self.ctx.room)
+                .await
+                .map_err(anyhow_to_tool_error)?;
 
-            serde_json::to_string(&exits).map_err(ToolError::JsonError)
+            serde_json::to_string(&json!({ "exits": exits, "locked": locked }))
+                .map_err(ToolError::JsonError)
         })
     }
 }
@@ -547,14 +555,9 @@ impl ToolDyn for SshwarmaJoin {
             let parsed: JoinArgs = serde_json::from_str(&args).map_err(ToolError::JsonError)?;
             debug!(tool = "sshwarma_join", from = %self.ctx.room, to = %parsed.room, "navigation");
 
-            let summary = ops::join(
-                &self.ctx.state,
-                &self.ctx.username,
-                Some(&self.ctx.room),
-                &parsed.room,
-            )
-            .await
-            .map_err(anyhow_to_tool_error)?;
+            let summary = ops::join(&self.ctx.state, &self.ctx.username, &parsed.room)
+                .await
+                .map_err(anyhow_to_tool_error)?;
 
             serde_json::to_string(&summary).map_err(ToolError::JsonError)
         })
@@ -653,6 +656,60 @@ impl ToolDyn for SshwarmaGo {
     }
 }
 
+/// Walk the shortest exit path to a room
+#[derive(Clone)]
+struct SshwarmaTravel {
+    ctx: ToolContext,
+}
+
+#[derive(Deserialize)]
+struct TravelArgs {
+    room: String,
+}
+
+impl ToolDyn for SshwarmaTravel {
+    fn name(&self) -> String {
+        "sshwarma_travel".to_string()
+    }
+
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            ToolDefinition {
+                name: "sshwarma_travel".to_string(),
+                description: "Travel to a room by the shortest path through exits, passing through each room on the way".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "room": {
+                            "type": "string",
+                            "description": "Name of the room to travel to"
+                        }
+                    },
+                    "required": ["room"]
+                }),
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            let parsed: TravelArgs = serde_json::from_str(&args).map_err(ToolError::JsonError)?;
+            debug!(tool = "sshwarma_travel", from = %self.ctx.room, to = %parsed.room, "navigation");
+
+            let trip = ops::travel(
+                &self.ctx.state,
+                &self.ctx.username,
+                &self.ctx.room,
+                &parsed.room,
+            )
+            .await
+            .map_err(anyhow_to_tool_error)?;
+
+            serde_json::to_string(&trip).map_err(ToolError::JsonError)
+        })
+    }
+}
+
 /// Create a new room
 #[derive(Clone)]
 struct SshwarmaCreate {
@@ -693,14 +750,9 @@ impl ToolDyn for SshwarmaCreate {
             let parsed: CreateArgs = serde_json::from_str(&args).map_err(ToolError::JsonError)?;
             debug!(tool = "sshwarma_create", room = %parsed.name, "room creation");
 
-            let summary = ops::create_room(
-                &self.ctx.state,
-                &self.ctx.username,
-                &parsed.name,
-                Some(&self.ctx.room),
-            )
-            .await
-            .map_err(anyhow_to_tool_error)?;
+            let summary = ops::create_room(&self.ctx.state, &self.ctx.username, &parsed.name)
+                .await
+                .map_err(anyhow_to_tool_error)?;
 
             serde_json::to_string(&summary).map_err(ToolError::JsonError)
         })
@@ -716,6 +768,8 @@ struct SshwarmaFork {
 #[derive(Deserialize)]
 struct ForkArgs {
     name: String,
+    #[serde(default)]
+    at: Option<String>,
 }
 
 impl ToolDyn for SshwarmaFork {
@@ -727,13 +781,17 @@ impl ToolDyn for SshwarmaFork {
         Box::pin(async move {
             ToolDefinition {
                 name: "sshwarma_fork".to_string(),
-                description: "Fork the current room (copies vibe, assets) and join it".to_string(),
+                description: "Fork the current room (copies vibe, assets, and shares its history up to a message) and join it".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
                         "name": {
                             "type": "string",
                             "description": "Name for the new forked room"
+                        },
+                        "at": {
+                            "type": "string",
+                            "description": "Message to branch from: a row id, or -N for the Nth latest message (default: the latest)"
                         }
                     },
                     "required": ["name"]
@@ -752,6 +810,7 @@ impl ToolDyn for SshwarmaFork {
                 &self.ctx.username,
                 &self.ctx.room,
                 &parsed.name,
+                parsed.at.as_deref(),
             )
             .await
             .map_err(anyhow_to_tool_error)?;
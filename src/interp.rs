@@ -22,22 +22,27 @@ pub fn parse(input: &str) -> Input {
     }
 
     if let Some(rest) = input.strip_prefix('/') {
-        let (name, args) = match rest.find(' ') {
-            Some(pos) => (rest[..pos].to_string(), rest[pos + 1..].to_string()),
-            None => (rest.to_string(), String::new()),
-        };
+        let (name, args) = split_word(rest);
         Input::Command { name, args }
     } else if let Some(rest) = input.strip_prefix('@') {
-        let (model, message) = match rest.find(' ') {
-            Some(pos) => (rest[..pos].to_string(), rest[pos + 1..].to_string()),
-            None => (rest.to_string(), String::new()),
-        };
+        let (model, message) = split_word(rest);
         Input::Mention { model, message }
     } else {
         Input::Chat(input.to_string())
     }
 }
 
+/// Split off the first word; the rest may span several lines
+fn split_word(s: &str) -> (String, String) {
+    match s.find(char::is_whitespace) {
+        Some(pos) => {
+            let sep = s[pos..].chars().next().map_or(1, char::len_utf8);
+            (s[..pos].to_string(), s[pos + sep..].to_string())
+        }
+        None => (s.to_string(), String::new()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,4 +76,15 @@ mod tests {
             _ => panic!("expected chat"),
         }
     }
+
+    #[test]
+    fn test_parse_multiline_mention() {
+        match parse("@qwen-8b\nwhat does this do?\n  fn main() {}") {
+            Input::Mention { model, message } => {
+                assert_eq!(model, "qwen-8b");
+                assert_eq!(message, "what does this do?\n  fn main() {}");
+            }
+            _ => panic!("expected mention"),
+        }
+    }
 }
@@ -15,6 +15,7 @@ pub mod model;
 pub mod ops;
 pub mod paths;
 pub mod player;
+pub mod shutdown;
 pub mod ssh;
 pub mod state;
 pub mod status;
@@ -4,7 +4,7 @@
 //! This allows status at top, bottom, both sides, or any layout Lua wants.
 
 use std::collections::HashSet;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock, Weak};
 use tokio::sync::Notify;
 
 /// Tag-based dirty tracking with arbitrary string tags.
@@ -16,6 +16,8 @@ use tokio::sync::Notify;
 pub struct DirtyState {
     tags: RwLock<HashSet<String>>,
     signal: Notify,
+    /// Screens sharing this state, each taking its own copy of every mark
+    subscribers: RwLock<Vec<Weak<DirtyState>>>,
 }
 
 impl DirtyState {
@@ -23,15 +25,13 @@ impl DirtyState {
         Self {
             tags: RwLock::new(HashSet::new()),
             signal: Notify::new(),
+            subscribers: RwLock::new(Vec::new()),
         }
     }
 
     /// Mark a tag dirty and signal waiters
     pub fn mark(&self, tag: impl Into<String>) {
-        let mut tags = self.tags.write().unwrap();
-        tags.insert(tag.into());
-        drop(tags);
-        self.signal.notify_one();
+        self.mark_many([tag.into()]);
     }
 
     /// Mark all provided tags dirty
@@ -40,12 +40,31 @@ impl DirtyState {
         I: IntoIterator<Item = S>,
         S: Into<String>,
     {
+        let new_tags: Vec<String> = new_tags.into_iter().map(Into::into).collect();
         let mut tags = self.tags.write().unwrap();
-        for tag in new_tags {
-            tags.insert(tag.into());
-        }
+        tags.extend(new_tags.iter().cloned());
         drop(tags);
         self.signal.notify_one();
+
+        let mut subscribers = self.subscribers.write().unwrap();
+        subscribers.retain(|sub| match sub.upgrade() {
+            Some(sub) => {
+                sub.mark_many(new_tags.iter().cloned());
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// A separate dirty set that receives every mark made here
+    ///
+    /// Each screen showing the same session takes from its own subscriber,
+    /// so one render doesn't swallow tags another screen still needs.
+    /// Marks made on the subscriber itself stay local to it.
+    pub fn subscribe(&self) -> Arc<DirtyState> {
+        let sub = Arc::new(DirtyState::new());
+        self.subscribers.write().unwrap().push(Arc::downgrade(&sub));
+        sub
     }
 
     /// Take all dirty tags, clearing the set
@@ -75,6 +94,24 @@ impl Default for DirtyState {
 mod tests {
     use super::*;
 
+    #[test]
+    fn subscribers_get_their_own_copy() {
+        let dirty = DirtyState::new();
+        let a = dirty.subscribe();
+        let b = dirty.subscribe();
+
+        dirty.mark("chat");
+        a.mark("resize");
+        assert_eq!(a.take().len(), 2);
+        assert!(a.take().is_empty());
+        assert_eq!(b.take(), HashSet::from(["chat".to_string()]));
+
+        // Dropped subscribers are pruned on the next mark
+        drop(b);
+        dirty.mark("input");
+        assert_eq!(dirty.subscribers.read().unwrap().len(), 1);
+    }
+
     #[test]
     fn mark_arbitrary_tags() {
         let dirty = DirtyState::new();
@@ -8,6 +8,7 @@ pub mod context;
 pub mod data;
 pub mod dirty;
 pub mod mcp_bridge;
+pub mod predicate;
 pub mod registry;
 pub mod reload;
 pub mod render;
@@ -106,6 +107,12 @@ const PAGES_MODULE: &str = include_str!("../embedded/ui/pages.lua");
 /// Embedded scroll module for scroll state
 const SCROLL_MODULE: &str = include_str!("../embedded/ui/scroll.lua");
 
+/// Embedded mouse module for mouse events and clickable regions
+const MOUSE_MODULE: &str = include_str!("../embedded/ui/mouse.lua");
+
+/// Embedded completion module for tab completion
+const COMPLETE_MODULE: &str = include_str!("../embedded/ui/complete.lua");
+
 /// Embedded mode module for vim-style modes
 const MODE_MODULE: &str = include_str!("../embedded/ui/mode.lua");
 
@@ -172,6 +179,24 @@ const COMMANDS_RELOAD_MODULE: &str = include_str!("../embedded/commands/reload.l
 /// Embedded conjure commands
 const COMMANDS_CONJURE_MODULE: &str = include_str!("../embedded/commands/conjure.lua");
 
+/// Embedded admin commands
+const COMMANDS_ADMIN_MODULE: &str = include_str!("../embedded/commands/admin.lua");
+
+/// Embedded zone commands
+const COMMANDS_ZONE_MODULE: &str = include_str!("../embedded/commands/zone.lua");
+
+/// Embedded keymap commands
+const COMMANDS_KEYS_MODULE: &str = include_str!("../embedded/commands/keys.lua");
+
+/// Embedded terminal commands
+const COMMANDS_TERM_MODULE: &str = include_str!("../embedded/commands/term.lua");
+
+/// Embedded recording commands
+const COMMANDS_RECORD_MODULE: &str = include_str!("../embedded/commands/record.lua");
+
+/// Embedded bundle commands
+const COMMANDS_BUNDLE_MODULE: &str = include_str!("../embedded/commands/bundle.lua");
+
 // MCP tool modules (for Claude Code integration)
 const MCP_INIT_MODULE: &str = include_str!("../embedded/mcp/init.lua");
 const MCP_ROOMS_MODULE: &str = include_str!("../embedded/mcp/rooms.lua");
@@ -188,6 +213,9 @@ const MCP_ROOM_CONTEXT_MODULE: &str = include_str!("../embedded/mcp/room_context
 const MCP_INVENTORY_MODULE: &str = include_str!("../embedded/mcp/inventory.lua");
 const MCP_THINGS_MODULE: &str = include_str!("../embedded/mcp/things.lua");
 const MCP_SCRIPTS_MODULE: &str = include_str!("../embedded/mcp/scripts.lua");
+const MCP_TEMPLATES_MODULE: &str = include_str!("../embedded/mcp/templates.lua");
+const MCP_TRASH_MODULE: &str = include_str!("../embedded/mcp/trash.lua");
+const MCP_BUNDLES_MODULE: &str = include_str!("../embedded/mcp/bundles.lua");
 const MCP_ECHO_TEST_MODULE: &str = include_str!("../embedded/mcp/echo_test.lua");
 
 /// Registry of embedded Lua modules
@@ -229,6 +257,8 @@ impl EmbeddedModules {
         modules.insert("ui.bars".to_string(), BARS_MODULE);
         modules.insert("ui.pages".to_string(), PAGES_MODULE);
         modules.insert("ui.scroll".to_string(), SCROLL_MODULE);
+        modules.insert("ui.mouse".to_string(), MOUSE_MODULE);
+        modules.insert("ui.complete".to_string(), COMPLETE_MODULE);
         modules.insert("ui.mode".to_string(), MODE_MODULE);
 
         // Command modules
@@ -241,6 +271,12 @@ impl EmbeddedModules {
         modules.insert("commands.debug".to_string(), COMMANDS_DEBUG_MODULE);
         modules.insert("commands.reload".to_string(), COMMANDS_RELOAD_MODULE);
         modules.insert("commands.conjure".to_string(), COMMANDS_CONJURE_MODULE);
+        modules.insert("commands.admin".to_string(), COMMANDS_ADMIN_MODULE);
+        modules.insert("commands.zone".to_string(), COMMANDS_ZONE_MODULE);
+        modules.insert("commands.keys".to_string(), COMMANDS_KEYS_MODULE);
+        modules.insert("commands.term".to_string(), COMMANDS_TERM_MODULE);
+        modules.insert("commands.record".to_string(), COMMANDS_RECORD_MODULE);
+        modules.insert("commands.bundle".to_string(), COMMANDS_BUNDLE_MODULE);
 
         // MCP tool modules (for Claude Code integration)
         // Override by placing files in ~/.config/sshwarma/lua/mcp/
@@ -259,6 +295,9 @@ impl EmbeddedModules {
         modules.insert("mcp.inventory".to_string(), MCP_INVENTORY_MODULE);
         modules.insert("mcp.things".to_string(), MCP_THINGS_MODULE);
         modules.insert("mcp.scripts".to_string(), MCP_SCRIPTS_MODULE);
+        modules.insert("mcp.templates".to_string(), MCP_TEMPLATES_MODULE);
+        modules.insert("mcp.trash".to_string(), MCP_TRASH_MODULE);
+        modules.insert("mcp.bundles".to_string(), MCP_BUNDLES_MODULE);
         modules.insert("mcp.echo_test".to_string(), MCP_ECHO_TEST_MODULE);
 
         Self { modules }
@@ -459,13 +498,6 @@ impl LuaRuntime {
             .map_err(|e| anyhow::anyhow!("failed to load embedded layout module: {}", e))?;
         loaded.set("ui.layout", layout_chunk)?;
 
-        let bars_chunk = lua
-            .load(BARS_MODULE)
-            .set_name("embedded:ui/bars.lua")
-            .eval::<Table>()
-            .map_err(|e| anyhow::anyhow!("failed to load embedded bars module: {}", e))?;
-        loaded.set("ui.bars", bars_chunk)?;
-
         let pages_chunk = lua
             .load(PAGES_MODULE)
             .set_name("embedded:ui/pages.lua")
@@ -480,6 +512,28 @@ impl LuaRuntime {
             .map_err(|e| anyhow::anyhow!("failed to load embedded scroll module: {}", e))?;
         loaded.set("ui.scroll", scroll_chunk)?;
 
+        // Mouse needs pages and scroll; bars need mouse for clickable items
+        let mouse_chunk = lua
+            .load(MOUSE_MODULE)
+            .set_name("embedded:ui/mouse.lua")
+            .eval::<Table>()
+            .map_err(|e| anyhow::anyhow!("failed to load embedded mouse module: {}", e))?;
+        loaded.set("ui.mouse", mouse_chunk)?;
+
+        let bars_chunk = lua
+            .load(BARS_MODULE)
+            .set_name("embedded:ui/bars.lua")
+            .eval::<Table>()
+            .map_err(|e| anyhow::anyhow!("failed to load embedded bars module: {}", e))?;
+        loaded.set("ui.bars", bars_chunk)?;
+
+        let complete_chunk = lua
+            .load(COMPLETE_MODULE)
+            .set_name("embedded:ui/complete.lua")
+            .eval::<Table>()
+            .map_err(|e| anyhow::anyhow!("failed to load embedded complete module: {}", e))?;
+        loaded.set("ui.complete", complete_chunk)?;
+
         // Load mode module - defines on_input() globally
         let mode_chunk = lua
             .load(MODE_MODULE)
@@ -547,6 +601,36 @@ impl LuaRuntime {
                 COMMANDS_CONJURE_MODULE,
                 "embedded:commands/conjure.lua",
             ),
+            (
+                "commands.admin",
+                COMMANDS_ADMIN_MODULE,
+                "embedded:commands/admin.lua",
+            ),
+            (
+                "commands.zone",
+                COMMANDS_ZONE_MODULE,
+                "embedded:commands/zone.lua",
+            ),
+            (
+                "commands.keys",
+                COMMANDS_KEYS_MODULE,
+                "embedded:commands/keys.lua",
+            ),
+            (
+                "commands.term",
+                COMMANDS_TERM_MODULE,
+                "embedded:commands/term.lua",
+            ),
+            (
+                "commands.record",
+                COMMANDS_RECORD_MODULE,
+                "embedded:commands/record.lua",
+            ),
+            (
+                "commands.bundle",
+                COMMANDS_BUNDLE_MODULE,
+                "embedded:commands/bundle.lua",
+            ),
         ];
 
         for (name, code, chunk_name) in cmd_modules {
@@ -844,13 +928,24 @@ impl LuaRuntime {
             MCP_SCRIPTS_MODULE,
             "embedded:mcp/scripts.lua",
         )?;
+        load_module(
+            "mcp.templates",
+            MCP_TEMPLATES_MODULE,
+            "embedded:mcp/templates.lua",
+        )?;
+        load_module("mcp.trash", MCP_TRASH_MODULE, "embedded:mcp/trash.lua")?;
+        load_module(
+            "mcp.bundles",
+            MCP_BUNDLES_MODULE,
+            "embedded:mcp/bundles.lua",
+        )?;
         load_module(
             "mcp.echo_test",
             MCP_ECHO_TEST_MODULE,
             "embedded:mcp/echo_test.lua",
         )?;
 
-        debug!("Preloaded {} embedded MCP modules", 15);
+        debug!("Preloaded {} embedded MCP modules", 16);
         Ok(())
     }
 
@@ -1390,7 +1485,8 @@ impl LuaRuntime {
         }
 
         // Create draw context for full screen
-        let ctx = crate::ui::LuaDrawContext::new(render_buffer, 0, 0, width, height);
+        let ctx = crate::ui::LuaDrawContext::new(render_buffer, 0, 0, width, height)
+            .with_caps(self.tool_state.term_caps());
 
         // Call on_tick(dirty_tags, tick, ctx) - new signature
         // For backwards compatibility, also try (tick, ctx) if that fails
@@ -1572,42 +1668,32 @@ impl LuaRuntime {
             anyhow::anyhow!("{}", msg)
         })?;
 
-        // Parse the action table
-        match result {
-            Value::Nil => Ok(None),
-            Value::Table(t) => {
-                let action_type: String = t
-                    .get("type")
-                    .map_err(|e| anyhow::anyhow!("action missing 'type': {}", e))?;
-
-                let action = match action_type.as_str() {
-                    "none" => InputAction::None,
-                    "redraw" => InputAction::Redraw,
-                    "execute" | "send" => {
-                        let text: String = t.get("text").map_err(|e| {
-                            anyhow::anyhow!("execute/send action missing 'text': {}", e)
-                        })?;
-                        InputAction::Execute(text)
-                    }
-                    "tab" => InputAction::Tab,
-                    "clear_screen" => InputAction::ClearScreen,
-                    "quit" => InputAction::Quit,
-                    "escape" => InputAction::Escape,
-                    "page_up" => InputAction::PageUp,
-                    "page_down" => InputAction::PageDown,
-                    _ => {
-                        warn!("unknown input action type: {}", action_type);
-                        InputAction::None
-                    }
-                };
+        parse_input_action(result, "on_input")
+    }
 
-                Ok(Some(action))
-            }
-            _ => {
-                warn!("on_input returned unexpected type: {:?}", result);
-                Ok(None)
-            }
-        }
+    /// Hand a completed bracketed paste to Lua's on_paste() function
+    ///
+    /// Lua decides where the text goes (input line, compose buffer or a
+    /// data thing). `truncated` is set when the paste hit the size cap.
+    pub fn call_on_paste(&self, text: &str, truncated: bool) -> Result<Option<InputAction>> {
+        let on_paste_fn: Value = self
+            .lua
+            .globals()
+            .get("on_paste")
+            .map_err(|e| anyhow::anyhow!("failed to get on_paste: {}", e))?;
+
+        let Value::Function(func) = on_paste_fn else {
+            debug!("on_paste function not defined");
+            return Ok(None);
+        };
+
+        let result: Value = func.call((text, truncated)).map_err(|e| {
+            let msg = format!("on_paste() call failed: {}", e);
+            record_lua_error("on_paste", &msg);
+            anyhow::anyhow!("{}", msg)
+        })?;
+
+        parse_input_action(result, "on_paste")
     }
 
     /// Dispatch a command through Lua's command system
@@ -1653,6 +1739,28 @@ impl LuaRuntime {
 
         Ok(Some(CommandResult { text, mode, title }))
     }
+
+    /// Complete the word at the input cursor
+    ///
+    /// Calls `ui.complete.tab()`, which edits the input buffer in place.
+    /// Returns the number of candidates found.
+    pub fn call_tab_complete(&self) -> Result<usize> {
+        let complete: Table = self
+            .lua
+            .load("return require('ui.complete')")
+            .eval()
+            .map_err(|e| anyhow::anyhow!("failed to get complete module: {}", e))?;
+
+        let tab_fn: mlua::Function = complete
+            .get("tab")
+            .map_err(|e| anyhow::anyhow!("complete.tab not found: {}", e))?;
+
+        tab_fn.call::<usize>(()).map_err(|e| {
+            let msg = format!("complete.tab() failed: {}", e);
+            record_lua_error("complete.tab", &msg);
+            anyhow::anyhow!("{}", msg)
+        })
+    }
 }
 
 /// Action returned by Lua input handler
@@ -1664,6 +1772,8 @@ pub enum InputAction {
     Redraw,
     /// Execute the given line
     Execute(String),
+    /// Execute the given line as a reply to a row
+    Reply { text: String, row_id: String },
     /// Tab completion requested
     Tab,
     /// Clear screen requested
@@ -1678,6 +1788,54 @@ pub enum InputAction {
     PageDown,
 }
 
+/// Convert an action table returned by on_input/on_paste
+fn parse_input_action(result: Value, caller: &str) -> Result<Option<InputAction>> {
+    match result {
+        Value::Nil => Ok(None),
+        Value::Table(t) => {
+            let action_type: String = t
+                .get("type")
+                .map_err(|e| anyhow::anyhow!("action missing 'type': {}", e))?;
+
+            let action = match action_type.as_str() {
+                "none" => InputAction::None,
+                "redraw" => InputAction::Redraw,
+                "execute" | "send" => {
+                    let text: String = t.get("text").map_err(|e| {
+                        anyhow::anyhow!("execute/send action missing 'text': {}", e)
+                    })?;
+                    InputAction::Execute(text)
+                }
+                "reply" => {
+                    let text: String = t
+                        .get("text")
+                        .map_err(|e| anyhow::anyhow!("reply action missing 'text': {}", e))?;
+                    let row_id: String = t
+                        .get("row_id")
+                        .map_err(|e| anyhow::anyhow!("reply action missing 'row_id': {}", e))?;
+                    InputAction::Reply { text, row_id }
+                }
+                "tab" => InputAction::Tab,
+                "clear_screen" => InputAction::ClearScreen,
+                "quit" => InputAction::Quit,
+                "escape" => InputAction::Escape,
+                "page_up" => InputAction::PageUp,
+                "page_down" => InputAction::PageDown,
+                _ => {
+                    warn!("unknown input action type: {}", action_type);
+                    InputAction::None
+                }
+            };
+
+            Ok(Some(action))
+        }
+        _ => {
+            warn!("{} returned unexpected type: {:?}", caller, result);
+            Ok(None)
+        }
+    }
+}
+
 /// Result from Lua command dispatch
 #[derive(Debug, Clone)]
 pub struct CommandResult {
@@ -1717,18 +1875,16 @@ mod tests {
     use crate::state::SharedState;
     use crate::world::World;
     use std::sync::Arc;
-    use tokio::sync::RwLock;
 
     /// Reusable test fixture with real components (mirrors tools.rs TestInstance)
     struct TestInstance {
         shared_state: Arc<SharedState>,
-        world: Arc<RwLock<World>>,
         models: Arc<ModelRegistry>,
     }
 
     impl TestInstance {
         fn new() -> anyhow::Result<Self> {
-            let db = Arc::new(Database::open(":memory:")?);
+            let db = Arc::new(Database::in_memory()?);
 
             let mut models = ModelRegistry::new();
             models.register(ModelHandle {
@@ -1743,31 +1899,28 @@ mod tests {
             });
             let models = Arc::new(models);
 
-            let world = Arc::new(RwLock::new(World::new()));
-
             let shared_state = Arc::new(SharedState {
-                world: world.clone(),
+                world: Arc::new(World::new(db.clone())),
                 db,
                 config: Config::default(),
                 llm: Arc::new(LlmClient::new()?),
                 models: models.clone(),
                 mcp: Arc::new(McpManager::new()),
                 lua_reload: LuaReloadSender::new(),
+                shutdown: Arc::new(crate::shutdown::Shutdown::new()),
+                sessions: Arc::new(crate::ssh::SessionStore::new(std::time::Duration::ZERO)),
             });
 
             Ok(Self {
                 shared_state,
-                world,
                 models,
             })
         }
         async fn create_room(&self, name: &str, vibe: Option<&str>) {
-            let mut world = self.world.write().await;
-            world.create_room(name.to_string());
+            let db = &self.shared_state.db;
+            let _ = db.create_room(name, None);
             if let Some(v) = vibe {
-                if let Some(room) = world.get_room_mut(name) {
-                    room.context.vibe = Some(v.to_string());
-                }
+                let _ = db.set_vibe(name, Some(v));
             }
         }
 
@@ -1838,6 +1991,110 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_term_caps_pick_glyphs() {
+        use std::sync::{Arc, Mutex};
+
+        let runtime = LuaRuntime::new().expect("should create runtime");
+        let render = |runtime: &LuaRuntime| {
+            let buffer = Arc::new(Mutex::new(crate::ui::RenderBuffer::new(80, 8)));
+            let tags = std::collections::HashSet::from(["status".to_string()]);
+            runtime
+                .call_on_tick_with_tags(&tags, 1, buffer.clone(), 80, 8)
+                .expect("should call on_tick");
+            let buf = buffer.lock().unwrap();
+            buf.to_ansi()
+        };
+
+        assert!(render(&runtime).contains('\u{E0B0}'));
+
+        // A Linux console gets ASCII separators
+        runtime
+            .tool_state()
+            .set_term_caps(crate::ui::TermCaps::detect("linux", None));
+        assert!(!render(&runtime).contains('\u{E0B0}'));
+
+        // /term overrides go through tools.set_term_caps
+        let result: String = runtime
+            .lua
+            .load(
+                r#"
+                local caps = tools.set_term_caps({ colors = "256", glyphs = "powerline" })
+                local _, err = tools.set_term_caps({ colors = "lots" })
+                return caps.colors .. "," .. caps.glyphs .. "|" .. err
+            "#,
+            )
+            .eval()
+            .expect("should set caps");
+        assert_eq!(result, "256,powerline|unknown color depth: lots");
+        assert_eq!(
+            runtime.tool_state().term_caps().color,
+            crate::ui::ColorDepth::Ansi256
+        );
+        assert!(render(&runtime).contains('\u{E0B0}'));
+    }
+
+    #[test]
+    fn test_term_caps_per_connection() {
+        use crate::ui::{ColorDepth, ConnectionCaps, TermCaps};
+
+        let runtime = LuaRuntime::new().expect("should create runtime");
+        let state = runtime.tool_state();
+        let kitty = ConnectionCaps::default();
+        let console = ConnectionCaps::default();
+
+        state.use_term_caps(&kitty);
+        state.set_term_caps(TermCaps::detect("xterm-kitty", None));
+        state.use_term_caps(&console);
+        state.set_term_caps(TermCaps::detect("linux", None));
+
+        // Mirroring the session from a console leaves the kitty screen alone
+        assert_eq!(kitty.read().unwrap().color, ColorDepth::TrueColor);
+        assert_eq!(state.term_caps().term, "linux");
+        state.use_term_caps(&kitty);
+        assert_eq!(state.term_caps().term, "xterm-kitty");
+    }
+
+    #[test]
+    fn test_scroll_reflow_on_resize() {
+        let runtime = LuaRuntime::new().expect("should create runtime");
+
+        let result: String = runtime
+            .lua
+            .load(
+                r#"
+                local scroll = require('ui.scroll')
+                local seen = {}
+
+                -- Following stays pinned to the bottom as the viewport grows
+                scroll.set_content_height("chat", 100)
+                scroll.set_viewport_height("chat", 20)
+                scroll.set_content_height("chat", 100)
+                scroll.set_viewport_height("chat", 30)
+                table.insert(seen, scroll.get("chat").offset)
+
+                -- A scrolled-back page keeps its place when rewrapped narrower
+                scroll.set_content_height("help", 100)
+                scroll.set_viewport_height("help", 20)
+                scroll.to_top("help")
+                scroll.down("help", 40)
+                scroll.reflow_all()
+                scroll.set_content_height("help", 200)
+                table.insert(seen, scroll.get("help").offset)
+
+                -- Offsets never run past the end
+                scroll.set_content_height("help", 30)
+                table.insert(seen, scroll.get("help").offset)
+
+                return table.concat(seen, ",")
+            "#,
+            )
+            .eval()
+            .expect("should scroll");
+
+        assert_eq!(result, "70,80,10");
+    }
+
     #[test]
     fn test_notification_queue() {
         let runtime = LuaRuntime::new().expect("should create runtime");
@@ -1905,6 +2162,200 @@ mod tests {
         assert!(on_input.is_function(), "on_input should be a function");
     }
 
+    #[test]
+    fn test_hotkey_keyspecs() {
+        let runtime = LuaRuntime::new().expect("should create runtime");
+
+        let result: String = runtime
+            .lua
+            .load(
+                r#"
+                local mode = require('ui.mode')
+                local function show(slot)
+                    local spec = mode.parse_keyspec(slot)
+                    if not spec then return "nil" end
+                    return table.concat(spec.modes, "+") .. "=" .. table.concat(spec.keys, " ")
+                end
+                return table.concat({
+                    show("hotkey:ctrl-g"),
+                    show("hotkey:normal:gp"),
+                    show("hotkey:insert:f2"),
+                    show("hotkey:g,ctrl-p"),
+                    show("hotkey:x"),
+                    show("hotkey:insert:"),
+                    show("command:fish"),
+                }, "|")
+            "#,
+            )
+            .eval()
+            .expect("should parse keyspecs");
+
+        assert_eq!(
+            result,
+            "normal+insert=ctrl_g|normal=g p|insert=f2|normal=g ctrl_p|normal=x|nil|nil"
+        );
+    }
+
+    #[test]
+    fn test_tab_completion_cycles() {
+        let runtime = LuaRuntime::new().expect("should create runtime");
+
+        let result: String = runtime
+            .lua
+            .load(
+                r#"
+                local input = require('ui.input')
+                local complete = require('ui.complete')
+                local seen = {}
+
+                input.set_text("/nav o")
+                table.insert(seen, tostring(complete.tab()))
+                table.insert(seen, input.get_state().text)
+                table.insert(seen, tostring(#complete.popup().items))
+                complete.tab()
+                table.insert(seen, input.get_state().text)
+                complete.tab()
+                table.insert(seen, input.get_state().text)
+
+                -- A single candidate completes with a trailing space
+                complete.reset()
+                input.set_text("/reload d")
+                complete.tab()
+                table.insert(seen, input.get_state().text)
+                table.insert(seen, tostring(complete.popup()))
+
+                return table.concat(seen, "|")
+            "#,
+            )
+            .eval()
+            .expect("should complete");
+
+        assert_eq!(result, "2|/nav off|2|/nav on|/nav off|/reload default |nil");
+    }
+
+    #[test]
+    fn test_history_reverse_search() {
+        let runtime = LuaRuntime::new().expect("should create runtime");
+
+        let result: String = runtime
+            .lua
+            .load(
+                r#"
+                local input = require('ui.input')
+                local mode = require('ui.mode')
+                local seen = {}
+
+                for _, line in ipairs({ "/join den", "hello", "/join lab", "hello" }) do
+                    input.history_add(line)
+                end
+                table.insert(seen, table.concat(input.history_entries(), ","))
+
+                mode.set("insert")
+                input.set_text("draft")
+                mode.handle_key({ type = "ctrl", char = "r" })
+                for _, c in ipairs({ "j", "o" }) do
+                    mode.handle_key({ type = "char", char = c })
+                end
+                table.insert(seen, input.get_state().text)
+                mode.handle_key({ type = "ctrl", char = "r" })
+                table.insert(seen, input.get_state().text)
+                mode.handle_key({ type = "char", char = "x" })
+                table.insert(seen, tostring(input.search_state().failed))
+
+                -- Escape restores what was typed before the search
+                mode.handle_key({ type = "escape" })
+                table.insert(seen, input.get_state().text)
+                table.insert(seen, tostring(input.search_active()))
+
+                -- Lines with secrets stay out of the database
+                table.insert(seen, tostring(input.should_persist("/mcp connect x http://k")))
+                table.insert(seen, tostring(input.should_persist(" secret")))
+
+                return table.concat(seen, "|")
+            "#,
+            )
+            .eval()
+            .expect("should search");
+
+        assert_eq!(
+            result,
+            "/join den,/join lab,hello|/join lab|/join den|true|draft|false|false|false"
+        );
+    }
+
+    #[test]
+    fn test_paste_is_one_message() {
+        let runtime = LuaRuntime::new().expect("should create runtime");
+
+        let action = runtime
+            .call_on_paste("fn main() {\r\n\tprintln!(\"hi\");\r\n}\r\n", false)
+            .expect("should paste");
+        assert_eq!(action, Some(InputAction::Redraw));
+
+        let action = runtime.call_on_input(b"\r").expect("should submit");
+        assert_eq!(
+            action,
+            Some(InputAction::Execute(
+                "fn main() {\n    println!(\"hi\");\n}".to_string()
+            ))
+        );
+
+        // Alt+Enter adds a line instead of sending
+        runtime
+            .call_on_input(b"ione\x1b\rtwo")
+            .expect("should type");
+        let text: String = runtime
+            .lua
+            .load("return require('ui.input').get_state().text")
+            .eval()
+            .expect("should read input");
+        assert_eq!(text, "one\ntwo");
+    }
+
+    #[test]
+    fn test_mouse_dispatch() {
+        let runtime = LuaRuntime::new().expect("should create runtime");
+
+        let result: String = runtime
+            .lua
+            .load(
+                r#"
+                local input = require('ui.input')
+                local mouse = require('ui.mouse')
+                local seen = {}
+
+                local press = input.parse("\27[<0;5;3M")[1]
+                table.insert(seen, string.format("%s %s %d,%d", press.button, press.action, press.x, press.y))
+                local wheel = input.parse("\27[<65;1;1M")[1]
+                table.insert(seen, wheel.button .. " " .. wheel.action)
+                local drag = input.parse("\27[<36;2;2M")[1]
+                table.insert(seen, string.format("%s %s %s", drag.button, drag.action, tostring(drag.shift)))
+
+                -- Later regions sit on top; false passes the event down
+                mouse.begin_frame()
+                local ctx = { x = 2, y = 1 }
+                mouse.region(ctx, 0, 0, 10, 3, function() mouse.select("row1") end)
+                mouse.region(ctx, 0, 0, 10, 2, function() return false end)
+                mouse.handle(press)
+                table.insert(seen, tostring(mouse.selected()))
+
+                mouse.begin_frame()
+                mouse.select(nil)
+                mouse.handle(press)
+                table.insert(seen, tostring(mouse.selected()))
+
+                return table.concat(seen, "|")
+            "#,
+            )
+            .eval()
+            .expect("should dispatch");
+
+        assert_eq!(
+            result,
+            "left press 4,2|wheel_down scroll|left drag true|row1|nil"
+        );
+    }
+
     #[test]
     fn test_user_config_path() {
         let path = user_screen_script_path();
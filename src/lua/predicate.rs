@@ -0,0 +1,108 @@
+//! Exit predicates
+//!
+//! A predicate thing's code returns a function that gets the move being
+//! attempted and answers `true` to let it through, or `false`/`nil` or a
+//! string saying why not:
+//!
+//! ```lua
+//! return function(move)
+//!     if move.user == "guest" then return "guests stay in the lobby" end
+//!     return true
+//! end
+//! ```
+//!
+//! Each check runs in its own sandboxed Luau state with no `tools`, a small
+//! memory limit and a time limit, so a predicate can't change the world or
+//! stall a move.
+
+use super::tools::json_to_lua;
+use anyhow::{anyhow, Result};
+use mlua::{Lua, Value, VmState};
+use std::time::{Duration, Instant};
+
+const PREDICATE_MEMORY_LIMIT: usize = 8 * 1024 * 1024;
+const PREDICATE_TIME_LIMIT: Duration = Duration::from_millis(250);
+
+/// What a predicate decided
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    /// Denied, with the predicate's reason if it gave one
+    Deny(Option<String>),
+}
+
+/// Run predicate code against a move
+///
+/// `args` is passed to the predicate as a table. Errors cover code that
+/// fails to load, doesn't return a function, raises, or runs too long.
+pub fn check(code: &str, name: &str, args: &serde_json::Value) -> Result<Verdict> {
+    let lua = Lua::new();
+    lua.set_memory_limit(PREDICATE_MEMORY_LIMIT)
+        .map_err(|e| anyhow!("failed to limit predicate memory: {}", e))?;
+    lua.sandbox(true)
+        .map_err(|e| anyhow!("failed to sandbox predicate: {}", e))?;
+
+    let deadline = Instant::now() + PREDICATE_TIME_LIMIT;
+    lua.set_interrupt(move |_| {
+        if Instant::now() > deadline {
+            Err(mlua::Error::runtime("predicate took too long"))
+        } else {
+            Ok(VmState::Continue)
+        }
+    });
+
+    let func: mlua::Function = lua
+        .load(code)
+        .set_name(format!("predicate:{}", name))
+        .eval()
+        .map_err(|e| anyhow!("failed to load predicate: {}", e))?;
+    let args = json_to_lua(&lua, args).map_err(|e| anyhow!("failed to convert args: {}", e))?;
+
+    match func
+        .call::<Value>(args)
+        .map_err(|e| anyhow!("predicate failed: {}", e))?
+    {
+        Value::Boolean(true) => Ok(Verdict::Allow),
+        Value::String(reason) => Ok(Verdict::Deny(Some(reason.to_str()?.to_string()))),
+        _ => Ok(Verdict::Deny(None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_predicate_verdicts() -> Result<()> {
+        let code = r#"
+            return function(move)
+                if move.user == "guest" then return "guests stay in the lobby" end
+                if move.direction == "down" then return false end
+                return true
+            end
+        "#;
+        let args = |user: &str, direction: &str| json!({"user": user, "direction": direction});
+
+        assert_eq!(check(code, "t", &args("alice", "north"))?, Verdict::Allow);
+        assert_eq!(
+            check(code, "t", &args("guest", "north"))?,
+            Verdict::Deny(Some("guests stay in the lobby".to_string()))
+        );
+        assert_eq!(
+            check(code, "t", &args("alice", "down"))?,
+            Verdict::Deny(None)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_predicate_failures() {
+        let args = json!({});
+        assert!(check("return 42", "t", &args).is_err());
+        assert!(check("return function() error('nope') end", "t", &args).is_err());
+        assert!(check("return function() while true do end end", "t", &args).is_err());
+        assert!(check("return function() return tools end", "t", &args)
+            .is_ok_and(|v| v == Verdict::Deny(None)));
+    }
+}
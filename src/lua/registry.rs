@@ -17,13 +17,12 @@ use crate::mcp::McpManager;
 use crate::state::SharedState;
 use crate::status::StatusTracker;
 use crate::world::World;
-use tokio::sync::RwLock;
 
 /// Context passed to tool handlers
 pub struct ToolContext {
     pub db: Arc<Database>,
     pub mcp: Arc<McpManager>,
-    pub world: Arc<RwLock<World>>,
+    pub world: Arc<World>,
     pub status_tracker: Arc<StatusTracker>,
     pub username: Option<String>,
     pub room: Option<String>,
@@ -110,7 +109,12 @@ impl ToolRegistry {
 
             // Query room info from DB
             if let Some(ref room_name) = ctx.room {
-                let vibe = ctx.db.get_vibe(room_name).ok().flatten();
+                let vibe = ctx
+                    .world
+                    .get_room(room_name)
+                    .ok()
+                    .flatten()
+                    .and_then(|r| r.vibe);
                 let exits = ctx.db.get_exits(room_name).unwrap_or_default();
 
                 result["room"] = serde_json::json!({
@@ -120,36 +124,34 @@ impl ToolRegistry {
 
                 result["exits"] = serde_json::json!(exits);
 
-                // Try to get participants from world (non-blocking)
-                if let Ok(world) = ctx.world.try_read() {
-                    if let Some(room) = world.get_room(room_name) {
-                        let status_snapshot = ctx.status_tracker.snapshot();
-
-                        let participants: Vec<_> = room
-                            .users
-                            .iter()
-                            .map(|name| {
-                                let status = status_snapshot.get(name);
-                                serde_json::json!({
-                                    "name": name,
-                                    "kind": "user",
-                                    "status": status.map(|s| s.text()).unwrap_or_default(),
-                                    "active": status.map(|s| s.is_active()).unwrap_or(false),
-                                })
+                // Participants from world presence
+                if let Ok(Some(room)) = ctx.world.get_room(room_name) {
+                    let status_snapshot = ctx.status_tracker.snapshot();
+
+                    let participants: Vec<_> = room
+                        .users
+                        .iter()
+                        .map(|name| {
+                            let status = status_snapshot.get(name);
+                            serde_json::json!({
+                                "name": name,
+                                "kind": "user",
+                                "status": status.map(|s| s.text()).unwrap_or_default(),
+                                "active": status.map(|s| s.is_active()).unwrap_or(false),
                             })
-                            .chain(room.models.iter().map(|model| {
-                                let status = status_snapshot.get(&model.short_name);
-                                serde_json::json!({
-                                    "name": model.short_name,
-                                    "kind": "model",
-                                    "status": status.map(|s| s.text()).unwrap_or_default(),
-                                    "active": status.map(|s| s.is_active()).unwrap_or(false),
-                                })
-                            }))
-                            .collect();
+                        })
+                        .chain(room.models.iter().map(|model| {
+                            let status = status_snapshot.get(model);
+                            serde_json::json!({
+                                "name": model,
+                                "kind": "model",
+                                "status": status.map(|s| s.text()).unwrap_or_default(),
+                                "active": status.map(|s| s.is_active()).unwrap_or(false),
+                            })
+                        }))
+                        .collect();
 
-                        result["participants"] = serde_json::json!(participants);
-                    }
+                    result["participants"] = serde_json::json!(participants);
                 }
             }
 
@@ -170,17 +172,17 @@ impl ToolRegistry {
         // Room tool - get current room info
         self.register("room", |ctx, _args| {
             if let Some(ref room_name) = ctx.room {
-                let vibe = ctx.db.get_vibe(room_name).ok().flatten();
+                let vibe = ctx
+                    .world
+                    .get_room(room_name)
+                    .ok()
+                    .flatten()
+                    .and_then(|r| r.vibe);
                 let exits = ctx.db.get_exits(room_name).unwrap_or_default();
 
-                let (user_count, model_count) = if let Ok(world) = ctx.world.try_read() {
-                    if let Some(room) = world.get_room(room_name) {
-                        (room.users.len(), room.models.len())
-                    } else {
-                        (0, 0)
-                    }
-                } else {
-                    (0, 0)
+                let (user_count, model_count) = match ctx.world.get_room(room_name) {
+                    Ok(Some(room)) => (room.users.len(), room.models.len()),
+                    _ => (0, 0),
                 };
 
                 Ok(serde_json::json!({
@@ -288,7 +290,7 @@ mod tests {
         let registry = ToolRegistry::new();
         let db = Arc::new(Database::in_memory().unwrap());
         let mcp = Arc::new(McpManager::new());
-        let world = Arc::new(RwLock::new(World::new()));
+        let world = Arc::new(World::new(db.clone()));
         let status_tracker = Arc::new(StatusTracker::new());
 
         let ctx = ToolContext {
@@ -3,6 +3,7 @@
 //! Provides Lua functions that bridge to Rust state and MCP tools.
 //! All functions are registered in a `tools` global table.
 
+use crate::db::merge::MergeMode;
 use crate::lua::cache::ToolCache;
 use crate::lua::context::{build_notifications_table, NotificationLevel, PendingNotification};
 use crate::lua::dirty::DirtyState;
@@ -11,7 +12,9 @@ use crate::lua::tool_middleware::ToolMiddleware;
 use crate::model::ModelHandle;
 use crate::state::SharedState;
 use crate::status::{Status, StatusTracker};
-use crate::ui::{LuaDrawContext, RenderBuffer};
+use crate::ui::{
+    ColorDepth, ConnectionCaps, Glyphs, LuaDrawContext, Recorder, RenderBuffer, TermCaps,
+};
 use mlua::{Lua, Result as LuaResult, Table, Value};
 use std::sync::Arc;
 // unicode-display-width handles PUA and grapheme clusters correctly
@@ -69,6 +72,11 @@ pub struct LuaToolState {
     /// Tag-based dirty tracking for partial screen updates
     /// Lua defines regions; Rust provides primitives
     dirty: Arc<DirtyState>,
+    /// What the terminal of the connection Lua is running for can display
+    /// (detected or set by /term)
+    term_caps: Arc<std::sync::RwLock<ConnectionCaps>>,
+    /// Asciicast recording of what the session's screen shows (/record)
+    recorder: Arc<Recorder>,
 }
 
 impl LuaToolState {
@@ -83,20 +91,23 @@ impl LuaToolState {
             middleware: ToolMiddleware::new(),
             input_state: Arc::new(std::sync::RwLock::new(InputState::default())),
             dirty: Arc::new(DirtyState::new()),
+            term_caps: Arc::new(std::sync::RwLock::new(ConnectionCaps::default())),
+            recorder: Arc::new(Recorder::new()),
         }
     }
 
     /// Get the dirty state for screen refresh task
     ///
-    /// Screen loop waits on `dirty.notified()` and calls `dirty.take()` to get dirty tags.
+    /// Each screen loop takes a `dirty.subscribe()`, waits on its `notified()`
+    /// and calls `take()` to get dirty tags.
     pub fn dirty(&self) -> &Arc<DirtyState> {
         &self.dirty
     }
 
     /// Mark a tag dirty for partial screen updates
     ///
-    /// Conventional tags: "status", "chat", "input"
-    /// Lua can define its own tag names for custom layouts.
+    /// Conventional tags: "status", "chat", "input", plus "resize" when the
+    /// terminal changes size. Lua can define its own tag names for custom layouts.
     pub fn mark_dirty(&self, tag: &str) {
         self.dirty.mark(tag);
     }
@@ -119,6 +130,48 @@ impl LuaToolState {
             .unwrap_or_default()
     }
 
+    /// Run Lua for a connection: term_caps() and set_term_caps() use its
+    /// capabilities until another connection takes over
+    ///
+    /// Call with the runtime locked, before the connection's Lua runs.
+    pub fn use_term_caps(&self, caps: &ConnectionCaps) {
+        if let Ok(mut guard) = self.term_caps.write() {
+            if !Arc::ptr_eq(&guard, caps) {
+                *guard = caps.clone();
+            }
+        }
+    }
+
+    /// The current connection's capabilities
+    fn connection_caps(&self) -> ConnectionCaps {
+        self.term_caps
+            .read()
+            .map(|guard| guard.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the current connection's terminal capabilities
+    pub fn term_caps(&self) -> TermCaps {
+        self.connection_caps()
+            .read()
+            .map(|guard| guard.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the current connection's terminal capabilities and redraw
+    /// everything with them
+    pub fn set_term_caps(&self, caps: TermCaps) {
+        if let Ok(mut guard) = self.connection_caps().write() {
+            *guard = caps;
+        }
+        self.dirty.mark_many(["status", "chat", "input"]);
+    }
+
+    /// Get the session's recorder; screens feed it what they send
+    pub fn recorder(&self) -> &Arc<Recorder> {
+        &self.recorder
+    }
+
     /// Set the hardware cursor position (called by Lua after rendering input)
     ///
     /// Row and column are 1-indexed ANSI positions.
@@ -373,38 +426,23 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
             let room_name = room_name.unwrap();
 
             // Get room from world
-            {
-                let world = tokio::task::block_in_place(|| shared.world.blocking_read());
-                if let Some(room) = world.get_room(&room_name) {
-                    // Description
-                    if let Some(ref desc) = room.description {
-                        result.set("description", desc.clone())?;
-                    } else {
-                        result.set("description", Value::Nil)?;
-                    }
+            let room = shared.world.get_room(&room_name).ok().flatten();
+            let exits_table = lua.create_table()?;
+            if let Some(room) = room {
+                result.set("description", room.description)?;
+                result.set("vibe", room.vibe)?;
+                result.set("zone", room.zone)?;
 
-                    // Users array
-                    let users_table = lua.create_table()?;
-                    for (i, user) in room.users.iter().enumerate() {
-                        users_table.set(i + 1, user.clone())?;
-                    }
-                    result.set("users", users_table)?;
+                // Users array
+                let users_table = lua.create_table()?;
+                for (i, user) in room.users.into_iter().enumerate() {
+                    users_table.set(i + 1, user)?;
                 }
-            }
-
-            // Get vibe from DB
-            let vibe = shared.db.get_vibe(&room_name).ok().flatten();
-            if let Some(v) = vibe {
-                result.set("vibe", v)?;
-            } else {
-                result.set("vibe", Value::Nil)?;
-            }
+                result.set("users", users_table)?;
 
-            // Get exits from DB
-            let exits = shared.db.get_exits(&room_name).unwrap_or_default();
-            let exits_table = lua.create_table()?;
-            for (dir, dest) in exits {
-                exits_table.set(dir, dest)?;
+                for (dir, dest) in room.exits {
+                    exits_table.set(dir, dest)?;
+                }
             }
             result.set("exits", exits_table)?;
 
@@ -433,8 +471,7 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
             if let Some(shared) = state.shared_state() {
                 // Get users from room
                 if let Some(ref room) = room_name {
-                    let world = tokio::task::block_in_place(|| shared.world.blocking_read());
-                    if let Some(room_data) = world.get_room(room) {
+                    if let Ok(Some(room_data)) = shared.world.get_room(room) {
                         for user in &room_data.users {
                             let entry = lua.create_table()?;
                             entry.set("name", user.clone())?;
@@ -487,7 +524,124 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("exits", exits_fn)?;
 
-    // tools.vibe() -> string or nil
+    // tools.exit_locks(room?) -> {direction = {target, rule, hint, open}} | nil, error
+    // open says whether the current user gets through
+    let exit_locks_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, room: Option<String>| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let Some(room) = room.or_else(|| state.current_room_name()) else {
+                return Ok((None, Some("not in a room".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::locked_exits(
+                    &shared,
+                    &agent_name,
+                    &room,
+                ))
+            }) {
+                Ok(locked) => {
+                    let map = lua.create_table()?;
+                    for (direction, exit) in locked {
+                        let entry = lua.create_table()?;
+                        entry.set("target", exit.target)?;
+                        entry.set("rule", exit.rule)?;
+                        entry.set("hint", exit.hint)?;
+                        entry.set("open", exit.open)?;
+                        map.set(direction, entry)?;
+                    }
+                    Ok((Some(map), None))
+                }
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("exit_locks", exit_locks_fn)?;
+
+    // tools.lock_exit(direction, rule?, room?) -> true | nil, error
+    // rule is closed, members, capability:<name> or check:<thing>; nil unlocks
+    let lock_exit_fn = {
+        let state = state.clone();
+        lua.create_function(
+            move |_lua, (direction, rule, room): (String, Option<String>, Option<String>)| {
+                let Some(shared) = state.shared_state() else {
+                    return Ok((None, Some("no shared state".to_string())));
+                };
+                let Some(room) = room.or_else(|| state.current_room_name()) else {
+                    return Ok((None, Some("not in a room".to_string())));
+                };
+                let lock = match rule.as_deref().map(crate::db::locks::ExitLock::parse) {
+                    Some(Ok(lock)) => Some(lock),
+                    Some(Err(e)) => return Ok((None, Some(format!("{:#}", e)))),
+                    None => None,
+                };
+                let agent_name = state
+                    .current_agent_name()
+                    .unwrap_or_else(|| "anonymous".to_string());
+                match tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(crate::ops::lock_exit(
+                        &shared,
+                        &agent_name,
+                        &room,
+                        &direction,
+                        lock.as_ref(),
+                    ))
+                }) {
+                    Ok(()) => Ok((Some(true), None)),
+                    Err(e) => Ok((None, Some(format!("{:#}", e)))),
+                }
+            },
+        )?
+    };
+    tools.set("lock_exit", lock_exit_fn)?;
+
+    // tools.room_member(room, name, add) -> members | nil, error
+    let room_member_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (room, member, add): (String, String, bool)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::set_member(
+                    &shared,
+                    &agent_name,
+                    &room,
+                    &member,
+                    add,
+                ))
+            }) {
+                Ok(members) => Ok((Some(members), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("room_member", room_member_fn)?;
+
+    // tools.room_members(room) -> members | nil, error
+    let room_members_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, room: String| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            match shared.db.get_room_members(&room) {
+                Ok(members) => Ok((Some(members), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("room_members", room_members_fn)?;
+
+    // tools.vibe() -> string or nil (the zone's vibe if the room has none)
     let vibe_fn = {
         let state = state.clone();
         lua.create_function(move |_lua, ()| {
@@ -495,7 +649,12 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
             let room_name = state.current_room_name();
 
             if let (Some(shared), Some(room)) = (state.shared_state(), room_name) {
-                Ok(shared.db.get_vibe(&room).ok().flatten())
+                Ok(shared
+                    .world
+                    .get_room(&room)
+                    .ok()
+                    .flatten()
+                    .and_then(|r| r.vibe))
             } else {
                 Ok(None)
             }
@@ -597,6 +756,114 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("set_cursor_pos", set_cursor_pos_fn)?;
 
+    // tools.input_history(limit?) -> [line, ...] oldest first
+    // Persisted input lines for the current agent (default limit: 500)
+    let input_history_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, limit: Option<usize>| {
+            let list = lua.create_table()?;
+            let (Some(shared), Some(ctx)) = (state.shared_state(), state.session_context()) else {
+                return Ok(list);
+            };
+            let limit = limit.unwrap_or(crate::db::input_history::INPUT_HISTORY_LIMIT);
+            let lines = shared
+                .db
+                .list_input_history(&ctx.agent_id, limit)
+                .unwrap_or_default();
+            for (i, line) in lines.into_iter().enumerate() {
+                list.set(i + 1, line)?;
+            }
+            Ok(list)
+        })?
+    };
+    tools.set("input_history", input_history_fn)?;
+
+    // tools.input_history_add(line) -> bool
+    // Persist a submitted line for the current agent
+    let input_history_add_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, line: String| {
+            let (Some(shared), Some(ctx)) = (state.shared_state(), state.session_context()) else {
+                return Ok(false);
+            };
+            match shared.db.record_input(&ctx.agent_id, &line) {
+                Ok(()) => Ok(true),
+                Err(e) => {
+                    tracing::warn!("failed to record input history: {}", e);
+                    Ok(false)
+                }
+            }
+        })?
+    };
+    tools.set("input_history_add", input_history_add_fn)?;
+
+    // tools.input_history_clear() -> number of lines removed
+    let input_history_clear_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, ()| {
+            let (Some(shared), Some(ctx)) = (state.shared_state(), state.session_context()) else {
+                return Ok(0);
+            };
+            Ok(shared
+                .db
+                .clear_input_history(&ctx.agent_id)
+                .unwrap_or_default())
+        })?
+    };
+    tools.set("input_history_clear", input_history_clear_fn)?;
+
+    // tools.row_pin(row_id, pinned?) -> new pinned state | nil, error
+    // Pin or unpin a row of the current room; toggles when pinned is omitted
+    let row_pin_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (row_id, pinned): (String, Option<bool>)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let Some(room) = state.current_room_name() else {
+                return Ok((None, Some("not in a room".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::pin_row(
+                    &shared,
+                    &agent_name,
+                    &room,
+                    &row_id,
+                    pinned,
+                ))
+            }) {
+                Ok(pinned) => Ok((Some(pinned), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("row_pin", row_pin_fn)?;
+
+    // tools.row_react(row_id, reaction) -> true (added), false (removed) or nil
+    // Toggle the current agent's reaction on a row
+    let row_react_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (row_id, reaction): (String, String)| {
+            let (Some(shared), Some(ctx)) = (state.shared_state(), state.session_context()) else {
+                return Ok(None);
+            };
+            match shared
+                .db
+                .toggle_row_reaction(&row_id, &ctx.agent_id, &reaction)
+            {
+                Ok(added) => Ok(Some(added)),
+                Err(e) => {
+                    tracing::warn!("failed to react to row: {}", e);
+                    Ok(None)
+                }
+            }
+        })?
+    };
+    tools.set("row_react", row_react_fn)?;
+
     // tools.mark_dirty(tag) -> nil
     // Mark a region tag dirty for partial screen updates
     // Conventional tags: "status", "chat", "input" (Lua can define others)
@@ -609,6 +876,118 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("mark_dirty", mark_dirty_fn)?;
 
+    // tools.term_caps() -> {term, colorterm, colors, glyphs}
+    // Same table draw contexts expose as ctx.caps
+    let term_caps_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, ()| {
+            let caps = state.term_caps();
+            let table = lua.create_table()?;
+            table.set("term", caps.term)?;
+            table.set("colorterm", caps.colorterm)?;
+            table.set("colors", caps.color.name())?;
+            table.set("glyphs", caps.glyphs.name())?;
+            Ok(table)
+        })?
+    };
+    tools.set("term_caps", term_caps_fn)?;
+
+    // tools.set_term_caps({colors?, glyphs?, detect?}) -> caps table | nil, error
+    // Override detected capabilities; detect = true re-runs detection
+    let set_term_caps_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, opts: Table| {
+            let mut caps = state.term_caps();
+            if opts.get::<Option<bool>>("detect")?.unwrap_or(false) {
+                caps = TermCaps::detect(&caps.term, caps.colorterm.as_deref());
+            }
+            if let Some(colors) = opts.get::<Option<String>>("colors")? {
+                match ColorDepth::parse(&colors) {
+                    Some(color) => caps.color = color,
+                    None => return Ok((None, Some(format!("unknown color depth: {}", colors)))),
+                }
+            }
+            if let Some(glyphs) = opts.get::<Option<String>>("glyphs")? {
+                match Glyphs::parse(&glyphs) {
+                    Some(g) => caps.glyphs = g,
+                    None => return Ok((None, Some(format!("unknown glyph set: {}", glyphs)))),
+                }
+            }
+
+            let table = lua.create_table()?;
+            table.set("colors", caps.color.name())?;
+            table.set("glyphs", caps.glyphs.name())?;
+            state.set_term_caps(caps);
+            Ok((Some(table), None))
+        })?
+    };
+    tools.set("set_term_caps", set_term_caps_fn)?;
+
+    // tools.record_start() -> {path} | nil, error
+    // Start an asciicast recording of what this session's screen shows
+    let record_start_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, ()| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            let path = std::path::PathBuf::from(&shared.config.recordings_dir)
+                .join(crate::ui::cast::cast_file_name(&agent_name));
+            let title = format!("sshwarma: {}", agent_name);
+            if let Err(e) = state
+                .recorder()
+                .start(path.clone(), &title, &state.term_caps().term)
+            {
+                return Ok((None, Some(format!("{:#}", e))));
+            }
+            tracing::info!(agent = %agent_name, path = %path.display(), "recording started");
+
+            // Render right away so a screen claims the recording
+            state.dirty().mark_many(["status", "chat", "input"]);
+
+            let table = lua.create_table()?;
+            table.set("path", path.to_string_lossy().into_owned())?;
+            Ok((Some(table), None))
+        })?
+    };
+    tools.set("record_start", record_start_fn)?;
+
+    // tools.record_stop() -> {path, seconds, events, truncated} | nil, error
+    let record_stop_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, ()| {
+            let summary = match state.recorder().stop() {
+                Ok(Some(summary)) => summary,
+                Ok(None) => return Ok((None, Some("not recording".to_string()))),
+                Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+            };
+            tracing::info!(path = %summary.path.display(), events = summary.events, "recording stopped");
+
+            let table = lua.create_table()?;
+            table.set("path", summary.path.to_string_lossy().into_owned())?;
+            table.set("seconds", summary.duration.as_secs_f64())?;
+            table.set("events", summary.events)?;
+            table.set("truncated", summary.truncated)?;
+            Ok((Some(table), None))
+        })?
+    };
+    tools.set("record_stop", record_stop_fn)?;
+
+    // tools.recording() -> path | nil
+    let recording_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, ()| {
+            Ok(state
+                .recorder()
+                .path()
+                .map(|p| p.to_string_lossy().into_owned()))
+        })?
+    };
+    tools.set("recording", recording_fn)?;
+
     // tools.mark_all_dirty(tags) -> nil
     // Mark multiple region tags dirty at once
     let mark_all_dirty_fn = {
@@ -664,6 +1043,25 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                         limit
                     };
                     if let Ok(rows) = shared.db.list_recent_buffer_rows(&buffer.id, fetch_limit) {
+                        // Reactions as "👍 2 🎉 1", and who each reply answers
+                        let row_ids: Vec<String> = rows.iter().map(|r| r.id.clone()).collect();
+                        let mut reactions: std::collections::HashMap<String, Vec<String>> =
+                            std::collections::HashMap::new();
+                        for (row_id, reaction, count) in
+                            shared.db.list_reaction_counts(&row_ids).unwrap_or_default()
+                        {
+                            reactions
+                                .entry(row_id)
+                                .or_default()
+                                .push(format!("{} {}", reaction, count));
+                        }
+                        let replies: std::collections::HashMap<String, String> = shared
+                            .db
+                            .list_replies_from(&row_ids)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .collect();
+
                         let mut idx = 1;
                         let mut count = 0;
                         for db_row in rows.iter().filter(|r| !r.ephemeral) {
@@ -671,8 +1069,9 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                                 break;
                             }
 
-                            // Include messages and tool rows
-                            let is_message = db_row.content_method.starts_with("message.");
+                            // Include messages, merged fork blocks and tool rows
+                            let is_message = db_row.content_method.starts_with("message.")
+                                || db_row.content_method == "meta.merge";
                             let is_tool_call = db_row.content_method == "tool.call";
                             let is_tool_result = db_row.content_method == "tool.result";
 
@@ -714,6 +1113,13 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                             row.set("row_id", db_row.id.clone())?;
                             row.set("parent_row_id", db_row.parent_row_id.clone())?;
                             row.set("collapsed", db_row.collapsed)?;
+                            row.set("pinned", db_row.pinned)?;
+                            if let Some(list) = reactions.get(&db_row.id) {
+                                row.set("reactions", list.join(" "))?;
+                            }
+                            if let Some(target) = replies.get(&db_row.id) {
+                                row.set("reply_to", target.clone())?;
+                            }
                             row.set("is_model", db_row.content_method == "message.model")?;
                             row.set("is_thinking", false)?;
                             row.set(
@@ -906,25 +1312,23 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("history_stats", history_stats_fn)?;
 
-    // tools.rooms() -> [{name, user_count, model_count, description}]
+    // tools.rooms() -> [{name, user_count, model_count, description, archived}]
     let rooms_fn = {
         let state = state.clone();
         lua.create_function(move |lua, ()| {
             let list = lua.create_table()?;
 
             if let Some(shared) = state.shared_state() {
-                let world = tokio::task::block_in_place(|| shared.world.blocking_read());
-                let mut idx = 1;
-                for (name, room) in &world.rooms {
+                let rooms = shared.world.list_rooms().unwrap_or_default();
+                for (i, room) in rooms.into_iter().enumerate() {
                     let row = lua.create_table()?;
-                    row.set("name", name.clone())?;
+                    row.set("name", room.name)?;
+                    row.set("archived", room.archived)?;
+                    row.set("zone", room.zone)?;
                     row.set("user_count", room.users.len())?;
                     row.set("model_count", room.models.len())?;
-                    if let Some(ref desc) = room.description {
-                        row.set("description", desc.clone())?;
-                    }
-                    list.set(idx, row)?;
-                    idx += 1;
+                    row.set("description", room.description)?;
+                    list.set(i + 1, row)?;
                 }
             }
 
@@ -1557,7 +1961,8 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("thing_create", thing_create_fn)?;
 
-    // thing_delete(qualified_name) -> {success, error?}
+    // thing_delete(qualified_name_or_id) -> {success, error?}
+    // Moves the thing to the trash (see tools.trash, tools.restore)
     let thing_delete_fn = {
         let state = state.clone();
         lua.create_function(move |lua, qualified_name: String| -> mlua::Result<Value> {
@@ -1572,7 +1977,14 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
             };
 
             // Find the thing first
-            let thing = match shared.db.get_thing_by_qualified_name(&qualified_name) {
+            let found = match shared.db.get_thing_by_qualified_name(&qualified_name) {
+                Ok(None) => shared
+                    .db
+                    .get_thing(&qualified_name)
+                    .map(|t| t.filter(|t| !t.is_deleted())),
+                other => other,
+            };
+            let thing = match found {
                 Ok(Some(t)) => t,
                 Ok(None) => {
                     let result = lua.create_table()?;
@@ -1588,8 +2000,11 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                 }
             };
 
-            // Soft delete
-            match shared.db.soft_delete_thing(&thing.id) {
+            // Soft delete, remembering who did it
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match shared.db.trash_thing(&thing.id, &agent_name) {
                 Ok(()) => {
                     let result = lua.create_table()?;
                     result.set("success", true)?;
@@ -1606,55 +2021,260 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("thing_delete", thing_delete_fn)?;
 
-    // things_children(parent_id) -> array of thing tables
-    let things_children_fn = {
+    // tools.trash(target?) -> [{id, name, qualified_name, kind, deleted_by, deleted_at}] | nil, error
+    // target is "me" (default) or "room"; most recently deleted first
+    let trash_fn = {
         let state = state.clone();
-        lua.create_function(move |lua, parent_id: String| {
-            let shared = match state.shared_state() {
-                Some(s) => s,
-                None => return lua.create_table(),
+        lua.create_function(move |lua, target: Option<String>| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
             };
-
-            let children = shared.db.get_thing_children(&parent_id).unwrap_or_default();
-            let result = lua.create_table()?;
-            for (i, thing) in children.iter().enumerate() {
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            let room = state.current_room_name();
+            let target = target.unwrap_or_else(|| "me".to_string());
+            let entries = match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::trash(
+                    &shared,
+                    &agent_name,
+                    &target,
+                    room.as_deref(),
+                ))
+            }) {
+                Ok(entries) => entries,
+                Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+            };
+            let list = lua.create_table()?;
+            for (i, entry) in entries.into_iter().enumerate() {
                 let t = lua.create_table()?;
-                t.set("id", thing.id.clone())?;
-                t.set("kind", thing.kind.as_str())?;
-                t.set("name", thing.name.clone())?;
-                t.set("qualified_name", thing.qualified_name.clone())?;
-                t.set("available", thing.available)?;
-                result.set(i + 1, t)?;
+                t.set("id", entry.id)?;
+                t.set("name", entry.name)?;
+                t.set("qualified_name", entry.qualified_name)?;
+                t.set("kind", entry.kind)?;
+                t.set("deleted_by", entry.deleted_by)?;
+                t.set("deleted_at", entry.deleted_at)?;
+                list.set(i + 1, t)?;
             }
-            Ok(result)
+            Ok((Some(list), None))
         })?
     };
-    tools.set("things_children", things_children_fn)?;
+    tools.set("trash", trash_fn)?;
 
-    // things_find(pattern) -> array of thing tables (glob search on qualified_name)
-    let things_find_fn = {
+    // tools.restore(qualified_name_or_id, rename?) -> {id, name, qualified_name, parent_id} | nil, error
+    // rename gives it a new qualified name when its old one has been taken
+    let restore_fn = {
         let state = state.clone();
-        lua.create_function(move |lua, pattern: String| {
-            let shared = match state.shared_state() {
-                Some(s) => s,
-                None => return lua.create_table(),
+        lua.create_function(move |lua, (name, rename): (String, Option<String>)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
             };
-
-            let things = shared
-                .db
-                .find_things_by_qualified_name(&pattern)
-                .unwrap_or_default();
-            let result = lua.create_table()?;
-            for (i, thing) in things.iter().enumerate() {
-                let t = lua.create_table()?;
-                t.set("id", thing.id.clone())?;
-                t.set("kind", thing.kind.as_str())?;
-                t.set("name", thing.name.clone())?;
-                t.set("qualified_name", thing.qualified_name.clone())?;
-                t.set("available", thing.available)?;
-                result.set(i + 1, t)?;
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::restore(
+                    &shared,
+                    &agent_name,
+                    &name,
+                    rename.as_deref(),
+                ))
+            }) {
+                Ok(thing) => {
+                    let t = lua.create_table()?;
+                    t.set("id", thing.id)?;
+                    t.set("name", thing.name)?;
+                    t.set("qualified_name", thing.qualified_name)?;
+                    t.set("parent_id", thing.parent_id)?;
+                    Ok((Some(t), None))
+                }
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
             }
-            Ok(result)
+        })?
+    };
+    tools.set("restore", restore_fn)?;
+
+    // tools.bundle_export(target, opts?) -> {text, things, equipment, path?} | nil, error
+    // target: "me", "room", or a thing's qualified name or id
+    // opts = { format?: "toml"|"json", equipment?: bool, file?: string, force?: bool }
+    let bundle_export_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, (target, opts): (String, Option<Table>)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            let room = state.current_room_name();
+
+            let format = opts
+                .as_ref()
+                .and_then(|t| t.get::<Option<String>>("format").ok().flatten());
+            let format = match format.as_deref() {
+                None => crate::db::bundles::BundleFormat::Toml,
+                Some(f) => match crate::db::bundles::BundleFormat::parse(f) {
+                    Some(format) => format,
+                    None => return Ok((None, Some(format!("unknown bundle format: {}", f)))),
+                },
+            };
+            let with_equipment = opts
+                .as_ref()
+                .and_then(|t| t.get::<Option<bool>>("equipment").ok().flatten())
+                .unwrap_or(false);
+            let file = opts
+                .as_ref()
+                .and_then(|t| t.get::<Option<String>>("file").ok().flatten());
+            let force = opts
+                .as_ref()
+                .and_then(|t| t.get::<Option<bool>>("force").ok().flatten())
+                .unwrap_or(false);
+
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::export_bundle(
+                    &shared,
+                    &agent_name,
+                    &target,
+                    room.as_deref(),
+                    format,
+                    with_equipment,
+                    file.as_deref(),
+                    force,
+                ))
+            }) {
+                Ok(export) => {
+                    let t = lua.create_table()?;
+                    t.set("text", export.text)?;
+                    t.set("things", export.things)?;
+                    t.set("equipment", export.equipment)?;
+                    t.set("path", export.path)?;
+                    Ok((Some(t), None))
+                }
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("bundle_export", bundle_export_fn)?;
+
+    // tools.bundle_import(opts) -> {things, roots, renamed, replaced, equipped, skipped} | nil, error
+    // opts = { text?: string, file?: string, equip?: bool, replace?: bool }
+    // Imports into the caller's inventory; equip applies the bundle's recipes
+    let bundle_import_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, opts: Table| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            let room = state.current_room_name();
+            let text: Option<String> = opts.get("text")?;
+            let file: Option<String> = opts.get("file")?;
+            let equip = opts.get::<Option<bool>>("equip")?.unwrap_or(false);
+            let replace = opts.get::<Option<bool>>("replace")?.unwrap_or(false);
+
+            let result = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    let text = match (text, file) {
+                        (Some(text), _) => text,
+                        (None, Some(file)) => crate::ops::read_bundle_file(&shared, &file).await?,
+                        (None, None) => anyhow::bail!("give a bundle's text or file"),
+                    };
+                    crate::ops::import_bundle(
+                        &shared,
+                        &agent_name,
+                        &text,
+                        replace,
+                        equip,
+                        room.as_deref(),
+                    )
+                    .await
+                })
+            });
+            match result {
+                Ok(report) => {
+                    let t = lua.create_table()?;
+                    t.set("things", report.things)?;
+                    t.set("roots", report.roots)?;
+                    t.set("renamed", report.renamed)?;
+                    t.set("replaced", report.replaced)?;
+                    t.set("equipped", report.equipped)?;
+                    t.set("skipped", report.skipped)?;
+                    Ok((Some(t), None))
+                }
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("bundle_import", bundle_import_fn)?;
+
+    // tools.bundles() -> array of bundle file names | nil, error
+    let bundles_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, ()| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::bundles(&shared))
+            }) {
+                Ok(files) => Ok((Some(files), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("bundles", bundles_fn)?;
+
+    // things_children(parent_id) -> array of thing tables
+    let things_children_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, parent_id: String| {
+            let shared = match state.shared_state() {
+                Some(s) => s,
+                None => return lua.create_table(),
+            };
+
+            let children = shared.db.get_thing_children(&parent_id).unwrap_or_default();
+            let result = lua.create_table()?;
+            for (i, thing) in children.iter().enumerate() {
+                let t = lua.create_table()?;
+                t.set("id", thing.id.clone())?;
+                t.set("kind", thing.kind.as_str())?;
+                t.set("name", thing.name.clone())?;
+                t.set("qualified_name", thing.qualified_name.clone())?;
+                t.set("available", thing.available)?;
+                result.set(i + 1, t)?;
+            }
+            Ok(result)
+        })?
+    };
+    tools.set("things_children", things_children_fn)?;
+
+    // things_find(pattern) -> array of thing tables (glob search on qualified_name)
+    let things_find_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, pattern: String| {
+            let shared = match state.shared_state() {
+                Some(s) => s,
+                None => return lua.create_table(),
+            };
+
+            let things = shared
+                .db
+                .find_things_by_qualified_name(&pattern)
+                .unwrap_or_default();
+            let result = lua.create_table()?;
+            for (i, thing) in things.iter().enumerate() {
+                let t = lua.create_table()?;
+                t.set("id", thing.id.clone())?;
+                t.set("kind", thing.kind.as_str())?;
+                t.set("name", thing.name.clone())?;
+                t.set("qualified_name", thing.qualified_name.clone())?;
+                t.set("available", thing.available)?;
+                result.set(i + 1, t)?;
+            }
+            Ok(result)
         })?
     };
     tools.set("things_find", things_find_fn)?;
@@ -1856,6 +2476,10 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     Some(s) => s,
                     None => return Ok(false),
                 };
+                shared
+                    .db
+                    .ensure_room_id_writable(&room_id)
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
 
                 shared
                     .db
@@ -1883,6 +2507,10 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     Some(s) => s,
                     None => return Ok(false),
                 };
+                shared
+                    .db
+                    .ensure_room_id_writable(&room_id)
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
 
                 shared
                     .db
@@ -1981,7 +2609,7 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     tools.set("agent_unequip", agent_unequip_fn)?;
 
     // get_room_equipment(room_id, slot_filter) -> array of equipped things
-    // slot_filter: nil=all, ""=NULL slot only, "hook:wrap"=specific slot
+    // slot_filter: nil=all, ""=NULL slot only, "hook:wrap"=specific slot, "hook:*"=glob
     let get_room_equipment_fn = {
         let state = state.clone();
         lua.create_function(
@@ -2010,6 +2638,7 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     t.set("description", item.thing.description.clone())?;
                     t.set("code", item.thing.code.clone())?;
                     t.set("default_slot", item.thing.default_slot.clone())?;
+                    t.set("params", item.thing.params.clone())?;
                     t.set("available", item.thing.available)?;
                     result.set(i + 1, t)?;
                 }
@@ -2020,7 +2649,7 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     tools.set("get_room_equipment", get_room_equipment_fn)?;
 
     // get_agent_equipment(agent_id, slot_filter) -> array of equipped things
-    // slot_filter: nil=all, ""=NULL slot only, "command:fish"=specific slot
+    // slot_filter: nil=all, ""=NULL slot only, "command:fish"=specific slot, "hotkey:*"=glob
     let get_agent_equipment_fn = {
         let state = state.clone();
         lua.create_function(
@@ -2049,6 +2678,7 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     t.set("description", item.thing.description.clone())?;
                     t.set("code", item.thing.code.clone())?;
                     t.set("default_slot", item.thing.default_slot.clone())?;
+                    t.set("params", item.thing.params.clone())?;
                     t.set("available", item.thing.available)?;
                     result.set(i + 1, t)?;
                 }
@@ -2119,19 +2749,11 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                 }
             };
 
-            // Look up current room name from room_id
-            let current_room_name = session
-                .room_id
-                .as_ref()
-                .and_then(|id| shared.db.get_room(id).ok().flatten())
-                .map(|r| r.name);
-
             // Use ops::join
             match tokio::task::block_in_place(|| {
                 tokio::runtime::Handle::current().block_on(crate::ops::join(
                     &shared,
                     &agent_name,
-                    current_room_name.as_deref(),
                     &room_name,
                 ))
             }) {
@@ -2183,11 +2805,32 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("join", join_fn)?;
 
-    // tools.create(room_name, description?) -> {success, room, error}
+    // What a room template set up, for tools.create() and tools.apply_template()
+    fn blueprint_report_table(
+        lua: &Lua,
+        report: crate::db::templates::BlueprintReport,
+    ) -> LuaResult<Table> {
+        let table = lua.create_table()?;
+        table.set("kv", report.kv)?;
+        table.set("equipped", report.equipped)?;
+        table.set("exits", report.exits)?;
+        table.set("rows", report.rows)?;
+        table.set("scripts", report.scripts)?;
+        table.set("skipped", report.skipped)?;
+        Ok(table)
+    }
+
+    // tools.create(room_name, description?, template?) -> {success, room, template?, error}
+    // With a template, `template` reports what the blueprint set up
     let create_fn = {
         let state = state.clone();
         lua.create_function(
-            move |lua, (room_name, _description): (String, Option<String>)| {
+            move |lua,
+                  (room_name, _description, template): (
+                String,
+                Option<String>,
+                Option<String>,
+            )| {
                 let result = lua.create_table()?;
 
                 let shared = match state.shared_state() {
@@ -2218,23 +2861,25 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     }
                 };
 
-                // Look up current room name from room_id
-                let current_room_name = session
-                    .room_id
-                    .as_ref()
-                    .and_then(|id| shared.db.get_room(id).ok().flatten())
-                    .map(|r| r.name);
-
-                // Use ops::create_room
+                // Use ops::create_room, or ops::create_room_from_template
                 match tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(crate::ops::create_room(
-                        &shared,
-                        &agent_name,
-                        &room_name,
-                        current_room_name.as_deref(),
-                    ))
+                    tokio::runtime::Handle::current().block_on(async {
+                        match template.as_deref() {
+                            Some(template) => crate::ops::create_room_from_template(
+                                &shared,
+                                &agent_name,
+                                &room_name,
+                                template,
+                            )
+                            .await
+                            .map(|(summary, report)| (summary, Some(report))),
+                            None => crate::ops::create_room(&shared, &agent_name, &room_name)
+                            .await
+                            .map(|summary| (summary, None)),
+                        }
+                    })
                 }) {
-                    Ok(room_summary) => {
+                    Ok((room_summary, report)) => {
                         // Look up room_id from room_name
                         let room_id = shared
                             .db
@@ -2253,7 +2898,12 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                         result.set("success", true)?;
                         let room_table = lua.create_table()?;
                         room_table.set("name", room_summary.name)?;
+                        room_table.set("vibe", room_summary.vibe)?;
                         result.set("room", room_table)?;
+
+                        if let Some(report) = report {
+                            result.set("template", blueprint_report_table(lua, report)?)?;
+                        }
                     }
                     Err(e) => {
                         result.set("success", false)?;
@@ -2267,6 +2917,105 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("create", create_fn)?;
 
+    // tools.templates() -> [{name, qualified_name, description, created_by}]
+    let templates_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, ()| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let templates = match shared.db.list_templates() {
+                Ok(templates) => templates,
+                Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+            };
+            let list = lua.create_table()?;
+            for (i, thing) in templates.into_iter().enumerate() {
+                let entry = lua.create_table()?;
+                entry.set("name", thing.name)?;
+                entry.set("qualified_name", thing.qualified_name)?;
+                entry.set("description", thing.description)?;
+                entry.set("created_by", thing.created_by)?;
+                list.set(i + 1, entry)?;
+            }
+            Ok((Some(list), None))
+        })?
+    };
+    tools.set("templates", templates_fn)?;
+
+    // tools.save_template(name, room?) -> {name, kv, equipment, exits, rows, scripts} | nil, error
+    // Captures the current room unless another is named
+    let save_template_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, (name, room): (String, Option<String>)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let Some(room) = room.or_else(|| {
+                state
+                    .session_context()
+                    .and_then(|ctx| ctx.room_id)
+                    .and_then(|id| shared.db.get_room(&id).ok().flatten())
+                    .map(|r| r.name)
+            }) else {
+                return Ok((None, Some("not in a room".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::save_template(
+                    &shared,
+                    &agent_name,
+                    &room,
+                    &name,
+                ))
+            }) {
+                Ok(blueprint) => {
+                    let table = lua.create_table()?;
+                    table.set(
+                        "name",
+                        format!("{}{}", crate::db::templates::TEMPLATE_PREFIX, name),
+                    )?;
+                    table.set("room", room)?;
+                    table.set("kv", blueprint.kv.len())?;
+                    table.set("equipment", blueprint.equipment.len())?;
+                    table.set("exits", blueprint.exits.len())?;
+                    table.set("rows", blueprint.rows.len())?;
+                    table.set("scripts", blueprint.scripts.len())?;
+                    Ok((Some(table), None))
+                }
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("save_template", save_template_fn)?;
+
+    // tools.apply_template(room, template) -> {kv, equipped, exits, rows, scripts, skipped} | nil, error
+    let apply_template_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, (room, template): (String, String)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::apply_template(
+                    &shared,
+                    &agent_name,
+                    &room,
+                    &template,
+                ))
+            }) {
+                Ok(report) => Ok((Some(blueprint_report_table(lua, report)?), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("apply_template", apply_template_fn)?;
+
     // tools.leave() -> {success, error}
     let leave_fn = {
         let state = state.clone();
@@ -2436,10 +3185,454 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                 }
             }
 
-            Ok(result)
+            Ok(result)
+        })?
+    };
+    tools.set("go", go_fn)?;
+
+    // tools.travel(room) -> {success, room?, path?, error?}
+    // Walks the shortest exit path, with presence rows at each hop
+    let travel_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, target: String| {
+            let result = lua.create_table()?;
+
+            let shared = match state.shared_state() {
+                Some(s) => s,
+                None => {
+                    result.set("success", false)?;
+                    result.set("error", "no shared state")?;
+                    return Ok(result);
+                }
+            };
+
+            let session = match state.session_context() {
+                Some(s) => s,
+                None => {
+                    result.set("success", false)?;
+                    result.set("error", "no session context")?;
+                    return Ok(result);
+                }
+            };
+
+            let agent_name = match shared.db.get_agent(&session.agent_id).ok().flatten() {
+                Some(a) => a.name,
+                None => {
+                    result.set("success", false)?;
+                    result.set("error", "agent not found")?;
+                    return Ok(result);
+                }
+            };
+
+            let room_name = match session
+                .room_id
+                .as_ref()
+                .and_then(|id| shared.db.get_room(id).ok().flatten())
+                .map(|r| r.name)
+            {
+                Some(r) => r,
+                None => {
+                    result.set("success", false)?;
+                    result.set("error", "not in a room")?;
+                    return Ok(result);
+                }
+            };
+
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::travel(
+                    &shared,
+                    &agent_name,
+                    &room_name,
+                    &target,
+                ))
+            }) {
+                Ok(trip) => {
+                    let room_id = shared
+                        .db
+                        .get_room_by_name(&trip.room.name)
+                        .ok()
+                        .flatten()
+                        .map(|r| r.id);
+
+                    state.set_session_context(Some(crate::lua::SessionContext {
+                        agent_id: session.agent_id.clone(),
+                        model: None,
+                        room_id,
+                    }));
+
+                    result.set("success", true)?;
+                    let path_table = lua.create_table()?;
+                    for (i, hop) in trip.path.iter().enumerate() {
+                        let hop_table = lua.create_table()?;
+                        hop_table.set("direction", hop.direction.as_str())?;
+                        hop_table.set("room", hop.room.as_str())?;
+                        path_table.set(i + 1, hop_table)?;
+                    }
+                    result.set("path", path_table)?;
+                    let room_table = lua.create_table()?;
+                    room_table.set("name", trip.room.name)?;
+                    if let Some(desc) = trip.room.description {
+                        room_table.set("description", desc)?;
+                    }
+                    if let Some(vibe) = trip.room.vibe {
+                        room_table.set("vibe", vibe)?;
+                    }
+                    result.set("room", room_table)?;
+                }
+                Err(e) => {
+                    result.set("success", false)?;
+                    result.set("error", e.to_string())?;
+                }
+            }
+
+            Ok(result)
+        })?
+    };
+    tools.set("travel", travel_fn)?;
+
+    // tools.map(radius) -> {here, you, rooms = [{name, distance, exits, users}]} | nil, error
+    // Rooms within radius exits of the current room, nearest first
+    let map_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, radius: Option<usize>| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let Some(here) = state
+                .session_context()
+                .and_then(|ctx| ctx.room_id)
+                .and_then(|id| shared.db.get_room(&id).ok().flatten())
+                .map(|r| r.name)
+            else {
+                return Ok((None, Some("not in a room".to_string())));
+            };
+
+            let rooms = match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::map(
+                    &shared,
+                    &here,
+                    radius.unwrap_or(2),
+                ))
+            }) {
+                Ok(rooms) => rooms,
+                Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+            };
+
+            let table = lua.create_table()?;
+            table.set("here", here.as_str())?;
+            table.set("you", state.current_agent_name())?;
+            let rooms_table = lua.create_table()?;
+            for (i, room) in rooms.into_iter().enumerate() {
+                let entry = lua.create_table()?;
+                entry.set("name", room.name)?;
+                entry.set("distance", room.distance)?;
+                let exits = lua.create_table()?;
+                for (direction, target) in room.exits {
+                    exits.set(direction, target)?;
+                }
+                entry.set("exits", exits)?;
+                entry.set("users", room.users)?;
+                rooms_table.set(i + 1, entry)?;
+            }
+            table.set("rooms", rooms_table)?;
+            Ok((Some(table), None))
+        })?
+    };
+    tools.set("map", map_fn)?;
+
+    // tools.zones() -> [{name, owner, vibe, access, members, equipment, rooms}] | nil, error
+    let zones_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, ()| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let zones = match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::zones(&shared))
+            }) {
+                Ok(zones) => zones,
+                Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+            };
+            let list = lua.create_table()?;
+            for (i, zone) in zones.into_iter().enumerate() {
+                list.set(i + 1, zone_table(lua, zone)?)?;
+            }
+            Ok((Some(list), None))
+        })?
+    };
+    tools.set("zones", zones_fn)?;
+
+    // tools.zone(name?) -> {name, owner, vibe, access, members, equipment, rooms} | nil, error
+    // Defaults to the current room's zone
+    let zone_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, name: Option<String>| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let name = match name {
+                Some(name) => name,
+                None => {
+                    let room = state.current_room_name();
+                    match room.and_then(|r| shared.world.get_room(&r).ok().flatten()) {
+                        Some(room) => match room.zone {
+                            Some(zone) => zone,
+                            None => {
+                                return Ok((None, Some(format!("{} isn't in a zone", room.name))))
+                            }
+                        },
+                        None => return Ok((None, Some("not in a room".to_string()))),
+                    }
+                }
+            };
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::get_zone(&shared, &name))
+            }) {
+                Ok(zone) => Ok((Some(zone_table(lua, zone)?), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone", zone_fn)?;
+
+    // tools.zone_create(name) -> zone | nil, error
+    let zone_create_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, name: String| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::create_zone(
+                    &shared,
+                    &agent_name,
+                    &name,
+                ))
+            }) {
+                Ok(zone) => Ok((Some(zone_table(lua, zone)?), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone_create", zone_create_fn)?;
+
+    // tools.zone_delete(name) -> true | nil, error
+    // Only empty zones can be deleted
+    let zone_delete_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, name: String| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::delete_zone(
+                    &shared,
+                    &agent_name,
+                    &name,
+                ))
+            }) {
+                Ok(()) => Ok((Some(true), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone_delete", zone_delete_fn)?;
+
+    // tools.zone_room(room, zone?) -> inherited equipment count | nil, error
+    // Puts the room in the zone; a nil zone takes it out of its zone
+    let zone_room_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (room, zone): (String, Option<String>)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::set_room_zone(
+                    &shared,
+                    &agent_name,
+                    &room,
+                    zone.as_deref(),
+                ))
+            }) {
+                Ok(equipped) => Ok((Some(equipped), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone_room", zone_room_fn)?;
+
+    // tools.zone_vibe(zone, vibe?) -> true | nil, error
+    // nil clears the zone's vibe
+    let zone_vibe_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (zone, vibe): (String, Option<String>)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::set_zone_vibe(
+                    &shared,
+                    &agent_name,
+                    &zone,
+                    vibe.as_deref(),
+                ))
+            }) {
+                Ok(()) => Ok((Some(true), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone_vibe", zone_vibe_fn)?;
+
+    // tools.zone_access(zone, rule?) -> true | nil, error
+    // rule is closed, members, capability:<name> or check:<thing>; nil opens the zone
+    let zone_access_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (zone, rule): (String, Option<String>)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let lock = match rule.as_deref().map(crate::db::locks::ExitLock::parse) {
+                Some(Ok(lock)) => Some(lock),
+                Some(Err(e)) => return Ok((None, Some(format!("{:#}", e)))),
+                None => None,
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::set_zone_access(
+                    &shared,
+                    &agent_name,
+                    &zone,
+                    lock.as_ref(),
+                ))
+            }) {
+                Ok(()) => Ok((Some(true), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone_access", zone_access_fn)?;
+
+    // tools.zone_member(zone, name, add) -> members | nil, error
+    let zone_member_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (zone, member, add): (String, String, bool)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::set_zone_member(
+                    &shared,
+                    &agent_name,
+                    &zone,
+                    &member,
+                    add,
+                ))
+            }) {
+                Ok(members) => Ok((Some(members), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone_member", zone_member_fn)?;
+
+    // tools.zone_equip(zone, thing, slot?) -> room count | nil, error
+    // Every room in the zone inherits the thing, now and after joining
+    let zone_equip_fn = {
+        let state = state.clone();
+        lua.create_function(
+            move |_lua, (zone, thing, slot): (String, String, Option<String>)| {
+                let Some(shared) = state.shared_state() else {
+                    return Ok((None, Some("no shared state".to_string())));
+                };
+                let agent_name = state
+                    .current_agent_name()
+                    .unwrap_or_else(|| "anonymous".to_string());
+                match tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(crate::ops::zone_equip(
+                        &shared,
+                        &agent_name,
+                        &zone,
+                        &thing,
+                        slot.as_deref(),
+                    ))
+                }) {
+                    Ok(equipped) => Ok((Some(equipped), None)),
+                    Err(e) => Ok((None, Some(format!("{:#}", e)))),
+                }
+            },
+        )?
+    };
+    tools.set("zone_equip", zone_equip_fn)?;
+
+    // tools.zone_unequip(zone, thing) -> true | nil, error
+    // The zone's rooms stop inheriting the thing
+    let zone_unequip_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (zone, thing): (String, String)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::zone_unequip(
+                    &shared,
+                    &agent_name,
+                    &zone,
+                    &thing,
+                ))
+            }) {
+                Ok(()) => Ok((Some(true), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("zone_unequip", zone_unequip_fn)?;
+
+    // tools.zone_who(zone) -> [{room, users}] | nil, error
+    // Occupied rooms in the zone, by name
+    let zone_who_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, zone: String| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let who = match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::zone_who(&shared, &zone))
+            }) {
+                Ok(who) => who,
+                Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+            };
+            let list = lua.create_table()?;
+            for (i, (room, users)) in who.into_iter().enumerate() {
+                let entry = lua.create_table()?;
+                entry.set("room", room)?;
+                entry.set("users", users)?;
+                list.set(i + 1, entry)?;
+            }
+            Ok((Some(list), None))
         })?
     };
-    tools.set("go", go_fn)?;
+    tools.set("zone_who", zone_who_fn)?;
 
     // tools.dig(direction, target_room, bidirectional?) -> {success, reverse, error}
     let dig_fn =
@@ -2505,10 +3698,11 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
         };
     tools.set("dig", dig_fn)?;
 
-    // tools.fork(new_name) -> {success, room, error}
+    // tools.fork(new_name, at?) -> {success, room, error}
+    // at is a row reference to branch from (default: the latest row)
     let fork_fn = {
         let state = state.clone();
-        lua.create_function(move |lua, new_name: String| {
+        lua.create_function(move |lua, (new_name, at): (String, Option<String>)| {
             let result = lua.create_table()?;
 
             let shared = match state.shared_state() {
@@ -2561,6 +3755,7 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     &agent_name,
                     &room_name,
                     &new_name,
+                    at.as_deref(),
                 ))
             }) {
                 Ok(room_summary) => {
@@ -2598,6 +3793,303 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("fork", fork_fn)?;
 
+    // tools.branches() -> [{name, parent?, fork_at?, fork_row_id?}]
+    // Every room with the room it was forked from and the message it
+    // branched at, oldest room first
+    let branches_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, ()| {
+            let list = lua.create_table()?;
+            let Some(shared) = state.shared_state() else {
+                return Ok(list);
+            };
+            let db = &shared.db;
+
+            let parents = match db.list_room_parents() {
+                Ok(p) => p,
+                Err(e) => {
+                    tracing::warn!("failed to list room parents: {}", e);
+                    return Ok(list);
+                }
+            };
+
+            for (i, (name, parent)) in parents.into_iter().enumerate() {
+                let entry = lua.create_table()?;
+                entry.set("name", name.as_str())?;
+                if parent.is_some() {
+                    // Where the fork's chat branched off
+                    let fork_row = db
+                        .get_room_by_name(&name)
+                        .ok()
+                        .flatten()
+                        .and_then(|room| {
+                            db.list_room_buffers_by_type(
+                                &room.id,
+                                crate::db::buffers::BufferType::RoomChat,
+                            )
+                            .ok()
+                        })
+                        .and_then(|buffers| buffers.into_iter().next())
+                        .and_then(|buffer| buffer.fork_row_id)
+                        .and_then(|id| db.get_row(&id).ok().flatten());
+                    if let Some(row) = fork_row {
+                        let author = row
+                            .source_agent_id
+                            .as_deref()
+                            .and_then(|id| db.get_agent(id).ok().flatten())
+                            .map(|a| a.name)
+                            .unwrap_or_else(|| "system".to_string());
+                        let content = row.content.as_deref().unwrap_or("");
+                        let first_line = content.lines().next().unwrap_or("");
+                        let mut preview: String = first_line.chars().take(48).collect();
+                        if preview.len() < content.len() {
+                            preview.push('…');
+                        }
+                        entry.set("fork_at", format!("{}: {}", author, preview))?;
+                        entry.set("fork_row_id", row.id)?;
+                    }
+                }
+                entry.set("parent", parent)?;
+                list.set(i + 1, entry)?;
+            }
+            Ok(list)
+        })?
+    };
+    tools.set("branches", branches_fn)?;
+
+    // tools.merge_preview(fork) -> {fork, parent, rows, kv, equipment} | nil, error
+    // What merging a fork back into its parent would change: row counts per
+    // mode, room_kv differences and equipment differences
+    let merge_preview_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, fork: String| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let plan = match shared.db.plan_merge(&fork) {
+                Ok(plan) => plan,
+                Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+            };
+
+            let table = lua.create_table()?;
+            table.set("fork", plan.fork_room.as_str())?;
+            table.set("parent", plan.parent_room.as_str())?;
+
+            let rows = lua.create_table()?;
+            for mode in [MergeMode::All, MergeMode::Pinned, MergeMode::Summary] {
+                rows.set(mode.as_str(), plan.rows_for(mode).len())?;
+            }
+            table.set("rows", rows)?;
+
+            let kv = lua.create_table()?;
+            for (i, change) in plan.kv.iter().enumerate() {
+                let entry = lua.create_table()?;
+                entry.set("key", change.key.as_str())?;
+                entry.set("parent", change.parent.as_deref())?;
+                entry.set("fork", change.fork.as_deref())?;
+                entry.set("conflict", change.conflict)?;
+                kv.set(i + 1, entry)?;
+            }
+            table.set("kv", kv)?;
+
+            let equipment = lua.create_table()?;
+            for (i, change) in plan.equipment.iter().enumerate() {
+                let entry = lua.create_table()?;
+                let thing = &change.item.thing;
+                entry.set(
+                    "name",
+                    thing.qualified_name.as_deref().unwrap_or(&thing.name),
+                )?;
+                entry.set("slot", change.item.slot.as_deref())?;
+                entry.set("equip", change.equip)?;
+                equipment.set(i + 1, entry)?;
+            }
+            table.set("equipment", equipment)?;
+
+            Ok((Some(table), None))
+        })?
+    };
+    tools.set("merge_preview", merge_preview_fn)?;
+
+    // tools.merge(fork, mode, model?) -> {parent, row_id, rows, kv, equipment} | nil, error
+    // mode is "all", "pinned" or "summary"; model picks who summarizes.
+    // Summaries are written in the background: the result is {parent, rows,
+    // pending = true} and a notification follows when the merge lands.
+    let merge_fn = {
+        let state = state.clone();
+        lua.create_function(
+            move |lua, (fork, mode, model): (String, String, Option<String>)| {
+                let Some(shared) = state.shared_state() else {
+                    return Ok((None, Some("no shared state".to_string())));
+                };
+                let Some(mode) = MergeMode::parse(&mode) else {
+                    return Ok((None, Some(format!("unknown merge mode '{}'", mode))));
+                };
+                let agent_name = state
+                    .current_agent_name()
+                    .unwrap_or_else(|| "anonymous".to_string());
+
+                let prepared = match crate::ops::prepare_merge(
+                    &shared,
+                    &agent_name,
+                    &fork,
+                    mode,
+                    model.as_deref(),
+                ) {
+                    Ok(prepared) => prepared,
+                    Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+                };
+
+                let table = lua.create_table()?;
+                table.set("parent", prepared.plan.parent_room.as_str())?;
+
+                // Don't hold the Lua lock while a model writes the summary
+                if mode == MergeMode::Summary {
+                    table.set("rows", prepared.plan.rows_for(mode).len())?;
+                    table.set("pending", true)?;
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        match crate::ops::finish_merge(&shared, prepared).await {
+                            Ok((plan, _)) => state.push_notification(
+                                format!("Merged a summary of {} into {}", fork, plan.parent_room),
+                                5000,
+                            ),
+                            Err(e) => state.push_notification_with_level(
+                                format!("merge {}: {:#}", fork, e),
+                                8000,
+                                NotificationLevel::Error,
+                            ),
+                        }
+                    });
+                    return Ok((Some(table), None));
+                }
+
+                let merged = tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current()
+                        .block_on(crate::ops::finish_merge(&shared, prepared))
+                });
+                let (_, outcome) = match merged {
+                    Ok(merged) => merged,
+                    Err(e) => return Ok((None, Some(format!("{:#}", e)))),
+                };
+
+                table.set("row_id", outcome.row_id)?;
+                table.set("rows", outcome.rows_merged)?;
+                table.set("kv", outcome.kv_applied)?;
+                table.set("equipment", outcome.equipment_applied)?;
+                Ok((Some(table), None))
+            },
+        )?
+    };
+    tools.set("merge", merge_fn)?;
+
+    // tools.archive_room(room, archived) -> true | nil, error
+    // Archived rooms are read-only and left out of room lists
+    let archive_room_fn = {
+        let state = state.clone();
+        lua.create_function(move |_lua, (room, archived): (String, bool)| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::set_room_archived(
+                    &shared,
+                    &agent_name,
+                    &room,
+                    archived,
+                ))
+            }) {
+                Ok(()) => Ok((Some(true), None)),
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("archive_room", archive_room_fn)?;
+
+    // Shared shape for tools.delete_room_preview() and tools.delete_room()
+    fn room_deletion_table(
+        lua: &Lua,
+        room: &str,
+        owner: Option<String>,
+        deletion: &crate::db::rooms::RoomDeletion,
+    ) -> LuaResult<Table> {
+        let table = lua.create_table()?;
+        table.set("room", room)?;
+        table.set("owner", owner)?;
+        table.set("buffers", deletion.buffers)?;
+        table.set("rows", deletion.rows)?;
+        table.set("equipment", deletion.equipment)?;
+        let exits = lua.create_table()?;
+        for (i, (from, direction)) in deletion.exits_in.iter().enumerate() {
+            let exit = lua.create_table()?;
+            exit.set("room", from.as_str())?;
+            exit.set("direction", direction.as_str())?;
+            exits.set(i + 1, exit)?;
+        }
+        table.set("exits_in", exits)?;
+        table.set("forks", deletion.forks.clone())?;
+        Ok(table)
+    }
+
+    // tools.delete_room_preview(room) -> {room, owner, buffers, rows, equipment, exits_in, forks} | nil, error
+    let delete_room_preview_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, room: String| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let planned = shared
+                .db
+                .get_room_by_name(&room)
+                .and_then(|found| match found {
+                    Some(r) => shared.db.plan_room_deletion(&r.id),
+                    None => Err(anyhow::anyhow!("room '{}' not found", room)),
+                });
+            match planned {
+                Ok(deletion) => {
+                    let owner = shared.db.get_room_owner(&room).ok().flatten();
+                    let table = room_deletion_table(lua, &room, owner, &deletion)?;
+                    Ok((Some(table), None))
+                }
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("delete_room_preview", delete_room_preview_fn)?;
+
+    // tools.delete_room(room) -> {room, buffers, rows, equipment, exits_in} | nil, error
+    // Owner or admin only; the room must be empty
+    let delete_room_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, room: String| {
+            let Some(shared) = state.shared_state() else {
+                return Ok((None, Some("no shared state".to_string())));
+            };
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            let owner = shared.db.get_room_owner(&room).ok().flatten();
+            match tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::ops::delete_room(
+                    &shared,
+                    &agent_name,
+                    &room,
+                ))
+            }) {
+                Ok(deletion) => {
+                    tracing::info!(agent = %agent_name, room = %room, "room deleted");
+                    let table = room_deletion_table(lua, &room, owner, &deletion)?;
+                    Ok((Some(table), None))
+                }
+                Err(e) => Ok((None, Some(format!("{:#}", e)))),
+            }
+        })?
+    };
+    tools.set("delete_room", delete_room_fn)?;
+
     // tools.inventory() -> {equipped = [...], available = [...]}
     let inventory_fn = {
         let state = state.clone();
@@ -2702,6 +4194,11 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     .map(|r| r.id)
                     .unwrap_or_else(|| "lobby".to_string())
             });
+            if let Err(e) = shared.db.ensure_room_id_writable(&context_id) {
+                result.set("success", false)?;
+                result.set("error", format!("{:#}", e))?;
+                return Ok(result);
+            }
 
             // Find the thing by qualified name
             let thing = match shared.db.get_thing_by_qualified_name(&qualified_name) {
@@ -2796,6 +4293,11 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     .map(|r| r.id)
                     .unwrap_or_else(|| "lobby".to_string())
             });
+            if let Err(e) = shared.db.ensure_room_id_writable(&context_id) {
+                result.set("success", false)?;
+                result.set("error", format!("{:#}", e))?;
+                return Ok(result);
+            }
 
             // Find the thing by qualified name
             let thing = match shared.db.get_thing_by_qualified_name(&qualified_name) {
@@ -3396,6 +4898,50 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("bootstrap_world", bootstrap_world_fn)?;
 
+    // tools.backup() -> {success, path, bytes, kept, error}
+    // Take an online database backup into the backup dir (admin only)
+    let backup_fn = {
+        let state = state.clone();
+        lua.create_function(move |lua, ()| {
+            let result = lua.create_table()?;
+
+            let Some(shared) = state.shared_state() else {
+                result.set("success", false)?;
+                result.set("error", "no shared state")?;
+                return Ok(result);
+            };
+
+            let agent_name = state
+                .current_agent_name()
+                .unwrap_or_else(|| "anonymous".to_string());
+            if !shared.config.is_admin(&agent_name) {
+                result.set("success", false)?;
+                result.set("error", "admin only")?;
+                return Ok(result);
+            }
+
+            let dir = std::path::PathBuf::from(&shared.config.backup_dir);
+            let keep = shared.config.backup_keep;
+            match tokio::task::block_in_place(|| shared.db.backup_rotated(&dir, keep)) {
+                Ok(path) => {
+                    let bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    tracing::info!(agent = %agent_name, path = %path.display(), "manual backup");
+                    result.set("success", true)?;
+                    result.set("path", path.to_string_lossy().into_owned())?;
+                    result.set("bytes", bytes)?;
+                    result.set("kept", keep)?;
+                }
+                Err(e) => {
+                    result.set("success", false)?;
+                    result.set("error", e.to_string())?;
+                }
+            }
+
+            Ok(result)
+        })?
+    };
+    tools.set("backup", backup_fn)?;
+
     // =========================================================================
     // DB Primitives (Phase 3) - Direct database access for Lua
     // =========================================================================
@@ -3500,7 +5046,7 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("db_row", db_row_fn)?;
 
-    // tools.db_rooms() -> array of room tables
+    // tools.db_rooms() -> array of room tables (archived ones flagged)
     let db_rooms_fn = {
         let state = state.clone();
         lua.create_function(move |lua, ()| {
@@ -3528,6 +5074,11 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                 if let Ok(Some(desc)) = shared.db.get_room_kv(&room.id, "description") {
                     room_table.set("description", desc)?;
                 }
+                if let Ok(Some(owner)) = shared.db.get_room_kv(&room.id, "owner") {
+                    room_table.set("owner", owner)?;
+                }
+                let archived = matches!(shared.db.get_room_kv(&room.id, "archived"), Ok(Some(_)));
+                room_table.set("archived", archived)?;
 
                 result.set(i + 1, room_table)?;
             }
@@ -3753,17 +5304,6 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
             // Create room in DB
             match shared.db.create_room(&name, description.as_deref()) {
                 Ok(()) => {
-                    // Also create in memory
-                    tokio::task::block_in_place(|| {
-                        tokio::runtime::Handle::current().block_on(async {
-                            let mut world = shared.world.write().await;
-                            world.create_room(name.clone());
-                            if let Some(room) = world.get_room_mut(&name) {
-                                room.description = description.clone();
-                            }
-                        });
-                    });
-
                     result.set("success", true)?;
                     result.set("room", name)?;
                 }
@@ -3808,19 +5348,15 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                 Ok(Some(_)) => {}
             }
 
+            if let Err(e) = shared.db.ensure_room_writable(&room) {
+                result.set("success", false)?;
+                result.set("error", format!("{:#}", e))?;
+                return Ok(result);
+            }
+
             // Set vibe
             match shared.db.set_vibe(&room, Some(&vibe)) {
                 Ok(()) => {
-                    // Update in-memory state
-                    tokio::task::block_in_place(|| {
-                        tokio::runtime::Handle::current().block_on(async {
-                            let mut world = shared.world.write().await;
-                            if let Some(room_obj) = world.get_room_mut(&room) {
-                                room_obj.context.vibe = Some(vibe.clone());
-                            }
-                        });
-                    });
-
                     result.set("success", true)?;
                 }
                 Err(e) => {
@@ -3887,6 +5423,19 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     Ok(Some(_)) => {}
                 }
 
+                let ends = if bidirectional {
+                    vec![&room, &target]
+                } else {
+                    vec![&room]
+                };
+                for name in ends {
+                    if let Err(e) = shared.db.ensure_room_writable(name) {
+                        result.set("success", false)?;
+                        result.set("error", format!("{:#}", e))?;
+                        return Ok(result);
+                    }
+                }
+
                 // Add forward exit
                 if let Err(e) = shared.db.add_exit(&room, &direction, &target) {
                     result.set("success", false)?;
@@ -3933,87 +5482,82 @@ pub fn register_tools(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
     };
     tools.set("db_add_exit", db_add_exit_fn)?;
 
-    // tools.db_fork_room(source, new_name) -> {success, room?, error}
+    // tools.db_fork_room(source, new_name, at?) -> {success, room?, error}
+    // at is a row reference to branch from (default: the latest row)
     let db_fork_room_fn = {
         let state = state.clone();
-        lua.create_function(move |lua, (source, new_name): (String, String)| {
-            let result = lua.create_table()?;
-
-            let shared = match state.shared_state() {
-                Some(s) => s,
-                None => {
-                    result.set("success", false)?;
-                    result.set("error", "no shared state")?;
-                    return Ok(result);
-                }
-            };
+        lua.create_function(
+            move |lua, (source, new_name, at): (String, String, Option<String>)| {
+                let result = lua.create_table()?;
 
-            // Validate new name
-            if !new_name
-                .chars()
-                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-            {
-                result.set("success", false)?;
-                result.set(
-                    "error",
-                    "Room name can only contain letters, numbers, dashes, and underscores",
-                )?;
-                return Ok(result);
-            }
+                let shared = match state.shared_state() {
+                    Some(s) => s,
+                    None => {
+                        result.set("success", false)?;
+                        result.set("error", "no shared state")?;
+                        return Ok(result);
+                    }
+                };
 
-            // Check source exists
-            match shared.db.get_room_by_name(&source) {
-                Ok(None) => {
-                    result.set("success", false)?;
-                    result.set("error", format!("Source room '{}' not found", source))?;
-                    return Ok(result);
-                }
-                Err(e) => {
+                // Validate new name
+                if !new_name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+                {
                     result.set("success", false)?;
-                    result.set("error", e.to_string())?;
+                    result.set(
+                        "error",
+                        "Room name can only contain letters, numbers, dashes, and underscores",
+                    )?;
                     return Ok(result);
                 }
-                Ok(Some(_)) => {}
-            }
 
-            // Check new room doesn't exist
-            match shared.db.get_room_by_name(&new_name) {
-                Ok(Some(_)) => {
-                    result.set("success", false)?;
-                    result.set("error", format!("Room '{}' already exists", new_name))?;
-                    return Ok(result);
-                }
-                Err(e) => {
-                    result.set("success", false)?;
-                    result.set("error", e.to_string())?;
-                    return Ok(result);
+                // Check source exists
+                match shared.db.get_room_by_name(&source) {
+                    Ok(None) => {
+                        result.set("success", false)?;
+                        result.set("error", format!("Source room '{}' not found", source))?;
+                        return Ok(result);
+                    }
+                    Err(e) => {
+                        result.set("success", false)?;
+                        result.set("error", e.to_string())?;
+                        return Ok(result);
+                    }
+                    Ok(Some(_)) => {}
                 }
-                Ok(None) => {}
-            }
-
-            // Fork room
-            match shared.db.fork_room(&source, &new_name) {
-                Ok(()) => {
-                    // Create in memory
-                    tokio::task::block_in_place(|| {
-                        tokio::runtime::Handle::current().block_on(async {
-                            let mut world = shared.world.write().await;
-                            world.create_room(new_name.clone());
-                        });
-                    });
 
-                    result.set("success", true)?;
-                    result.set("room", new_name)?;
-                    result.set("source", source)?;
+                // Check new room doesn't exist
+                match shared.db.get_room_by_name(&new_name) {
+                    Ok(Some(_)) => {
+                        result.set("success", false)?;
+                        result.set("error", format!("Room '{}' already exists", new_name))?;
+                        return Ok(result);
+                    }
+                    Err(e) => {
+                        result.set("success", false)?;
+                        result.set("error", e.to_string())?;
+                        return Ok(result);
+                    }
+                    Ok(None) => {}
                 }
-                Err(e) => {
-                    result.set("success", false)?;
-                    result.set("error", e.to_string())?;
+
+                // Fork room
+                match shared.db.fork_room_at(&source, &new_name, at.as_deref()) {
+                    Ok(()) => {
+                        result.set("success", true)?;
+                        result.set("room", new_name)?;
+                        result.set("source", source)?;
+                    }
+                    Err(e) => {
+                        result.set("success", false)?;
+                        result.set("error", e.to_string())?;
+                    }
                 }
-            }
 
-            Ok(result)
-        })?
+                Ok(result)
+            },
+        )?
     };
     tools.set("db_fork_room", db_fork_room_fn)?;
 
@@ -4167,6 +5711,19 @@ pub fn register_mcp_tool_registration(
     Ok(())
 }
 
+/// Lua table for a zone, as tools.zones and tools.zone return it
+fn zone_table(lua: &Lua, zone: crate::ops::ZoneInfo) -> LuaResult<Table> {
+    let table = lua.create_table()?;
+    table.set("name", zone.name)?;
+    table.set("owner", zone.owner)?;
+    table.set("vibe", zone.vibe)?;
+    table.set("access", zone.access)?;
+    table.set("members", zone.members)?;
+    table.set("equipment", zone.equipment)?;
+    table.set("rooms", zone.rooms)?;
+    Ok(table)
+}
+
 /// Convert serde_json::Value to mlua::Value
 pub fn json_to_lua(lua: &Lua, value: &serde_json::Value) -> LuaResult<Value> {
     match value {
@@ -4315,9 +5872,7 @@ pub fn register_sshwarma_call(lua: &Lua, state: LuaToolState) -> LuaResult<()> {
                     ToolContext {
                         db,
                         mcp: std::sync::Arc::new(crate::mcp::McpManager::new()),
-                        world: std::sync::Arc::new(tokio::sync::RwLock::new(
-                            crate::world::World::new(),
-                        )),
+                        world: std::sync::Arc::new(crate::world::World::new(db.clone())),
                         status_tracker,
                         username,
                         room,
@@ -4459,20 +6014,18 @@ mod tests {
     use crate::world::World;
     use mlua::Function;
     use std::sync::Arc;
-    use tokio::sync::RwLock;
 
     /// Reusable test fixture with real components (no mocks)
     struct TestInstance {
         shared_state: Arc<SharedState>,
         #[allow(dead_code)]
         db: Arc<Database>,
-        world: Arc<RwLock<World>>,
         models: Arc<ModelRegistry>,
     }
 
     impl TestInstance {
         fn new() -> anyhow::Result<Self> {
-            let db = Arc::new(Database::open(":memory:")?);
+            let db = Arc::new(Database::in_memory()?);
 
             let mut models = ModelRegistry::new();
             models.register(ModelHandle {
@@ -4487,38 +6040,30 @@ mod tests {
             });
             let models = Arc::new(models);
 
-            let world = Arc::new(RwLock::new(World::new()));
-
             let shared_state = Arc::new(SharedState {
-                world: world.clone(),
+                world: Arc::new(World::new(db.clone())),
                 db: db.clone(),
                 config: Config::default(),
                 llm: Arc::new(LlmClient::new()?),
                 models: models.clone(),
                 mcp: Arc::new(McpManager::new()),
                 lua_reload: LuaReloadSender::new(),
+                shutdown: Arc::new(crate::shutdown::Shutdown::new()),
+                sessions: Arc::new(crate::ssh::SessionStore::new(std::time::Duration::ZERO)),
             });
 
             Ok(Self {
                 shared_state,
                 db,
-                world,
                 models,
             })
         }
         /// Create room with optional vibe
         async fn create_room(&self, name: &str, vibe: Option<&str>) {
-            // Create in memory world
-            let mut world = self.world.write().await;
-            world.create_room(name.to_string());
+            let _ = self.db.create_room(name, None);
             if let Some(v) = vibe {
-                if let Some(room) = world.get_room_mut(name) {
-                    room.context.vibe = Some(v.to_string());
-                }
+                let _ = self.db.set_vibe(name, Some(v));
             }
-            // Also create in database so room_id lookups work
-            drop(world);
-            let _ = self.db.create_room(name, None);
         }
 
         /// Add chat message to room's buffer
@@ -7,7 +7,7 @@
 use anyhow::{Context, Result};
 use russh::server::Server as _;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::Mutex;
 use tracing::{info, warn};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -20,7 +20,8 @@ use sshwarma::mcp::McpManager;
 use sshwarma::mcp_server::{self, McpServerState};
 use sshwarma::model::ModelRegistry;
 use sshwarma::paths;
-use sshwarma::ssh::SshServer;
+use sshwarma::shutdown;
+use sshwarma::ssh::{SessionStore, SshServer};
 use sshwarma::state::SharedState;
 use sshwarma::world::World;
 
@@ -50,6 +51,21 @@ async fn main() -> Result<()> {
     // Bootstrap world structure (creates lobby, internal tools, etc.)
     db.bootstrap_world().context("failed to bootstrap world")?;
 
+    // Repair anything a previous crash left half-done, before anyone connects
+    let report = db
+        .recover_after_crash()
+        .context("failed to run startup recovery")?;
+    if report.is_clean() {
+        info!("startup recovery: nothing to repair");
+    } else {
+        warn!(
+            interrupted_rows = report.interrupted_rows,
+            presence_left = report.presence_left,
+            sessions_closed = report.sessions_closed,
+            "startup recovery repaired state from an unclean shutdown"
+        );
+    }
+
     // Check users
     let users = db.list_users()?;
     if users.is_empty() {
@@ -79,20 +95,9 @@ async fn main() -> Result<()> {
     let models = ModelRegistry::from_config(&models_config);
     info!("{} models registered", models.list().len());
 
-    // Load rooms from database
-    let mut world = World::new();
-    let saved_rooms = db.get_all_rooms()?;
-    for room_info in &saved_rooms {
-        world.create_room(room_info.name.clone());
-        if let Some(room) = world.get_room_mut(&room_info.name) {
-            room.description = room_info.description.clone();
-        }
-    }
-    info!("{} rooms loaded from database", saved_rooms.len());
-
     // Wrap in Arc for sharing
-    let world = Arc::new(RwLock::new(world));
     let db = Arc::new(db);
+    let world = Arc::new(World::new(db.clone()));
     let llm = Arc::new(llm);
     let models = Arc::new(models);
     let mcp = Arc::new(McpManager::new());
@@ -110,7 +115,12 @@ async fn main() -> Result<()> {
         models: models.clone(),
         mcp,
         lua_reload,
+        shutdown: Arc::new(shutdown::Shutdown::new()),
+        sessions: Arc::new(SessionStore::new(std::time::Duration::from_secs(
+            config.session_grace_secs,
+        ))),
     });
+    state.sessions.track_presence(world.clone());
 
     // Run Lua startup script (can configure MCP connections, etc.)
     {
@@ -131,6 +141,7 @@ async fn main() -> Result<()> {
     }
 
     // Start MCP server for Claude Code
+    let mut mcp_handle = None;
     if config.mcp_server_port > 0 {
         use sshwarma::lua::{register_mcp_tool_registration, LuaRuntime};
         use sshwarma::mcp_server::McpToolRegistry;
@@ -160,7 +171,8 @@ async fn main() -> Result<()> {
             shared_state: state.clone(),
             tool_registry,
         });
-        let _mcp_handle = mcp_server::start_mcp_server(config.mcp_server_port, mcp_state).await?;
+        let handle = mcp_server::start_mcp_server(config.mcp_server_port, mcp_state).await?;
+        mcp_handle = Some(handle);
         info!(port = config.mcp_server_port, "MCP server started");
     }
 
@@ -214,12 +226,91 @@ async fn main() -> Result<()> {
         });
     }
 
-    // Start SSH server
-    let mut server = SshServer { state };
+    // Spawn background task for scheduled online backups
+    if config.backup_interval_mins > 0 {
+        let db = db.clone();
+        let dir = std::path::PathBuf::from(&config.backup_dir);
+        let keep = config.backup_keep;
+        let period = std::time::Duration::from_secs(config.backup_interval_mins * 60);
+        info!(
+            every_mins = config.backup_interval_mins,
+            keep, "scheduled backups enabled"
+        );
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            // First tick completes immediately; skip it so startup isn't slowed
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let db = db.clone();
+                let dir = dir.clone();
+                match tokio::task::spawn_blocking(move || db.backup_rotated(&dir, keep)).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => warn!(error = %e, "scheduled backup failed"),
+                    Err(e) => warn!(error = %e, "scheduled backup task panicked"),
+                }
+            }
+        });
+    }
+
+    // Spawn background task that purges old things from the trash
+    if config.trash_retention_days > 0 {
+        let db = db.clone();
+        let age = std::time::Duration::from_secs(config.trash_retention_days * 24 * 60 * 60);
+        info!(
+            retention_days = config.trash_retention_days,
+            "trash retention enabled"
+        );
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+            loop {
+                ticker.tick().await;
+                let db = db.clone();
+                match tokio::task::spawn_blocking(move || db.purge_trash_older_than(age)).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(purged)) => info!(purged, "purged old things from the trash"),
+                    Ok(Err(e)) => warn!(error = %e, "trash purge failed"),
+                    Err(e) => warn!(error = %e, "trash purge task panicked"),
+                }
+            }
+        });
+    }
+
+    // Start SSH server; dropping the accept loop on a signal stops new
+    // connections while existing sessions keep running until drained
+    let mut server = SshServer {
+        state: state.clone(),
+    };
     info!("listening on {}", config.listen_addr);
-    server
-        .run_on_address(Arc::new(russh_config), config.listen_addr)
-        .await?;
+    let signal = tokio::select! {
+        result = server.run_on_address(Arc::new(russh_config), config.listen_addr) => {
+            result?;
+            return Ok(());
+        }
+        signal = shutdown::signal() => signal.context("failed to listen for signals")?,
+    };
+
+    info!(signal, "shutdown requested");
+    let grace = std::time::Duration::from_secs(config.shutdown_grace_secs);
+    tokio::select! {
+        _ = shutdown::drain(&state, grace) => {}
+        _ = shutdown::signal() => warn!("second signal received, exiting immediately"),
+    }
+
+    // Let the MCP server finish in-flight requests, but don't hang on
+    // clients holding streams open
+    if let Some(handle) = mcp_handle {
+        let abort = handle.abort_handle();
+        if tokio::time::timeout(std::time::Duration::from_secs(5), handle)
+            .await
+            .is_err()
+        {
+            warn!("MCP server did not stop in time, aborting");
+            abort.abort();
+        }
+    }
 
     Ok(())
 }
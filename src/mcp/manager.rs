@@ -258,6 +258,26 @@ impl McpManager {
         })
     }
 
+    /// Close every connection, returning how many were open.
+    ///
+    /// Used at server shutdown. Connected services are cancelled so their
+    /// transports close cleanly rather than being dropped mid-request.
+    pub async fn shutdown(&self) -> usize {
+        let mut desired = self.desired.write().await;
+        let mut connections = self.connections.write().await;
+
+        desired.clear();
+        let count = connections.len();
+        for (name, conn) in connections.drain() {
+            debug!(mcp.server = %name, "closing MCP connection");
+            conn.cancel.cancel();
+            if let Some(service) = conn.service {
+                service.cancellation_token().cancel();
+            }
+        }
+        count
+    }
+
     /// Get status of a single connection.
     pub fn status(&self, name: &str) -> Option<ConnectionStatus> {
         tokio::task::block_in_place(|| {
@@ -25,12 +25,14 @@ use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 // ScriptScope is imported locally where needed
+use crate::db::events::{RowEvent, RowEventKind};
 use crate::db::Database;
 use crate::llm::LlmClient;
 use crate::lua::{LuaRuntime, WrapState};
 use crate::model::{ModelBackend, ModelHandle, ModelRegistry};
 use crate::state::SharedState;
 use crate::world::World;
+use tokio::sync::broadcast::{self, error::RecvError, error::TryRecvError};
 use tokio::sync::{Mutex, RwLock};
 
 use rmcp::model::JsonObject;
@@ -173,7 +175,7 @@ pub fn generate_schema<T: schemars::JsonSchema>() -> Arc<JsonObject> {
 
 /// Shared state for the MCP server
 pub struct McpServerState {
-    pub world: Arc<RwLock<World>>,
+    pub world: Arc<World>,
     pub db: Arc<Database>,
     pub llm: Arc<LlmClient>,
     pub models: Arc<ModelRegistry>,
@@ -193,6 +195,8 @@ pub struct McpSession {
     pub display_name: String,
     /// Current room - auto-joins room matching display_name on identify()
     pub current_room: Option<String>,
+    /// Where presence is recorded while the session is in a room
+    world: Arc<World>,
     /// Per-session Lua runtime
     pub lua_runtime: Arc<Mutex<LuaRuntime>>,
     /// When this session was created
@@ -215,6 +219,7 @@ impl McpSession {
             LuaRuntime::new().context("Failed to create Lua runtime for MCP session")?;
 
         // Set up the shared state in the Lua tool state
+        let world = shared_state.world.clone();
         lua_runtime
             .tool_state()
             .set_shared_state(Some(shared_state));
@@ -224,35 +229,56 @@ impl McpSession {
             agent_id: agent.id,
             display_name,
             current_room: None,
+            world,
             lua_runtime: Arc::new(Mutex::new(lua_runtime)),
             created_at: Utc::now(),
         })
     }
 
     /// Update the session identity - returns the old name
+    ///
+    /// If the session is in a room, the old name leaves it and the new one
+    /// takes its place.
     pub fn update_identity(&mut self, db: &Database, new_name: &str) -> Result<String> {
-        let old_name = std::mem::replace(&mut self.display_name, new_name.to_string());
-
         // Get or create agent for the new identity
         let agent = db
             .get_or_create_human_agent(new_name)
             .context("Failed to get or create agent for new identity")?;
         self.agent_id = agent.id;
 
+        let old_name = std::mem::replace(&mut self.display_name, new_name.to_string());
+        if let Some(room) = self.current_room.clone() {
+            self.world.release(&self.presence_holder())?;
+            self.world.hold(&self.presence_holder(), new_name)?;
+            self.world.enter(new_name, &room)?;
+        }
+
         Ok(old_name)
     }
 
-    /// Join a room by name
-    pub fn join_room(&mut self, db: &Database, room_name: &str) -> Result<()> {
-        // Verify the room exists (or could be created)
-        let _room = db
-            .get_room_by_name(room_name)
-            .context("Failed to look up room")?
-            .ok_or_else(|| anyhow::anyhow!("Room '{}' does not exist", room_name))?;
-
+    /// Join a room by name, subject to its zone and exit locks like /join
+    pub async fn join_room(&mut self, state: &SharedState, room_name: &str) -> Result<()> {
+        crate::ops::join(state, &self.display_name, room_name).await?;
+        self.world
+            .hold(&self.presence_holder(), &self.display_name)?;
         self.current_room = Some(room_name.to_string());
         Ok(())
     }
+
+    /// Key this session holds its name's presence under (see `World::hold`)
+    fn presence_holder(&self) -> String {
+        format!("mcp:{}", self.id)
+    }
+}
+
+impl Drop for McpSession {
+    fn drop(&mut self) {
+        if self.current_room.is_some() {
+            if let Err(e) = self.world.release(&self.presence_holder()) {
+                warn!(session = %self.id, "failed to leave room: {:#}", e);
+            }
+        }
+    }
 }
 
 impl crate::ops::MentionSession for McpSession {
@@ -274,6 +300,8 @@ impl crate::ops::MentionSession for McpSession {
 pub struct SshwarmaMcpServer {
     state: Arc<McpServerState>,
     session: Arc<RwLock<McpSession>>,
+    /// Row changes since this session connected, drained by wait_for_changes
+    row_events: Arc<Mutex<broadcast::Receiver<RowEvent>>>,
 }
 
 /// Parameters for preview_wrap
@@ -304,15 +332,32 @@ pub struct IdentifyParams {
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct WhoamiParams {}
 
+/// Parameters for wait_for_changes - block until a room's chat changes
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WaitForChangesParams {
+    #[schemars(description = "Room to watch (defaults to the current room)")]
+    pub room: Option<String>,
+    #[schemars(description = "Seconds to wait before giving up (default 30, max 120)")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Default and maximum wait for wait_for_changes
+const WAIT_DEFAULT_SECS: u64 = 30;
+const WAIT_MAX_SECS: u64 = 120;
+
 impl SshwarmaMcpServer {
     pub fn new(state: Arc<McpServerState>) -> Result<Self> {
         // Create a per-connection session
         let session = McpSession::new(&state.db, state.shared_state.clone())
             .context("Failed to create MCP session")?;
 
+        // Subscribe up front so changes between calls aren't missed
+        let row_events = state.db.subscribe_rows();
+
         Ok(Self {
             state,
             session: Arc::new(RwLock::new(session)),
+            row_events: Arc::new(Mutex::new(row_events)),
         })
     }
 
@@ -440,7 +485,12 @@ impl SshwarmaMcpServer {
 
         // Try to auto-join a room matching the new name
         let auto_joined = if let Ok(Some(_room)) = self.state.db.get_room_by_name(&params.name) {
-            session.current_room = Some(params.name.clone());
+            if let Err(e) = session
+                .join_room(&self.state.shared_state, &params.name)
+                .await
+            {
+                return format!("Error joining {}: {}", params.name, e);
+            }
             true
         } else {
             false
@@ -499,6 +549,98 @@ impl SshwarmaMcpServer {
 
         output
     }
+
+    async fn wait_for_changes(&self, params: WaitForChangesParams) -> String {
+        let room_name = match params.room {
+            Some(room) => room,
+            None => match self.session.read().await.current_room.clone() {
+                Some(room) => room,
+                None => return "No room specified and not in a room.".to_string(),
+            },
+        };
+
+        let db = self.state.db.clone();
+        let name = room_name.clone();
+        let buffer_id = match db
+            .blocking(move |db| {
+                let room = db
+                    .get_room_by_name(&name)?
+                    .ok_or_else(|| anyhow::anyhow!("Room '{}' does not exist", name))?;
+                Ok(db.get_or_create_room_chat_buffer(&room.id)?.id)
+            })
+            .await
+        {
+            Ok(id) => id,
+            Err(e) => return format!("Error: {}", e),
+        };
+
+        let timeout_secs = params
+            .timeout_secs
+            .unwrap_or(WAIT_DEFAULT_SECS)
+            .clamp(1, WAIT_MAX_SECS);
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(timeout_secs);
+
+        let mut rx = self.row_events.lock().await;
+        let mut changes: Vec<RowEvent> = Vec::new();
+
+        // Take what queued up since the last call. Events from every room
+        // land here, so a backlog that overflowed just starts over from now.
+        loop {
+            match rx.try_recv() {
+                Ok(event) if event.buffer_id == buffer_id => changes.push(event),
+                Ok(_) => {}
+                Err(TryRecvError::Lagged(_)) => {
+                    *rx = rx.resubscribe();
+                    changes.clear();
+                    break;
+                }
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            }
+        }
+        let mut lagged = false;
+
+        // Wait for the first matching event, then take whatever else is queued
+        while changes.is_empty() && !lagged {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Ok(event)) if event.buffer_id == buffer_id => changes.push(event),
+                Ok(Ok(_)) => {}
+                Ok(Err(RecvError::Lagged(_))) => lagged = true,
+                Ok(Err(RecvError::Closed)) | Err(_) => break,
+            }
+        }
+        loop {
+            match rx.try_recv() {
+                Ok(event) if event.buffer_id == buffer_id => changes.push(event),
+                Ok(_) => {}
+                Err(TryRecvError::Lagged(_)) => lagged = true,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            }
+        }
+
+        if lagged {
+            return format!(
+                "Missed some changes in {}; re-read it with get_history.",
+                room_name
+            );
+        }
+        if changes.is_empty() {
+            return format!("No changes in {} after {}s.", room_name, timeout_secs);
+        }
+
+        // Streamed chunks for one row collapse into a single line
+        changes.dedup_by(|b, a| {
+            a.row_id == b.row_id && a.kind == b.kind && a.kind == RowEventKind::Streamed
+        });
+        let mut output = format!("{} change(s) in {}:", changes.len(), room_name);
+        for event in &changes {
+            output.push_str(&format!(
+                "\n- {} {}",
+                event.kind.as_str(),
+                event.row_id.get(..8).unwrap_or(&event.row_id)
+            ));
+        }
+        output
+    }
 }
 
 impl ServerHandler for SshwarmaMcpServer {
@@ -507,7 +649,8 @@ impl ServerHandler for SshwarmaMcpServer {
             instructions: Some(
                 "sshwarma MCP server - interact with collaborative rooms. \
                  Use list_rooms to see rooms, get_history to see conversations, \
-                 say to send messages, and ask_model to chat with AI models."
+                 say to send messages, ask_model to chat with AI models, and \
+                 wait_for_changes to block until a room has new activity."
                     .into(),
             ),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
@@ -540,6 +683,11 @@ impl ServerHandler for SshwarmaMcpServer {
             "Get current session identity and status",
             generate_schema::<WhoamiParams>(),
         ));
+        tools.push(Tool::new(
+            "wait_for_changes",
+            "Wait until messages in a room are added, edited, or streamed, then list what changed",
+            generate_schema::<WaitForChangesParams>(),
+        ));
         tools.push(Tool::new(
             "preview_wrap",
             "Preview what context would be composed for an LLM interaction",
@@ -607,6 +755,11 @@ impl ServerHandler for SshwarmaMcpServer {
                     .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
                 self.whoami(p).await
             }
+            "wait_for_changes" => {
+                let p: WaitForChangesParams = serde_json::from_value(params_value)
+                    .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+                self.wait_for_changes(p).await
+            }
             "preview_wrap" => {
                 let p: PreviewWrapParams = serde_json::from_value(params_value)
                     .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
@@ -631,6 +784,7 @@ pub async fn start_mcp_server(
 ) -> Result<tokio::task::JoinHandle<()>> {
     let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
     info!(port, "MCP server listening");
+    let shutdown = state.shared_state.shutdown.clone();
 
     let service = StreamableHttpService::new(
         move || {
@@ -646,7 +800,11 @@ pub async fn start_mcp_server(
     let router = axum::Router::new().nest_service("/mcp", service);
 
     let handle = tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, router).await {
+        // Stop taking requests once shutdown starts; open streams are closed
+        // by the caller's timeout
+        let serve = axum::serve(listener, router)
+            .with_graceful_shutdown(async move { shutdown.wait().await });
+        if let Err(e) = serve.await {
             tracing::error!("MCP server error: {}", e);
         }
     });
@@ -3,7 +3,8 @@
 //! Pure async functions that take state + args and return Result<T>.
 //! No formatting - callers decide how to present results.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
@@ -11,9 +12,16 @@ use rig::tool::server::ToolServer;
 use serde::Serialize;
 use tokio::sync::{mpsc, Mutex};
 
+use crate::db::bundles::{Bundle, BundleFormat, ImportOptions, ImportReport};
+use crate::db::locks::ExitLock;
+use crate::db::merge::{MergeMode, MergeOutcome, MergePlan};
 use crate::db::rows::Row;
+use crate::db::templates::{BlueprintEquip, BlueprintReport, RoomBlueprint};
+use crate::db::things::{Thing, ThingKind};
+use crate::db::zones::{Zone, ZoneSettings};
 use crate::internal_tools::{InternalToolConfig, ToolContext};
 use crate::llm::StreamChunk;
+use crate::lua::predicate::{self, Verdict};
 use crate::lua::{LuaRuntime, WrapState};
 use crate::model::ModelHandle;
 use crate::ssh::RowUpdate;
@@ -73,6 +81,7 @@ pub async fn handle_mention_create_rows(
     if message.trim().is_empty() {
         return Err(anyhow!("Message cannot be empty"));
     }
+    state.db.ensure_room_writable(&room_name)?;
 
     // Look up model
     let model = state
@@ -180,6 +189,9 @@ pub async fn spawn_model_response(
     lua_runtime: Option<Arc<Mutex<LuaRuntime>>>,
     update_tx: Option<mpsc::Sender<RowUpdate>>,
 ) -> Result<tokio::task::JoinHandle<()>> {
+    if state.shutdown.is_shutting_down() {
+        return Err(anyhow!("server is shutting down"));
+    }
     let llm = state.llm.clone();
 
     // Get MCP tools for rig agent
@@ -325,7 +337,11 @@ pub async fn spawn_model_response(
     let row_id = config.placeholder_row_id.clone();
     let room_for_tracking = config.room_name.clone();
 
+    // Counted until the task ends so shutdown can wait for it
+    let stream_guard = state.shutdown.stream_started();
+
     let handle = tokio::spawn(async move {
+        let _stream_guard = stream_guard;
         tracing::info!("spawn_model_response: background task started");
 
         // Get buffer_id and agent_id for tool call tracking
@@ -482,8 +498,8 @@ pub struct RoomSummary {
     pub description: Option<String>,
     pub users: Vec<String>,
     pub models: Vec<String>,
-    pub artifact_count: usize,
     pub vibe: Option<String>,
+    pub zone: Option<String>,
     pub exits: HashMap<String, String>,
 }
 
@@ -492,49 +508,48 @@ pub struct RoomSummary {
 pub struct RoomInfo {
     pub name: String,
     pub user_count: usize,
+    pub zone: Option<String>,
 }
 
 /// Get room summary
 pub async fn look(state: &SharedState, room_name: &str) -> Result<RoomSummary> {
-    let world = state.world.read().await;
-    let room = world
-        .get_room(room_name)
+    let room = state
+        .world
+        .get_room(room_name)?
         .ok_or_else(|| anyhow!("Room '{}' not found", room_name))?;
 
-    let vibe = state.db.get_vibe(room_name).ok().flatten();
-    let exits = state.db.get_exits(room_name).unwrap_or_default();
-
     Ok(RoomSummary {
-        name: room.name.clone(),
-        description: room.description.clone(),
-        users: room.users.clone(),
-        models: room.models.iter().map(|m| m.short_name.clone()).collect(),
-        artifact_count: room.artifacts.len(),
-        vibe,
-        exits,
+        name: room.name,
+        description: room.description,
+        users: room.users,
+        models: room.models,
+        vibe: room.vibe,
+        zone: room.zone,
+        exits: room.exits,
     })
 }
 
-/// Get users in room
+/// Get everyone in a room, people first
 pub async fn who(state: &SharedState, room_name: &str) -> Result<Vec<String>> {
-    let world = state.world.read().await;
-    let room = world
-        .get_room(room_name)
+    let room = state
+        .world
+        .get_room(room_name)?
         .ok_or_else(|| anyhow!("Room '{}' not found", room_name))?;
 
-    Ok(room.users.clone())
+    Ok(room.occupants())
 }
 
-/// List all rooms
+/// List rooms, leaving out archived ones
 pub async fn rooms(state: &SharedState) -> Result<Vec<RoomInfo>> {
-    let world = state.world.read().await;
-    let room_list = world.list_rooms();
-
-    Ok(room_list
+    Ok(state
+        .world
+        .list_rooms()?
         .into_iter()
+        .filter(|r| !r.archived)
         .map(|r| RoomInfo {
+            user_count: r.users.len() + r.models.len(),
             name: r.name,
-            user_count: r.user_count,
+            zone: r.zone,
         })
         .collect())
 }
@@ -565,6 +580,49 @@ pub struct HistoryEntry {
     pub content: String,
 }
 
+/// Pin or unpin a row of the room's history, toggling when pinned is None
+///
+/// The room's owner, its members and admins may pin; rooms nobody owns let
+/// anyone in them pin. Returns the new pinned state.
+pub async fn pin_row(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    row_id: &str,
+    pinned: Option<bool>,
+) -> Result<bool> {
+    let row = state
+        .db
+        .get_row(row_id)?
+        .ok_or_else(|| anyhow!("No such row."))?;
+    let buffer = state.db.get_or_create_room_buffer(room_name)?;
+    if row.buffer_id != buffer.id {
+        return Err(anyhow!("That row isn't in {}.", room_name));
+    }
+
+    let owner = state.db.get_room_owner(room_name)?;
+    let members = state.db.get_room_members(room_name)?;
+    let allowed = match owner.as_deref() {
+        None => true,
+        Some(owner) => {
+            owner == username
+                || members.iter().any(|m| m == username)
+                || state.config.is_admin(username)
+        }
+    };
+    if !allowed {
+        return Err(anyhow!(
+            "Only {} and the members of {} can pin here.",
+            owner.unwrap_or_default(),
+            room_name
+        ));
+    }
+
+    let pinned = pinned.unwrap_or(!row.pinned);
+    state.db.set_row_pinned(row_id, pinned)?;
+    Ok(pinned)
+}
+
 /// Get room exits
 pub async fn exits(state: &SharedState, room_name: &str) -> Result<HashMap<String, String>> {
     state.db.get_exits(room_name)
@@ -592,21 +650,15 @@ pub struct ToolInfo {
     pub description: String,
 }
 
-/// Get vibe for room
+/// Get vibe for room, falling back to its zone's
 pub async fn get_vibe(state: &SharedState, room_name: &str) -> Result<Option<String>> {
-    state.db.get_vibe(room_name)
+    Ok(state.world.get_room(room_name)?.and_then(|r| r.vibe))
 }
 
 /// Set vibe for room
 pub async fn set_vibe(state: &SharedState, room_name: &str, vibe: &str) -> Result<()> {
+    state.db.ensure_room_writable(room_name)?;
     state.db.set_vibe(room_name, Some(vibe))?;
-
-    // Update in-memory state
-    let mut world = state.world.write().await;
-    if let Some(room) = world.get_room_mut(room_name) {
-        room.context.vibe = Some(vibe.to_string());
-    }
-
     Ok(())
 }
 
@@ -621,6 +673,7 @@ pub async fn set_room_navigation(
     room_name: &str,
     enabled: bool,
 ) -> Result<()> {
+    state.db.ensure_room_writable(room_name)?;
     state.db.set_room_navigation(room_name, enabled)?;
     Ok(())
 }
@@ -629,6 +682,8 @@ pub async fn set_room_navigation(
 pub async fn say(state: &SharedState, room_name: &str, sender: &str, message: &str) -> Result<()> {
     use crate::db::rows::Row;
 
+    state.db.ensure_room_writable(room_name)?;
+
     // Get or create the room's buffer
     let buffer = state.db.get_or_create_room_buffer(room_name)?;
 
@@ -644,60 +699,89 @@ pub async fn say(state: &SharedState, room_name: &str, sender: &str, message: &s
 
 // Navigation operations
 
-/// Join a room
-pub async fn join(
+/// Join a room, leaving whichever room the user was in
+///
+/// Exit locks apply here too: when every way into the room is locked
+/// against the user, joining it by name is refused as well.
+pub async fn join(state: &SharedState, username: &str, target_room: &str) -> Result<RoomSummary> {
+    if let Some(why) = way_in_denial(state, username, target_room).await? {
+        return Err(anyhow!(why));
+    }
+    enter(state, username, target_room).await
+}
+
+/// Move into a room the user already has a way into; only its zone is checked
+async fn enter(state: &SharedState, username: &str, target_room: &str) -> Result<RoomSummary> {
+    if let Some(why) = zone_denial(state, username, target_room).await? {
+        return Err(anyhow!(why));
+    }
+    state.world.enter(username, target_room)?;
+    look(state, target_room).await
+}
+
+/// Why `username` can't get into a room by any of its exits, or None when
+/// one of them is open to them (or the room has none leading in)
+async fn way_in_denial(
     state: &SharedState,
     username: &str,
-    current_room: Option<&str>,
     target_room: &str,
-) -> Result<RoomSummary> {
-    // Ensure room buffer exists in database (outside lock)
-    let buffer = state.db.get_or_create_room_buffer(target_room)?;
-
-    // Single write lock: leave old room, check target exists, join target.
-    // This avoids a TOCTOU race between read-check and write-modify.
-    {
-        let mut world = state.world.write().await;
-
-        // Leave current room if in one
-        if let Some(current) = current_room {
-            if let Some(room) = world.get_room_mut(current) {
-                room.remove_user(username);
+) -> Result<Option<String>> {
+    if state.config.is_admin(username) {
+        return Ok(None);
+    }
+    let ways_in: Vec<(String, String)> = state
+        .db
+        .exit_graph()?
+        .into_iter()
+        .filter(|(room, _)| room != target_room)
+        .flat_map(|(room, exits)| {
+            exits
+                .into_iter()
+                .filter(|(_, to)| to == target_room)
+                .map(move |(direction, _)| (room.clone(), direction))
+        })
+        .collect();
+
+    // Open and cheaply checked ways first; predicates only if none of those let them in
+    let mut denial = None;
+    let mut predicates = Vec::new();
+    for (from, direction) in ways_in {
+        let Some(lock) = state.db.get_exit_locks(&from)?.remove(&direction) else {
+            return Ok(None);
+        };
+        match exit_check(state, username, &lock, &from, &direction, target_room) {
+            LockCheck::Done(None) => return Ok(None),
+            LockCheck::Done(Some(refusal)) => {
+                denial.get_or_insert_with(|| exit_refusal(&refusal, &direction, target_room));
             }
+            LockCheck::Run(check) => predicates.push(((from, direction), check)),
         }
-
-        // Check target exists AND join atomically
-        let room = world.get_room_mut(target_room).ok_or_else(|| {
-            anyhow!(
-                "No room named '{}'. Use /create {} to make one.",
-                target_room,
-                target_room
-            )
-        })?;
-        room.add_user(username.to_string());
-        if room.buffer_id.is_none() {
-            room.set_buffer_id(buffer.id.clone());
+    }
+    for ((_, direction), refusal) in run_predicates(predicates).await? {
+        match refusal {
+            None => return Ok(None),
+            Some(refusal) => {
+                denial.get_or_insert_with(|| exit_refusal(&refusal, &direction, target_room));
+            }
         }
     }
 
-    look(state, target_room).await
+    Ok(denial.map(|why| format!("Every way into {} is locked. {}", target_room, why)))
 }
 
-/// Leave room (return to lobby)
+/// Leave a room, if the user is in it
 pub async fn leave(state: &SharedState, username: &str, room_name: &str) -> Result<()> {
-    let mut world = state.world.write().await;
-    if let Some(room) = world.get_room_mut(room_name) {
-        room.remove_user(username);
+    if state.world.room_of(username)?.as_deref() == Some(room_name) {
+        state.world.leave(username)?;
     }
     Ok(())
 }
 
-/// Create a new room
+/// Create a new room and move into it
 pub async fn create_room(
     state: &SharedState,
     username: &str,
     room_name: &str,
-    current_room: Option<&str>,
 ) -> Result<RoomSummary> {
     // Validate name
     if !room_name
@@ -709,43 +793,114 @@ pub async fn create_room(
         ));
     }
 
-    // DB write first — if we crash after this, there's a room in DB with no
-    // in-memory state, which is fine (it'll be loaded on next startup).
-    // The reverse (in-memory ghost room with no DB row) is worse.
+    if state.db.get_room_by_name(room_name)?.is_some() {
+        return Err(anyhow!(
+            "Room '{}' already exists. Use /join {} to enter.",
+            room_name,
+            room_name
+        ));
+    }
     state.db.create_room(room_name, None)?;
+    state.db.set_room_owner(room_name, username)?;
 
-    // Single write lock: check doesn't exist, leave old room, create, join.
-    {
-        let mut world = state.world.write().await;
-        if world.get_room(room_name).is_some() {
-            return Err(anyhow!(
-                "Room '{}' already exists. Use /join {} to enter.",
-                room_name,
-                room_name
-            ));
-        }
+    join(state, username, room_name).await
+}
 
-        if let Some(current) = current_room {
-            if let Some(room) = world.get_room_mut(current) {
-                room.remove_user(username);
-            }
-        }
+/// Create a room set up from a template
+///
+/// The template is checked before anything is created.
+pub async fn create_room_from_template(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    template: &str,
+) -> Result<(RoomSummary, BlueprintReport)> {
+    if state.db.get_template(template)?.is_none() {
+        return Err(anyhow!(
+            "No template named '{}'. /template lists them.",
+            template
+        ));
+    }
+    create_room(state, username, room_name).await?;
+    let report = apply_template(state, username, room_name, template).await?;
+    Ok((look(state, room_name).await?, report))
+}
+
+/// Set a room up from a template
+///
+/// Parts of the blueprint that no longer apply are listed in the report.
+pub async fn apply_template(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    template: &str,
+) -> Result<BlueprintReport> {
+    state.db.ensure_room_writable(room_name)?;
+    let (_, blueprint) = state
+        .db
+        .get_template(template)?
+        .ok_or_else(|| anyhow!("No template named '{}'. /template lists them.", template))?;
+    let agent_id = state.db.get_agent_by_name(username)?.map(|a| a.id);
+    let report = state
+        .db
+        .apply_blueprint(room_name, &blueprint, agent_id.as_deref(), username)?;
 
-        world.create_room(room_name.to_string());
-        if let Some(room) = world.get_room_mut(room_name) {
-            room.add_user(username.to_string());
+    tracing::info!(
+        room = room_name,
+        template,
+        user = username,
+        "applied room template"
+    );
+    Ok(report)
+}
+
+/// Save a room as a template, replacing an older template of that name
+///
+/// Only whoever saved a template, or an admin, can replace it.
+pub async fn save_template(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    template: &str,
+) -> Result<RoomBlueprint> {
+    if template.is_empty()
+        || !template
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(anyhow!(
+            "Template name can only contain letters, numbers, dashes, and underscores."
+        ));
+    }
+    if let Some((existing, _)) = state.db.get_template(template)? {
+        if let Some(owner) = existing.created_by.filter(|o| o != username) {
+            if !state.config.is_admin(username) {
+                return Err(anyhow!(
+                    "template:{} belongs to {}. Pick another name.",
+                    template,
+                    owner
+                ));
+            }
         }
     }
 
-    look(state, room_name).await
+    let blueprint = state.db.room_blueprint(room_name)?;
+    state
+        .db
+        .save_template(template, &blueprint, Some(username))?;
+    Ok(blueprint)
 }
 
 /// Fork a room (copy context)
+///
+/// The fork shares the source's chat history up to `at_row` (a row
+/// reference, see `Database::resolve_row_ref`), or all of it when None.
 pub async fn fork_room(
     state: &SharedState,
     username: &str,
     source_room: &str,
     new_room: &str,
+    at_row: Option<&str>,
 ) -> Result<RoomSummary> {
     // Validate name
     if !new_room
@@ -757,34 +912,236 @@ pub async fn fork_room(
         ));
     }
 
-    // Check target doesn't exist
-    {
-        let world = state.world.read().await;
-        if world.get_room(new_room).is_some() {
-            return Err(anyhow!("Room '{}' already exists.", new_room));
-        }
+    if state.db.get_room_by_name(new_room)?.is_some() {
+        return Err(anyhow!("Room '{}' already exists.", new_room));
     }
 
-    // Fork in database
-    state.db.fork_room(source_room, new_room)?;
+    state.db.fork_room_at(source_room, new_room, at_row)?;
+    state.db.set_room_owner(new_room, username)?;
+    if let Some(zone) = state.db.room_zone(source_room)? {
+        state.db.set_room_zone(new_room, Some(&zone.name))?;
+    }
 
-    // Create in memory and join
-    {
-        let mut world = state.world.write().await;
-        world.create_room(new_room.to_string());
+    join(state, username, new_room).await
+}
+
+/// Most transcript text sent to a model when summarizing a fork; the
+/// oldest messages are left out past this
+const MAX_SUMMARY_TRANSCRIPT: usize = 32 * 1024;
+
+/// A checked merge, ready to apply
+pub struct PreparedMerge {
+    pub plan: MergePlan,
+    pub mode: MergeMode,
+    agent_id: String,
+    /// Who summarizes and the prompt to send them (summary mode)
+    summary: Option<(ModelHandle, String)>,
+}
+
+/// Merge a fork back into the room it was forked from
+///
+/// Summary mode asks `model` to summarize the fork's messages; without one
+/// it uses the model that spoke last in the fork, then any available model.
+pub async fn merge_room(
+    state: &SharedState,
+    username: &str,
+    fork: &str,
+    mode: MergeMode,
+    model: Option<&str>,
+) -> Result<(MergePlan, MergeOutcome)> {
+    let prepared = prepare_merge(state, username, fork, mode, model)?;
+    finish_merge(state, prepared).await
+}
+
+/// Check a merge and pick its summarizer without calling any model
+///
+/// Only the parent room's owner or an admin may merge into it.
+pub fn prepare_merge(
+    state: &SharedState,
+    username: &str,
+    fork: &str,
+    mode: MergeMode,
+    model: Option<&str>,
+) -> Result<PreparedMerge> {
+    let plan = state.db.plan_merge(fork)?;
+    require_room_owner(state, username, &plan.parent_room)?;
+    state.db.ensure_room_writable(&plan.parent_room)?;
+    let agent = state.db.get_or_create_human_agent(username)?;
+
+    let summary = if mode == MergeMode::Summary {
+        Some(summary_request(state, &plan, model)?)
+    } else {
+        None
+    };
 
-        // Leave source room
-        if let Some(room) = world.get_room_mut(source_room) {
-            room.remove_user(username);
+    Ok(PreparedMerge {
+        plan,
+        mode,
+        agent_id: agent.id,
+        summary,
+    })
+}
+
+/// Summarize (in summary mode) and apply a prepared merge
+pub async fn finish_merge(
+    state: &SharedState,
+    prepared: PreparedMerge,
+) -> Result<(MergePlan, MergeOutcome)> {
+    let PreparedMerge {
+        plan,
+        mode,
+        agent_id,
+        summary,
+    } = prepared;
+
+    let summary = match summary {
+        Some((handle, prompt)) => {
+            let text = state.llm.chat(&handle, &prompt).await?;
+            let author = state.db.get_or_create_model_agent(&handle.short_name)?;
+            Some((author.id, text))
         }
+        None => None,
+    };
+
+    // The parent may have been archived while the model was summarizing
+    state.db.ensure_room_writable(&plan.parent_room)?;
+    let outcome = state.db.apply_merge(
+        &plan,
+        mode,
+        &agent_id,
+        summary.as_ref().map(|(a, t)| (a.as_str(), t.as_str())),
+    )?;
+
+    Ok((plan, outcome))
+}
 
-        // Join new room
-        if let Some(room) = world.get_room_mut(new_room) {
-            room.add_user(username.to_string());
+/// The model to summarize a fork with and the prompt to send it
+fn summary_request(
+    state: &SharedState,
+    plan: &MergePlan,
+    model: Option<&str>,
+) -> Result<(ModelHandle, String)> {
+    let rows = plan.rows_for(MergeMode::Summary);
+    if rows.is_empty() {
+        return Err(anyhow!(
+            "'{}' has no messages to summarize.",
+            plan.fork_room
+        ));
+    }
+
+    // Newest messages first, until the transcript is full
+    let mut lines = Vec::new();
+    let mut size = 0;
+    let mut last_model = None;
+    for row in rows.iter().rev() {
+        let author = match &row.source_agent_id {
+            Some(id) => state
+                .db
+                .get_agent(id)?
+                .map(|a| a.name)
+                .unwrap_or_else(|| "unknown".to_string()),
+            None => "system".to_string(),
+        };
+        if last_model.is_none() && row.content_method == "message.model" {
+            last_model = Some(author.clone());
+        }
+        let line = format!("{}: {}\n", author, row.content.as_deref().unwrap_or(""));
+        if size + line.len() > MAX_SUMMARY_TRANSCRIPT {
+            lines.push("(earlier messages left out)\n".to_string());
+            break;
         }
+        size += line.len();
+        lines.push(line);
+    }
+    lines.reverse();
+    let transcript = lines.concat();
+
+    let handle = match model {
+        Some(name) => state
+            .models
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown model '{}'.", name))?,
+        None => {
+            let mut available = state.models.available();
+            available.sort_by(|a, b| a.short_name.cmp(&b.short_name));
+            last_model
+                .and_then(|name| state.models.get(&name).filter(|m| m.available))
+                .or_else(|| available.first().copied())
+                .cloned()
+                .ok_or_else(|| anyhow!("No models available to summarize with."))?
+        }
+    };
+
+    let prompt = format!(
+        "This conversation happened in '{}', a fork of the room '{}'. \
+         Summarize it for the people in '{}': lead with conclusions and decisions, \
+         then open questions. Keep it under 200 words.\n\n{}",
+        plan.fork_room, plan.parent_room, plan.parent_room, transcript
+    );
+    Ok((handle, prompt))
+}
+
+/// Rooms that are part of the world's skeleton and can't be archived or deleted
+const PROTECTED_ROOMS: &[&str] = &["lobby", "home"];
+
+/// Archive a room (read-only, hidden from room lists) or reopen it
+///
+/// Only the room's owner or an admin may do either.
+pub async fn set_room_archived(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    archived: bool,
+) -> Result<()> {
+    if archived && PROTECTED_ROOMS.contains(&room_name) {
+        return Err(anyhow!("{} can't be archived.", room_name));
+    }
+    if state.db.get_room_by_name(room_name)?.is_none() {
+        return Err(anyhow!("Room '{}' not found.", room_name));
+    }
+    require_room_owner(state, username, room_name)?;
+    if state.db.is_room_archived(room_name)? == archived {
+        return Err(anyhow!(
+            "{} is {} archived.",
+            room_name,
+            if archived { "already" } else { "not" }
+        ));
+    }
+    state.db.set_room_archived(room_name, archived)?;
+    tracing::info!(room = room_name, archived, "room archive state changed");
+    Ok(())
+}
+
+/// Delete a room and everything in it
+///
+/// Only the room's owner or an admin may delete it, and only once everyone
+/// has left. Exits in other rooms that led here are removed and reported.
+pub async fn delete_room(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+) -> Result<crate::db::rooms::RoomDeletion> {
+    if PROTECTED_ROOMS.contains(&room_name) {
+        return Err(anyhow!("{} can't be deleted.", room_name));
+    }
+    let room = state
+        .db
+        .get_room_by_name(room_name)?
+        .ok_or_else(|| anyhow!("Room '{}' not found.", room_name))?;
+
+    let owner = state.db.get_room_owner(room_name)?;
+    if owner.as_deref() != Some(username) && !state.config.is_admin(username) {
+        return Err(match owner {
+            Some(owner) => anyhow!("Only {} or an admin can delete {}.", owner, room_name),
+            None => anyhow!("Only an admin can delete {}.", room_name),
+        });
     }
 
-    look(state, new_room).await
+    // Nobody can walk in while the delete runs
+    state
+        .world
+        .while_empty(room_name, || state.db.delete_room(&room.id))
 }
 
 /// Navigate via exit
@@ -797,7 +1154,13 @@ pub async fn go(
     let exits = state.db.get_exits(current_room)?;
 
     match exits.get(direction) {
-        Some(target) => join(state, username, Some(current_room), target).await,
+        Some(target) => {
+            if let Some(why) = exit_denial(state, username, current_room, direction, target).await?
+            {
+                return Err(anyhow!(why));
+            }
+            enter(state, username, target).await
+        }
         None => {
             if exits.is_empty() {
                 Err(anyhow!("No exits from this room."))
@@ -817,30 +1180,1323 @@ pub async fn go(
     }
 }
 
-/// Dig an exit
-pub async fn dig(
+/// Only the room's owner or an admin may change who gets through it
+fn require_room_owner(state: &SharedState, username: &str, room_name: &str) -> Result<()> {
+    let owner = state.db.get_room_owner(room_name)?;
+    if owner.as_deref() == Some(username) || state.config.is_admin(username) {
+        return Ok(());
+    }
+    Err(match owner {
+        Some(owner) => anyhow!("Only {} or an admin can change {}.", owner, room_name),
+        None => anyhow!("Only an admin can change {}.", room_name),
+    })
+}
+
+/// Why `username` can't take an exit, or None when they can
+pub async fn exit_denial(
     state: &SharedState,
+    username: &str,
     from_room: &str,
     direction: &str,
-    to_room: &str,
-) -> Result<String> {
-    // Create exit
-    state.db.add_exit(from_room, direction, to_room)?;
+    target_room: &str,
+) -> Result<Option<String>> {
+    let Some(lock) = state.db.get_exit_locks(from_room)?.remove(direction) else {
+        return Ok(None);
+    };
+    let refusal = exit_check(state, username, &lock, from_room, direction, target_room)
+        .finish()
+        .await?;
+    Ok(refusal.map(|refusal| exit_refusal(&refusal, direction, target_room)))
+}
 
-    // Create reverse exit
-    let reverse = match direction {
-        "north" => "south",
-        "south" => "north",
-        "east" => "west",
-        "west" => "east",
-        "up" => "down",
-        "down" => "up",
-        "in" => "out",
-        "out" => "in",
-        _ => "back",
+/// Capabilities of a user's agent, none if the lookup fails
+fn agent_capabilities(state: &SharedState, username: &str) -> Vec<String> {
+    state
+        .db
+        .get_agent_by_name(username)
+        .ok()
+        .flatten()
+        .map(|agent| agent.capabilities)
+        .unwrap_or_default()
+}
+
+/// Why a lock turns someone away, for each caller to put in its own words
+enum Refusal {
+    Closed,
+    NotMember,
+    MissingCapability(String),
+    /// The predicate's thing is gone or has no code
+    MissingCheck(String),
+    /// The predicate said no, with its reason if it gave one
+    Denied(Option<String>),
+    /// The predicate failed to run: its thing and the error
+    Failed(String, String),
+}
+
+/// A lock rule's answer, or the predicate that has to run to get one
+enum LockCheck {
+    Done(Option<Refusal>),
+    Run(PendingPredicate),
+}
+
+impl LockCheck {
+    /// The refusal, running the predicate on the blocking pool if need be
+    async fn finish(self) -> Result<Option<Refusal>> {
+        match self {
+            LockCheck::Done(refusal) => Ok(refusal),
+            LockCheck::Run(check) => Ok(run_predicates(vec![((), check)])
+                .await?
+                .pop()
+                .and_then(|(_, refusal)| refusal)),
+        }
+    }
+}
+
+/// Apply one lock rule. `is_member` and `predicate` are only called for the
+/// rules that need them; predicates are handed back to run off the async
+/// workers.
+fn lock_refusal(
+    state: &SharedState,
+    username: &str,
+    lock: &ExitLock,
+    is_member: impl FnOnce() -> bool,
+    predicate: impl FnOnce(&str) -> PendingPredicate,
+) -> LockCheck {
+    LockCheck::Done(match lock {
+        ExitLock::Closed => Some(Refusal::Closed),
+        ExitLock::Members => (!is_member()).then_some(Refusal::NotMember),
+        ExitLock::Capability(cap) => (!agent_capabilities(state, username).contains(cap))
+            .then(|| Refusal::MissingCapability(cap.clone())),
+        ExitLock::Predicate(thing) => return LockCheck::Run(predicate(thing)),
+    })
+}
+
+/// Apply one exit lock. Lookups that fail count as a denial.
+fn exit_check(
+    state: &SharedState,
+    username: &str,
+    lock: &ExitLock,
+    from_room: &str,
+    direction: &str,
+    target_room: &str,
+) -> LockCheck {
+    if state.config.is_admin(username) {
+        return LockCheck::Done(None);
+    }
+    let is_member = || {
+        let owner = state.db.get_room_owner(target_room).ok().flatten();
+        let members = state.db.get_room_members(target_room).unwrap_or_default();
+        owner.as_deref() == Some(username) || members.iter().any(|m| m == username)
     };
+    lock_refusal(state, username, lock, is_member, |thing| {
+        pending_predicate(state, username, thing, from_room, direction, target_room)
+    })
+}
 
-    state.db.add_exit(to_room, reverse, from_room)?;
+/// Word a refusal for the exit going `direction` to `target_room`
+fn exit_refusal(refusal: &Refusal, direction: &str, target_room: &str) -> String {
+    match refusal {
+        Refusal::Closed => format!("The way {} is closed.", direction),
+        Refusal::NotMember => format!(
+            "The way {} is for members of {} only.",
+            direction, target_room
+        ),
+        Refusal::MissingCapability(cap) => {
+            format!("The way {} needs the '{}' capability.", direction, cap)
+        }
+        Refusal::MissingCheck(thing) => format!(
+            "The way {} is locked, and its check {} is missing.",
+            direction, thing
+        ),
+        Refusal::Denied(Some(reason)) => format!("The way {} is locked: {}", direction, reason),
+        Refusal::Denied(None) => format!("The way {} is locked.", direction),
+        Refusal::Failed(thing, e) => {
+            format!("The way {} is locked ({} failed: {}).", direction, thing, e)
+        }
+    }
+}
 
-    Ok(reverse.to_string())
+/// A predicate lock with everything it needs to run
+struct PendingPredicate {
+    thing: String,
+    /// None when the predicate's thing is gone or has no code
+    code: Option<String>,
+    args: serde_json::Value,
+}
+
+impl PendingPredicate {
+    /// Look up the predicate's code to run against `args`
+    fn new(state: &SharedState, thing: &str, args: serde_json::Value) -> Self {
+        let code = state
+            .db
+            .get_thing_by_qualified_name(thing)
+            .ok()
+            .flatten()
+            .and_then(|t| t.code);
+        Self {
+            thing: thing.to_string(),
+            code,
+            args,
+        }
+    }
+
+    /// Run the predicate, returning why it refuses, if it does
+    fn run(&self) -> Option<Refusal> {
+        let Some(code) = &self.code else {
+            return Some(Refusal::MissingCheck(self.thing.clone()));
+        };
+        match predicate::check(code, &self.thing, &self.args) {
+            Ok(Verdict::Allow) => None,
+            Ok(Verdict::Deny(reason)) => Some(Refusal::Denied(reason)),
+            Err(e) => {
+                tracing::warn!(thing = %self.thing, args = %self.args, "lock check failed: {:#}", e);
+                Some(Refusal::Failed(self.thing.clone(), format!("{:#}", e)))
+            }
+        }
+    }
+}
+
+/// Look up what an exit's predicate lock needs to run
+fn pending_predicate(
+    state: &SharedState,
+    username: &str,
+    thing: &str,
+    from_room: &str,
+    direction: &str,
+    target_room: &str,
+) -> PendingPredicate {
+    let args = serde_json::json!({
+        "user": username,
+        "from": from_room,
+        "to": target_room,
+        "direction": direction,
+        "capabilities": agent_capabilities(state, username),
+        "members": state.db.get_room_members(target_room).unwrap_or_default(),
+    });
+    PendingPredicate::new(state, thing, args)
+}
+
+/// Run predicate locks on the blocking pool, returning each one's key with
+/// its refusal
+///
+/// A predicate can run for a while, so they never run on the async workers.
+async fn run_predicates<K: Send + 'static>(
+    checks: Vec<(K, PendingPredicate)>,
+) -> Result<Vec<(K, Option<Refusal>)>> {
+    if checks.is_empty() {
+        return Ok(Vec::new());
+    }
+    let results = tokio::task::spawn_blocking(move || {
+        checks
+            .into_iter()
+            .map(|(key, check)| (key, check.run()))
+            .collect()
+    })
+    .await?;
+    Ok(results)
+}
+
+/// A locked exit as /exits shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct LockedExit {
+    pub target: String,
+    /// The rule as stored, e.g. `capability:deploy`
+    pub rule: String,
+    pub hint: String,
+    /// Whether this user gets through
+    pub open: bool,
+}
+
+/// A room's locked exits, and whether `username` can take each one
+pub async fn locked_exits(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+) -> Result<BTreeMap<String, LockedExit>> {
+    let exits = state.db.get_exits(room_name)?;
+    let mut locked = BTreeMap::new();
+    let mut predicates = Vec::new();
+    for (direction, lock) in state.db.get_exit_locks(room_name)? {
+        let Some(target) = exits.get(&direction).cloned() else {
+            continue;
+        };
+        let open = match exit_check(state, username, &lock, room_name, &direction, &target) {
+            LockCheck::Done(refusal) => refusal.is_none(),
+            LockCheck::Run(check) => {
+                predicates.push((direction.clone(), check));
+                false
+            }
+        };
+        locked.insert(
+            direction,
+            LockedExit {
+                rule: lock.to_string(),
+                hint: lock.hint(&target),
+                target,
+                open,
+            },
+        );
+    }
+    for (direction, refusal) in run_predicates(predicates).await? {
+        if let Some(exit) = locked.get_mut(&direction) {
+            exit.open = refusal.is_none();
+        }
+    }
+    Ok(locked)
+}
+
+/// Lock an exit with a rule, or unlock it with None
+pub async fn lock_exit(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    direction: &str,
+    lock: Option<&ExitLock>,
+) -> Result<()> {
+    if !state.db.get_exits(room_name)?.contains_key(direction) {
+        return Err(anyhow!("No exit '{}' from {}.", direction, room_name));
+    }
+    require_room_owner(state, username, room_name)?;
+    state.db.ensure_room_writable(room_name)?;
+    if let Some(ExitLock::Predicate(thing)) = lock {
+        let found = state.db.get_thing_by_qualified_name(thing)?;
+        if !found.is_some_and(|t| t.code.is_some()) {
+            return Err(anyhow!("{} isn't a thing with code.", thing));
+        }
+    }
+    state.db.set_exit_lock(room_name, direction, lock)
+}
+
+/// Add or remove a member of a room, returning the new member list
+pub async fn set_member(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    member: &str,
+    add: bool,
+) -> Result<Vec<String>> {
+    if state.db.get_room_by_name(room_name)?.is_none() {
+        return Err(anyhow!("Room '{}' not found.", room_name));
+    }
+    require_room_owner(state, username, room_name)?;
+    state.db.ensure_room_writable(room_name)?;
+    let mut members = state.db.get_room_members(room_name)?;
+    members.retain(|m| m != member);
+    if add {
+        members.push(member.to_string());
+    }
+    state.db.set_room_members(room_name, &members)?;
+    Ok(members)
+}
+
+// Zone operations
+
+/// A zone as /zone shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneInfo {
+    pub name: String,
+    pub owner: Option<String>,
+    pub vibe: Option<String>,
+    /// Access rule, e.g. `members`
+    pub access: Option<String>,
+    pub members: Vec<String>,
+    /// Things each room in the zone gets, by qualified name
+    pub equipment: Vec<String>,
+    pub rooms: Vec<String>,
+}
+
+fn zone_info(state: &SharedState, zone: Zone) -> Result<ZoneInfo> {
+    let rooms = state.db.zone_rooms(&zone.name)?;
+    Ok(ZoneInfo {
+        name: zone.name,
+        owner: zone.owner,
+        vibe: zone.settings.vibe,
+        access: zone.settings.access,
+        members: zone.settings.members,
+        equipment: zone
+            .settings
+            .equipment
+            .into_iter()
+            .map(|eq| eq.thing)
+            .collect(),
+        rooms,
+    })
+}
+
+/// List zones with their rooms
+pub async fn zones(state: &SharedState) -> Result<Vec<ZoneInfo>> {
+    state
+        .db
+        .list_zones()?
+        .into_iter()
+        .map(|zone| zone_info(state, zone))
+        .collect()
+}
+
+/// Look up one zone
+pub async fn get_zone(state: &SharedState, zone: &str) -> Result<ZoneInfo> {
+    let zone = state
+        .db
+        .get_zone(zone)?
+        .ok_or_else(|| anyhow!("No zone named '{}'. /zone lists them.", zone))?;
+    zone_info(state, zone)
+}
+
+/// Create a zone owned by `username`
+pub async fn create_zone(state: &SharedState, username: &str, zone: &str) -> Result<ZoneInfo> {
+    if zone.is_empty()
+        || !zone
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(anyhow!(
+            "Zone name can only contain letters, numbers, dashes, and underscores."
+        ));
+    }
+    let zone = state.db.create_zone(zone, username)?;
+    tracing::info!(zone = %zone.name, user = username, "zone created");
+    zone_info(state, zone)
+}
+
+/// Only the zone's owner or an admin may change its settings
+fn require_zone_owner(state: &SharedState, username: &str, zone: &str) -> Result<Zone> {
+    let zone = state
+        .db
+        .get_zone(zone)?
+        .ok_or_else(|| anyhow!("No zone named '{}'. /zone lists them.", zone))?;
+    if zone.owner.as_deref() == Some(username) || state.config.is_admin(username) {
+        return Ok(zone);
+    }
+    Err(match zone.owner {
+        Some(owner) => anyhow!("Only {} or an admin can change {}.", owner, zone.name),
+        None => anyhow!("Only an admin can change {}.", zone.name),
+    })
+}
+
+/// Change a zone's settings as its owner
+fn update_zone(
+    state: &SharedState,
+    username: &str,
+    zone: &str,
+    update: impl FnOnce(&mut ZoneSettings),
+) -> Result<ZoneSettings> {
+    let mut settings = require_zone_owner(state, username, zone)?.settings;
+    update(&mut settings);
+    state.db.set_zone_settings(zone, &settings)?;
+    Ok(settings)
+}
+
+/// Set a zone's vibe, or clear it with None
+pub async fn set_zone_vibe(
+    state: &SharedState,
+    username: &str,
+    zone: &str,
+    vibe: Option<&str>,
+) -> Result<()> {
+    update_zone(state, username, zone, |s| s.vibe = vibe.map(String::from))?;
+    Ok(())
+}
+
+/// Guard a zone's rooms with a rule, or open them with None
+///
+/// The zone's owner and members always get in; the rule decides for
+/// everyone else.
+pub async fn set_zone_access(
+    state: &SharedState,
+    username: &str,
+    zone: &str,
+    lock: Option<&ExitLock>,
+) -> Result<()> {
+    if let Some(ExitLock::Predicate(thing)) = lock {
+        let found = state.db.get_thing_by_qualified_name(thing)?;
+        if !found.is_some_and(|t| t.code.is_some()) {
+            return Err(anyhow!("{} isn't a thing with code.", thing));
+        }
+    }
+    update_zone(state, username, zone, |s| {
+        s.access = lock.map(|l| l.to_string())
+    })?;
+    Ok(())
+}
+
+/// Add or remove a member of a zone, returning the new member list
+pub async fn set_zone_member(
+    state: &SharedState,
+    username: &str,
+    zone: &str,
+    member: &str,
+    add: bool,
+) -> Result<Vec<String>> {
+    let settings = update_zone(state, username, zone, |s| {
+        s.members.retain(|m| m != member);
+        if add {
+            s.members.push(member.to_string());
+        }
+    })?;
+    Ok(settings.members)
+}
+
+/// Add a thing to a zone's equipment, which the zone's rooms inherit
+///
+/// Returns how many rooms the zone has.
+pub async fn zone_equip(
+    state: &SharedState,
+    username: &str,
+    zone: &str,
+    thing: &str,
+    slot: Option<&str>,
+) -> Result<usize> {
+    let found = state
+        .db
+        .get_thing_by_qualified_name(thing)?
+        .ok_or_else(|| anyhow!("Thing '{}' not found.", thing))?;
+    let equip = BlueprintEquip {
+        thing: found.qualified_name.unwrap_or(found.id),
+        slot: slot.map(String::from),
+        config: None,
+        priority: 0.0,
+    };
+    update_zone(state, username, zone, |s| {
+        s.equipment
+            .retain(|eq| !(eq.thing == equip.thing && eq.slot == equip.slot));
+        s.equipment.push(equip.clone());
+    })?;
+    Ok(state.db.zone_rooms(zone)?.len())
+}
+
+/// Take a thing out of a zone's equipment
+///
+/// The zone's rooms stop having it, unless they equip it themselves.
+pub async fn zone_unequip(
+    state: &SharedState,
+    username: &str,
+    zone: &str,
+    thing: &str,
+) -> Result<()> {
+    let mut found = false;
+    update_zone(state, username, zone, |s| {
+        let before = s.equipment.len();
+        s.equipment.retain(|eq| eq.thing != thing);
+        found = s.equipment.len() != before;
+    })?;
+    if !found {
+        return Err(anyhow!("{} isn't part of zone {}.", thing, zone));
+    }
+    Ok(())
+}
+
+/// Put a room in a zone, or take it out with None
+///
+/// Adding a room takes owning it and belonging to the zone; taking one out
+/// can also be done by the zone's owner. Returns how many things the room
+/// inherits from its zone.
+pub async fn set_room_zone(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+    zone: Option<&str>,
+) -> Result<usize> {
+    if PROTECTED_ROOMS.contains(&room_name) {
+        return Err(anyhow!("{} can't be put in a zone.", room_name));
+    }
+    if state.db.get_room_by_name(room_name)?.is_none() {
+        return Err(anyhow!("Room '{}' not found.", room_name));
+    }
+    state.db.ensure_room_writable(room_name)?;
+    let admin = state.config.is_admin(username);
+
+    match zone {
+        Some(name) => {
+            require_room_owner(state, username, room_name)?;
+            let zone = state
+                .db
+                .get_zone(name)?
+                .ok_or_else(|| anyhow!("No zone named '{}'. /zone lists them.", name))?;
+            if !admin && !zone.is_member(username) {
+                return Err(anyhow!("Only members of {} can add rooms to it.", name));
+            }
+        }
+        None => {
+            let Some(current) = state.db.room_zone(room_name)? else {
+                return Err(anyhow!("{} isn't in a zone.", room_name));
+            };
+            if current.owner.as_deref() != Some(username) {
+                require_room_owner(state, username, room_name)?;
+            }
+        }
+    }
+
+    state.db.set_room_zone(room_name, zone)?;
+    tracing::info!(
+        room = room_name,
+        ?zone,
+        user = username,
+        "room zone changed"
+    );
+    Ok(state
+        .db
+        .room_zone(room_name)?
+        .map_or(0, |z| z.settings.equipment.len()))
+}
+
+/// Delete an empty zone
+pub async fn delete_zone(state: &SharedState, username: &str, zone: &str) -> Result<()> {
+    require_zone_owner(state, username, zone)?;
+    state.db.delete_zone(zone)
+}
+
+/// Who is in each of a zone's rooms, leaving out empty rooms
+pub async fn zone_who(state: &SharedState, zone: &str) -> Result<BTreeMap<String, Vec<String>>> {
+    let mut who = BTreeMap::new();
+    for room in state.db.zone_rooms(zone)? {
+        let present = state.world.users_in(&room)?;
+        if !present.is_empty() {
+            who.insert(room, present);
+        }
+    }
+    Ok(who)
+}
+
+/// Apply a room's zone access rule, or None when it has none for `username`
+///
+/// The zone's owner and members always get in. Returns the zone's name
+/// along with the check.
+fn zone_check(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+) -> Result<Option<(String, LockCheck)>> {
+    let Some(zone) = state.db.room_zone(room_name)? else {
+        return Ok(None);
+    };
+    let Some(lock) = zone.access() else {
+        return Ok(None);
+    };
+    if state.config.is_admin(username) || zone.is_member(username) {
+        return Ok(None);
+    }
+
+    let is_member = || zone.is_member(username);
+    let predicate = |thing: &str| {
+        let args = serde_json::json!({
+            "user": username,
+            "zone": zone.name,
+            "room": room_name,
+            "capabilities": agent_capabilities(state, username),
+            "members": zone.settings.members,
+        });
+        PendingPredicate::new(state, thing, args)
+    };
+    let check = lock_refusal(state, username, &lock, is_member, predicate);
+    Ok(Some((zone.name, check)))
+}
+
+/// Why `username` can't enter a room because of its zone, or None when they can
+pub async fn zone_denial(
+    state: &SharedState,
+    username: &str,
+    room_name: &str,
+) -> Result<Option<String>> {
+    let Some((zone, check)) = zone_check(state, username, room_name)? else {
+        return Ok(None);
+    };
+    let refusal = check.finish().await?;
+    Ok(refusal.map(|refusal| zone_refusal(&refusal, room_name, &zone)))
+}
+
+/// Word a refusal for entering `room` in `zone`
+fn zone_refusal(refusal: &Refusal, room: &str, zone: &str) -> String {
+    match refusal {
+        Refusal::Closed => format!("{} is in zone {}, which is closed.", room, zone),
+        Refusal::NotMember => format!(
+            "{} is in zone {}, which is for its members only.",
+            room, zone
+        ),
+        Refusal::MissingCapability(cap) => format!(
+            "{} is in zone {}, which needs the '{}' capability.",
+            room, zone, cap
+        ),
+        Refusal::MissingCheck(thing) => format!(
+            "{} is in zone {}, and its check {} is missing.",
+            room, zone, thing
+        ),
+        Refusal::Denied(Some(reason)) => format!("{} is in zone {}: {}", room, zone, reason),
+        Refusal::Denied(None) => format!("{} is in zone {}, which is locked.", room, zone),
+        Refusal::Failed(thing, e) => format!(
+            "{} is in zone {}, which is locked ({} failed: {}).",
+            room, zone, thing, e
+        ),
+    }
+}
+
+/// A room on the map around the current one
+#[derive(Debug, Clone, Serialize)]
+pub struct MapRoom {
+    pub name: String,
+    /// Exits away from the centre room
+    pub distance: usize,
+    pub exits: BTreeMap<String, String>,
+    pub users: Vec<String>,
+}
+
+/// Rooms within `radius` exits of a room, nearest first
+pub async fn map(state: &SharedState, room_name: &str, radius: usize) -> Result<Vec<MapRoom>> {
+    let graph = state.db.exit_graph()?;
+
+    let mut seen = HashSet::from([room_name.to_string()]);
+    let mut queue = VecDeque::from([(room_name.to_string(), 0)]);
+    let mut rooms = Vec::new();
+
+    while let Some((name, distance)) = queue.pop_front() {
+        let exits = graph.get(&name).cloned().unwrap_or_default();
+        if distance < radius {
+            for target in exits.values() {
+                if seen.insert(target.clone()) {
+                    queue.push_back((target.clone(), distance + 1));
+                }
+            }
+        }
+        let users = state.world.users_in(&name)?;
+        rooms.push(MapRoom {
+            name,
+            distance,
+            exits,
+            users,
+        });
+    }
+
+    Ok(rooms)
+}
+
+/// One step of a /travel trip
+#[derive(Debug, Clone, Serialize)]
+pub struct TravelHop {
+    pub direction: String,
+    pub room: String,
+}
+
+/// Where a /travel trip went and where it ended up
+#[derive(Debug, Clone, Serialize)]
+pub struct TravelSummary {
+    pub path: Vec<TravelHop>,
+    pub room: RoomSummary,
+}
+
+/// Walk the shortest exit path to a room
+///
+/// Locked exits the user can't take are routed around. The traveller enters
+/// each room on the way, so people there see them come and go.
+pub async fn travel(
+    state: &SharedState,
+    username: &str,
+    current_room: &str,
+    target_room: &str,
+) -> Result<TravelSummary> {
+    if current_room == target_room {
+        return Err(anyhow!("You're already in {}.", target_room));
+    }
+    // Route around exits this user can't take and zones they can't enter.
+    // The search only applies the cheap rules; predicate locks run on the
+    // blocking pool for the exits and zones of the path it finds, and a path
+    // through one that says no is searched again without it. Denials are
+    // keyed by (room, direction), with no direction for a room's zone.
+    let admin = state.config.is_admin(username);
+    // A room whose locks can't be read has no way out: failing closed
+    let mut locks: HashMap<String, Option<BTreeMap<String, ExitLock>>> = HashMap::new();
+    // Whether each zone lets them in, None when that takes a predicate
+    let mut zones_open: HashMap<String, Option<bool>> = HashMap::new();
+    let mut denied: HashSet<(String, Option<String>)> = HashSet::new();
+    let path = loop {
+        let passable = |room: &str, direction: &str, to: &str| {
+            let room_locks = locks.entry(room.to_string()).or_insert_with(|| {
+                match state.db.get_exit_locks(room) {
+                    Ok(locks) => Some(locks),
+                    Err(e) => {
+                        tracing::warn!(room, "can't read exit locks: {:#}", e);
+                        None
+                    }
+                }
+            });
+            let Some(room_locks) = room_locks else {
+                return false;
+            };
+            let exit_open = match room_locks.get(direction) {
+                Some(ExitLock::Predicate(_)) => {
+                    !denied.contains(&(room.to_string(), Some(direction.to_string())))
+                }
+                Some(lock) => matches!(
+                    exit_check(state, username, lock, room, direction, to),
+                    LockCheck::Done(None)
+                ),
+                None => true,
+            };
+            let zone_open = *zones_open.entry(to.to_string()).or_insert_with(|| {
+                match zone_check(state, username, to) {
+                    Ok(None) => Some(true),
+                    Ok(Some((_, LockCheck::Done(refusal)))) => Some(refusal.is_none()),
+                    Ok(Some((_, LockCheck::Run(_)))) => None,
+                    Err(_) => Some(false),
+                }
+            });
+            exit_open && zone_open.unwrap_or_else(|| !denied.contains(&(to.to_string(), None)))
+        };
+        let Some(path) = state
+            .db
+            .find_exit_path_where(current_room, target_room, passable)?
+        else {
+            break None;
+        };
+        if admin {
+            break Some(path);
+        }
+
+        let mut checks = Vec::new();
+        let mut from = current_room;
+        for (direction, room) in &path {
+            if let Some(ExitLock::Predicate(thing)) = locks
+                .get(from)
+                .and_then(|l| l.as_ref())
+                .and_then(|l| l.get(direction))
+            {
+                let check = pending_predicate(state, username, thing, from, direction, room);
+                checks.push(((from.to_string(), Some(direction.clone())), check));
+            }
+            if zones_open.get(room.as_str()) == Some(&None) {
+                if let Some((_, LockCheck::Run(check))) = zone_check(state, username, room)? {
+                    checks.push(((room.clone(), None), check));
+                }
+            }
+            from = room;
+        }
+        match run_predicates(checks)
+            .await?
+            .into_iter()
+            .find(|(_, why)| why.is_some())
+        {
+            Some((blocker, _)) => {
+                denied.insert(blocker);
+            }
+            None => break Some(path),
+        }
+    };
+    let Some(path) = path else {
+        // Every way there is locked: say what's in the way on the shortest one
+        if let Some(blocked) = state.db.find_exit_path(current_room, target_room)? {
+            let mut from = current_room;
+            for (direction, room) in &blocked {
+                let denial = match exit_denial(state, username, from, direction, room).await? {
+                    Some(why) => Some(why),
+                    None => zone_denial(state, username, room).await?,
+                };
+                if let Some(why) = denial {
+                    return Err(anyhow!(
+                        "Can't get to {}. In {}: {}",
+                        target_room,
+                        from,
+                        why
+                    ));
+                }
+                from = room;
+            }
+        }
+        return Err(anyhow!(
+            "No path from {} to {}. Use /map to see what's nearby.",
+            current_room,
+            target_room
+        ));
+    };
+
+    for (_, room) in &path {
+        state.world.enter(username, room)?;
+    }
+
+    let room = look(state, target_room).await?;
+    Ok(TravelSummary {
+        path: path
+            .into_iter()
+            .map(|(direction, room)| TravelHop { direction, room })
+            .collect(),
+        room,
+    })
+}
+
+/// Dig an exit
+pub async fn dig(
+    state: &SharedState,
+    from_room: &str,
+    direction: &str,
+    to_room: &str,
+) -> Result<String> {
+    // Both ends get an exit
+    state.db.ensure_room_writable(from_room)?;
+    state.db.ensure_room_writable(to_room)?;
+
+    // Create exit
+    state.db.add_exit(from_room, direction, to_room)?;
+
+    // Create reverse exit
+    let reverse = match direction {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        "up" => "down",
+        "down" => "up",
+        "in" => "out",
+        "out" => "in",
+        _ => "back",
+    };
+
+    state.db.add_exit(to_room, reverse, from_room)?;
+
+    Ok(reverse.to_string())
+}
+
+// Trash operations
+
+/// A deleted thing as /trash shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub name: String,
+    pub qualified_name: Option<String>,
+    pub kind: String,
+    pub deleted_by: Option<String>,
+    /// Unix timestamp ms
+    pub deleted_at: i64,
+}
+
+/// Deleted things from the user's inventory (`me`) or a room (`room`)
+pub async fn trash(
+    state: &SharedState,
+    username: &str,
+    target: &str,
+    room_name: Option<&str>,
+) -> Result<Vec<TrashEntry>> {
+    let parent = match target {
+        "" | "me" => state.db.ensure_agent_thing(username)?,
+        "room" => {
+            let room_name = room_name.ok_or_else(|| anyhow!("You're not in a room."))?;
+            state
+                .db
+                .get_room_by_name(room_name)?
+                .ok_or_else(|| anyhow!("Room '{}' not found.", room_name))?
+                .id
+        }
+        other => return Err(anyhow!("Unknown trash '{}'. Use me or room.", other)),
+    };
+
+    Ok(state
+        .db
+        .list_trash(&parent)?
+        .into_iter()
+        .map(|item| TrashEntry {
+            id: item.thing.id,
+            name: item.thing.name,
+            qualified_name: item.thing.qualified_name,
+            kind: item.thing.kind.as_str().to_string(),
+            deleted_by: item.deleted_by,
+            deleted_at: item.thing.deleted_at.unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Restore a deleted thing by qualified name or ID, optionally renamed
+///
+/// Whoever deleted or created it can restore it, as can an admin or anyone
+/// whose inventory it was in. Things whose parent is gone land in the
+/// restorer's inventory. A rename keeps the thing's namespace or moves it
+/// into the restorer's.
+pub async fn restore(
+    state: &SharedState,
+    username: &str,
+    name: &str,
+    rename: Option<&str>,
+) -> Result<Thing> {
+    let item = state
+        .db
+        .find_trashed(name)?
+        .ok_or_else(|| anyhow!("Nothing called '{}' in the trash.", name))?;
+    let inventory = state.db.ensure_agent_thing(username)?;
+
+    let allowed = state.config.is_admin(username)
+        || item.deleted_by.as_deref() == Some(username)
+        || item.thing.created_by.as_deref() == Some(username)
+        || item.thing.parent_id.as_deref() == Some(inventory.as_str());
+    if !allowed {
+        return Err(anyhow!(
+            "Only whoever deleted or created {} (or an admin) can restore it.",
+            name
+        ));
+    }
+    if let Some(rename) = rename {
+        let namespace = match rename.split_once(':') {
+            Some((ns, rest))
+                if !ns.is_empty() && !rest.is_empty() && !rename.contains(char::is_whitespace) =>
+            {
+                ns
+            }
+            _ => return Err(anyhow!("'{}' isn't a valid qualified name.", rename)),
+        };
+        // Keep it where it was, or move it into the restorer's own namespace
+        let original = item
+            .thing
+            .qualified_name
+            .as_deref()
+            .and_then(|q| q.split_once(':'))
+            .map(|(ns, _)| ns);
+        if namespace != username && Some(namespace) != original {
+            let namespaces = match original {
+                Some(ns) if ns != username => format!("{}: or {}:", ns, username),
+                _ => format!("{}:", username),
+            };
+            return Err(anyhow!(
+                "{} can only be restored under {}, not {}:.",
+                name,
+                namespaces,
+                namespace
+            ));
+        }
+    }
+
+    let thing = state
+        .db
+        .restore_from_trash(&item.thing.id, rename, &inventory)?;
+    tracing::info!(thing = %thing.id, user = username, "thing restored from trash");
+    Ok(thing)
+}
+
+// Bundle operations
+
+/// A bundle written out for /bundle export
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleExport {
+    pub text: String,
+    pub things: usize,
+    pub equipment: usize,
+    /// Where it was saved, if a file was given
+    pub path: Option<String>,
+}
+
+/// A file in the bundles directory; plain names only, no paths
+fn bundle_path(state: &SharedState, file: &str) -> Result<PathBuf> {
+    if file.is_empty()
+        || file.starts_with('.')
+        || !file
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(anyhow!(
+            "Bundle file names can only contain letters, numbers, dashes, underscores, and dots."
+        ));
+    }
+    Ok(PathBuf::from(&state.config.bundles_dir).join(file))
+}
+
+/// The agent whose inventory holds a thing (or who is the thing), if any
+fn holding_agent(state: &SharedState, thing: &Thing) -> Result<Option<String>> {
+    let mut seen = HashSet::new();
+    let mut current = Some(thing.clone());
+    while let Some(thing) = current {
+        if thing.kind == ThingKind::Agent {
+            return Ok(Some(thing.name));
+        }
+        if !seen.insert(thing.id.clone()) {
+            break;
+        }
+        current = match thing.parent_id {
+            Some(parent) => state.db.get_thing(&parent)?,
+            None => None,
+        };
+    }
+    Ok(None)
+}
+
+/// Bundle a thing (by qualified name or ID), the user's inventory (`me`)
+/// or what's in the current room (`room`)
+///
+/// Things in someone else's inventory can only be bundled by whoever made
+/// them or an admin. With a file name the bundle is also saved in the
+/// bundles directory; a name without an extension gets one for `format`,
+/// and one with an extension picks the format. An existing file is only
+/// replaced with `force`.
+#[allow(clippy::too_many_arguments)]
+pub async fn export_bundle(
+    state: &SharedState,
+    username: &str,
+    target: &str,
+    room_name: Option<&str>,
+    format: BundleFormat,
+    with_equipment: bool,
+    file: Option<&str>,
+    force: bool,
+) -> Result<BundleExport> {
+    let root = match target {
+        "" | "me" => state.db.ensure_agent_thing(username)?,
+        "room" => {
+            let room_name = room_name.ok_or_else(|| anyhow!("You're not in a room."))?;
+            state
+                .db
+                .get_room_by_name(room_name)?
+                .ok_or_else(|| anyhow!("Room '{}' not found.", room_name))?
+                .id
+        }
+        name => {
+            let thing = match state.db.get_thing_by_qualified_name(name)? {
+                Some(thing) => thing,
+                None => state
+                    .db
+                    .get_thing(name)?
+                    .filter(|t| !t.is_deleted())
+                    .ok_or_else(|| anyhow!("No thing called '{}'.", name))?,
+            };
+            let allowed =
+                state.config.is_admin(username) || thing.created_by.as_deref() == Some(username);
+            if !allowed {
+                if let Some(holder) = holding_agent(state, &thing)?.filter(|h| h != username) {
+                    return Err(anyhow!(
+                        "{} is in {}'s inventory. Only they (or an admin) can bundle it.",
+                        name,
+                        holder
+                    ));
+                }
+            }
+            thing.id
+        }
+    };
+    let bundle = state.db.export_bundle(&root, with_equipment)?;
+
+    let (format, path) = match file {
+        Some(file) => {
+            let mut path = bundle_path(state, file)?;
+            if path.extension().is_none() {
+                path.set_extension(format.extension());
+            }
+            if path.exists() && !force {
+                return Err(anyhow!(
+                    "{} already exists. Use --force to replace it.",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                ));
+            }
+            (BundleFormat::from_path(&path), Some(path))
+        }
+        None => (format, None),
+    };
+    let text = bundle.render(format)?;
+    if let Some(ref path) = path {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, &text)
+            .map_err(|e| anyhow!("Couldn't write {}: {}", path.display(), e))?;
+        tracing::info!(user = username, target, path = %path.display(), "exported bundle");
+    }
+
+    Ok(BundleExport {
+        text,
+        things: bundle.count(),
+        equipment: bundle.equipment.len(),
+        path: path.map(|p| p.to_string_lossy().into_owned()),
+    })
+}
+
+/// Read a bundle file from the bundles directory
+///
+/// A name without an extension finds `<name>.toml` or `<name>.json`.
+pub async fn read_bundle_file(state: &SharedState, file: &str) -> Result<String> {
+    let path = bundle_path(state, file)?;
+    let candidates = if path.extension().is_some() {
+        vec![path]
+    } else {
+        vec![path.with_extension("toml"), path.with_extension("json")]
+    };
+    for path in &candidates {
+        if path.exists() {
+            return std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Couldn't read {}: {}", path.display(), e));
+        }
+    }
+    Err(anyhow!(
+        "No bundle called '{}'. /bundle list shows them.",
+        file
+    ))
+}
+
+/// Bundle files in the bundles directory, by name
+pub async fn bundles(state: &SharedState) -> Result<Vec<String>> {
+    let dir = PathBuf::from(&state.config.bundles_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<String> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".toml") || name.ends_with(".json"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Import a bundle into the user's inventory
+///
+/// With `equip`, the bundle's recipes are applied: room recipes in the
+/// current room and agent recipes for the user. Qualified names move into
+/// the user's namespace unless an admin imports. Taken qualified names get
+/// a suffix unless `replace` is set, which trashes the current holders and
+/// needs an admin.
+pub async fn import_bundle(
+    state: &SharedState,
+    username: &str,
+    text: &str,
+    replace: bool,
+    equip: bool,
+    room_name: Option<&str>,
+) -> Result<ImportReport> {
+    if replace && !state.config.is_admin(username) {
+        return Err(anyhow!(
+            "Only an admin can replace things. Without replacing, taken names get a suffix."
+        ));
+    }
+    let bundle = Bundle::parse(text)?;
+
+    // Only admins install into other namespaces
+    let namespace = (!state.config.is_admin(username)).then(|| username.to_string());
+    let mut options = ImportOptions {
+        parent_id: state.db.ensure_agent_thing(username)?,
+        created_by: Some(username.to_string()),
+        namespace,
+        replace,
+        ..Default::default()
+    };
+    if equip {
+        if let Some(room_name) = room_name {
+            options.equip_room = state.db.get_room_by_name(room_name)?.map(|r| r.id);
+        }
+        options.equip_agent = state.db.get_agent_by_name(username)?.map(|a| a.id);
+    }
+    let report = state.db.import_bundle(&bundle, &options)?;
+    tracing::info!(
+        user = username,
+        things = report.things,
+        equipped = report.equipped,
+        "imported bundle"
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::Database;
+    use crate::llm::LlmClient;
+    use crate::lua::LuaReloadSender;
+    use crate::mcp::McpManager;
+    use crate::model::ModelRegistry;
+    use crate::world::World;
+
+    fn test_state() -> Result<SharedState> {
+        let db = Arc::new(Database::in_memory()?);
+        Ok(SharedState {
+            world: Arc::new(World::new(db.clone())),
+            db,
+            config: Config::default(),
+            llm: Arc::new(LlmClient::new()?),
+            models: Arc::new(ModelRegistry::new()),
+            mcp: Arc::new(McpManager::new()),
+            lua_reload: LuaReloadSender::new(),
+            shutdown: Arc::new(crate::shutdown::Shutdown::new()),
+            sessions: Arc::new(crate::ssh::SessionStore::new(std::time::Duration::ZERO)),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_join_needs_an_open_way_in() -> Result<()> {
+        let state = test_state()?;
+        for room in ["hall", "side", "vault", "island"] {
+            state.db.create_room(room, None)?;
+        }
+        state.db.add_exit("hall", "down", "vault")?;
+        state
+            .db
+            .set_exit_lock("hall", "down", Some(&ExitLock::Closed))?;
+
+        // The only way in is closed, so /join can't skip it
+        let err = join(&state, "alice", "vault").await.unwrap_err();
+        assert!(err.to_string().contains("The way down is closed"));
+        assert!(state.world.room_of("alice")?.is_none());
+
+        // Rooms nothing leads into stay open
+        join(&state, "alice", "island").await?;
+
+        // Any open way in is enough
+        state.db.add_exit("side", "in", "vault")?;
+        join(&state, "alice", "vault").await?;
+        assert_eq!(state.world.room_of("alice")?.as_deref(), Some("vault"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_archived_room_rejects_changes() -> Result<()> {
+        let state = test_state()?;
+        state.db.create_room("attic", None)?;
+        set_vibe(&state, "attic", "dusty").await?;
+
+        state.db.set_room_archived("attic", true)?;
+        let err = set_vibe(&state, "attic", "tidy").await.unwrap_err();
+        assert!(err.to_string().contains("read-only"));
+        assert_eq!(state.db.get_vibe("attic")?.as_deref(), Some("dusty"));
+
+        state.db.set_room_archived("attic", false)?;
+        set_vibe(&state, "attic", "tidy").await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_rename_keeps_namespaces() -> Result<()> {
+        let state = test_state()?;
+        let mut fish = Thing::data("fish", "blub");
+        fish.qualified_name = Some("amy:fish".to_string());
+        fish.created_by = Some("amy".to_string());
+        state.db.insert_thing(&fish)?;
+        state.db.trash_thing(&fish.id, "amy")?;
+
+        for taken in ["bob:fish", "sshwarma:fish", "fish"] {
+            assert!(restore(&state, "amy", "amy:fish", Some(taken))
+                .await
+                .is_err());
+        }
+        let thing = restore(&state, "amy", "amy:fish", Some("amy:trout")).await?;
+        assert_eq!(thing.qualified_name.as_deref(), Some("amy:trout"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_stays_out_of_other_inventories() -> Result<()> {
+        let state = test_state()?;
+        let inventory = state.db.ensure_agent_thing("alice")?;
+        let mut note = Thing::data("note", "for alice").with_parent(&inventory);
+        note.qualified_name = Some("alice:note".to_string());
+        state.db.insert_thing(&note)?;
+
+        let export = |user: &'static str| {
+            export_bundle(
+                &state,
+                user,
+                "alice:note",
+                None,
+                BundleFormat::Toml,
+                false,
+                None,
+                false,
+            )
+        };
+        let err = export("bob").await.unwrap_err();
+        assert!(err.to_string().contains("alice's inventory"));
+        assert_eq!(export("alice").await?.things, 1);
+
+        Ok(())
+    }
 }
@@ -7,7 +7,10 @@
 //! ```text
 //! ~/.local/share/sshwarma/     (XDG_DATA_HOME)
 //! ├── sshwarma.db
-//! └── host_key
+//! ├── host_key
+//! ├── backups/
+//! ├── bundles/
+//! └── recordings/
 //!
 //! ~/.config/sshwarma/          (XDG_CONFIG_HOME)
 //! ├── models.toml
@@ -22,6 +25,9 @@
 //! | `SSHWARMA_DB` | Database path | `~/.local/share/sshwarma/sshwarma.db` |
 //! | `SSHWARMA_HOST_KEY` | Host key path | `~/.local/share/sshwarma/host_key` |
 //! | `SSHWARMA_MODELS_CONFIG` | Models config | `~/.config/sshwarma/models.toml` |
+//! | `SSHWARMA_BACKUP_DIR` | Database backups | `~/.local/share/sshwarma/backups` |
+//! | `SSHWARMA_RECORDINGS_DIR` | Session recordings | `~/.local/share/sshwarma/recordings` |
+//! | `SSHWARMA_BUNDLES_DIR` | Thing bundles | `~/.local/share/sshwarma/bundles` |
 
 use anyhow::{Context, Result};
 use std::path::PathBuf;
@@ -86,6 +92,33 @@ pub fn models_config_path() -> PathBuf {
         .unwrap_or_else(|_| config_dir().join("models.toml"))
 }
 
+/// Get the database backup directory
+///
+/// Priority: `SSHWARMA_BACKUP_DIR` env var > `data_dir()/backups`
+pub fn backup_dir() -> PathBuf {
+    std::env::var("SSHWARMA_BACKUP_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| data_dir().join("backups"))
+}
+
+/// Get the session recording directory
+///
+/// Priority: `SSHWARMA_RECORDINGS_DIR` env var > `data_dir()/recordings`
+pub fn recordings_dir() -> PathBuf {
+    std::env::var("SSHWARMA_RECORDINGS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| data_dir().join("recordings"))
+}
+
+/// Get the thing bundle directory
+///
+/// Priority: `SSHWARMA_BUNDLES_DIR` env var > `data_dir()/bundles`
+pub fn bundles_dir() -> PathBuf {
+    std::env::var("SSHWARMA_BUNDLES_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| data_dir().join("bundles"))
+}
+
 /// Ensure required directories exist
 ///
 /// Creates `data_dir()` and `config_dir()` if they don't exist.
@@ -115,6 +148,9 @@ pub fn log_paths() {
     info!("📂 database: {}", db_path().display());
     info!("📂 host key: {}", host_key_path().display());
     info!("📂 models config: {}", models_config_path().display());
+    info!("📂 backups: {}", backup_dir().display());
+    info!("📂 recordings: {}", recordings_dir().display());
+    info!("📂 bundles: {}", bundles_dir().display());
 }
 
 #[cfg(test)]
@@ -130,6 +166,9 @@ mod tests {
         env::remove_var("SSHWARMA_DB");
         env::remove_var("SSHWARMA_HOST_KEY");
         env::remove_var("SSHWARMA_MODELS_CONFIG");
+        env::remove_var("SSHWARMA_BACKUP_DIR");
+        env::remove_var("SSHWARMA_RECORDINGS_DIR");
+        env::remove_var("SSHWARMA_BUNDLES_DIR");
         env::remove_var("XDG_DATA_HOME");
         env::remove_var("XDG_CONFIG_HOME");
     }
@@ -161,6 +200,15 @@ mod tests {
         clear_path_env_vars();
     }
 
+    #[test]
+    fn test_env_var_override_backup_dir() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        clear_path_env_vars();
+        env::set_var("SSHWARMA_BACKUP_DIR", "/custom/backups");
+        assert_eq!(backup_dir(), PathBuf::from("/custom/backups"));
+        clear_path_env_vars();
+    }
+
     #[test]
     fn test_xdg_data_home_override() {
         let _lock = ENV_LOCK.lock().unwrap();
@@ -168,6 +216,12 @@ mod tests {
         env::set_var("XDG_DATA_HOME", "/xdg/data");
         assert_eq!(data_dir(), PathBuf::from("/xdg/data/sshwarma"));
         assert_eq!(db_path(), PathBuf::from("/xdg/data/sshwarma/sshwarma.db"));
+        assert_eq!(backup_dir(), PathBuf::from("/xdg/data/sshwarma/backups"));
+        assert_eq!(
+            recordings_dir(),
+            PathBuf::from("/xdg/data/sshwarma/recordings")
+        );
+        assert_eq!(bundles_dir(), PathBuf::from("/xdg/data/sshwarma/bundles"));
         clear_path_env_vars();
     }
 
@@ -0,0 +1,347 @@
+//! Graceful shutdown
+//!
+//! On SIGTERM or SIGINT the server stops accepting connections and counts
+//! down in every client's status bar while in-flight model responses finish.
+//! Once they're done (or the grace period runs out) buffered text is flushed,
+//! leftover streaming rows are finalized as interrupted, each terminal is
+//! taken out of the alternate screen, and MCP connections are closed.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use russh::server::Handle;
+use russh::{ChannelId, CryptoVec};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::lua::{LuaToolState, NotificationLevel};
+use crate::state::SharedState;
+
+/// Turn off mouse reports and bracketed paste, show the cursor and leave
+/// the alternate screen
+const RESTORE_TERMINAL: &str = "\x1b[?1006l\x1b[?1000l\x1b[?2004l\x1b[?25h\x1b[?1049l";
+
+/// How long to wait on a single client while closing its channel
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Seconds remaining at which the countdown is re-announced
+const COUNTDOWN_MARKS: [u64; 5] = [10, 5, 3, 2, 1];
+
+/// A connected SSH client that needs its terminal restored on shutdown
+struct Client {
+    username: String,
+    handle: Handle,
+    channel: ChannelId,
+    tool_state: LuaToolState,
+}
+
+/// Shutdown coordinator shared by every session
+///
+/// Tracks connected SSH clients and in-flight model responses so the drain
+/// can notify the former and wait on the latter.
+pub struct Shutdown {
+    token: CancellationToken,
+    clients: Mutex<HashMap<u64, Client>>,
+    next_client: AtomicU64,
+    active_streams: AtomicUsize,
+    streams_done: Notify,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            clients: Mutex::new(HashMap::new()),
+            next_client: AtomicU64::new(0),
+            active_streams: AtomicUsize::new(0),
+            streams_done: Notify::new(),
+        }
+    }
+
+    /// Whether shutdown has started
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolve once shutdown has started
+    pub async fn wait(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Start shutting down (idempotent)
+    pub fn begin(&self) {
+        self.token.cancel();
+    }
+
+    /// Track a client's shell channel until the returned guard is dropped
+    pub fn register_client(
+        self: &Arc<Self>,
+        username: &str,
+        handle: Handle,
+        channel: ChannelId,
+        tool_state: LuaToolState,
+    ) -> ClientGuard {
+        let id = self.next_client.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.insert(
+                id,
+                Client {
+                    username: username.to_string(),
+                    handle,
+                    channel,
+                    tool_state,
+                },
+            );
+        }
+        ClientGuard {
+            shutdown: self.clone(),
+            id,
+        }
+    }
+
+    /// Count a model response as in flight until the guard is dropped
+    pub fn stream_started(self: &Arc<Self>) -> StreamGuard {
+        self.active_streams.fetch_add(1, Ordering::SeqCst);
+        StreamGuard {
+            shutdown: self.clone(),
+        }
+    }
+
+    /// Number of model responses still running
+    pub fn active_streams(&self) -> usize {
+        self.active_streams.load(Ordering::SeqCst)
+    }
+
+    /// Number of connected shell clients
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// Show a notification in every client's status bar
+    fn notify_clients(&self, message: &str, ttl_ms: i64) {
+        let Ok(clients) = self.clients.lock() else {
+            return;
+        };
+        for client in clients.values() {
+            client.tool_state.push_notification_with_level(
+                message.to_string(),
+                ttl_ms,
+                NotificationLevel::Warning,
+            );
+        }
+    }
+
+    fn take_clients(&self) -> Vec<Client> {
+        self.clients
+            .lock()
+            .map(|mut c| c.drain().map(|(_, client)| client).collect())
+            .unwrap_or_default()
+    }
+
+    /// Wait for in-flight streams, announcing the countdown as it runs
+    ///
+    /// Returns the number of streams still running when time ran out.
+    async fn wait_for_streams(&self, grace: Duration) -> usize {
+        let deadline = Instant::now() + grace;
+        let mut announced = grace.as_secs();
+        self.notify_clients(&format!("Server shutting down in {}s", announced), 2000);
+
+        loop {
+            // Register for the wakeup before checking, so a stream finishing
+            // in between isn't missed
+            let done = self.streams_done.notified();
+            if self.active_streams() == 0 {
+                return 0;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return self.active_streams();
+            }
+
+            let remaining = (deadline - now).as_secs_f64().ceil() as u64;
+            if remaining < announced {
+                if let Some(&mark) = COUNTDOWN_MARKS.iter().find(|&&m| m == remaining) {
+                    self.notify_clients(&format!("Server shutting down in {}s", mark), 2000);
+                }
+                announced = remaining;
+            }
+
+            tokio::select! {
+                _ = done => {}
+                _ = tokio::time::sleep(Duration::from_millis(250)) => {}
+            }
+        }
+    }
+}
+
+/// Removes a client from the shutdown registry when its session ends
+pub struct ClientGuard {
+    shutdown: Arc<Shutdown>,
+    id: u64,
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        if let Ok(mut clients) = self.shutdown.clients.lock() {
+            clients.remove(&self.id);
+        }
+    }
+}
+
+/// Marks a model response finished when dropped
+pub struct StreamGuard {
+    shutdown: Arc<Shutdown>,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        if self.shutdown.active_streams.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shutdown.streams_done.notify_waiters();
+        }
+    }
+}
+
+/// Wait for SIGTERM or SIGINT, returning the signal's name
+pub async fn signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = term.recv() => Ok("SIGTERM"),
+            result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map(|_| "SIGINT")
+    }
+}
+
+/// Run the shutdown sequence
+///
+/// The caller should already have stopped accepting connections.
+pub async fn drain(state: &SharedState, grace: Duration) {
+    let shutdown = &state.shutdown;
+    shutdown.begin();
+    info!(
+        clients = shutdown.client_count(),
+        streams = shutdown.active_streams(),
+        grace_secs = grace.as_secs(),
+        "draining sessions"
+    );
+
+    let unfinished = shutdown.wait_for_streams(grace).await;
+    if unfinished > 0 {
+        warn!(
+            unfinished,
+            "grace period expired with streams still running"
+        );
+    }
+
+    // Persist buffered chunks, then close out whatever is still streaming
+    let result = state
+        .db
+        .blocking(|db| {
+            let flushed = db.flush_all_appends()?;
+            let interrupted = db.finalize_streaming_rows()?;
+            Ok((flushed, interrupted))
+        })
+        .await;
+    match result {
+        Ok((flushed, interrupted)) => {
+            info!(flushed, interrupted, "finalized streaming rows")
+        }
+        Err(e) => warn!(error = %e, "failed to finalize streaming rows"),
+    }
+
+    // Give every terminal back before the connection goes away
+    let clients = shutdown.take_clients();
+    let closed = clients.len();
+    for client in clients {
+        let bye = format!(
+            "{}sshwarma is shutting down. See you soon!\r\n",
+            RESTORE_TERMINAL
+        );
+        let close = async {
+            let _ = client
+                .handle
+                .data(client.channel, CryptoVec::from(bye.as_bytes()))
+                .await;
+            let _ = client.handle.eof(client.channel).await;
+            let _ = client.handle.close(client.channel).await;
+        };
+        if tokio::time::timeout(CLOSE_TIMEOUT, close).await.is_err() {
+            warn!(user = %client.username, "timed out closing client channel");
+        }
+    }
+
+    // Nobody is connected any more
+    let result = state
+        .db
+        .blocking(|db| Ok((db.close_open_presence()?, db.close_open_sessions()?)))
+        .await;
+    match result {
+        Ok((presence_left, sessions_closed)) => info!(
+            closed,
+            presence_left, sessions_closed, "closed client sessions"
+        ),
+        Err(e) => warn!(error = %e, "failed to close presence and sessions"),
+    }
+
+    let mcp_closed = state.mcp.shutdown().await;
+    info!(mcp_closed, "shutdown complete");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_streams_returns_when_done() {
+        let shutdown = Arc::new(Shutdown::new());
+        let guard = shutdown.stream_started();
+        assert_eq!(shutdown.active_streams(), 1);
+
+        let waiter = {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move { shutdown.wait_for_streams(Duration::from_secs(30)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(guard);
+
+        let unfinished = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("drain should not wait out the grace period")
+            .unwrap();
+        assert_eq!(unfinished, 0);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_streams_gives_up_after_grace() {
+        let shutdown = Arc::new(Shutdown::new());
+        let _guard = shutdown.stream_started();
+
+        let unfinished = shutdown.wait_for_streams(Duration::from_millis(300)).await;
+        assert_eq!(unfinished, 1);
+    }
+
+    #[test]
+    fn test_begin_is_idempotent() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_shutting_down());
+        shutdown.begin();
+        shutdown.begin();
+        assert!(shutdown.is_shutting_down());
+    }
+}
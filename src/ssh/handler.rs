@@ -1,19 +1,22 @@
 //! SSH connection handler
 
-use crate::db::rows::Row;
 use crate::lua::{mcp_request_handler, LuaRuntime, McpBridge};
 use crate::model::ModelHandle;
 use crate::ops::{spawn_model_response, ModelResponseConfig};
 use crate::player::PlayerSession;
+use crate::shutdown::ClientGuard;
+use crate::ssh::paste::{InputChunk, PasteAssembler};
 use crate::ssh::screen::spawn_screen_refresh;
 use crate::ssh::session::SessionState;
+use crate::ssh::store::{AttachMode, SessionAttachment};
 use crate::ssh::streaming::{push_updates_task, RowUpdate};
 use crate::state::SharedState;
+use crate::ui::{ConnectionCaps, TermCaps};
 use anyhow::Result;
 use russh::server::{self, Handle, Msg, Session};
 use russh::{Channel, ChannelId, CryptoVec, Pty};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, watch, Mutex};
 use tracing::{info, warn};
 
 /// Per-connection SSH handler
@@ -21,6 +24,13 @@ pub struct SshHandler {
     pub state: Arc<SharedState>,
     pub player: Option<PlayerSession>,
     pub term_size: (u16, u16),
+    /// `term` from the pty request (e.g. "xterm-256color")
+    pub term: String,
+    /// `COLORTERM` if the client sent it as an env request
+    pub colorterm: Option<String>,
+    /// This connection's terminal capabilities, kept apart from the
+    /// (possibly shared) session's Lua state
+    pub term_caps: ConnectionCaps,
     /// Session state for buffer rendering
     pub session_state: Arc<Mutex<SessionState>>,
     /// Sender for row updates from background tasks
@@ -32,6 +42,14 @@ pub struct SshHandler {
     pub lua_runtime: Option<Arc<Mutex<LuaRuntime>>>,
     pub mcp_bridge: Option<Arc<McpBridge>>,
     pub mcp_request_rx: Option<mpsc::Receiver<crate::lua::mcp_bridge::McpRequest>>,
+    /// Keeps this client registered for shutdown while the handler lives
+    pub shutdown_guard: Option<ClientGuard>,
+    /// Bracketed paste in progress
+    paste: PasteAssembler,
+    /// Tells the screen refresh task about window changes
+    resize_tx: Option<watch::Sender<(u16, u16)>>,
+    /// This connection's hold on its (possibly shared) session
+    attachment: Option<SessionAttachment>,
 }
 
 impl SshHandler {
@@ -41,6 +59,9 @@ impl SshHandler {
             state: state.clone(),
             player: None,
             term_size: (80, 24),
+            term: String::new(),
+            colorterm: None,
+            term_caps: ConnectionCaps::default(),
             session_state: Arc::new(Mutex::new(SessionState::new())),
             update_tx,
             update_rx: Some(update_rx),
@@ -49,6 +70,10 @@ impl SshHandler {
             lua_runtime: None,
             mcp_bridge: None,
             mcp_request_rx: None,
+            shutdown_guard: None,
+            paste: PasteAssembler::new(),
+            resize_tx: None,
+            attachment: None,
         }
     }
 
@@ -56,7 +81,7 @@ impl SshHandler {
     // Lua Helper Methods
     // =========================================================================
 
-    /// Execute a closure with the Lua runtime locked.
+    /// Execute a closure with the Lua runtime locked, for this connection.
     ///
     /// Returns None if no Lua runtime is available.
     pub async fn with_lua<F, R>(&self, f: F) -> Option<R>
@@ -65,6 +90,7 @@ impl SshHandler {
     {
         let lua_runtime = self.lua_runtime.as_ref()?;
         let lua = lua_runtime.lock().await;
+        lua.tool_state().use_term_caps(&self.term_caps);
         Some(f(&lua))
     }
 
@@ -191,27 +217,9 @@ impl SshHandler {
             }
         }
 
-        // Add join presence row
+        // Presence, with its join row
         if let Some(ref player) = self.player {
-            let mut row = Row::new(&buffer.id, "presence.join");
-            row.content = Some(player.username.clone());
-            self.state.db.append_row(&mut row)?;
-        }
-
-        // Update in-memory world
-        {
-            let mut world = self.state.world.write().await;
-            if world.get_room(room_name).is_none() {
-                world.create_room_with_buffer(room_name.to_string(), buffer.id.clone());
-            }
-            if let Some(room) = world.get_room_mut(room_name) {
-                if let Some(ref player) = self.player {
-                    room.add_user(player.username.clone());
-                }
-                if room.buffer_id.is_none() {
-                    room.set_buffer_id(buffer.id);
-                }
-            }
+            self.state.world.enter(&player.username, room_name)?;
         }
 
         Ok(())
@@ -227,19 +235,9 @@ impl SshHandler {
             return Ok(());
         };
 
-        // Add leave presence row
-        if let Ok(buffer) = self.state.db.get_or_create_room_buffer(room_name) {
-            let mut row = Row::new(&buffer.id, "presence.leave");
-            row.content = Some(player.username.clone());
-            let _ = self.state.db.append_row(&mut row);
-        }
-
-        // Update in-memory world
-        {
-            let mut world = self.state.world.write().await;
-            if let Some(room) = world.get_room_mut(room_name) {
-                room.remove_user(&player.username);
-            }
+        // Presence, with its leave row
+        if self.state.world.room_of(&player.username)?.as_deref() == Some(room_name.as_str()) {
+            self.state.world.leave(&player.username)?;
         }
 
         // Clear session buffer
@@ -343,6 +341,14 @@ impl server::Handler for SshHandler {
         user: &str,
         key: &russh::keys::PublicKey,
     ) -> Result<server::Auth, Self::Error> {
+        if self.state.shutdown.is_shutting_down() {
+            info!(user, "rejecting login during shutdown");
+            return Ok(server::Auth::Reject {
+                proceed_with_methods: None,
+                partial_success: false,
+            });
+        }
+
         let key_str = key.to_string();
 
         // Check existing user
@@ -397,7 +403,7 @@ impl server::Handler for SshHandler {
     async fn pty_request(
         &mut self,
         _channel: ChannelId,
-        _term: &str,
+        term: &str,
         col_width: u32,
         row_height: u32,
         _pix_width: u32,
@@ -406,7 +412,22 @@ impl server::Handler for SshHandler {
         _session: &mut Session,
     ) -> Result<(), Self::Error> {
         self.term_size = (col_width as u16, row_height as u16);
-        // Screen refresh task will pick up new dimensions on next tick
+        self.term = term.to_string();
+        Ok(())
+    }
+
+    async fn env_request(
+        &mut self,
+        _channel: ChannelId,
+        variable_name: &str,
+        variable_value: &str,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        // Only COLORTERM matters (it's how terminals advertise 24-bit color);
+        // clients need SendEnv COLORTERM for it to arrive
+        if variable_name == "COLORTERM" {
+            self.colorterm = Some(variable_value.to_string());
+        }
         Ok(())
     }
 
@@ -415,6 +436,85 @@ impl server::Handler for SshHandler {
         channel: ChannelId,
         session: &mut Session,
     ) -> Result<(), Self::Error> {
+        self.start_shell(channel, session, AttachMode::Auto).await
+    }
+
+    /// `ssh -t host attach` or `ssh -t host new` picks how to attach
+    async fn exec_request(
+        &mut self,
+        channel: ChannelId,
+        data: &[u8],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        let command = String::from_utf8_lossy(data);
+        let Some(mode) = AttachMode::parse(&command) else {
+            info!(command = %command, "unknown exec command");
+            let usage = "usage: ssh -t host [attach|new]\r\n";
+            let _ = session.extended_data(channel, 1, CryptoVec::from(usage.as_bytes()));
+            let _ = session.exit_status_request(channel, 2);
+            let _ = session.close(channel);
+            return Ok(());
+        };
+        self.start_shell(channel, session, mode).await
+    }
+
+    async fn data(
+        &mut self,
+        channel: ChannelId,
+        data: &[u8],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        for chunk in self.paste.feed(data) {
+            match chunk {
+                InputChunk::Keys(bytes) => self.handle_keys(channel, session, &bytes).await,
+                InputChunk::Paste { text, truncated } => {
+                    let result = self
+                        .with_lua(|lua| lua.call_on_paste(&text, truncated))
+                        .await;
+                    self.handle_lua_input(channel, session, result).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn window_change_request(
+        &mut self,
+        _channel: ChannelId,
+        col_width: u32,
+        row_height: u32,
+        _pix_width: u32,
+        _pix_height: u32,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.term_size = (col_width as u16, row_height as u16);
+        if let Some(ref resize_tx) = self.resize_tx {
+            let _ = resize_tx.send(self.term_size);
+        }
+        Ok(())
+    }
+
+    async fn channel_eof(
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        // Exit alternate screen buffer and show cursor before disconnect
+        // This restores the terminal to normal state
+        let cleanup_seq = "\x1b[?1006l\x1b[?1000l\x1b[?2004l\x1b[?25h\x1b[?1049l";
+        let _ = session.data(channel, CryptoVec::from(cleanup_seq.as_bytes()));
+        Ok(())
+    }
+}
+
+impl SshHandler {
+    /// Set up the terminal and screen for this connection's session
+    async fn start_shell(
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+        mode: AttachMode,
+    ) -> Result<()> {
         let (width, height) = self.term_size;
 
         // Store handles
@@ -426,30 +526,35 @@ impl server::Handler for SshHandler {
         // \x1b[2J = clear entire screen
         // \x1b[H = cursor to home (0,0)
         // \x1b[?25l = hide cursor (screen refresh will show it at input position)
-        let init_seq = "\x1b[?1049h\x1b[2J\x1b[H\x1b[?25l";
+        // \x1b[?2004h = bracketed paste (pastes arrive wrapped, see ssh::paste)
+        // \x1b[?1000h\x1b[?1006h = mouse button/wheel reports in SGR format
+        let init_seq = "\x1b[?1049h\x1b[2J\x1b[H\x1b[?25l\x1b[?2004h\x1b[?1000h\x1b[?1006h";
         let _ = session.data(channel, CryptoVec::from(init_seq.as_bytes()));
 
-        // Auto-join user to lobby and send welcome notification
-        if let Err(e) = self.join_room("lobby").await {
-            tracing::warn!("failed to join lobby: {}", e);
-        }
+        if !self.attach_session(mode).await {
+            // Fresh session: auto-join user to lobby and send welcome notification
+            if let Err(e) = self.join_room("lobby").await {
+                tracing::warn!("failed to join lobby: {}", e);
+            }
 
-        if let Some(ref player) = self.player {
-            if let Some(ref lua_runtime) = self.lua_runtime {
-                let lua = lua_runtime.lock().await;
-                // Welcome as notification
-                lua.tool_state()
-                    .push_notification(format!("Welcome, {}!", player.username), 5000);
+            if let Some(ref player) = self.player {
+                if let Some(ref lua_runtime) = self.lua_runtime {
+                    let lua = lua_runtime.lock().await;
+                    // Welcome as notification
+                    lua.tool_state()
+                        .push_notification(format!("Welcome, {}!", player.username), 5000);
+                }
             }
         }
 
-        // Spawn background task for model streaming updates
+        // Spawn background task for model streaming updates and row events
         if let Some(update_rx) = self.update_rx.take() {
             let db = self.state.db.clone();
+            let row_rx = db.subscribe_rows();
             let lua_runtime = self.lua_runtime.clone();
 
             tokio::spawn(async move {
-                push_updates_task(update_rx, db, lua_runtime).await;
+                push_updates_task(update_rx, row_rx, db, lua_runtime).await;
             });
         }
 
@@ -463,16 +568,35 @@ impl server::Handler for SshHandler {
             return Ok(());
         };
 
+        // Register for shutdown so the terminal can be restored on SIGTERM
+        if let Some(ref player) = self.player {
+            let tool_state = lua_rt.lock().await.tool_state().clone();
+            self.shutdown_guard = Some(self.state.shutdown.register_client(
+                &player.username,
+                session.handle(),
+                channel,
+                tool_state,
+            ));
+        }
+
+        let caps = TermCaps::detect(&self.term, self.colorterm.as_deref());
+        info!(term = %caps.term, colors = caps.color.name(), glyphs = caps.glyphs.name(), "terminal capabilities");
+        if let Ok(mut guard) = self.term_caps.write() {
+            *guard = caps;
+        }
+
         // Subscribe to hot reload events for this session
         let lua_reload_rx = self.state.lua_reload.subscribe();
+        let (resize_tx, size_rx) = watch::channel((width, height));
+        self.resize_tx = Some(resize_tx);
         spawn_screen_refresh(
             session.handle(),
             channel,
             lua_rt,
             self.state.clone(),
             lua_reload_rx,
-            width,
-            height,
+            size_rx,
+            self.term_caps.clone(),
         );
 
         // Spawn MCP request handler
@@ -489,76 +613,107 @@ impl server::Handler for SshHandler {
         Ok(())
     }
 
-    async fn data(
-        &mut self,
-        channel: ChannelId,
-        data: &[u8],
-        session: &mut Session,
-    ) -> Result<(), Self::Error> {
-        // Reject oversized input (4KB max per SSH data frame).
-        // Normal typing and reasonable pastes are well under this limit.
-        if data.len() > 4096 {
-            tracing::warn!("Rejecting oversized input: {} bytes", data.len());
-            return Ok(());
+    /// Attach this connection to one of the user's stored sessions
+    ///
+    /// Returns true when an existing session was picked up, in which case
+    /// its Lua state, room and streaming task replace the ones created at
+    /// login. Otherwise the fresh session is stored for later reattaching.
+    async fn attach_session(&mut self, mode: AttachMode) -> bool {
+        let Some(username) = self.player.as_ref().map(|p| p.username.clone()) else {
+            return false;
+        };
+        let store = self.state.sessions.clone();
+
+        let Some(attachment) = store.resume(&username, mode) else {
+            if let Some(ref lua_runtime) = self.lua_runtime {
+                self.attachment = Some(store.create(
+                    &username,
+                    lua_runtime.clone(),
+                    self.session_state.clone(),
+                    self.update_tx.clone(),
+                ));
+            }
+            return false;
+        };
+
+        let stored = attachment.session().clone();
+        let mirrors = stored.attached() - 1;
+        self.lua_runtime = Some(stored.lua_runtime.clone());
+        self.session_state = stored.session_state.clone();
+        self.update_tx = stored.update_tx.clone();
+        // The stored session's streaming task is still running
+        self.update_rx = None;
+        self.attachment = Some(attachment);
+
+        // Pick up whichever room the session was in
+        let room = self.current_room().await;
+        if let Some(ref mut player) = self.player {
+            player.current_room = room;
         }
 
-        // Forward raw bytes to Lua for parsing - no fallback, fail visibly
-        let Some(ref lua_runtime) = self.lua_runtime else {
-            tracing::error!("No Lua runtime available for input handling");
-            return Ok(());
+        info!(user = %username, id = stored.id, mirrors, "session resumed");
+        let msg = match mirrors {
+            0 => "Session resumed".to_string(),
+            1 => "Mirroring a session open on 1 other connection".to_string(),
+            n => format!("Mirroring a session open on {} other connections", n),
         };
+        self.push_notification(msg, 5000).await;
+        true
+    }
 
-        let lua = lua_runtime.lock().await;
-        match lua.call_on_input(data) {
-            Ok(Some(action)) => {
-                drop(lua); // Release lock before handling action
-                self.handle_input_action(channel, session, action).await;
-            }
-            Ok(None) => {
-                // No action needed, just mark input dirty for redraw
-                lua.tool_state().mark_dirty("input");
-            }
-            Err(e) => {
-                // Log error visibly - no silent fallback
-                tracing::error!("Lua input handling failed: {}", e);
-                lua.tool_state().push_notification_with_level(
-                    format!("Input error: {}", e),
-                    5000,
-                    crate::lua::NotificationLevel::Error,
-                );
-            }
+    /// End the session for good instead of keeping it for reattaching
+    ///
+    /// Other connections mirroring it keep it alive.
+    fn end_session(&mut self) {
+        if let Some(attachment) = self.attachment.take() {
+            attachment.close();
         }
-        Ok(())
     }
 
-    async fn window_change_request(
-        &mut self,
-        _channel: ChannelId,
-        col_width: u32,
-        row_height: u32,
-        _pix_width: u32,
-        _pix_height: u32,
-        _session: &mut Session,
-    ) -> Result<(), Self::Error> {
-        self.term_size = (col_width as u16, row_height as u16);
-        // Screen refresh task will pick up new dimensions on next tick
-        Ok(())
+    /// Forward typed keys to Lua's input parser
+    async fn handle_keys(&mut self, channel: ChannelId, session: &mut Session, bytes: &[u8]) {
+        // Reject oversized input (4KB max per SSH data frame).
+        // Normal typing is well under this; pastes arrive bracketed and
+        // are assembled separately.
+        if bytes.len() > 4096 {
+            tracing::warn!("Rejecting oversized input: {} bytes", bytes.len());
+            return;
+        }
+
+        let result = self.with_lua(|lua| lua.call_on_input(bytes)).await;
+        self.handle_lua_input(channel, session, result).await;
+
+        // Keys replayed after a broken hotkey sequence can queue more actions
+        while let Some(Ok(Some(action))) = self.with_lua(|lua| lua.call_on_input(b"")).await {
+            self.handle_input_action(channel, session, action).await;
+        }
     }
 
-    async fn channel_eof(
+    /// Act on what on_input/on_paste returned - no fallback, fail visibly
+    async fn handle_lua_input(
         &mut self,
         channel: ChannelId,
         session: &mut Session,
-    ) -> Result<(), Self::Error> {
-        // Exit alternate screen buffer and show cursor before disconnect
-        // This restores the terminal to normal state
-        let cleanup_seq = "\x1b[?25h\x1b[?1049l";
-        let _ = session.data(channel, CryptoVec::from(cleanup_seq.as_bytes()));
-        Ok(())
+        result: Option<Result<Option<crate::lua::InputAction>>>,
+    ) {
+        match result {
+            None => {
+                tracing::error!("No Lua runtime available for input handling");
+            }
+            Some(Ok(Some(action))) => {
+                self.handle_input_action(channel, session, action).await;
+            }
+            Some(Ok(None)) => {
+                // No action needed, just mark input dirty for redraw
+                self.mark_dirty("input").await;
+            }
+            Some(Err(e)) => {
+                tracing::error!("Lua input handling failed: {}", e);
+                self.push_error(format!("Input error: {}", e)).await;
+            }
+        }
     }
-}
 
-impl SshHandler {
     /// Handle action from Lua input parser
     async fn handle_input_action(
         &mut self,
@@ -577,6 +732,7 @@ impl SshHandler {
 
             InputAction::Execute(line) => {
                 if line.trim() == "/quit" {
+                    self.end_session();
                     let _ = session.close(channel);
                     return;
                 }
@@ -589,8 +745,21 @@ impl SshHandler {
                 self.mark_dirty("chat").await;
             }
 
+            InputAction::Reply { text, row_id } => {
+                if let Err(e) = self.process_reply(channel, session, &text, &row_id).await {
+                    self.push_notification(format!("Error: {}", e), 5000).await;
+                }
+
+                self.mark_dirty("input").await;
+                self.mark_dirty("chat").await;
+            }
+
             InputAction::Tab => {
-                // Tab completion TODO: rewrite in Lua
+                // Completion edits the Lua input buffer directly
+                if let Some(Err(e)) = self.with_lua(|lua| lua.call_tab_complete()).await {
+                    tracing::warn!("tab completion failed: {}", e);
+                }
+                self.mark_dirty("input").await;
             }
 
             InputAction::ClearScreen => {
@@ -600,6 +769,7 @@ impl SshHandler {
             }
 
             InputAction::Quit => {
+                self.end_session();
                 let _ = session.close(channel);
             }
 
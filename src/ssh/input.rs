@@ -1,6 +1,6 @@
 //! Input handling and command dispatch
 
-use crate::db::rows::Row;
+use crate::db::rows::{LinkType, Row};
 use crate::interp::{self, Input};
 use crate::status::Status;
 use anyhow::Result;
@@ -40,22 +40,49 @@ impl SshHandler {
         Ok(())
     }
 
-    /// Handle chat message (add to room buffer)
+    /// Process a line entered as a reply to a row
+    ///
+    /// Chat lines are linked to the row they answer; commands and mentions
+    /// run as usual.
+    pub async fn process_reply(
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+        line: &str,
+        row_id: &str,
+    ) -> Result<()> {
+        let Input::Chat(message) = interp::parse(line) else {
+            return self.process_input(channel, session, line).await;
+        };
+
+        if let Some(reply_id) = self.handle_chat(channel, session, &message).await? {
+            self.state
+                .db
+                .create_row_link(&reply_id, row_id, LinkType::Reply)?;
+        }
+        Ok(())
+    }
+
+    /// Handle chat message (add to room buffer), returning the new row's id
     async fn handle_chat(
         &mut self,
         _channel: ChannelId,
         _session: &mut Session,
         message: &str,
-    ) -> Result<()> {
+    ) -> Result<Option<String>> {
         let Some(ref player) = self.player else {
             self.push_error("Not authenticated").await;
-            return Ok(());
+            return Ok(None);
         };
 
         let Some(room_name) = self.current_room().await else {
             self.push_error("Not in a room. Use /join <room>").await;
-            return Ok(());
+            return Ok(None);
         };
+        if let Err(e) = self.state.db.ensure_room_writable(&room_name) {
+            self.push_error(e.to_string()).await;
+            return Ok(None);
+        }
 
         // Get buffer
         let buffer = self.state.db.get_or_create_room_buffer(&room_name)?;
@@ -67,7 +94,7 @@ impl SshHandler {
         let mut row = Row::message(&buffer.id, &agent.id, message, false);
         self.state.db.append_row(&mut row)?;
 
-        Ok(())
+        Ok(Some(row.id))
     }
 
     /// Handle @mention (spawn model response)
@@ -111,6 +138,12 @@ impl SshHandler {
         };
 
         let room_name = self.current_room().await;
+        if let Some(ref name) = room_name {
+            if let Err(e) = self.state.db.ensure_room_writable(name) {
+                self.push_error(e.to_string()).await;
+                return Ok(());
+            }
+        }
         let room_id = if let Some(ref name) = room_name {
             self.state
                 .db
@@ -196,6 +229,12 @@ impl SshHandler {
         // Get session info for context
         let username = self.player.as_ref().map(|p| p.username.clone());
         let room_name = self.current_room().await;
+        if let Some(ref name) = room_name {
+            if let Err(e) = self.state.db.ensure_room_writable(name) {
+                self.push_error(e.to_string()).await;
+                return Ok(());
+            }
+        }
         let room_id = if let Some(ref name) = room_name {
             self.state
                 .db
@@ -208,6 +247,7 @@ impl SshHandler {
         };
 
         let lua = lua_runtime.lock().await;
+        lua.tool_state().use_term_caps(&self.term_caps);
 
         // Set session context so commands can access tools.session()
         if let Some(ref user) = username {
@@ -225,7 +265,7 @@ impl SshHandler {
         }
 
         tracing::info!("dispatch_command: calling lua.call_dispatch_command");
-        match lua.call_dispatch_command(name, args) {
+        match tokio::task::block_in_place(|| lua.call_dispatch_command(name, args)) {
             Ok(Some(cmd_result)) => {
                 tracing::debug!(
                     mode = %cmd_result.mode,
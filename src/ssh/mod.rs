@@ -4,8 +4,10 @@
 
 mod handler;
 mod input;
+mod paste;
 mod screen;
 mod session;
+mod store;
 mod streaming;
 
 use std::net::SocketAddr;
@@ -18,6 +20,7 @@ use crate::state::SharedState;
 
 pub use handler::SshHandler;
 pub use session::SessionState;
+pub use store::{AttachMode, SessionAttachment, SessionStore, StoredSession};
 pub use streaming::RowUpdate;
 
 /// SSH server implementation
@@ -0,0 +1,181 @@
+//! Bracketed paste assembly
+//!
+//! With bracketed paste enabled (`\x1b[?2004h`) the terminal wraps pasted
+//! text in `\x1b[200~` ... `\x1b[201~`. A paste can span many SSH data
+//! frames, so its bytes are collected here and handed over as one piece
+//! instead of being parsed as keystrokes.
+
+/// Sent by the terminal before pasted text
+pub const PASTE_START: &[u8] = b"\x1b[200~";
+
+/// Sent by the terminal after pasted text
+pub const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Largest paste kept; anything past this is dropped
+pub const PASTE_MAX_BYTES: usize = 1024 * 1024;
+
+/// A piece of client input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputChunk {
+    /// Bytes to parse as keys
+    Keys(Vec<u8>),
+    /// A complete paste
+    Paste { text: String, truncated: bool },
+}
+
+/// Collects bracketed pastes across data frames
+#[derive(Debug, Default)]
+pub struct PasteAssembler {
+    /// Pasted bytes so far, while inside a paste
+    buf: Option<Vec<u8>>,
+    truncated: bool,
+}
+
+impl PasteAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a paste has started but not finished
+    pub fn in_paste(&self) -> bool {
+        self.buf.is_some()
+    }
+
+    /// Split a data frame into keys and completed pastes
+    pub fn feed(&mut self, mut data: &[u8]) -> Vec<InputChunk> {
+        let mut chunks = Vec::new();
+
+        while !data.is_empty() {
+            let Some(buf) = self.buf.as_mut() else {
+                // The start marker always arrives whole: holding back a
+                // partial one would delay a bare Escape keypress
+                match find(data, PASTE_START) {
+                    Some(pos) => {
+                        if pos > 0 {
+                            chunks.push(InputChunk::Keys(data[..pos].to_vec()));
+                        }
+                        self.buf = Some(Vec::new());
+                        self.truncated = false;
+                        data = &data[pos + PASTE_START.len()..];
+                    }
+                    None => {
+                        chunks.push(InputChunk::Keys(data.to_vec()));
+                        data = &[];
+                    }
+                }
+                continue;
+            };
+
+            // The end marker may be split across frames, so search the
+            // tail of what we already have as well
+            let overlap = buf.len().min(PASTE_END.len() - 1);
+            let search_from = buf.len() - overlap;
+            buf.extend_from_slice(data);
+            data = &[];
+
+            if let Some(pos) = find(&buf[search_from..], PASTE_END) {
+                let end = search_from + pos;
+                let rest = buf.split_off(end + PASTE_END.len());
+                buf.truncate(end);
+                let bytes = self.buf.take().unwrap_or_default();
+                chunks.push(self.finish(bytes));
+                // Keys typed after the paste in the same frame
+                chunks.extend(self.feed(&rest));
+                return chunks;
+            }
+
+            // Keep room for a partial end marker while capping the size
+            if buf.len() > PASTE_MAX_BYTES + PASTE_END.len() {
+                let keep = buf.split_off(buf.len() - (PASTE_END.len() - 1));
+                buf.truncate(PASTE_MAX_BYTES);
+                buf.extend_from_slice(&keep);
+                self.truncated = true;
+            }
+        }
+
+        chunks
+    }
+
+    fn finish(&mut self, mut bytes: Vec<u8>) -> InputChunk {
+        let mut truncated = std::mem::take(&mut self.truncated);
+        if bytes.len() > PASTE_MAX_BYTES {
+            bytes.truncate(PASTE_MAX_BYTES);
+            truncated = true;
+        }
+        InputChunk::Paste {
+            text: String::from_utf8_lossy(&bytes).into_owned(),
+            truncated,
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paste(text: &str) -> InputChunk {
+        InputChunk::Paste {
+            text: text.to_string(),
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_keys_pass_through() {
+        let mut p = PasteAssembler::new();
+        assert_eq!(
+            p.feed(b"hi\x1b"),
+            vec![InputChunk::Keys(b"hi\x1b".to_vec())]
+        );
+        assert!(!p.in_paste());
+    }
+
+    #[test]
+    fn test_paste_in_one_frame() {
+        let mut p = PasteAssembler::new();
+        let chunks = p.feed(b"a\x1b[200~line 1\rline 2\x1b[201~b");
+        assert_eq!(
+            chunks,
+            vec![
+                InputChunk::Keys(b"a".to_vec()),
+                paste("line 1\rline 2"),
+                InputChunk::Keys(b"b".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paste_across_frames() {
+        let mut p = PasteAssembler::new();
+        assert!(p.feed(b"\x1b[200~first ").is_empty());
+        assert!(p.in_paste());
+        // End marker split between frames
+        assert!(p.feed(b"second\x1b[20").is_empty());
+        assert_eq!(p.feed(b"1~"), vec![paste("first second")]);
+        assert!(!p.in_paste());
+    }
+
+    #[test]
+    fn test_oversized_paste_is_truncated() {
+        let mut p = PasteAssembler::new();
+        p.feed(PASTE_START);
+        let block = vec![b'x'; 64 * 1024];
+        for _ in 0..(PASTE_MAX_BYTES / block.len() + 2) {
+            assert!(p.feed(&block).is_empty());
+        }
+        let chunks = p.feed(PASTE_END);
+        match &chunks[..] {
+            [InputChunk::Paste { text, truncated }] => {
+                assert_eq!(text.len(), PASTE_MAX_BYTES);
+                assert!(truncated);
+            }
+            other => panic!("expected one paste, got {:?}", other),
+        }
+    }
+}
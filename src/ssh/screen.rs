@@ -5,12 +5,19 @@
 
 use crate::lua::{LuaReloadReceiver, LuaRuntime, NotificationLevel};
 use crate::state::SharedState;
-use crate::ui::RenderBuffer;
+use crate::ui::{ConnectionCaps, Recorder, RenderBuffer};
 use russh::server::Handle;
 use russh::{ChannelId, CryptoVec};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::sync::Mutex as TokioMutex;
+use tokio::sync::{watch, Mutex as TokioMutex};
+
+/// Terminal size as (columns, rows), updated by the handler on window change
+pub type TermSizeReceiver = watch::Receiver<(u16, u16)>;
+
+/// Identifies screens to the session recorder
+static NEXT_SCREEN_ID: AtomicU64 = AtomicU64::new(1);
 
 /// Spawn the screen refresh task
 pub fn spawn_screen_refresh(
@@ -19,8 +26,8 @@ pub fn spawn_screen_refresh(
     lua_runtime: Arc<TokioMutex<LuaRuntime>>,
     state: Arc<SharedState>,
     lua_reload_rx: LuaReloadReceiver,
-    term_width: u16,
-    term_height: u16,
+    size_rx: TermSizeReceiver,
+    caps: ConnectionCaps,
 ) {
     tokio::spawn(async move {
         screen_refresh_task(
@@ -29,8 +36,8 @@ pub fn spawn_screen_refresh(
             lua_runtime,
             state,
             lua_reload_rx,
-            term_width,
-            term_height,
+            size_rx,
+            caps,
         )
         .await;
     });
@@ -46,13 +53,22 @@ async fn screen_refresh_task(
     lua_runtime: Arc<TokioMutex<LuaRuntime>>,
     _state: Arc<SharedState>,
     mut lua_reload_rx: LuaReloadReceiver,
-    term_width: u16,
-    term_height: u16,
+    mut size_rx: TermSizeReceiver,
+    caps: ConnectionCaps,
 ) {
-    // Get the dirty state for event-driven updates
-    let dirty = {
+    let (mut term_width, mut term_height) = *size_rx.borrow_and_update();
+    let screen_id = NEXT_SCREEN_ID.fetch_add(1, Ordering::Relaxed);
+
+    // Get the dirty state for event-driven updates. This screen takes
+    // from its own subscriber, so other screens mirroring the same session
+    // still see every tag.
+    let (dirty, recorder) = {
         let lua = lua_runtime.lock().await;
-        lua.tool_state().dirty().clone()
+        let tool_state = lua.tool_state();
+        (
+            tool_state.dirty().subscribe(),
+            tool_state.recorder().clone(),
+        )
     };
 
     // Double-buffered rendering for efficient diffing
@@ -80,9 +96,12 @@ async fn screen_refresh_task(
             0,
             term_width,
             term_height,
+            (&recorder, screen_id),
+            &caps,
         )
         .await
         {
+            recorder.release(screen_id);
             return; // Connection closed
         }
     }
@@ -91,15 +110,23 @@ async fn screen_refresh_task(
         // Wait for either:
         // 1. Dirty signal (something changed, redraw)
         // 2. Lua reload event (module changed on disk)
-        // 3. 500ms timeout (run background tasks)
+        // 3. Terminal resized
+        // 4. 500ms timeout (run background tasks)
         enum Event {
             Dirty,
             Reload(String),
+            Resize,
             Background,
         }
 
         let event = tokio::select! {
             _ = dirty.notified() => Event::Dirty,
+            changed = size_rx.changed() => {
+                match changed {
+                    Ok(()) => Event::Resize,
+                    Err(_) => break, // Handler dropped, session is gone
+                }
+            }
             reload = lua_reload_rx.recv() => {
                 match reload {
                     Some(e) => Event::Reload(e.module_name().to_string()),
@@ -129,6 +156,17 @@ async fn screen_refresh_task(
                     tracing::debug!("invalidated lua module: {}", module_name);
                 }
             }
+            Event::Resize => {
+                let (width, height) = *size_rx.borrow_and_update();
+                if (width, height) != (term_width, term_height) {
+                    // Buffers are reallocated at the new size on the next render
+                    term_width = width;
+                    term_height = height;
+                    recorder.resize(screen_id, width, height);
+                    dirty.mark_many(["resize", "status", "chat", "input"]);
+                    tracing::debug!("terminal resized to {}x{}", width, height);
+                }
+            }
             Event::Background => {
                 // 500ms background tick
                 background_tick += 1;
@@ -137,7 +175,10 @@ async fn screen_refresh_task(
                 // (This can call tools.mark_dirty() to trigger redraws)
                 {
                     let lua = lua_runtime.lock().await;
-                    if let Err(e) = lua.call_background(background_tick) {
+                    lua.tool_state().use_term_caps(&caps);
+                    if let Err(e) =
+                        tokio::task::block_in_place(|| lua.call_background(background_tick))
+                    {
                         tracing::debug!("lua background error: {}", e);
                     }
                 }
@@ -161,12 +202,17 @@ async fn screen_refresh_task(
             tick,
             term_width,
             term_height,
+            (&recorder, screen_id),
+            &caps,
         )
         .await
         {
             break; // Connection closed
         }
     }
+
+    // Let a mirroring screen carry on with the recording
+    recorder.release(screen_id);
 }
 
 /// Render the screen with tag-based dirty tracking and row diffing.
@@ -182,34 +228,69 @@ async fn render_screen_with_tags(
     tick: u64,
     term_width: u16,
     term_height: u16,
+    (recorder, screen_id): (&Recorder, u64),
+    caps: &ConnectionCaps,
 ) -> bool {
-    let diff_output = {
+    let mut repaint = false;
+    let (diff_output, cursor_row, cursor_col) = {
         let lua = lua_runtime.lock().await;
 
+        // Draw with this connection's caps, not those of whichever
+        // connection last ran the session's Lua
+        lua.tool_state().use_term_caps(caps);
+
         // Clear current buffer before drawing
         {
             let mut buf = current_buffer.lock().unwrap();
+            // A new size, a new color depth (from /term, colors are
+            // quantized on output), or a recording that starts with this
+            // frame means every row has to be sent again
+            let depth = lua.tool_state().term_caps().color;
+            if buf.width() != term_width || buf.height() != term_height {
+                *buf = RenderBuffer::new(term_width, term_height);
+            }
+            let claimed = recorder.claim(screen_id, term_width, term_height);
+            if claimed
+                || buf.color_depth() != depth
+                || last_buffer.width() != term_width
+                || last_buffer.height() != term_height
+            {
+                buf.set_color_depth(depth);
+                *last_buffer = RenderBuffer::new(term_width, term_height);
+                repaint = true;
+            }
             buf.clear();
         }
 
         // Call on_tick with dirty tags - Lua draws to full screen
         // Future: Lua can use dirty_tags to render only affected regions
+        // on_tick queries the database, so keep it off the async worker
         tracing::debug!("render_screen_with_tags: calling on_tick");
-        if let Err(e) = lua.call_on_tick_with_tags(
-            dirty_tags,
-            tick,
-            current_buffer.clone(),
-            term_width,
-            term_height,
-        ) {
+        if let Err(e) = tokio::task::block_in_place(|| {
+            lua.call_on_tick_with_tags(
+                dirty_tags,
+                tick,
+                current_buffer.clone(),
+                term_width,
+                term_height,
+            )
+        }) {
             tracing::warn!("on_tick error: {}", e);
             return true; // Continue, just skip this frame
         }
         tracing::debug!("render_screen_with_tags: on_tick completed");
 
+        // Get cursor position reported by Lua (set via tools.set_cursor_pos)
+        // before another screen on the same session renders over it
+        let input = lua.tool_state().input_state();
+
         // Generate diff ANSI - only rows that changed
         let buf = current_buffer.lock().unwrap();
-        buf.diff_ansi(last_buffer, 0)
+        (
+            buf.diff_ansi(last_buffer, 0),
+            input.cursor_row,
+            input.cursor_col,
+        )
     };
 
     // Update last_buffer for next comparison
@@ -218,19 +299,19 @@ async fn render_screen_with_tags(
         *last_buffer = buf.clone();
     }
 
-    // Get cursor position reported by Lua (set via tools.set_cursor_pos)
-    let (cursor_row, cursor_col) = {
-        let lua = lua_runtime.lock().await;
-        let input = lua.tool_state().input_state();
-        (input.cursor_row, input.cursor_col)
-    };
-
     // Only send if there are changes
     tracing::debug!(
         "render_screen_with_tags: diff_output len = {}",
         diff_output.len()
     );
-    if !diff_output.is_empty() {
+    if !diff_output.is_empty() || repaint {
+        // Diffing against a blank buffer skips blank rows, so clear first
+        let diff_output = if repaint {
+            format!("\x1b[2J{}", diff_output)
+        } else {
+            diff_output
+        };
+
         // Wrap in synchronized output to prevent tearing.
         // Position hardware cursor at Lua-reported position for layered blink effect.
         // Lua renders a visual cursor (styled background), hardware cursor overlays it.
@@ -243,6 +324,7 @@ async fn render_screen_with_tags(
             // No cursor position reported yet - hide hardware cursor
             format!("\x1b[?2026h{}\x1b[?25l\x1b[?2026l", diff_output)
         };
+        recorder.output(screen_id, &final_output);
 
         if handle
             .data(channel, CryptoVec::from(final_output.as_bytes()))
@@ -0,0 +1,386 @@
+//! Detached session store
+//!
+//! A session is the Lua runtime (pages, scroll, draft input, notifications)
+//! and room a user's terminal shows. It outlives the SSH connection: when
+//! the last connection showing it drops, the session is kept for a grace
+//! period so the next login can pick it up again, tmux-style.
+//!
+//! Connections attach in one of three ways (see `AttachMode`). A session
+//! shown by several connections at once is mirrored: they share one Lua
+//! state and each draws it at its own terminal size.
+//!
+//! Presence follows sessions: each stored session, attached or not, holds
+//! its user in the world (see `World::hold`), so a user leaves their room
+//! when the last thing holding them, SSH session or MCP client, is gone.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, Mutex as TokioMutex};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::lua::LuaRuntime;
+use crate::ssh::session::SessionState;
+use crate::ssh::streaming::RowUpdate;
+use crate::world::World;
+
+/// Key a stored session holds its user's presence under
+fn presence_holder(id: u64) -> String {
+    format!("ssh:{}", id)
+}
+
+/// How a new connection picks its session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttachMode {
+    /// Resume a detached session if there is one, otherwise start fresh
+    #[default]
+    Auto,
+    /// Join the most recently used session, mirroring it if it's attached
+    Attach,
+    /// Always start a fresh session, independent of any others
+    New,
+}
+
+impl AttachMode {
+    /// Parse the command from `ssh host <command>`
+    ///
+    /// An empty command is a plain login.
+    pub fn parse(command: &str) -> Option<Self> {
+        match command.trim() {
+            "" => Some(Self::Auto),
+            "attach" | "a" => Some(Self::Attach),
+            "new" => Some(Self::New),
+            _ => None,
+        }
+    }
+}
+
+/// A user's view state, kept across connections
+pub struct StoredSession {
+    pub id: u64,
+    pub username: String,
+    pub lua_runtime: Arc<TokioMutex<LuaRuntime>>,
+    pub session_state: Arc<TokioMutex<SessionState>>,
+    /// Feeds the session's streaming task, which lives as long as this does
+    pub update_tx: mpsc::Sender<RowUpdate>,
+    /// Connections currently showing this session
+    attached: AtomicUsize,
+    /// Bumped whenever the session is attached or detached, so an expiry
+    /// timer can tell that it's stale
+    generation: AtomicU64,
+    /// When the session was last attached or detached
+    last_used: Mutex<Instant>,
+}
+
+impl StoredSession {
+    /// Number of connections showing this session
+    pub fn attached(&self) -> usize {
+        self.attached.load(Ordering::SeqCst)
+    }
+
+    fn touch(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut last_used) = self.last_used.lock() {
+            *last_used = Instant::now();
+        }
+    }
+
+    fn last_used(&self) -> Instant {
+        self.last_used
+            .lock()
+            .map(|t| *t)
+            .unwrap_or_else(|_| Instant::now())
+    }
+}
+
+/// Sessions by id, shared by every connection
+pub struct SessionStore {
+    sessions: Mutex<HashMap<u64, Arc<StoredSession>>>,
+    next_id: AtomicU64,
+    /// How long a session with no connections is kept
+    grace: Duration,
+    /// Where sessions hold their user's presence
+    world: OnceLock<Arc<World>>,
+}
+
+impl SessionStore {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            grace,
+            world: OnceLock::new(),
+        }
+    }
+
+    /// Hold users in the world while they have sessions
+    pub fn track_presence(&self, world: Arc<World>) {
+        let _ = self.world.set(world);
+    }
+
+    /// How long detached sessions are kept
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    /// Store a new session, attached to the calling connection
+    pub fn create(
+        self: &Arc<Self>,
+        username: &str,
+        lua_runtime: Arc<TokioMutex<LuaRuntime>>,
+        session_state: Arc<TokioMutex<SessionState>>,
+        update_tx: mpsc::Sender<RowUpdate>,
+    ) -> SessionAttachment {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let session = Arc::new(StoredSession {
+            id,
+            username: username.to_string(),
+            lua_runtime,
+            session_state,
+            update_tx,
+            attached: AtomicUsize::new(0),
+            generation: AtomicU64::new(0),
+            last_used: Mutex::new(Instant::now()),
+        });
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(id, session.clone());
+        }
+        if let Some(world) = self.world.get() {
+            if let Err(e) = world.hold(&presence_holder(id), username) {
+                warn!(user = %username, "failed to hold presence: {:#}", e);
+            }
+        }
+        self.attach(session)
+    }
+
+    /// Attach a user's new connection to one of their existing sessions
+    ///
+    /// Returns None when the connection should start fresh.
+    pub fn resume(self: &Arc<Self>, username: &str, mode: AttachMode) -> Option<SessionAttachment> {
+        // Hold the lock while attaching so the session can't expire between
+        // being picked and being attached
+        let sessions = self.sessions.lock().ok()?;
+        let mine = sessions.values().filter(|s| s.username == username);
+        let session = match mode {
+            AttachMode::New => None,
+            AttachMode::Auto => mine
+                .filter(|s| s.attached() == 0)
+                .max_by_key(|s| s.last_used()),
+            AttachMode::Attach => mine.max_by_key(|s| s.last_used()),
+        }?;
+        Some(self.attach(session.clone()))
+    }
+
+    fn attach(self: &Arc<Self>, session: Arc<StoredSession>) -> SessionAttachment {
+        session.attached.fetch_add(1, Ordering::SeqCst);
+        session.touch();
+        SessionAttachment {
+            store: self.clone(),
+            session,
+            closed: false,
+        }
+    }
+
+    /// A user's sessions, most recently used first
+    pub fn list(&self, username: &str) -> Vec<Arc<StoredSession>> {
+        let mut list: Vec<_> = self
+            .sessions
+            .lock()
+            .map(|s| {
+                s.values()
+                    .filter(|s| s.username == username)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        list.sort_by_key(|s| std::cmp::Reverse(s.last_used()));
+        list
+    }
+
+    fn remove(&self, id: u64) {
+        let removed = match self.sessions.lock() {
+            Ok(mut sessions) => sessions.remove(&id),
+            Err(_) => None,
+        };
+        if let Some(session) = removed {
+            self.ended(&session);
+        }
+    }
+
+    /// Drop a session if it's still detached and nothing touched it since
+    fn expire(&self, id: u64, generation: u64) {
+        let expired = {
+            let Ok(mut sessions) = self.sessions.lock() else {
+                return;
+            };
+            let stale = sessions.get(&id).is_some_and(|s| {
+                s.attached() == 0 && s.generation.load(Ordering::SeqCst) == generation
+            });
+            if stale {
+                sessions.remove(&id)
+            } else {
+                None
+            }
+        };
+        if let Some(session) = expired {
+            info!(user = %session.username, id, "detached session expired");
+            self.ended(&session);
+        }
+    }
+
+    /// A session is gone: with nothing else holding them, the user leaves
+    /// their room
+    fn ended(&self, session: &StoredSession) {
+        let Some(world) = self.world.get() else {
+            return;
+        };
+        if let Err(e) = world.release(&presence_holder(session.id)) {
+            warn!(user = %session.username, "failed to leave room: {:#}", e);
+        }
+    }
+
+    fn detach(self: &Arc<Self>, session: &Arc<StoredSession>, keep: bool) {
+        if session.attached.fetch_sub(1, Ordering::SeqCst) != 1 {
+            return; // Still shown elsewhere
+        }
+        session.touch();
+
+        if !keep || self.grace.is_zero() {
+            self.remove(session.id);
+            return;
+        }
+
+        info!(user = %session.username, id = session.id, "session detached");
+        let generation = session.generation.load(Ordering::SeqCst);
+        let store = self.clone();
+        let id = session.id;
+        match tokio::runtime::Handle::try_current() {
+            Ok(rt) => {
+                rt.spawn(async move {
+                    tokio::time::sleep(store.grace).await;
+                    store.expire(id, generation);
+                });
+            }
+            // No runtime to time out on; don't keep it forever
+            Err(_) => self.remove(id),
+        }
+    }
+}
+
+/// A connection's hold on a session; dropping it detaches
+pub struct SessionAttachment {
+    store: Arc<SessionStore>,
+    session: Arc<StoredSession>,
+    closed: bool,
+}
+
+impl SessionAttachment {
+    pub fn session(&self) -> &Arc<StoredSession> {
+        &self.session
+    }
+
+    /// End the session instead of keeping it (e.g. on /quit)
+    ///
+    /// Sessions still shown on other connections carry on.
+    pub fn close(mut self) {
+        self.closed = true;
+        self.store.detach(&self.session, false);
+    }
+}
+
+impl Drop for SessionAttachment {
+    fn drop(&mut self) {
+        if !self.closed {
+            self.store.detach(&self.session, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_session(store: &Arc<SessionStore>, user: &str) -> SessionAttachment {
+        let (update_tx, _update_rx) = mpsc::channel(1);
+        store.create(
+            user,
+            Arc::new(TokioMutex::new(LuaRuntime::new().expect("lua"))),
+            Arc::new(TokioMutex::new(SessionState::new())),
+            update_tx,
+        )
+    }
+
+    #[test]
+    fn test_attach_modes() {
+        assert_eq!(AttachMode::parse(""), Some(AttachMode::Auto));
+        assert_eq!(AttachMode::parse(" attach "), Some(AttachMode::Attach));
+        assert_eq!(AttachMode::parse("new"), Some(AttachMode::New));
+        assert_eq!(AttachMode::parse("rm -rf"), None);
+    }
+
+    #[tokio::test]
+    async fn test_detached_session_is_resumed() {
+        let store = Arc::new(SessionStore::new(Duration::from_secs(60)));
+        let first = new_session(&store, "alice");
+        let id = first.session().id;
+
+        // While attached, a plain login starts fresh but attach mirrors
+        assert!(store.resume("alice", AttachMode::Auto).is_none());
+        let mirror = store.resume("alice", AttachMode::Attach).expect("mirror");
+        assert_eq!(mirror.session().id, id);
+        assert_eq!(mirror.session().attached(), 2);
+        drop(mirror);
+
+        drop(first);
+        assert_eq!(store.list("alice")[0].attached(), 0);
+        assert!(store.resume("alice", AttachMode::New).is_none());
+        assert!(store.resume("bob", AttachMode::Auto).is_none());
+        let resumed = store.resume("alice", AttachMode::Auto).expect("kept");
+        assert_eq!(resumed.session().id, id);
+    }
+
+    #[tokio::test]
+    async fn test_detached_session_expires() {
+        let store = Arc::new(SessionStore::new(Duration::from_millis(20)));
+        drop(new_session(&store, "alice"));
+        assert_eq!(store.list("alice").len(), 1);
+
+        // Reattaching in time keeps it past the first timer
+        let again = store.resume("alice", AttachMode::Auto).expect("kept");
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(store.list("alice").len(), 1);
+
+        drop(again);
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(store.list("alice").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_ends_session() {
+        let store = Arc::new(SessionStore::new(Duration::from_secs(60)));
+        new_session(&store, "alice").close();
+        assert!(store.list("alice").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_last_session_leaves_room() -> anyhow::Result<()> {
+        let db = Arc::new(crate::db::Database::in_memory()?);
+        db.create_room("lobby", None)?;
+        let world = Arc::new(World::new(db));
+        let store = Arc::new(SessionStore::new(Duration::from_secs(60)));
+        store.track_presence(world.clone());
+
+        let first = new_session(&store, "alice");
+        let second = new_session(&store, "alice");
+        world.enter("alice", "lobby")?;
+
+        first.close();
+        assert_eq!(world.users_in("lobby")?, vec!["alice"]);
+        second.close();
+        assert!(world.users_in("lobby")?.is_empty());
+        Ok(())
+    }
+}
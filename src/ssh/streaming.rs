@@ -2,12 +2,23 @@
 //!
 //! Handles model response streaming with Row updates.
 //! Updates are written to the database; Lua's on_tick renders them via tools.history().
+//! Database writes run on the blocking pool so token-rate updates don't stall
+//! the async executor. Chunks go through the database's write-behind buffer
+//! and are flushed every `APPEND_FLUSH_INTERVAL` or when the row is finalized.
+//!
+//! The same task listens for row events from every session and marks the chat
+//! region dirty only when the buffer this session is viewing changed.
 
+use crate::db::events::RowEvent;
+use crate::db::write_behind::APPEND_FLUSH_INTERVAL;
 use crate::db::Database;
-use crate::lua::LuaRuntime;
+use crate::lua::{LuaRuntime, LuaToolState};
 use crate::status::Status;
+use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::{mpsc, Mutex};
+use tokio::time::MissedTickBehavior;
 
 /// Update from background task for streaming responses
 #[derive(Debug)]
@@ -41,27 +52,110 @@ pub enum RowUpdate {
     },
 }
 
+/// The chat buffer a session is currently viewing
+///
+/// Follows the Lua session context, so room changes made by `/join` or
+/// `/go` are picked up on the next event.
+#[derive(Default)]
+struct WatchedBuffer {
+    room_id: Option<String>,
+    buffer_id: Option<String>,
+}
+
+impl WatchedBuffer {
+    /// Whether an event touches the buffer the session is viewing
+    ///
+    /// With no room to resolve, every event counts as a match.
+    async fn matches(
+        &mut self,
+        db: &Arc<Database>,
+        tool_state: &LuaToolState,
+        event: &RowEvent,
+    ) -> bool {
+        let room_id = tool_state.session_context().and_then(|ctx| ctx.room_id);
+        if room_id != self.room_id || self.buffer_id.is_none() {
+            self.buffer_id = match room_id.clone() {
+                Some(id) => db
+                    .blocking(move |db| db.get_or_create_room_chat_buffer(&id))
+                    .await
+                    .map(|buffer| buffer.id)
+                    .ok(),
+                None => None,
+            };
+            self.room_id = room_id;
+        }
+
+        match self.buffer_id {
+            Some(ref buffer_id) => *buffer_id == event.buffer_id,
+            None => true,
+        }
+    }
+}
+
 /// Background task that processes streaming updates
 ///
-/// Updates are written to the database only - Lua's on_tick renders them
-/// via tools.history() at ~100ms intervals.
+/// Updates are written to the database only. Row events from `row_rx` mark
+/// the chat region dirty, and Lua re-renders it via tools.history().
 pub async fn push_updates_task(
     mut rx: mpsc::Receiver<RowUpdate>,
+    mut row_rx: broadcast::Receiver<RowEvent>,
     db: Arc<Database>,
     lua_runtime: Option<Arc<Mutex<LuaRuntime>>>,
 ) {
-    while let Some(update) = rx.recv().await {
+    // Clone the tool state once so row events don't need the Lua mutex
+    let tool_state = match lua_runtime {
+        Some(ref lua_runtime) => Some(lua_runtime.lock().await.tool_state().clone()),
+        None => None,
+    };
+    let mut watched = WatchedBuffer::default();
+    let mut events_open = tool_state.is_some();
+
+    // Rows with buffered chunks that haven't been finalized yet
+    let mut streaming: HashSet<String> = HashSet::new();
+    let mut flush_tick = tokio::time::interval(APPEND_FLUSH_INTERVAL);
+    flush_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let update = tokio::select! {
+            update = rx.recv() => match update {
+                Some(update) => update,
+                None => break,
+            },
+            _ = flush_tick.tick(), if !streaming.is_empty() => {
+                flush_rows(&db, streaming.iter().cloned().collect()).await;
+                continue;
+            }
+            event = row_rx.recv(), if events_open => {
+                let Some(ref tool_state) = tool_state else {
+                    continue;
+                };
+                match event {
+                    Ok(event) => {
+                        if watched.matches(&db, tool_state, &event).await {
+                            tool_state.mark_dirty("chat");
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        // Missed events could be ours; redraw to be safe
+                        tracing::debug!(skipped, "row events lagged");
+                        tool_state.mark_dirty("chat");
+                    }
+                    Err(RecvError::Closed) => events_open = false,
+                }
+                continue;
+            }
+        };
+
         match update {
             RowUpdate::Chunk { row_id, text } => {
-                // Append to database row - Lua will render via tools.history()
-                if let Err(e) = db.append_to_row(&row_id, &text) {
+                // Buffer the chunk - readers see it merged, the tick writes it
+                streaming.insert(row_id.clone());
+                if let Err(e) = db
+                    .blocking(move |db| db.buffer_append(&row_id, &text))
+                    .await
+                {
                     tracing::error!("failed to append to row: {}", e);
                 }
-                // Signal chat region needs refresh
-                if let Some(ref lua_runtime) = lua_runtime {
-                    let lua = lua_runtime.lock().await;
-                    lua.tool_state().mark_dirty("chat");
-                }
             }
 
             RowUpdate::ToolCall {
@@ -73,10 +167,8 @@ pub async fn push_updates_task(
                 agent_id,
             } => {
                 // Update status for Lua HUD
-                if let Some(ref lua_runtime) = lua_runtime {
-                    let lua = lua_runtime.lock().await;
-                    lua.tool_state()
-                        .set_status(&model_name, Status::RunningTool(tool_name.clone()));
+                if let Some(ref tool_state) = tool_state {
+                    tool_state.set_status(&model_name, Status::RunningTool(tool_name.clone()));
                 }
 
                 // Create a proper tool.call row linked to the model message
@@ -87,15 +179,9 @@ pub async fn push_updates_task(
                     &tool_name,
                     tool_args.as_ref(),
                 );
-                if let Err(e) = db.append_row(&mut tool_row) {
+                if let Err(e) = db.blocking(move |db| db.append_row(&mut tool_row)).await {
                     tracing::error!("failed to create tool call row: {}", e);
                 }
-
-                // Signal chat region needs refresh
-                if let Some(ref lua_runtime) = lua_runtime {
-                    let lua = lua_runtime.lock().await;
-                    lua.tool_state().mark_dirty("chat");
-                }
             }
 
             RowUpdate::ToolResult {
@@ -109,49 +195,50 @@ pub async fn push_updates_task(
                 let mut result_row = crate::db::rows::Row::tool_result_with_parent(
                     &buffer_id, &row_id, &tool_name, &summary, success,
                 );
-                if let Err(e) = db.append_row(&mut result_row) {
+                if let Err(e) = db.blocking(move |db| db.append_row(&mut result_row)).await {
                     tracing::error!("failed to create tool result row: {}", e);
                 }
-
-                // Signal chat region needs refresh
-                if let Some(ref lua_runtime) = lua_runtime {
-                    let lua = lua_runtime.lock().await;
-                    lua.tool_state().mark_dirty("chat");
-                }
             }
 
             RowUpdate::Complete { row_id, model_name } => {
-                // Get the thinking.stream row content to create final message
-                if let Ok(Some(thinking_row)) = db.get_row(&row_id) {
-                    if let Some(content) = thinking_row.content {
-                        // Create a new message.model row with the final content
-                        let mut message_row =
-                            crate::db::rows::Row::new(&thinking_row.buffer_id, "message.model");
-                        message_row.source_agent_id = thinking_row.source_agent_id.clone();
-                        message_row.content = Some(content);
-                        message_row.mutable = false;
-
-                        if let Err(e) = db.append_row(&mut message_row) {
-                            tracing::error!("failed to create message.model row: {}", e);
-                        }
+                // finalize_row flushes the buffered tail before marking it final
+                streaming.remove(&row_id);
+                let result = db
+                    .blocking(move |db| {
+                        // Get the thinking.stream row content to create final message
+                        if let Ok(Some(thinking_row)) = db.get_row(&row_id) {
+                            if let Some(content) = thinking_row.content {
+                                // Create a new message.model row with the final content
+                                let mut message_row = crate::db::rows::Row::new(
+                                    &thinking_row.buffer_id,
+                                    "message.model",
+                                );
+                                message_row.source_agent_id = thinking_row.source_agent_id.clone();
+                                message_row.content = Some(content);
+                                message_row.mutable = false;
 
-                        // Mark the thinking.stream row as ephemeral (won't show in history)
-                        if let Err(e) = db.set_row_ephemeral(&row_id, true) {
-                            tracing::error!("failed to mark thinking row ephemeral: {}", e);
+                                if let Err(e) = db.append_row(&mut message_row) {
+                                    tracing::error!("failed to create message.model row: {}", e);
+                                }
+
+                                // Mark the thinking.stream row as ephemeral (won't show in history)
+                                if let Err(e) = db.set_row_ephemeral(&row_id, true) {
+                                    tracing::error!("failed to mark thinking row ephemeral: {}", e);
+                                }
+                            }
                         }
-                    }
-                }
 
-                // Finalize the thinking row
-                if let Err(e) = db.finalize_row(&row_id) {
+                        // Finalize the thinking row
+                        db.finalize_row(&row_id)
+                    })
+                    .await;
+                if let Err(e) = result {
                     tracing::error!("failed to finalize row: {}", e);
                 }
 
                 // Update status to idle for Lua HUD
-                if let Some(ref lua_runtime) = lua_runtime {
-                    let lua = lua_runtime.lock().await;
-                    lua.tool_state().set_status(&model_name, Status::Idle);
-                    lua.tool_state().mark_dirty("chat");
+                if let Some(ref tool_state) = tool_state {
+                    tool_state.set_status(&model_name, Status::Idle);
                 }
             }
 
@@ -160,23 +247,49 @@ pub async fn push_updates_task(
                 model_name,
                 message,
             } => {
-                // Write error into the thinking row so the user sees what happened
-                if let Err(e) = db.append_to_row(&row_id, &format!("\n\nError: {}", message)) {
-                    tracing::error!("failed to append error to row: {}", e);
-                }
+                streaming.remove(&row_id);
+                let result = db
+                    .blocking(move |db| {
+                        // Write error into the thinking row so the user sees what happened
+                        if let Err(e) =
+                            db.append_to_row(&row_id, &format!("\n\nError: {}", message))
+                        {
+                            tracing::error!("failed to append error to row: {}", e);
+                        }
 
-                // Finalize the thinking row (keeps it visible, not ephemeral)
-                if let Err(e) = db.finalize_row(&row_id) {
+                        // Finalize the thinking row (keeps it visible, not ephemeral)
+                        db.finalize_row(&row_id)
+                    })
+                    .await;
+                if let Err(e) = result {
                     tracing::error!("failed to finalize error row: {}", e);
                 }
 
                 // Update status to idle for Lua HUD
-                if let Some(ref lua_runtime) = lua_runtime {
-                    let lua = lua_runtime.lock().await;
-                    lua.tool_state().set_status(&model_name, Status::Idle);
-                    lua.tool_state().mark_dirty("chat");
+                if let Some(ref tool_state) = tool_state {
+                    tool_state.set_status(&model_name, Status::Idle);
                 }
             }
         }
     }
+
+    // Sender dropped (session closed) - don't leave text stranded in memory
+    if !streaming.is_empty() {
+        flush_rows(&db, streaming.into_iter().collect()).await;
+    }
+}
+
+/// Write buffered chunks for the given rows
+async fn flush_rows(db: &Arc<Database>, row_ids: Vec<String>) {
+    let result = db
+        .blocking(move |db| {
+            for row_id in &row_ids {
+                db.flush_row_appends(row_id)?;
+            }
+            Ok(())
+        })
+        .await;
+    if let Err(e) = result {
+        tracing::error!("failed to flush streamed chunks: {}", e);
+    }
 }
@@ -1,7 +1,6 @@
 //! Shared server state
 
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
 use crate::config::Config;
 use crate::db::Database;
@@ -9,11 +8,13 @@ use crate::llm::LlmClient;
 use crate::lua::LuaReloadSender;
 use crate::mcp::McpManager;
 use crate::model::ModelRegistry;
+use crate::shutdown::Shutdown;
+use crate::ssh::SessionStore;
 use crate::world::World;
 
 /// The shared world state accessible by both SSH and MCP servers
 pub struct SharedState {
-    pub world: Arc<RwLock<World>>,
+    pub world: Arc<World>,
     pub db: Arc<Database>,
     pub config: Config,
     pub llm: Arc<LlmClient>,
@@ -21,4 +22,8 @@ pub struct SharedState {
     pub mcp: Arc<McpManager>,
     /// Broadcast sender for Lua hot reload events
     pub lua_reload: LuaReloadSender,
+    /// Coordinates draining sessions on SIGTERM/SIGINT
+    pub shutdown: Arc<Shutdown>,
+    /// Sessions kept alive across reconnects
+    pub sessions: Arc<SessionStore>,
 }
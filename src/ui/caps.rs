@@ -0,0 +1,385 @@
+//! Terminal capabilities
+//!
+//! What a client's terminal can display, detected from the `term` value of
+//! its pty request and any `COLORTERM` it sends. Detection errs on the side
+//! of less: a terminal we don't recognise gets 256 colors and no Powerline
+//! glyphs. `/term` overrides it per connection.
+
+use crossterm::style::Color;
+use std::sync::{Arc, RwLock};
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorDepth {
+    /// 24-bit RGB
+    #[default]
+    TrueColor,
+    /// xterm 256-color palette
+    Ansi256,
+    /// The 16 standard colors
+    Ansi16,
+    /// No color at all
+    Mono,
+}
+
+/// Which glyphs separators and decorations may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Glyphs {
+    /// Powerline/Nerd Font private use glyphs
+    #[default]
+    Powerline,
+    /// Standard Unicode only
+    Unicode,
+    /// Plain ASCII
+    Ascii,
+}
+
+/// Detected (or overridden) terminal capabilities for a connection
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TermCaps {
+    /// `term` from the pty request
+    pub term: String,
+    /// `COLORTERM` from the client's environment, if sent
+    pub colorterm: Option<String>,
+    pub color: ColorDepth,
+    pub glyphs: Glyphs,
+}
+
+/// One connection's capabilities, shared with its screen
+///
+/// Mirrored and reattached sessions share their Lua state but not their
+/// terminals, so each connection keeps its own.
+pub type ConnectionCaps = Arc<RwLock<TermCaps>>;
+
+/// Terminals known to do 24-bit color even without COLORTERM
+const TRUECOLOR_TERMS: &[&str] = &[
+    "alacritty",
+    "contour",
+    "foot",
+    "ghostty",
+    "iterm",
+    "kitty",
+    "wezterm",
+];
+
+impl TermCaps {
+    /// Work out capabilities from what the client told us
+    pub fn detect(term: &str, colorterm: Option<&str>) -> Self {
+        let term_lower = term.to_lowercase();
+        let colorterm_lower = colorterm.map(str::to_lowercase);
+
+        let color = if term_lower == "dumb" {
+            ColorDepth::Mono
+        } else if matches!(colorterm_lower.as_deref(), Some("truecolor" | "24bit"))
+            || term_lower.ends_with("-direct")
+            || TRUECOLOR_TERMS.iter().any(|t| term_lower.contains(t))
+        {
+            ColorDepth::TrueColor
+        } else if term_lower.contains("256color") || term_lower.is_empty() {
+            ColorDepth::Ansi256
+        } else if term_lower == "linux"
+            || term_lower.starts_with("vt")
+            || term_lower.starts_with("ansi")
+            || term_lower.starts_with("cons")
+            || term_lower.contains("16color")
+            || matches!(term_lower.as_str(), "xterm" | "screen" | "tmux" | "rxvt")
+        {
+            ColorDepth::Ansi16
+        } else {
+            ColorDepth::Ansi256
+        };
+
+        // Powerline glyphs need a patched font we can't see, so only assume
+        // them for the modern terminals that usually ship with one
+        let glyphs = if color == ColorDepth::Mono
+            || term_lower == "linux"
+            || term_lower.starts_with("vt")
+            || term_lower.starts_with("ansi")
+            || term_lower.starts_with("cons")
+        {
+            Glyphs::Ascii
+        } else if color == ColorDepth::TrueColor {
+            Glyphs::Powerline
+        } else {
+            Glyphs::Unicode
+        };
+
+        Self {
+            term: term.to_string(),
+            colorterm: colorterm.map(str::to_string),
+            color,
+            glyphs,
+        }
+    }
+}
+
+impl ColorDepth {
+    /// Parse a `/term` color setting
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "truecolor" | "24bit" | "rgb" => Some(Self::TrueColor),
+            "256" => Some(Self::Ansi256),
+            "16" => Some(Self::Ansi16),
+            "mono" | "none" | "2" => Some(Self::Mono),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TrueColor => "truecolor",
+            Self::Ansi256 => "256",
+            Self::Ansi16 => "16",
+            Self::Mono => "mono",
+        }
+    }
+
+    /// Map a color onto what this depth can show (None drops it)
+    ///
+    /// 256 and 16 color results are `Color::AnsiValue` palette indexes.
+    pub fn quantize(&self, color: Color) -> Option<Color> {
+        match self {
+            Self::TrueColor => Some(color),
+            Self::Mono => None,
+            Self::Ansi256 => match color {
+                Color::Rgb { r, g, b } => Some(Color::AnsiValue(nearest_256(r, g, b))),
+                other => Some(other),
+            },
+            Self::Ansi16 => {
+                let (r, g, b) = to_rgb(color)?;
+                Some(Color::AnsiValue(nearest_16(r, g, b)))
+            }
+        }
+    }
+
+    /// Append the SGR escape setting a foreground or background color
+    pub fn write_color(&self, out: &mut String, color: Color, foreground: bool) {
+        use crossterm::style::{SetBackgroundColor, SetForegroundColor};
+        use crossterm::Command;
+
+        let Some(color) = self.quantize(color) else {
+            return;
+        };
+        match (self, color) {
+            // 16-color terminals don't understand 38;5 palette escapes
+            (Self::Ansi16, Color::AnsiValue(n)) => {
+                let base = match (foreground, n < 8) {
+                    (true, true) => 30,
+                    (true, false) => 90,
+                    (false, true) => 40,
+                    (false, false) => 100,
+                };
+                out.push_str(&format!("\x1b[{}m", base + (n % 8) as u16));
+            }
+            _ if foreground => {
+                let _ = SetForegroundColor(color).write_ansi(out);
+            }
+            _ => {
+                let _ = SetBackgroundColor(color).write_ansi(out);
+            }
+        }
+    }
+}
+
+impl Glyphs {
+    /// Parse a `/term` glyph setting
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "powerline" | "nerd" => Some(Self::Powerline),
+            "unicode" => Some(Self::Unicode),
+            "ascii" => Some(Self::Ascii),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Powerline => "powerline",
+            Self::Unicode => "unicode",
+            Self::Ascii => "ascii",
+        }
+    }
+}
+
+/// The standard 16 colors, as xterm draws them
+const ANSI_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Levels of each channel in the 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_cube_level(v: u8) -> usize {
+    (0..CUBE_LEVELS.len())
+        .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+        .unwrap_or(0)
+}
+
+/// Closest xterm-256 index, from the color cube or the gray ramp
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (
+        nearest_cube_level(r),
+        nearest_cube_level(g),
+        nearest_cube_level(b),
+    );
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let avg = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = (avg.saturating_sub(8) / 10).min(23) as u8;
+    let gray_level = 8 + gray_step * 10;
+    let gray = (gray_level, gray_level, gray_level);
+
+    if distance((r, g, b), gray) < distance((r, g, b), cube) {
+        232 + gray_step
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Closest of the 16 standard colors by hue and brightness
+///
+/// Plain RGB distance sends pastel themes to gray, so colored input keeps
+/// its hue: each channel well above the weakest one picks a color bit.
+fn nearest_16(r: u8, g: u8, b: u8) -> u8 {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+
+    if chroma < 40 {
+        return match max {
+            0..=63 => 0,
+            64..=159 => 8,
+            160..=223 => 7,
+            _ => 15,
+        };
+    }
+
+    let mid = min as u16 + chroma as u16 / 2;
+    let bit = |v: u8, n: u8| if v as u16 > mid { n } else { 0 };
+    let hue = bit(r, 1) | bit(g, 2) | bit(b, 4);
+    if max > 200 {
+        hue + 8
+    } else {
+        hue
+    }
+}
+
+/// RGB value of any crossterm color (None for Reset)
+fn to_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    let index = match color {
+        Color::Rgb { r, g, b } => return Some((r, g, b)),
+        Color::Reset => return None,
+        Color::AnsiValue(n) => n,
+        Color::Black => 0,
+        Color::DarkRed => 1,
+        Color::DarkGreen => 2,
+        Color::DarkYellow => 3,
+        Color::DarkBlue => 4,
+        Color::DarkMagenta => 5,
+        Color::DarkCyan => 6,
+        Color::Grey => 7,
+        Color::DarkGrey => 8,
+        Color::Red => 9,
+        Color::Green => 10,
+        Color::Yellow => 11,
+        Color::Blue => 12,
+        Color::Magenta => 13,
+        Color::Cyan => 14,
+        Color::White => 15,
+    };
+    Some(match index {
+        0..=15 => ANSI_16[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[((i / 6) % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let caps = TermCaps::detect("xterm-256color", Some("truecolor"));
+        assert_eq!(caps.color, ColorDepth::TrueColor);
+        assert_eq!(caps.glyphs, Glyphs::Powerline);
+
+        let caps = TermCaps::detect("xterm-256color", None);
+        assert_eq!(caps.color, ColorDepth::Ansi256);
+        assert_eq!(caps.glyphs, Glyphs::Unicode);
+
+        let caps = TermCaps::detect("linux", None);
+        assert_eq!(caps.color, ColorDepth::Ansi16);
+        assert_eq!(caps.glyphs, Glyphs::Ascii);
+
+        assert_eq!(
+            TermCaps::detect("xterm-kitty", None).color,
+            ColorDepth::TrueColor
+        );
+        assert_eq!(TermCaps::detect("dumb", None).color, ColorDepth::Mono);
+    }
+
+    #[test]
+    fn test_quantize_256() {
+        // Exact cube and gray ramp entries map to themselves
+        assert_eq!(nearest_256(255, 0, 0), 196);
+        assert_eq!(nearest_256(95, 135, 175), 16 + 36 + 2 * 6 + 3);
+        assert_eq!(nearest_256(128, 128, 128), 244);
+
+        let c = ColorDepth::Ansi256.quantize(Color::Rgb {
+            r: 125,
+            g: 207,
+            b: 255,
+        });
+        assert_eq!(c, Some(Color::AnsiValue(117)));
+        // Palette colors pass through
+        assert_eq!(ColorDepth::Ansi256.quantize(Color::Grey), Some(Color::Grey));
+    }
+
+    #[test]
+    fn test_quantize_16_and_mono() {
+        let red = Color::Rgb {
+            r: 247,
+            g: 118,
+            b: 142,
+        };
+        assert_eq!(ColorDepth::Ansi16.quantize(red), Some(Color::AnsiValue(9)));
+        assert_eq!(ColorDepth::Mono.quantize(red), None);
+
+        let mut out = String::new();
+        ColorDepth::Ansi16.write_color(&mut out, red, true);
+        ColorDepth::Ansi16.write_color(&mut out, Color::Black, false);
+        assert_eq!(out, "\x1b[91m\x1b[40m");
+    }
+}
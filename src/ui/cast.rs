@@ -0,0 +1,466 @@
+//! Asciicast v2 session recording
+//!
+//! Records what a screen sent to its client as an asciicast v2 file: a JSON
+//! header line, then one `[seconds, code, data]` line per output frame
+//! (`"o"`) or terminal resize (`"r"`). Files play back with
+//! `sshwarma-admin replay` or `asciinema play`.
+//!
+//! Recording is opt-in per session (`/record start|stop`). A session can be
+//! mirrored on several connections, so the first screen to render after
+//! `start` claims the recording and only its output is written.
+//!
+//! Screens only format event lines; a writer thread per recording does the
+//! file I/O, so rendering never waits on the disk.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Extension for recording files
+pub const CAST_EXT: &str = ".cast";
+
+/// Recordings stop taking output past this size
+pub const MAX_CAST_BYTES: u64 = 64 * 1024 * 1024;
+
+/// First line of a cast file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u32,
+    pub width: u16,
+    pub height: u16,
+    /// Unix time the recording started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Only TERM is recorded
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+/// One event line
+#[derive(Debug, Clone, PartialEq)]
+pub enum CastEvent {
+    /// Bytes written to the terminal
+    Output(String),
+    /// Terminal resized to (columns, rows)
+    Resize(u16, u16),
+    /// Input, markers, or anything else; kept but not played
+    Other(String, String),
+}
+
+/// A parsed recording
+#[derive(Debug, Clone)]
+pub struct Cast {
+    pub header: CastHeader,
+    /// Events with their offset from the start in seconds
+    pub events: Vec<(f64, CastEvent)>,
+}
+
+impl Cast {
+    /// Parse asciicast v2 text
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let first = lines.next().context("empty recording")?;
+        let header: CastHeader = serde_json::from_str(first).context("invalid asciicast header")?;
+        if header.version != 2 {
+            anyhow::bail!("unsupported asciicast version {}", header.version);
+        }
+
+        let mut events = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let (time, code, data): (f64, String, String) = serde_json::from_str(line)
+                .with_context(|| format!("invalid event on line {}", i + 2))?;
+            let event = match code.as_str() {
+                "o" => CastEvent::Output(data),
+                "r" => match parse_size(&data) {
+                    Some((w, h)) => CastEvent::Resize(w, h),
+                    None => anyhow::bail!("invalid resize on line {}: {}", i + 2, data),
+                },
+                _ => CastEvent::Other(code, data),
+            };
+            events.push((time, event));
+        }
+
+        Ok(Self { header, events })
+    }
+
+    /// Read and parse a cast file
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Offset of the last event in seconds
+    pub fn duration(&self) -> f64 {
+        self.events.last().map(|(t, _)| *t).unwrap_or(0.0)
+    }
+}
+
+fn parse_size(s: &str) -> Option<(u16, u16)> {
+    let (w, h) = s.split_once('x')?;
+    Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+}
+
+/// File name for a new recording, e.g. `alice-20260101T120000Z.cast`
+pub fn cast_file_name(username: &str) -> String {
+    let ts = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let name: String = username
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{}{}", name, ts, CAST_EXT)
+}
+
+/// What `Recorder::stop` reports
+#[derive(Debug, Clone)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    pub duration: Duration,
+    pub events: usize,
+    /// Output stopped being recorded at `MAX_CAST_BYTES`
+    pub truncated: bool,
+}
+
+/// What screens send to a recording's writer thread
+enum WriterMsg {
+    Line(String),
+    Flush,
+}
+
+/// Write lines to `out` until every sender is gone, then flush
+fn write_lines(mut out: BufWriter<File>, rx: mpsc::Receiver<WriterMsg>) -> std::io::Result<()> {
+    for msg in rx {
+        match msg {
+            WriterMsg::Line(line) => writeln!(out, "{}", line)?,
+            WriterMsg::Flush => out.flush()?,
+        }
+    }
+    out.flush()
+}
+
+struct Recording {
+    path: PathBuf,
+    title: String,
+    term: String,
+    tx: mpsc::Sender<WriterMsg>,
+    writer: JoinHandle<std::io::Result<()>>,
+    /// Set when the header is written (the first claim)
+    started: Option<Instant>,
+    /// Screen whose output is being recorded
+    screen: Option<u64>,
+    events: usize,
+    bytes: u64,
+    truncated: bool,
+}
+
+impl Recording {
+    /// Queue a message for the writer; fails once the writer has given up
+    fn send(&self, msg: WriterMsg) -> std::io::Result<()> {
+        self.tx
+            .send(msg)
+            .map_err(|_| std::io::Error::other("recording writer stopped"))
+    }
+
+    fn write_header(&mut self, width: u16, height: u16) -> std::io::Result<()> {
+        let mut env = HashMap::new();
+        if !self.term.is_empty() {
+            env.insert("TERM".to_string(), self.term.clone());
+        }
+        let header = CastHeader {
+            version: 2,
+            width,
+            height,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            title: Some(self.title.clone()),
+            env,
+        };
+        self.send(WriterMsg::Line(serde_json::to_string(&header)?))?;
+        self.started = Some(Instant::now());
+        Ok(())
+    }
+
+    fn write_event(&mut self, code: &str, data: &str) -> std::io::Result<()> {
+        let elapsed = self
+            .started
+            .map(|s| s.elapsed().as_secs_f64())
+            .unwrap_or(0.0);
+        // Microsecond precision, like asciinema itself
+        let time = (elapsed * 1_000_000.0).round() / 1_000_000.0;
+        let line = serde_json::to_string(&(time, code, data))?;
+        let len = line.len() as u64 + 1;
+        self.send(WriterMsg::Line(line))?;
+        self.events += 1;
+        self.bytes += len;
+        Ok(())
+    }
+
+    /// Close the channel and wait for the writer to finish the file
+    fn finish(self) -> std::io::Result<()> {
+        drop(self.tx);
+        self.writer
+            .join()
+            .unwrap_or_else(|_| Err(std::io::Error::other("recording writer panicked")))
+    }
+}
+
+/// Opt-in recorder shared by the screens showing one session
+#[derive(Default)]
+pub struct Recorder {
+    active: Mutex<Option<Recording>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start recording into `path`
+    ///
+    /// Nothing is written until a screen claims the recording.
+    pub fn start(&self, path: PathBuf, title: &str, term: &str) -> Result<()> {
+        let mut active = self
+            .active
+            .lock()
+            .map_err(|_| anyhow::anyhow!("recorder lock poisoned"))?;
+        if let Some(ref rec) = *active {
+            anyhow::bail!("already recording to {}", rec.path.display());
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let file =
+            File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+
+        let (tx, rx) = mpsc::channel();
+        let out = BufWriter::new(file);
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            let result = write_lines(out, rx);
+            if let Err(ref e) = result {
+                tracing::warn!(path = %writer_path.display(), "recording write failed: {}", e);
+            }
+            result
+        });
+
+        *active = Some(Recording {
+            path,
+            title: title.to_string(),
+            term: term.to_string(),
+            tx,
+            writer,
+            started: None,
+            screen: None,
+            events: 0,
+            bytes: 0,
+            truncated: false,
+        });
+        Ok(())
+    }
+
+    /// Where the current recording is going, if there is one
+    pub fn path(&self) -> Option<PathBuf> {
+        self.active
+            .lock()
+            .ok()
+            .and_then(|a| a.as_ref().map(|r| r.path.clone()))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.path().is_some()
+    }
+
+    /// Let a screen take an unclaimed recording
+    ///
+    /// Returns true when `screen` just took it, meaning its next frame has
+    /// to repaint everything so the recording starts from a full screen.
+    pub fn claim(&self, screen: u64, width: u16, height: u16) -> bool {
+        self.with_recording(|rec| {
+            if rec.screen.is_some() {
+                return Ok(false);
+            }
+            rec.screen = Some(screen);
+            if rec.started.is_none() {
+                rec.write_header(width, height)?;
+            } else {
+                // Taken over from a screen that went away
+                rec.write_event("r", &format!("{}x{}", width, height))?;
+            }
+            Ok(true)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Let the recording be claimed by another screen (e.g. on disconnect)
+    pub fn release(&self, screen: u64) {
+        self.with_recording(|rec| {
+            if rec.screen == Some(screen) {
+                rec.screen = None;
+                rec.send(WriterMsg::Flush)?;
+            }
+            Ok(())
+        });
+    }
+
+    /// Record a frame sent by `screen`
+    pub fn output(&self, screen: u64, data: &str) {
+        self.with_recording(|rec| {
+            if rec.screen != Some(screen) || rec.truncated {
+                return Ok(());
+            }
+            if rec.bytes + data.len() as u64 > MAX_CAST_BYTES {
+                tracing::warn!(path = %rec.path.display(), "recording too large, no longer recording output");
+                rec.truncated = true;
+                return rec.send(WriterMsg::Flush);
+            }
+            rec.write_event("o", data)
+        });
+    }
+
+    /// Record a terminal resize on `screen`
+    pub fn resize(&self, screen: u64, width: u16, height: u16) {
+        self.with_recording(|rec| {
+            if rec.screen != Some(screen) || rec.truncated {
+                return Ok(());
+            }
+            rec.write_event("r", &format!("{}x{}", width, height))
+        });
+    }
+
+    /// Finish the recording, returning None if there wasn't one
+    pub fn stop(&self) -> Result<Option<RecordingSummary>> {
+        let rec = self
+            .active
+            .lock()
+            .map_err(|_| anyhow::anyhow!("recorder lock poisoned"))?
+            .take();
+        let Some(mut rec) = rec else {
+            return Ok(None);
+        };
+
+        // Never rendered: still leave a valid (empty) recording behind
+        if rec.started.is_none() {
+            rec.write_header(80, 24)?;
+        }
+        let summary = RecordingSummary {
+            duration: rec.started.map(|s| s.elapsed()).unwrap_or_default(),
+            path: rec.path.clone(),
+            events: rec.events,
+            truncated: rec.truncated,
+        };
+        rec.finish()
+            .with_context(|| format!("failed to write {}", summary.path.display()))?;
+
+        Ok(Some(summary))
+    }
+
+    /// Run `f` on the active recording, dropping the recording if writing fails
+    fn with_recording<R>(&self, f: impl FnOnce(&mut Recording) -> std::io::Result<R>) -> Option<R> {
+        let mut active = self.active.lock().ok()?;
+        let rec = active.as_mut()?;
+        match f(rec) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                tracing::warn!(path = %rec.path.display(), "recording failed, stopping: {}", e);
+                *active = None;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cast(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("sshwarma-cast-{}", uuid::Uuid::new_v4()))
+            .join(name)
+    }
+
+    #[test]
+    fn test_records_claimed_screen_only() {
+        let path = temp_cast("test.cast");
+        let recorder = Recorder::new();
+        recorder
+            .start(path.clone(), "demo", "xterm-256color")
+            .unwrap();
+        assert!(recorder.start(path.clone(), "again", "").is_err());
+
+        // Output before any claim is dropped, then screen 1 wins
+        recorder.output(1, "early");
+        assert!(recorder.claim(1, 100, 30));
+        assert!(!recorder.claim(2, 80, 24));
+        recorder.output(1, "\x1b[2Jhello");
+        recorder.output(2, "mirror");
+        recorder.resize(1, 120, 40);
+
+        // Screen 1 leaves, screen 2 takes over at its own size
+        recorder.release(1);
+        assert!(recorder.claim(2, 80, 24));
+        recorder.output(2, "bye");
+
+        let summary = recorder.stop().unwrap().expect("was recording");
+        assert_eq!(summary.path, path);
+        assert_eq!(summary.events, 4);
+        assert!(!recorder.is_recording());
+        assert!(recorder.stop().unwrap().is_none());
+
+        let cast = Cast::read(&path).unwrap();
+        assert_eq!((cast.header.width, cast.header.height), (100, 30));
+        assert_eq!(cast.header.title.as_deref(), Some("demo"));
+        assert_eq!(cast.header.env.get("TERM").unwrap(), "xterm-256color");
+        let events: Vec<_> = cast.events.into_iter().map(|(_, e)| e).collect();
+        assert_eq!(
+            events,
+            vec![
+                CastEvent::Output("\x1b[2Jhello".to_string()),
+                CastEvent::Resize(120, 40),
+                CastEvent::Resize(80, 24),
+                CastEvent::Output("bye".to_string()),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_parse_asciinema_cast() {
+        let text = r#"{"version": 2, "width": 80, "height": 24, "timestamp": 1504467315}
+[0.248848, "o", "\u001b[1;31mHello \u001b[32mWorld!\u001b[0m\n"]
+[1.001376, "i", "x"]
+[2.5, "r", "90x30"]
+"#;
+        let cast = Cast::parse(text).unwrap();
+        assert_eq!(cast.events.len(), 3);
+        assert_eq!(cast.events[1].1, CastEvent::Other("i".into(), "x".into()));
+        assert_eq!(cast.events[2].1, CastEvent::Resize(90, 30));
+        assert_eq!(cast.duration(), 2.5);
+
+        assert!(Cast::parse(r#"{"version": 1, "width": 80, "height": 24}"#).is_err());
+        assert!(Cast::parse("").is_err());
+    }
+
+    #[test]
+    fn test_cast_file_name() {
+        let name = cast_file_name("a/b c");
+        assert!(name.starts_with("a_b_c-"));
+        assert!(name.ends_with(CAST_EXT));
+    }
+}
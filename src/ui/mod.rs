@@ -4,6 +4,10 @@
 //!
 //! Input handling is done entirely in Lua (embedded/ui/input.lua, mode.lua).
 
+pub mod caps;
+pub mod cast;
 pub mod render;
 
+pub use caps::{ColorDepth, ConnectionCaps, Glyphs, TermCaps};
+pub use cast::{Cast, CastEvent, Recorder};
 pub use render::{Cell, LuaDrawContext, RenderBuffer, Style};
@@ -6,6 +6,8 @@
 use crossterm::style::{Attribute, Color};
 use mlua::prelude::*;
 
+use super::caps::{ColorDepth, TermCaps};
+
 /// A single cell in the render buffer
 #[derive(Debug, Clone, Default)]
 pub struct Cell {
@@ -173,6 +175,8 @@ pub struct RenderBuffer {
     width: u16,
     height: u16,
     cells: Vec<Cell>,
+    /// Colors are quantized to this depth when emitting ANSI
+    color: ColorDepth,
 }
 
 impl RenderBuffer {
@@ -182,6 +186,7 @@ impl RenderBuffer {
             width,
             height,
             cells: vec![Cell::default(); size],
+            color: ColorDepth::default(),
         }
     }
 
@@ -193,6 +198,15 @@ impl RenderBuffer {
         self.height
     }
 
+    pub fn color_depth(&self) -> ColorDepth {
+        self.color
+    }
+
+    /// Set the color depth used by the ANSI output methods
+    pub fn set_color_depth(&mut self, color: ColorDepth) {
+        self.color = color;
+    }
+
     fn index(&self, x: u16, y: u16) -> Option<usize> {
         if x < self.width && y < self.height {
             Some((y as usize) * (self.width as usize) + (x as usize))
@@ -448,7 +462,7 @@ impl RenderBuffer {
     /// // Output contains: ESC[17;1H (terminal is 1-indexed)
     /// ```
     pub fn row_to_ansi(&self, y: u16, screen_row: u16) -> String {
-        use crossterm::style::{ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor};
+        use crossterm::style::{ResetColor, SetAttribute};
         use crossterm::Command;
 
         if y >= self.height {
@@ -497,14 +511,14 @@ impl RenderBuffer {
 
             if cell.fg != last_fg {
                 if let Some(color) = cell.fg {
-                    let _ = SetForegroundColor(color).write_ansi(&mut output);
+                    self.color.write_color(&mut output, color, true);
                 }
                 last_fg = cell.fg;
             }
 
             if cell.bg != last_bg {
                 if let Some(color) = cell.bg {
-                    let _ = SetBackgroundColor(color).write_ansi(&mut output);
+                    self.color.write_color(&mut output, color, false);
                 }
                 last_bg = cell.bg;
             }
@@ -557,7 +571,7 @@ impl RenderBuffer {
 
     /// Internal renderer - if start_row is Some, use absolute positioning
     fn to_ansi_impl(&self, start_row: Option<u16>) -> String {
-        use crossterm::style::{ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor};
+        use crossterm::style::{ResetColor, SetAttribute};
         use crossterm::Command;
 
         let mut output = String::new();
@@ -602,14 +616,14 @@ impl RenderBuffer {
 
                 if cell.fg != last_fg {
                     if let Some(color) = cell.fg {
-                        let _ = SetForegroundColor(color).write_ansi(&mut output);
+                        self.color.write_color(&mut output, color, true);
                     }
                     last_fg = cell.fg;
                 }
 
                 if cell.bg != last_bg {
                     if let Some(color) = cell.bg {
-                        let _ = SetBackgroundColor(color).write_ansi(&mut output);
+                        self.color.write_color(&mut output, color, false);
                     }
                     last_bg = cell.bg;
                 }
@@ -637,6 +651,8 @@ pub struct LuaDrawContext {
     pub y: u16,
     pub width: u16,
     pub height: u16,
+    /// Terminal capabilities, exposed to Lua as `ctx.caps`
+    caps: TermCaps,
 }
 
 impl LuaDrawContext {
@@ -653,9 +669,16 @@ impl LuaDrawContext {
             y,
             width,
             height,
+            caps: TermCaps::default(),
         }
     }
 
+    /// Attach the session's terminal capabilities
+    pub fn with_caps(mut self, caps: TermCaps) -> Self {
+        self.caps = caps;
+        self
+    }
+
     fn with_buffer<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&mut RenderBuffer) -> R,
@@ -688,15 +711,26 @@ impl LuaDrawContext {
 impl LuaUserData for LuaDrawContext {
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
         // Field access
-        methods.add_meta_method(mlua::MetaMethod::Index, |_lua, this, key: String| match key
-            .as_str()
-        {
-            "x" => Ok(LuaValue::Integer(this.x as i32)),
-            "y" => Ok(LuaValue::Integer(this.y as i32)),
-            "w" | "width" => Ok(LuaValue::Integer(this.width as i32)),
-            "h" | "height" => Ok(LuaValue::Integer(this.height as i32)),
-            _ => Ok(LuaValue::Nil),
-        });
+        methods.add_meta_method(
+            mlua::MetaMethod::Index,
+            |lua, this, key: String| match key.as_str() {
+                "x" => Ok(LuaValue::Integer(this.x as i32)),
+                "y" => Ok(LuaValue::Integer(this.y as i32)),
+                "w" | "width" => Ok(LuaValue::Integer(this.width as i32)),
+                "h" | "height" => Ok(LuaValue::Integer(this.height as i32)),
+                // {term, colorterm, colors = "truecolor"|"256"|"16"|"mono",
+                //  glyphs = "powerline"|"unicode"|"ascii"}
+                "caps" => {
+                    let caps = lua.create_table()?;
+                    caps.set("term", this.caps.term.as_str())?;
+                    caps.set("colorterm", this.caps.colorterm.as_deref())?;
+                    caps.set("colors", this.caps.color.name())?;
+                    caps.set("glyphs", this.caps.glyphs.name())?;
+                    Ok(LuaValue::Table(caps))
+                }
+                _ => Ok(LuaValue::Nil),
+            },
+        );
 
         // ctx:print(x, y, text, style?)
         methods.add_method(
@@ -859,6 +893,7 @@ impl LuaUserData for LuaDrawContext {
                 y: new_y,
                 width: new_w,
                 height: new_h,
+                caps: this.caps.clone(),
             })
         });
     }
@@ -909,6 +944,7 @@ fn render_row(row: &Row, width: usize) -> String {
         "presence.join" => render_presence(content, "joined", width),
         "presence.leave" => render_presence(content, "left", width),
         "meta.compaction" => render_compaction(content, width),
+        "meta.merge" => render_system_message(content, width),
         "note.user" => render_note(content, width),
         _ => render_default(content, width),
     }
@@ -1478,6 +1514,26 @@ mod tests {
         assert!(ansi.contains("R"));
     }
 
+    #[test]
+    fn test_ansi_output_quantizes_colors() {
+        let mut buf = RenderBuffer::new(4, 1);
+        buf.print(
+            0,
+            0,
+            "hi",
+            &Style::new().fg(Color::Rgb { r: 255, g: 0, b: 0 }),
+        );
+
+        buf.set_color_depth(ColorDepth::Ansi256);
+        assert!(buf.to_ansi().contains("\x1b[38;5;196m"));
+
+        buf.set_color_depth(ColorDepth::Ansi16);
+        assert!(buf.row_to_ansi(0, 0).contains("\x1b[91m"));
+
+        buf.set_color_depth(ColorDepth::Mono);
+        assert!(!buf.to_ansi().contains("38;"));
+    }
+
     // ==========================================================================
     // Wide character (unicode display width) tests
     // ==========================================================================
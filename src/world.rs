@@ -1,207 +1,403 @@
-//! World state: rooms and their contents
+//! World state: rooms and who is in them
 //!
-//! Rooms are in-memory representations synced with the database.
-//! Messages are stored in the Row/Buffer system, not in Room structs.
+//! The database is the authority on rooms. `World` caches each room's
+//! metadata and drops cached rooms as `RoomEvent`s come in, so a change made
+//! through any path (ops, Lua, MCP, merges, templates) shows up everywhere.
+//!
+//! Presence lives here too: which room each connected user or model is in.
+//! Moving someone writes the presence.leave/presence.join rows to the chat of
+//! the rooms involved, so there's a single place that keeps the two in step.
+//!
+//! One name can be connected several times over (SSH sessions, MCP clients).
+//! Each connection holds the name (`hold`), and the name only leaves its room
+//! when the last holder lets go (`release`).
 
-use chrono::{DateTime, Utc};
-use std::collections::{HashMap, HashSet};
-use uuid::Uuid;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::broadcast::{self, error::TryRecvError};
 
-use crate::model::ModelHandle;
+use crate::db::agents::AgentKind;
+use crate::db::events::{RoomEvent, RoomEventKind};
+use crate::db::rows::Row;
+use crate::db::Database;
 
-/// A room where users and models interact
+/// A room as loaded from the database, with who is in it now
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Room {
-    pub id: RoomId,
+    /// Database room ID
+    pub id: String,
     pub name: String,
     pub description: Option<String>,
-    pub created_at: DateTime<Utc>,
+    /// The room's vibe, or its zone's if it has none
+    pub vibe: Option<String>,
+    /// Zone the room is grouped under
+    pub zone: Option<String>,
+    /// Exits to other rooms (direction -> room name)
+    pub exits: HashMap<String, String>,
+    pub archived: bool,
+    /// People present, in the order they arrived
     pub users: Vec<String>,
-    pub models: Vec<ModelHandle>,
-    pub artifacts: Vec<ArtifactRef>,
-    /// Buffer ID for the room's main chat buffer (from database)
-    /// None if the room buffer hasn't been created yet
-    pub buffer_id: Option<String>,
-    pub context: RoomContext,
+    /// Models present, in the order they arrived
+    pub models: Vec<String>,
 }
 
-/// Unique identifier for a room
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct RoomId(pub Uuid);
-
-impl RoomId {
-    pub fn new() -> Self {
-        Self(Uuid::new_v4())
+impl Room {
+    pub fn user_count(&self) -> usize {
+        self.users.len()
     }
-}
 
-impl Default for RoomId {
-    fn default() -> Self {
-        Self::new()
+    /// Everyone present, people first
+    pub fn occupants(&self) -> Vec<String> {
+        self.users.iter().chain(&self.models).cloned().collect()
     }
 }
 
-/// Reference to an artifact in the room
-#[derive(Debug, Clone)]
-pub struct ArtifactRef {
-    pub id: String,
-    pub artifact_type: ArtifactType,
-    pub name: String,
-    pub created_by: String,
-    pub created_at: DateTime<Utc>,
+/// Rooms loaded so far, and the events that say which ones went stale
+struct RoomCache {
+    rooms: HashMap<String, Room>,
+    events: broadcast::Receiver<RoomEvent>,
 }
 
-#[derive(Debug, Clone)]
-pub enum ArtifactType {
-    Midi,
-    Wav,
-    Text,
-    Image,
-    Other(String),
+impl RoomCache {
+    /// Drop rooms the database has changed since the last look
+    fn refresh(&mut self) {
+        loop {
+            match self.events.try_recv() {
+                // A deletion also removes exits from other rooms
+                Ok(RoomEvent {
+                    kind: RoomEventKind::Deleted,
+                    ..
+                })
+                | Err(TryRecvError::Lagged(_)) => self.rooms.clear(),
+                Ok(event) => self.rooms.retain(|_, room| room.id != event.room_id),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            }
+        }
+    }
 }
 
-/// Rich context for a room beyond basic metadata
-#[derive(Debug, Clone, Default)]
-pub struct RoomContext {
-    /// The vibe - atmosphere, mood, creative direction
-    pub vibe: Option<String>,
-    /// Named asset bindings - semantic roles like "drums", "main_theme"
-    pub assets: HashMap<String, AssetBinding>,
-    /// Exits to other rooms (direction -> room_name)
-    pub exits: HashMap<String, String>,
-    /// Freeform tags for categorization
-    pub tags: HashSet<String>,
-    /// Parent room for fork DAG
-    pub parent: Option<String>,
+/// Someone in a room
+#[derive(Debug, Clone)]
+struct Presence {
+    name: String,
+    room: String,
+    is_model: bool,
 }
 
-/// An asset bound to a room with a semantic role
-#[derive(Debug, Clone)]
-pub struct AssetBinding {
-    pub artifact_id: String,
-    pub role: String,
-    pub notes: Option<String>,
-    pub bound_by: String,
-    pub bound_at: DateTime<Utc>,
+/// The world: rooms from the database plus live presence
+pub struct World {
+    db: Arc<Database>,
+    cache: Mutex<RoomCache>,
+    presence: Mutex<Vec<Presence>>,
+    /// Connections keeping a name present: holder key -> name
+    holders: Mutex<HashMap<String, String>>,
 }
 
-impl Room {
-    pub fn new(name: String) -> Self {
+impl World {
+    pub fn new(db: Arc<Database>) -> Self {
+        let events = db.subscribe_rooms();
         Self {
-            id: RoomId::new(),
-            name,
-            description: None,
-            created_at: Utc::now(),
-            users: Vec::new(),
-            models: Vec::new(),
-            artifacts: Vec::new(),
-            buffer_id: None,
-            context: RoomContext::default(),
+            db,
+            cache: Mutex::new(RoomCache {
+                rooms: HashMap::new(),
+                events,
+            }),
+            presence: Mutex::new(Vec::new()),
+            holders: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Create a room with an existing buffer ID (from database)
-    pub fn with_buffer(name: String, buffer_id: String) -> Self {
-        Self {
-            id: RoomId::new(),
-            name,
-            description: None,
-            created_at: Utc::now(),
-            users: Vec::new(),
-            models: Vec::new(),
-            artifacts: Vec::new(),
-            buffer_id: Some(buffer_id),
-            context: RoomContext::default(),
-        }
+    fn cache(&self) -> Result<MutexGuard<'_, RoomCache>> {
+        self.cache
+            .lock()
+            .map_err(|e| anyhow!("world cache lock poisoned: {}", e))
     }
 
-    pub fn user_count(&self) -> usize {
-        self.users.len()
+    fn presence(&self) -> Result<MutexGuard<'_, Vec<Presence>>> {
+        self.presence
+            .lock()
+            .map_err(|e| anyhow!("world presence lock poisoned: {}", e))
     }
 
-    /// Add user to in-memory list
-    /// Note: Caller should add presence.join row to the buffer
-    pub fn add_user(&mut self, username: String) {
-        if !self.users.contains(&username) {
-            self.users.push(username);
+    /// Look a room up by name
+    pub fn get_room(&self, name: &str) -> Result<Option<Room>> {
+        let room = {
+            let mut cache = self.cache()?;
+            cache.refresh();
+            match cache.rooms.get(name) {
+                Some(room) => Some(room.clone()),
+                None => {
+                    let loaded = self.load_room(name)?;
+                    if let Some(ref room) = loaded {
+                        cache.rooms.insert(name.to_string(), room.clone());
+                    }
+                    loaded
+                }
+            }
+        };
+        let Some(mut room) = room else {
+            return Ok(None);
+        };
+
+        for p in self.presence()?.iter().filter(|p| p.room == name) {
+            if p.is_model {
+                room.models.push(p.name.clone());
+            } else {
+                room.users.push(p.name.clone());
+            }
         }
+        Ok(Some(room))
     }
 
-    /// Remove user from in-memory list
-    /// Note: Caller should add presence.leave row to the buffer
-    pub fn remove_user(&mut self, username: &str) {
-        self.users.retain(|u| u != username);
+    /// Every room, by name
+    pub fn list_rooms(&self) -> Result<Vec<Room>> {
+        let mut rooms = Vec::new();
+        for room in self.db.list_rooms()? {
+            if let Some(room) = self.get_room(&room.name)? {
+                rooms.push(room);
+            }
+        }
+        Ok(rooms)
     }
 
-    /// Get the buffer ID, panicking if not set
-    /// Use this when you're certain the buffer has been initialized
-    pub fn buffer_id(&self) -> &str {
-        self.buffer_id
-            .as_deref()
-            .expect("room buffer not initialized")
+    fn load_room(&self, name: &str) -> Result<Option<Room>> {
+        let Some(room) = self.db.get_room_by_name(name)? else {
+            return Ok(None);
+        };
+        let mut kv = self.db.get_all_room_kv(&room.id)?;
+        let exits = kv
+            .iter()
+            .filter_map(|(key, target)| {
+                let direction = key.strip_prefix("exit.")?;
+                Some((direction.to_string(), target.clone()))
+            })
+            .collect();
+        let zone = self.db.room_zone(name)?;
+        let vibe = kv
+            .remove("vibe")
+            .or_else(|| zone.as_ref().and_then(|z| z.settings.vibe.clone()));
+        Ok(Some(Room {
+            id: room.id,
+            name: room.name,
+            description: kv.remove("description"),
+            vibe,
+            zone: zone.map(|z| z.name),
+            exits,
+            archived: kv.contains_key("archived"),
+            users: Vec::new(),
+            models: Vec::new(),
+        }))
     }
 
-    /// Set the buffer ID (typically after creating via db.get_or_create_room_buffer)
-    pub fn set_buffer_id(&mut self, buffer_id: String) {
-        self.buffer_id = Some(buffer_id);
+    /// Names of everyone in a room, people first
+    pub fn users_in(&self, room: &str) -> Result<Vec<String>> {
+        let presence = self.presence()?;
+        let (models, mut users): (Vec<_>, Vec<_>) = presence
+            .iter()
+            .filter(|p| p.room == room)
+            .partition(|p| p.is_model);
+        users.extend(models);
+        Ok(users.into_iter().map(|p| p.name.clone()).collect())
     }
-}
 
-/// Summary info for room listing
-pub struct RoomSummary {
-    pub name: String,
-    pub user_count: usize,
-    pub model_count: usize,
-    pub artifact_count: usize,
-}
+    /// The room someone is in, if any
+    pub fn room_of(&self, name: &str) -> Result<Option<String>> {
+        Ok(self
+            .presence()?
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.room.clone()))
+    }
 
-/// The world: collection of rooms
-pub struct World {
-    pub rooms: HashMap<String, Room>,
-}
+    /// Move someone into a room, out of whichever room they were in
+    ///
+    /// Does nothing if they're already there.
+    pub fn enter(&self, name: &str, room: &str) -> Result<()> {
+        let mut presence = self.presence()?;
+        let current = presence.iter().position(|p| p.name == name);
+        if let Some(i) = current {
+            if presence[i].room == room {
+                return Ok(());
+            }
+        }
+        if self.db.get_room_by_name(room)?.is_none() {
+            return Err(anyhow!(
+                "No room named '{}'. Use /create {} to make one.",
+                room,
+                room
+            ));
+        }
 
-impl Default for World {
-    fn default() -> Self {
-        Self::new()
+        if let Some(i) = current {
+            let old = presence.remove(i);
+            self.presence_row(&old.room, name, "presence.leave")?;
+        }
+        self.presence_row(room, name, "presence.join")?;
+        let is_model = self
+            .db
+            .get_agent_by_name(name)?
+            .is_some_and(|agent| agent.kind == AgentKind::Model);
+        presence.push(Presence {
+            name: name.to_string(),
+            room: room.to_string(),
+            is_model,
+        });
+        Ok(())
     }
-}
 
-impl World {
-    pub fn new() -> Self {
-        Self {
-            rooms: HashMap::new(),
+    /// Take someone out of the world, returning the room they left
+    pub fn leave(&self, name: &str) -> Result<Option<String>> {
+        let mut presence = self.presence()?;
+        let Some(i) = presence.iter().position(|p| p.name == name) else {
+            return Ok(None);
+        };
+        let old = presence.remove(i);
+        self.presence_row(&old.room, name, "presence.leave")?;
+        Ok(Some(old.room))
+    }
+
+    /// Note that the connection `holder` is present as `name`
+    ///
+    /// Holder keys only need to be unique, e.g. `ssh:<session id>`.
+    pub fn hold(&self, holder: &str, name: &str) -> Result<()> {
+        self.holders()?.insert(holder.to_string(), name.to_string());
+        Ok(())
+    }
+
+    /// A connection is gone; when nothing else holds its name, that name
+    /// leaves its room. Returns the room left, if any.
+    pub fn release(&self, holder: &str) -> Result<Option<String>> {
+        let mut holders = self.holders()?;
+        let Some(name) = holders.remove(holder) else {
+            return Ok(None);
+        };
+        if holders.values().any(|n| *n == name) {
+            return Ok(None);
         }
+        self.leave(&name)
     }
 
-    pub fn create_room(&mut self, name: String) -> &Room {
-        let room = Room::new(name.clone());
-        self.rooms.insert(name.clone(), room);
-        self.rooms.get(&name).unwrap()
+    fn holders(&self) -> Result<MutexGuard<'_, HashMap<String, String>>> {
+        self.holders
+            .lock()
+            .map_err(|e| anyhow!("world holders lock poisoned: {}", e))
     }
 
-    /// Create a room with an existing buffer ID
-    pub fn create_room_with_buffer(&mut self, name: String, buffer_id: String) -> &Room {
-        let room = Room::with_buffer(name.clone(), buffer_id);
-        self.rooms.insert(name.clone(), room);
-        self.rooms.get(&name).unwrap()
+    /// Run `f` only if nobody is in the room, keeping anyone from entering
+    /// until it's done
+    ///
+    /// Errors with the names of whoever is still there.
+    pub fn while_empty<T>(&self, room: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let presence = self.presence()?;
+        let present: Vec<_> = presence
+            .iter()
+            .filter(|p| p.room == room)
+            .map(|p| p.name.as_str())
+            .collect();
+        if !present.is_empty() {
+            return Err(anyhow!(
+                "{} still has people in it: {}.",
+                room,
+                present.join(", ")
+            ));
+        }
+        f()
     }
 
-    pub fn get_room(&self, name: &str) -> Option<&Room> {
-        self.rooms.get(name)
+    fn presence_row(&self, room: &str, name: &str, method: &str) -> Result<()> {
+        let buffer = self.db.get_or_create_room_buffer(room)?;
+        let mut row = Row::new(&buffer.id, method);
+        row.content = Some(name.to_string());
+        self.db.append_row(&mut row)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn get_room_mut(&mut self, name: &str) -> Option<&mut Room> {
-        self.rooms.get_mut(name)
+    #[test]
+    fn test_room_cache_follows_database() -> Result<()> {
+        let db = Arc::new(Database::in_memory()?);
+        db.create_room("studio", Some("where it happens"))?;
+        let world = World::new(db.clone());
+
+        let room = world.get_room("studio")?.expect("studio should load");
+        assert_eq!(room.description.as_deref(), Some("where it happens"));
+        assert_eq!(room.vibe, None);
+
+        db.set_vibe("studio", Some("late night"))?;
+        db.add_exit("studio", "north", "lobby")?;
+        let room = world.get_room("studio")?.expect("studio should load");
+        assert_eq!(room.vibe.as_deref(), Some("late night"));
+        assert_eq!(room.exits.get("north").map(String::as_str), Some("lobby"));
+
+        db.create_room("lobby", None)?;
+        assert_eq!(world.list_rooms()?.len(), 2);
+        let studio = db.get_room_by_name("studio")?.unwrap();
+        db.delete_room(&studio.id)?;
+        assert!(world.get_room("studio")?.is_none());
+        Ok(())
     }
 
-    pub fn list_rooms(&self) -> Vec<RoomSummary> {
-        self.rooms
-            .values()
-            .map(|r| RoomSummary {
-                name: r.name.clone(),
-                user_count: r.users.len(),
-                model_count: r.models.len(),
-                artifact_count: r.artifacts.len(),
-            })
-            .collect()
+    #[test]
+    fn test_presence_moves_between_rooms() -> Result<()> {
+        let db = Arc::new(Database::in_memory()?);
+        db.create_room("lobby", None)?;
+        db.create_room("studio", None)?;
+        let world = World::new(db.clone());
+
+        world.enter("alice", "lobby")?;
+        world.enter("alice", "lobby")?;
+        world.enter("bob", "lobby")?;
+        assert_eq!(world.users_in("lobby")?, vec!["alice", "bob"]);
+        assert!(world.enter("alice", "nowhere").is_err());
+
+        world.enter("alice", "studio")?;
+        assert_eq!(world.get_room("studio")?.unwrap().users, vec!["alice"]);
+        assert_eq!(world.room_of("alice")?.as_deref(), Some("studio"));
+        assert!(world.while_empty("studio", || Ok(())).is_err());
+
+        assert_eq!(world.leave("alice")?.as_deref(), Some("studio"));
+        assert_eq!(world.leave("alice")?, None);
+        assert!(world.while_empty("studio", || Ok(())).is_ok());
+
+        let lobby = db.get_room_buffer_id("lobby")?.unwrap();
+        let methods: Vec<_> = db
+            .list_buffer_rows(&lobby)?
+            .into_iter()
+            .map(|r| (r.content_method, r.content.unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                ("presence.join".to_string(), "alice".to_string()),
+                ("presence.join".to_string(), "bob".to_string()),
+                ("presence.leave".to_string(), "alice".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_presence_lasts_until_last_holder_releases() -> Result<()> {
+        let db = Arc::new(Database::in_memory()?);
+        db.create_room("lobby", None)?;
+        let world = World::new(db);
+
+        // The same name over SSH and MCP
+        world.hold("ssh:1", "alice")?;
+        world.hold("mcp:a", "alice")?;
+        world.hold("mcp:b", "bob")?;
+        world.enter("alice", "lobby")?;
+        world.enter("bob", "lobby")?;
+
+        assert_eq!(world.release("mcp:a")?, None);
+        assert_eq!(world.users_in("lobby")?, vec!["alice", "bob"]);
+        assert_eq!(world.release("ssh:1")?.as_deref(), Some("lobby"));
+        assert_eq!(world.release("ssh:1")?, None);
+        assert_eq!(world.users_in("lobby")?, vec!["bob"]);
+        Ok(())
     }
 }
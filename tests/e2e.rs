@@ -25,6 +25,8 @@ use sshwarma::lua::{LuaReloadSender, LuaRuntime};
 use sshwarma::mcp::McpManager;
 use sshwarma::mcp_server::{self, McpServerState, McpToolRegistry};
 use sshwarma::model::{ModelBackend, ModelHandle, ModelRegistry};
+use sshwarma::shutdown::Shutdown;
+use sshwarma::ssh::SessionStore;
 use sshwarma::state::SharedState;
 use sshwarma::world::World;
 use std::time::Duration;
@@ -442,6 +444,8 @@ async fn start_sshwarma_mcp_server() -> Result<(String, tokio::task::JoinHandle<
         models: models.clone(),
         mcp: Arc::new(McpManager::new()),
         lua_reload: LuaReloadSender::new(),
+        shutdown: Arc::new(Shutdown::new()),
+        sessions: Arc::new(SessionStore::new(std::time::Duration::ZERO)),
     });
 
     // Create tool registry and Lua runtime